@@ -0,0 +1,95 @@
+use hyper::Uri;
+use std::net::{SocketAddr, ToSocketAddrs};
+
+/// Finds the host and port to connect to for a target. Targets can be given as urls, in
+/// which case the port defaults on the scheme, or as plain `host:port` pairs.
+pub fn host_and_port(target: &str) -> Result<(String, u16), String> {
+    if target.contains("://") {
+        let uri: Uri = target
+            .parse()
+            .map_err(|e| format!("Invalid url {}: {}", target, e))?;
+        let host = uri
+            .host()
+            .ok_or_else(|| format!("No host in {}", target))?
+            .trim_matches(|c| c == '[' || c == ']')
+            .to_string();
+        let port = uri.port().unwrap_or(match uri.scheme() {
+            Some("https") | Some("wss") => 443,
+            _ => 80,
+        });
+        return Ok((host, port));
+    }
+
+    let split = target
+        .rfind(':')
+        .ok_or_else(|| format!("Expected host:port but got {}", target))?;
+    let port = target[split + 1..]
+        .parse()
+        .map_err(|_| format!("Invalid port in {}", target))?;
+    let host = target[..split].trim_matches(|c| c == '[' || c == ']');
+    Ok((host.to_string(), port))
+}
+
+/// Finds the host of a target, which may also be given as just the host name.
+pub fn host(target: &str) -> String {
+    host_and_port(target)
+        .map(|(host, _)| host)
+        .unwrap_or_else(|_| target.to_string())
+}
+
+/// Resolves a target to the first address for its host, returning the host alongside it.
+pub fn resolve(target: &str) -> Result<(String, SocketAddr), String> {
+    let (host, port) = host_and_port(target)?;
+    let addr = (host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Could not resolve {}: {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("No addresses for {}", host))?;
+    Ok((host, addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_host_and_port_pairs() {
+        assert_eq!(
+            host_and_port("localhost:8080"),
+            Ok(("localhost".to_string(), 8080))
+        );
+        assert_eq!(host_and_port("[::1]:443"), Ok(("::1".to_string(), 443)));
+        assert!(host_and_port("localhost").is_err());
+        assert!(host_and_port("localhost:http").is_err());
+    }
+
+    #[test]
+    fn it_reads_urls_with_default_ports() {
+        assert_eq!(
+            host_and_port("https://example.com/path"),
+            Ok(("example.com".to_string(), 443))
+        );
+        assert_eq!(
+            host_and_port("http://example.com"),
+            Ok(("example.com".to_string(), 80))
+        );
+        assert_eq!(
+            host_and_port("http://example.com:8080/"),
+            Ok(("example.com".to_string(), 8080))
+        );
+    }
+
+    #[test]
+    fn it_reads_bare_hosts() {
+        assert_eq!(host("example.com"), "example.com");
+        assert_eq!(host("example.com:53"), "example.com");
+        assert_eq!(host("https://example.com/path"), "example.com");
+    }
+
+    #[test]
+    fn it_resolves_addresses() {
+        let (host, addr) = resolve("http://127.0.0.1:8080").unwrap();
+        assert_eq!(host, "127.0.0.1");
+        assert_eq!(addr, "127.0.0.1:8080".parse().unwrap());
+    }
+}
@@ -0,0 +1,67 @@
+use std::io;
+
+/// Reads a list of cores such as `0,2,4-7`.
+pub fn parse_cores(cores: &str) -> Result<Vec<usize>, String> {
+    let mut parsed = Vec::new();
+    for part in cores
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let invalid = || format!("Invalid core list {}", cores);
+        let mut bounds = part.splitn(2, '-').map(|core| core.trim().parse::<usize>());
+        match (bounds.next(), bounds.next()) {
+            (Some(Ok(core)), None) => parsed.push(core),
+            (Some(Ok(first)), Some(Ok(last))) if first <= last => parsed.extend(first..=last),
+            _ => return Err(invalid()),
+        }
+    }
+    if parsed.is_empty() {
+        return Err(format!("No cores in {}", cores));
+    }
+    Ok(parsed)
+}
+
+/// Pins the calling thread to the core.
+#[cfg(target_os = "linux")]
+pub fn pin(core: usize) -> io::Result<()> {
+    use libc;
+    use std::mem;
+
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin(_core: usize) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "Pinning threads to cores is only supported on linux",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_core_lists() {
+        assert_eq!(parse_cores("0,2,4-6"), Ok(vec![0, 2, 4, 5, 6]));
+        assert_eq!(parse_cores("3"), Ok(vec![3]));
+        assert!(parse_cores("").is_err());
+        assert!(parse_cores("4-2").is_err());
+        assert!(parse_cores("one").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn it_pins_the_thread() {
+        ::std::thread::spawn(|| pin(0).unwrap()).join().unwrap();
+    }
+}
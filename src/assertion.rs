@@ -0,0 +1,83 @@
+use regex::Regex;
+
+/// A check on a header of every response, which the response fails when its header is
+/// missing or does not hold, as when a proxy starts to answer with error pages of its own.
+#[derive(Clone, Debug)]
+pub struct HeaderAssertion {
+    name: String,
+    value: Expected,
+}
+
+#[derive(Clone, Debug)]
+enum Expected {
+    Equals(String),
+    Matches(Regex),
+}
+
+impl HeaderAssertion {
+    /// Asserts that a header has exactly the value, given as `Name: value`.
+    pub fn equals(assertion: &str) -> Result<HeaderAssertion, String> {
+        let (name, value) = split(assertion)?;
+        Ok(HeaderAssertion {
+            name,
+            value: Expected::Equals(value),
+        })
+    }
+
+    /// Asserts that a header has a value the regex matches, given as `Name: regex`.
+    pub fn matches(assertion: &str) -> Result<HeaderAssertion, String> {
+        let (name, value) = split(assertion)?;
+        let regex = Regex::new(&value).map_err(|e| format!("Invalid regex {}: {}", value, e))?;
+        Ok(HeaderAssertion {
+            name,
+            value: Expected::Matches(regex),
+        })
+    }
+
+    /// The name of the header the assertion is on.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the first value of the header, if the response had it, holds.
+    pub fn holds(&self, value: Option<&[u8]>) -> bool {
+        let value = match value {
+            Some(value) => String::from_utf8_lossy(value),
+            None => return false,
+        };
+        match self.value {
+            Expected::Equals(ref expected) => value.trim() == expected,
+            Expected::Matches(ref regex) => regex.is_match(value.trim()),
+        }
+    }
+}
+
+fn split(assertion: &str) -> Result<(String, String), String> {
+    match assertion.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("Expected a header assertion such as Name: value but got {}", assertion)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_checks_header_values() {
+        let equals = HeaderAssertion::equals("Content-Type: application/json").unwrap();
+        assert_eq!(equals.name(), "Content-Type");
+        assert!(equals.holds(Some(b"application/json")));
+        assert!(!equals.holds(Some(b"text/html")));
+        assert!(!equals.holds(None));
+
+        let matches = HeaderAssertion::matches("Cache-Control: max-age=\\d+").unwrap();
+        assert!(matches.holds(Some(b"public, max-age=60")));
+        assert!(!matches.holds(Some(b"no-store")));
+
+        assert!(HeaderAssertion::equals("no colon").is_err());
+        assert!(HeaderAssertion::matches("X: (").is_err());
+    }
+}
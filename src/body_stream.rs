@@ -0,0 +1,117 @@
+use content_length::ContentLength;
+use std::cmp;
+use std::io::{self, Read};
+
+const DEFAULT_SIZE: u64 = 1024 * 1024;
+const DEFAULT_CHUNK: usize = 64 * 1024;
+
+/// A generated request body that is streamed in chunks rather than sent with a length.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BodyStream {
+    size: u64,
+    chunk: usize,
+}
+
+impl BodyStream {
+    /// Parses the options given as `size=10MB` and `chunk=64KB`, either can be left out.
+    pub fn parse<'a, I>(specs: I) -> Result<BodyStream, String>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut stream = BodyStream {
+            size: DEFAULT_SIZE,
+            chunk: DEFAULT_CHUNK,
+        };
+        for spec in specs {
+            let mut parts = spec.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("size"), Some(size)) => stream.size = size.parse::<ContentLength>()?.bytes(),
+                (Some("chunk"), Some(chunk)) => {
+                    stream.chunk = chunk.parse::<ContentLength>()?.bytes() as usize
+                }
+                _ => {
+                    return Err(format!(
+                        "Expected size=<size> or chunk=<size> but got {}",
+                        spec
+                    ))
+                }
+            }
+        }
+        if stream.chunk == 0 {
+            return Err("The chunk size must be more than zero".to_string());
+        }
+        Ok(stream)
+    }
+
+    /// The size of the whole body.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The size of each chunk written to the connection.
+    pub fn chunk(&self) -> usize {
+        self.chunk
+    }
+
+    /// A reader over a fresh copy of the body, never reading more than a chunk at a time.
+    pub fn reader(&self) -> Generated {
+        Generated {
+            remaining: self.size,
+            chunk: self.chunk,
+        }
+    }
+}
+
+/// The bytes of a generated body.
+pub struct Generated {
+    remaining: u64,
+    chunk: usize,
+}
+
+impl Read for Generated {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = cmp::min(cmp::min(buf.len(), self.chunk) as u64, self.remaining) as usize;
+        for byte in &mut buf[..len] {
+            *byte = b'x';
+        }
+        self.remaining -= len as u64;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_options() {
+        assert_eq!(
+            BodyStream::parse(vec!["size=10MB", "chunk=1KB"]),
+            Ok(BodyStream {
+                size: 10 * 1024 * 1024,
+                chunk: 1024,
+            })
+        );
+        assert_eq!(
+            BodyStream::parse(vec![]),
+            Ok(BodyStream {
+                size: DEFAULT_SIZE,
+                chunk: DEFAULT_CHUNK,
+            })
+        );
+        assert!(BodyStream::parse(vec!["chunk=0"]).is_err());
+        assert!(BodyStream::parse(vec!["length=1"]).is_err());
+    }
+
+    #[test]
+    fn it_generates_the_body_in_chunks() {
+        let mut reader = BodyStream::parse(vec!["size=5", "chunk=2"])
+            .unwrap()
+            .reader();
+        let mut buf = [0; 8];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+}
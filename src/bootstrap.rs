@@ -0,0 +1,79 @@
+use rand::Rng;
+
+/// The most resamples drawn, fewer being drawn of large samples to bound the time taken.
+const RESAMPLES: usize = 1_000;
+
+/// The least resamples drawn, however large the sample.
+const MIN_RESAMPLES: usize = 100;
+
+/// Roughly how many values are drawn across all the resamples of a large sample.
+const BUDGET: usize = 10_000_000;
+
+/// The bounds within which a statistic of the population falls with the confidence, such as
+/// 95 for 95%, estimated by the percentiles of the statistic over resamples of the sample.
+/// The statistic may reorder the resample it is given.
+pub fn interval<R, F>(sample: &[f64], confidence: f64, statistic: F, rng: &mut R) -> (f64, f64)
+where
+    R: Rng,
+    F: Fn(&mut [f64]) -> f64,
+{
+    let resamples = (BUDGET / sample.len().max(1)).clamp(MIN_RESAMPLES, RESAMPLES);
+    let mut resample = vec![0f64; sample.len()];
+    let mut statistics: Vec<f64> = (0..resamples)
+        .map(|_| {
+            for value in resample.iter_mut() {
+                *value = sample[rng.gen_range(0..sample.len())];
+            }
+            statistic(&mut resample)
+        })
+        .collect();
+    statistics.sort_by(|a, b| a.partial_cmp(b).expect("Expected statistics to compare"));
+    let tail = (100f64 - confidence) / 200f64;
+    let at = |share: f64| {
+        let index = (share * (resamples - 1) as f64).round() as usize;
+        statistics[index]
+    };
+    (at(tail), at(1f64 - tail))
+}
+
+pub fn mean(values: &mut [f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The nearest ranked 99th percentile, as the summary reports it.
+pub fn p99(values: &mut [f64]) -> f64 {
+    let rank = (values.len() as f64 * 0.99).ceil() as usize;
+    let index = rank.clamp(1, values.len()) - 1;
+    *values
+        .select_nth_unstable_by(index, |a, b| a.partial_cmp(b).expect("Expected values to compare"))
+        .1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn it_bounds_the_statistics_of_a_sample() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let sample: Vec<f64> = (1..=1_000).map(f64::from).collect();
+        let (low, high) = interval(&sample, 95f64, mean, &mut rng);
+        assert!(low < 500.5 && 500.5 < high);
+        assert!(high - low < 60f64);
+
+        let (low, high) = interval(&sample, 95f64, p99, &mut rng);
+        assert!(low <= 990f64 && 990f64 <= high);
+        assert!(high <= 1_000f64);
+
+        assert_eq!(interval(&[3f64; 10], 99f64, mean, &mut rng), (3f64, 3f64));
+    }
+
+    #[test]
+    fn it_takes_the_nearest_ranked_p99() {
+        let mut values: Vec<f64> = (1..=200).rev().map(f64::from).collect();
+        assert_eq!(p99(&mut values), 198f64);
+        assert_eq!(p99(&mut [5f64]), 5f64);
+    }
+}
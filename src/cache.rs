@@ -0,0 +1,54 @@
+/// Works out whether a CDN or cache answered a request from its cache, from the headers
+/// the common ones set. Responses that say nothing about the cache, or that the cache
+/// would not store at all, are neither a hit nor a miss.
+pub fn is_hit(
+    cf_cache_status: Option<&str>,
+    x_cache: Option<&str>,
+    age: Option<&str>,
+) -> Option<bool> {
+    if let Some(status) = cf_cache_status {
+        return match status.trim().to_uppercase().as_str() {
+            "HIT" | "STALE" | "UPDATING" | "REVALIDATED" => Some(true),
+            "MISS" | "EXPIRED" => Some(false),
+            _ => None,
+        };
+    }
+    if let Some(x_cache) = x_cache {
+        // Caches in layers each add their own, the last being the one nearest the client.
+        let nearest = x_cache.rsplit(',').next().unwrap_or("").to_uppercase();
+        if nearest.contains("HIT") {
+            return Some(true);
+        }
+        if nearest.contains("MISS") {
+            return Some(false);
+        }
+    }
+    age.and_then(|age| age.trim().parse::<u64>().ok())
+        .map(|age| age > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_cloudflare_statuses() {
+        assert_eq!(is_hit(Some("HIT"), None, None), Some(true));
+        assert_eq!(is_hit(Some("expired"), None, None), Some(false));
+        assert_eq!(is_hit(Some("DYNAMIC"), Some("HIT"), None), None);
+    }
+
+    #[test]
+    fn it_reads_the_nearest_x_cache() {
+        assert_eq!(is_hit(None, Some("Hit from cloudfront"), None), Some(true));
+        assert_eq!(is_hit(None, Some("HIT, MISS"), None), Some(false));
+        assert_eq!(is_hit(None, Some("TCP_MISS"), Some("30")), Some(false));
+    }
+
+    #[test]
+    fn it_falls_back_on_age() {
+        assert_eq!(is_hit(None, None, Some("30")), Some(true));
+        assert_eq!(is_hit(None, None, Some("0")), Some(false));
+        assert_eq!(is_hit(None, None, None), None);
+    }
+}
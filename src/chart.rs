@@ -1,6 +1,17 @@
+/// How the bars of a chart are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    /// A block or half block to a datum on each row.
+    Bars,
+    /// Braille dots, two data to a character and four dots to a row, which shows smaller
+    /// differences in the same number of rows.
+    Braille,
+}
+
 /// A chart that can be used to render some set of data.
 pub struct Chart {
     height: u32,
+    style: Style,
     full: char,
     half_full: char,
     space: char,
@@ -11,6 +22,7 @@ impl Chart {
     pub fn new() -> Chart {
         Chart {
             height: 10,
+            style: Style::Bars,
             full: '▌',
             half_full: '▖',
             space: ' ',
@@ -23,12 +35,18 @@ impl Chart {
         self
     }
 
+    /// Configure how the bars are drawn.
+    pub fn style(mut self, style: Style) -> Chart {
+        self.style = style;
+        self
+    }
+
     /// Build the chart into a string.
     pub fn make<N>(&self, data: &[N]) -> String
     where
         N: Into<f64> + Clone,
     {
-        let data: Vec<f64> = data.into_iter().map(|d| d.clone().into()).collect();
+        let data: Vec<f64> = data.iter().map(|d| d.clone().into()).collect();
         let (min, max): (f64, f64) = data.iter().fold((0., 0.), |(min, max), datum| {
             let datum = *datum;
             (
@@ -36,6 +54,9 @@ impl Chart {
                 if max < datum { datum } else { max },
             )
         });
+        if self.style == Style::Braille {
+            return self.make_braille(&data, min, max);
+        }
         let row_increment = (max - min) / f64::from(self.height);
         let mut ret = String::with_capacity(self.height as usize * data.len() * 2);
         for row in 0..self.height {
@@ -61,6 +82,49 @@ impl Chart {
         }
         ret
     }
+
+    /// Draws each datum as a column of braille dots filled from the bottom, so that each
+    /// row has four levels rather than two.
+    fn make_braille(&self, data: &[f64], min: f64, max: f64) -> String {
+        // The dots of the left and right columns of a braille cell, from the bottom up.
+        const LEFT: [u32; 4] = [0x40, 0x04, 0x02, 0x01];
+        const RIGHT: [u32; 4] = [0x80, 0x20, 0x10, 0x08];
+        let levels = self.height * 4;
+        let dots: Vec<u32> = data
+            .iter()
+            .map(|datum| {
+                if max > min {
+                    ((datum - min) / (max - min) * f64::from(levels)).round() as u32
+                } else {
+                    0
+                }
+            })
+            .collect();
+        let mut ret = String::with_capacity(self.height as usize * data.len() * 2);
+        for row in 0..self.height {
+            let floor = (self.height - row - 1) * 4;
+            for pair in dots.chunks(2) {
+                let mut cell = 0;
+                for (datum, column) in pair.iter().zip(&[LEFT, RIGHT]) {
+                    let filled = datum.saturating_sub(floor).min(4) as usize;
+                    cell |= column[..filled].iter().fold(0, |cell, dot| cell | dot);
+                }
+                ret.push(if cell == 0 {
+                    self.space
+                } else {
+                    ::std::char::from_u32(0x2800 + cell).expect("Expected a braille character")
+                });
+            }
+            if row == 0 {
+                ret.push_str(&format!(" {}", max));
+            }
+            if row == self.height - 1 {
+                ret.push_str(&format!(" {}", min));
+            }
+            ret.push('\n');
+        }
+        ret
+    }
 }
 
 #[cfg(test)]
@@ -69,7 +133,7 @@ mod tests {
 
     #[test]
     fn it_makes_a_chart_of_default_height() {
-        let chart = Chart::new().make(&vec![1, 2, 3, 4, 3, 2, 1]);
+        let chart = Chart::new().make(&[1, 2, 3, 4, 3, 2, 1]);
         assert_eq!(
             chart,
             "   ▌    4
@@ -86,9 +150,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_draws_braille() {
+        let chart = Chart::new()
+            .height(2)
+            .style(Style::Braille)
+            .make(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(chart, "  ⣠⣾ 8\n⣠⣾⣿⣿ 0\n");
+    }
+
     #[test]
     fn it_can_change_the_height() {
-        let chart = Chart::new().height(4).make(&vec![1, 2, 3, 4, 3, 2, 1]);
+        let chart = Chart::new().height(4).make(&[1, 2, 3, 4, 3, 2, 1]);
         assert_eq!(
             chart,
             "   ▌    4
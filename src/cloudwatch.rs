@@ -0,0 +1,237 @@
+use reqwest::header::Headers;
+use reqwest::Client;
+use sigv4::SigV4;
+use stats::Summary;
+use std::env;
+use std::time::{Duration, SystemTime};
+
+/// How long cloudwatch has to take the metrics, so that it cannot hold up the end of a run.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The percentiles of the latencies that are published, each as a metric of its own.
+const PERCENTILES: [(&str, f64); 4] = [
+    ("p50", 50f64),
+    ("p90", 90f64),
+    ("p95", 95f64),
+    ("p99", 99f64),
+];
+
+/// Where the metrics of runs are published to cloudwatch, signed with the credentials aws
+/// tools would find.
+#[derive(Debug)]
+pub struct CloudWatch {
+    namespace: String,
+    endpoint: String,
+    sigv4: SigV4,
+}
+
+impl CloudWatch {
+    /// Parses options given as `namespace=LoadTests`, optionally followed by a `region=` and,
+    /// for stand-ins such as localstack, an `endpoint=`, all separated by commas. The region
+    /// is otherwise that of `AWS_REGION` or `AWS_DEFAULT_REGION`.
+    pub fn parse(options: &str) -> Result<CloudWatch, String> {
+        let (mut namespace, mut region, mut endpoint) = (None, None, None);
+        for option in options.split(',').map(str::trim) {
+            match option.split_once('=') {
+                Some(("namespace", value)) if !value.is_empty() => namespace = Some(value),
+                Some(("region", value)) if !value.is_empty() => region = Some(value.to_string()),
+                Some(("endpoint", value)) if !value.is_empty() => endpoint = Some(value),
+                _ => {
+                    return Err(format!(
+                        "Expected namespace=, region= or endpoint= but got {}",
+                        option
+                    ))
+                }
+            }
+        }
+        let namespace = namespace.ok_or_else(|| format!("No namespace= in {}", options))?;
+        let region = region
+            .or_else(|| env::var("AWS_REGION").ok())
+            .or_else(|| env::var("AWS_DEFAULT_REGION").ok())
+            .ok_or("No region= given, nor AWS_REGION set, to publish to cloudwatch in")?;
+        let endpoint = match endpoint {
+            Some(endpoint) => endpoint.to_string(),
+            None => format!("https://monitoring.{}.amazonaws.com/", region),
+        };
+        Ok(CloudWatch {
+            namespace: namespace.to_string(),
+            endpoint,
+            sigv4: SigV4::new(&region, "monitoring")?,
+        })
+    }
+
+    /// Publishes the latency percentiles, throughput and errors of a run, with its tags as
+    /// dimensions.
+    pub fn publish(&self, summary: &Summary, rps: f64, tags: &[&str]) -> Result<(), String> {
+        let failed = |e: String| format!("Could not publish to cloudwatch: {}", e);
+        let body = form(&self.parameters(summary, rps, tags));
+        let signed = self
+            .sigv4
+            .sign(
+                "POST",
+                &self.endpoint,
+                Some(body.as_bytes()),
+                SystemTime::now(),
+            )
+            .map_err(failed)?;
+        let mut headers = Headers::new();
+        headers.set_raw(
+            "Content-Type",
+            "application/x-www-form-urlencoded; charset=utf-8",
+        );
+        for (name, value) in signed {
+            headers.set_raw(name, value);
+        }
+        let client = Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(|e| failed(e.to_string()))?;
+        let mut response = client
+            .post(&self.endpoint)
+            .headers(headers)
+            .body(body)
+            .send()
+            .map_err(|e| failed(e.to_string()))?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let text = response.text().unwrap_or_default();
+            Err(failed(format!(
+                "{} answered with {}: {}",
+                self.endpoint,
+                response.status(),
+                text
+            )))
+        }
+    }
+
+    /// The parameters of the `PutMetricData` action that publishes a run.
+    fn parameters(&self, summary: &Summary, rps: f64, tags: &[&str]) -> Vec<(String, String)> {
+        let mut metrics = Vec::new();
+        for &(name, percentage) in &PERCENTILES {
+            if let Some(latency) = summary.latency_at(percentage) {
+                let ms = latency.as_secs_f64() * 1_000f64;
+                metrics.push((name, ms, "Milliseconds"));
+            }
+        }
+        metrics.push(("RequestsPerSecond", rps, "Count/Second"));
+        metrics.push(("Requests", summary.requests() as f64, "Count"));
+        metrics.push(("Errors", summary.errors() as f64, "Count"));
+
+        let dimensions = dimensions(tags);
+        let mut parameters = vec![
+            ("Action".to_string(), "PutMetricData".to_string()),
+            ("Version".to_string(), "2010-08-01".to_string()),
+            ("Namespace".to_string(), self.namespace.clone()),
+        ];
+        for (i, (name, value, unit)) in metrics.into_iter().enumerate() {
+            let member = format!("MetricData.member.{}", i + 1);
+            parameters.push((format!("{}.MetricName", member), name.to_string()));
+            parameters.push((format!("{}.Value", member), value.to_string()));
+            parameters.push((format!("{}.Unit", member), unit.to_string()));
+            for (j, (name, value)) in dimensions.iter().enumerate() {
+                let dimension = format!("{}.Dimensions.member.{}", member, j + 1);
+                parameters.push((format!("{}.Name", dimension), name.clone()));
+                parameters.push((format!("{}.Value", dimension), value.clone()));
+            }
+        }
+        parameters
+    }
+}
+
+/// The dimensions of the tags of a run, those such as `env=staging` by their own name and
+/// the rest joined by semicolons as a `Tag`, as the ledger joins them.
+fn dimensions(tags: &[&str]) -> Vec<(String, String)> {
+    let mut dimensions = Vec::new();
+    let mut plain = Vec::new();
+    for tag in tags {
+        match tag.split_once('=') {
+            Some((name, value)) if !name.is_empty() && !value.is_empty() => {
+                dimensions.push((name.to_string(), value.to_string()))
+            }
+            _ => plain.push(*tag),
+        }
+    }
+    if !plain.is_empty() {
+        dimensions.push(("Tag".to_string(), plain.join(";")));
+    }
+    dimensions
+}
+
+/// The parameters as an `application/x-www-form-urlencoded` body.
+fn form(parameters: &[(String, String)]) -> String {
+    let encode = |text: &str| -> String {
+        text.bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                    (byte as char).to_string()
+                }
+                _ => format!("%{:02X}", byte),
+            })
+            .collect()
+    };
+    parameters
+        .iter()
+        .map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content_length::ContentLength;
+    use stats::Fact;
+
+    #[test]
+    fn it_turns_tags_into_dimensions() {
+        assert_eq!(
+            dimensions(&["env=staging", "nightly", "release-1.4"]),
+            vec![
+                ("env".to_string(), "staging".to_string()),
+                ("Tag".to_string(), "nightly;release-1.4".to_string())
+            ]
+        );
+        assert!(dimensions(&[]).is_empty());
+    }
+
+    #[test]
+    fn it_puts_the_metrics_of_a_run() {
+        env::set_var("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE");
+        env::set_var("AWS_SECRET_ACCESS_KEY", "secret");
+        let cloudwatch = CloudWatch::parse("namespace=Load Tests, region=eu-west-1").unwrap();
+        assert_eq!(
+            cloudwatch.endpoint,
+            "https://monitoring.eu-west-1.amazonaws.com/"
+        );
+        let facts = [
+            Fact::record(ContentLength::zero(), 200, Duration::from_millis(10)),
+            Fact::record(ContentLength::zero(), 500, Duration::from_millis(20)),
+        ];
+        let parameters = cloudwatch.parameters(&Summary::from_facts(&facts), 12.5, &["env=ci"]);
+        let value = |name: &str| {
+            parameters
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(value("Namespace"), Some("Load Tests"));
+        assert_eq!(value("MetricData.member.1.MetricName"), Some("p50"));
+        assert_eq!(value("MetricData.member.1.Unit"), Some("Milliseconds"));
+        assert_eq!(
+            value("MetricData.member.5.MetricName"),
+            Some("RequestsPerSecond")
+        );
+        assert_eq!(value("MetricData.member.5.Value"), Some("12.5"));
+        assert_eq!(value("MetricData.member.7.MetricName"), Some("Errors"));
+        assert_eq!(value("MetricData.member.7.Value"), Some("1"));
+        assert_eq!(
+            value("MetricData.member.7.Dimensions.member.1.Name"),
+            Some("env")
+        );
+        assert!(form(&parameters).contains("&Namespace=Load%20Tests&"));
+
+        assert!(CloudWatch::parse("region=eu-west-1").is_err());
+        assert!(CloudWatch::parse("namespace=A,colour=blue").is_err());
+    }
+}
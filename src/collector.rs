@@ -1,36 +1,58 @@
-use std::{cmp, thread, sync::mpsc::{channel, Receiver, Sender}};
+use std::{thread, sync::mpsc::{channel, Receiver, Sender}};
 use message::Message;
 use plan::Plan;
+use reservoir::Reservoir;
+
+/// What the collector kept, along with how many it received in all.
+pub type Collected<T> = (Vec<T>, usize);
 
 /// Kicks off the collector which is a background thread. The collector will capture
-/// all data sent to the sender and then will return on the handle the entire dataset.
+/// all data sent to the sender and then will return on the handle the entire dataset,
+/// along with how many were received. Given a sample size, it only keeps a random sample
+/// of the data. Each message is also handed to `observe` as it arrives.
 ///
 /// The plan is essential to pre-allocating the array.
-pub fn start<T>(plan: Plan) -> (Sender<Message<T>>, thread::JoinHandle<Vec<T>>)
+pub fn start<T, F>(
+    plan: Plan,
+    sample: Option<usize>,
+    observe: F,
+) -> (Sender<Message<T>>, thread::JoinHandle<Collected<T>>)
 where
     T: 'static + Send,
+    F: FnMut(&T) + Send + 'static,
 {
     let (sender, receiver) = channel::<Message<T>>();
-    (sender, thread::spawn(move || collect(&receiver, plan)))
+    let handle = thread::spawn(move || {
+        let reservoir = match sample {
+            Some(size) => Reservoir::new(size),
+            None => Reservoir::unbounded(plan.requests()),
+        };
+        let reservoir = collect(&receiver, plan, reservoir, observe);
+        let seen = reservoir.seen();
+        (reservoir.into_vec(), seen)
+    });
+    (sender, handle)
 }
 
-fn collect<T>(receiver: &Receiver<Message<T>>, plan: Plan) -> Vec<T>
+fn collect<T, F>(
+    receiver: &Receiver<Message<T>>,
+    plan: Plan,
+    mut messages: Reservoir<T>,
+    mut observe: F,
+) -> Reservoir<T>
 where
     T: 'static + Send,
+    F: FnMut(&T),
 {
-    let chunk_size = cmp::max(plan.requests() / 10, 1);
     let mut eof_count = 0;
-    let mut messages: Vec<T> = Vec::with_capacity(plan.requests());
 
     while eof_count < plan.threads() {
         match receiver.recv().expect("To receive correctly") {
             Message::Body(message) => {
+                observe(&message);
                 messages.push(message);
-                if (messages.len() % (chunk_size)) == 0 {
-                    println!("{} requests", messages.len());
-                }
             }
-            Message::EOF => eof_count += 1,
+            Message::Eof => eof_count += 1,
         }
     }
     messages
@@ -43,21 +65,49 @@ mod message_collection_tests {
     #[test]
     fn it_ends_when_all_nones_are_received() {
         let plan = Plan::new(4, 0);
-        let (tx, handle) = start::<usize>(plan);
+        let (tx, handle) = start(plan, None, |_: &usize| ());
         for _ in 0..4 {
-            let _ = tx.send(Message::EOF);
+            let _ = tx.send(Message::Eof);
         }
-        assert_eq!(handle.join().unwrap(), Vec::<usize>::new());
+        assert_eq!(handle.join().unwrap(), (Vec::<usize>::new(), 0));
     }
 
     #[test]
     fn it_collects_all_data_received() {
         let plan = Plan::new(1, 0);
-        let (tx, handle) = start::<usize>(plan);
+        let (tx, handle) = start(plan, None, |_: &usize| ());
+        for n in 0..5 {
+            let _ = tx.send(Message::Body(n as usize));
+        }
+        let _ = tx.send(Message::Eof);
+        assert_eq!(handle.join().unwrap(), (vec![0, 1, 2, 3, 4], 5));
+    }
+
+    #[test]
+    fn it_samples_the_data_received() {
+        let plan = Plan::new(1, 0);
+        let (tx, handle) = start(plan, Some(2), |_: &usize| ());
         for n in 0..5 {
             let _ = tx.send(Message::Body(n as usize));
         }
-        let _ = tx.send(Message::EOF);
-        assert_eq!(handle.join().unwrap(), vec![0, 1, 2, 3, 4]);
+        let _ = tx.send(Message::Eof);
+        let (sample, seen) = handle.join().unwrap();
+        assert_eq!(sample.len(), 2);
+        assert_eq!(seen, 5);
+    }
+
+    #[test]
+    fn it_observes_each_message() {
+        let plan = Plan::new(1, 0);
+        let (observed, received) = channel();
+        let (tx, handle) = start(plan, None, move |&n: &usize| {
+            let _ = observed.send(n);
+        });
+        for n in 0..3 {
+            let _ = tx.send(Message::Body(n as usize));
+        }
+        let _ = tx.send(Message::Eof);
+        handle.join().unwrap();
+        assert_eq!(received.iter().collect::<Vec<_>>(), vec![0, 1, 2]);
     }
 }
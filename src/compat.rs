@@ -0,0 +1,177 @@
+/// The requests ab makes when given a time limit but no number of requests.
+const AB_TIME_LIMITED_REQUESTS: &str = "50000";
+
+/// The tools whose flags can be given in place of rench's.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tool {
+    Hey,
+    Ab,
+}
+
+impl Tool {
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Hey => "hey",
+            Tool::Ab => "ab",
+        }
+    }
+}
+
+/// Rewrites the arguments given with `--compat hey` or `--compat ab` into rench's own,
+/// leaving them alone otherwise. Flags without a rench equivalent are an error rather
+/// than being quietly dropped.
+pub fn translate(args: Vec<String>) -> Result<Vec<String>, String> {
+    let position = args
+        .iter()
+        .position(|arg| arg == "--compat" || arg.starts_with("--compat="));
+    let position = match position {
+        Some(position) => position,
+        None => return Ok(args),
+    };
+
+    let mut args = args;
+    let compat = args.remove(position);
+    let tool = match compat.split_once('=') {
+        Some((_, tool)) => tool.to_string(),
+        None if position < args.len() => args.remove(position),
+        None => return Err("Expected a tool after --compat".to_string()),
+    };
+    let tool = match tool.as_str() {
+        "hey" => Tool::Hey,
+        "ab" => Tool::Ab,
+        _ => {
+            return Err(format!(
+                "Expected --compat hey or --compat ab but got {}",
+                tool
+            ))
+        }
+    };
+
+    let mut translated = vec![args.remove(0)];
+    // ab only keeps connections alive when asked to with -k.
+    let mut keep_alive = tool == Tool::Hey;
+    let (mut requests, mut time_limited) = (false, false);
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| format!("Expected a value after {}'s {}", tool.name(), flag))
+        };
+        match (tool, arg.as_str()) {
+            (_, "-n") => {
+                requests = true;
+                translated.extend(vec!["-n".to_string(), value("-n")?]);
+            }
+            (_, "-c") => translated.extend(vec!["-c".to_string(), value("-c")?]),
+            (Tool::Hey, "-m") => match value("-m")?.to_uppercase().as_str() {
+                "GET" => (),
+                "HEAD" => translated.push("--head".to_string()),
+                method => return Err(format!("rench cannot send {} requests", method)),
+            },
+            (Tool::Hey, "-z") => {
+                translated.extend(vec!["--max-runtime".to_string(), value("-z")?]);
+            }
+            // hey takes a timeout of 0 as none at all.
+            (Tool::Hey, "-t") => match value("-t")? {
+                ref seconds if seconds == "0" => (),
+                seconds => translated.extend(vec!["--timeout".to_string(), seconds]),
+            },
+            (Tool::Hey, "-disable-keepalive") => keep_alive = false,
+            // Neither engine asks for compressed responses to begin with.
+            (Tool::Hey, "-disable-compression") => (),
+            (Tool::Ab, "-k") => keep_alive = true,
+            (Tool::Ab, "-i") => translated.push("--head".to_string()),
+            (Tool::Ab, "-t") => {
+                time_limited = true;
+                translated.extend(vec!["--max-runtime".to_string(), value("-t")?]);
+            }
+            (Tool::Ab, "-s") => translated.extend(vec!["--timeout".to_string(), value("-s")?]),
+            (_, flag) if flag.starts_with('-') => {
+                return Err(format!(
+                    "{}'s {} flag has no rench equivalent",
+                    tool.name(),
+                    flag
+                ))
+            }
+            _ => translated.push(arg),
+        }
+    }
+    if time_limited && !requests {
+        translated.extend(vec!["-n".to_string(), AB_TIME_LIMITED_REQUESTS.to_string()]);
+    }
+    if !keep_alive {
+        translated.extend(vec!["--pool-max-idle".to_string(), "0".to_string()]);
+    }
+    Ok(translated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn it_leaves_rench_arguments_alone() {
+        assert_eq!(
+            translate(args("rench -n 10 http://localhost")),
+            Ok(args("rench -n 10 http://localhost"))
+        );
+    }
+
+    #[test]
+    fn it_translates_hey_flags() {
+        assert_eq!(
+            translate(args(
+                "rench --compat hey -n 10 -c 2 -m HEAD -disable-keepalive http://localhost"
+            )),
+            Ok(args("rench -n 10 -c 2 --head http://localhost --pool-max-idle 0"))
+        );
+        assert_eq!(
+            translate(args("rench --compat hey -z 10s -t 5 http://localhost")),
+            Ok(args("rench --max-runtime 10s --timeout 5 http://localhost"))
+        );
+        assert_eq!(
+            translate(args("rench --compat hey -t 0 http://localhost")),
+            Ok(args("rench http://localhost"))
+        );
+    }
+
+    #[test]
+    fn it_translates_ab_flags() {
+        assert_eq!(
+            translate(args("rench --compat=ab -k -n 10 http://localhost/")),
+            Ok(args("rench -n 10 http://localhost/"))
+        );
+        assert_eq!(
+            translate(args("rench --compat ab -n 10 http://localhost/")),
+            Ok(args("rench -n 10 http://localhost/ --pool-max-idle 0"))
+        );
+        assert_eq!(
+            translate(args("rench --compat ab -k -i -t 10 -s 5 http://localhost/")),
+            Ok(args("rench --head --max-runtime 10 --timeout 5 http://localhost/ -n 50000"))
+        );
+    }
+
+    #[test]
+    fn it_translates_into_arguments_rench_accepts() {
+        for line in &[
+            "rench --compat hey -n 10 -c 2 -m HEAD -z 10s -t 5 -disable-keepalive http://localhost",
+            "rench --compat ab -i -t 10 -s 5 http://localhost/",
+        ] {
+            let translated = translate(args(line)).unwrap();
+            if let Err(e) = ::app().get_matches_from_safe(translated) {
+                panic!("Expected rench to accept the translation of {}: {}", line, e);
+            }
+        }
+    }
+
+    #[test]
+    fn it_rejects_flags_without_an_equivalent() {
+        assert!(translate(args("rench --compat hey -q 10 http://localhost")).is_err());
+        assert!(translate(args("rench --compat ab -n")).is_err());
+        assert!(translate(args("rench --compat wrk http://localhost")).is_err());
+    }
+}
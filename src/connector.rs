@@ -0,0 +1,172 @@
+use futures::{future, Future};
+use hyper::server::Service;
+use hyper::Uri;
+use hyper_tls::MaybeHttpsStream;
+use native_tls::backend::openssl::TlsStreamExt;
+use native_tls::TlsConnector;
+use openssl::ssl::SslRef;
+use std::cell::Cell;
+use std::io;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_core::net::TcpStream;
+use tls::{self, Pin};
+use tokio_tls::TlsConnectorExt;
+
+/// Secures the connections a connector opens to https urls with tls. The server is named
+/// as the url's host unless it is given another name to send, which the certificate is
+/// then checked against.
+pub struct HttpsConnector<C> {
+    http: C,
+    tls: Arc<TlsConnector>,
+    server_name: Option<String>,
+    pins: Vec<Pin>,
+}
+
+impl<C> HttpsConnector<C> {
+    pub fn new(http: C, tls: TlsConnector) -> HttpsConnector<C> {
+        HttpsConnector {
+            http,
+            tls: Arc::new(tls),
+            server_name: None,
+            pins: Vec::new(),
+        }
+    }
+
+    /// Sends the name to the server in place of the url's host.
+    pub fn with_server_name(mut self, server_name: Option<String>) -> Self {
+        self.server_name = server_name;
+        self
+    }
+
+    /// Fails connections to servers whose certificate has none of the pinned public keys.
+    pub fn with_pins(mut self, pins: Vec<Pin>) -> Self {
+        self.pins = pins;
+        self
+    }
+}
+
+impl<C> Service for HttpsConnector<C>
+where
+    C: Service<Request = Uri, Response = TcpStream, Error = io::Error>,
+    C::Future: 'static,
+{
+    type Request = Uri;
+    type Response = MaybeHttpsStream<TcpStream>;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = Self::Response, Error = io::Error>>;
+
+    fn call(&self, uri: Uri) -> Self::Future {
+        if uri.scheme() != Some("https") {
+            return Box::new(self.http.call(uri).map(MaybeHttpsStream::Http));
+        }
+        let name = match self.server_name.as_deref().or_else(|| uri.host()) {
+            Some(name) => name.to_string(),
+            None => {
+                let missing = io::Error::new(io::ErrorKind::InvalidInput, "The url has no host");
+                return Box::new(future::err(missing));
+            }
+        };
+        let (tls, pins) = (self.tls.clone(), self.pins.clone());
+        Box::new(self.http.call(uri).and_then(move |tcp| {
+            tls.connect_async(&name, tcp)
+                .map_err(io::Error::other)
+                .and_then(move |stream| {
+                    tls::check_pins(&pins, stream.get_ref().raw_stream().ssl())?;
+                    Ok(MaybeHttpsStream::Https(stream))
+                })
+        }))
+    }
+}
+
+/// Wraps a hyper connector to time the connections it opens. The client only connects
+/// when its pool has nothing to offer, so a connection timed while a request was made
+/// is the one that request opened.
+pub struct TimedConnector<C> {
+    inner: C,
+    connected: Rc<Cell<Option<Connection>>>,
+}
+
+/// What is known of a connection once it is open.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Connection {
+    pub duration: Duration,
+    /// The tls version negotiated, for connections secured with tls.
+    pub tls_version: Option<&'static str>,
+    /// The cipher suite negotiated, for connections secured with tls.
+    pub cipher: Option<String>,
+    /// The protocol the server chose by alpn, for connections that offered some.
+    pub alpn: Option<String>,
+    /// How many seconds were left before the server's certificate expired, for connections
+    /// secured with tls.
+    pub expires_in: Option<i64>,
+}
+
+impl Connection {
+    /// What is known of the connection, which took the duration to open.
+    pub fn new<S: Secured>(duration: Duration, io: &S) -> Connection {
+        let ssl = io.ssl();
+        Connection {
+            duration,
+            tls_version: ssl.map(SslRef::version),
+            cipher: ssl
+                .and_then(SslRef::current_cipher)
+                .map(|cipher| cipher.name().to_string()),
+            alpn: ssl
+                .and_then(SslRef::selected_alpn_protocol)
+                .map(|protocol| String::from_utf8_lossy(protocol).into_owned()),
+            expires_in: ssl.and_then(tls::expires_in),
+        }
+    }
+}
+
+/// A connection that may be secured with tls.
+pub trait Secured {
+    /// The tls session, when the connection has one.
+    fn ssl(&self) -> Option<&SslRef>;
+}
+
+impl<T> Secured for MaybeHttpsStream<T> {
+    fn ssl(&self) -> Option<&SslRef> {
+        match *self {
+            MaybeHttpsStream::Http(_) => None,
+            MaybeHttpsStream::Https(ref stream) => Some(stream.get_ref().raw_stream().ssl()),
+        }
+    }
+}
+
+impl<C> TimedConnector<C> {
+    pub fn new(inner: C) -> TimedConnector<C> {
+        TimedConnector {
+            inner,
+            connected: Rc::new(Cell::new(None)),
+        }
+    }
+
+    /// A handle on the last connection, which is taken when read.
+    pub fn connected(&self) -> Rc<Cell<Option<Connection>>> {
+        self.connected.clone()
+    }
+}
+
+impl<C> Service for TimedConnector<C>
+where
+    C: Service<Request = Uri, Error = io::Error>,
+    C::Response: Secured,
+    C::Future: 'static,
+{
+    type Request = Uri;
+    type Response = C::Response;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = C::Response, Error = io::Error>>;
+
+    fn call(&self, uri: Uri) -> Self::Future {
+        let start = Instant::now();
+        let connected = self.connected.clone();
+        Box::new(self.inner.call(uri).map(move |io| {
+            connected.set(Some(Connection::new(start.elapsed(), &io)));
+            io
+        }))
+    }
+}
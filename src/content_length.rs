@@ -1,5 +1,7 @@
-use std::ops::Add;
 use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub};
+use std::str::FromStr;
 
 /// Represents the content length of an http request. The ContentLength is
 /// a scalar value that represents the number of bytes (octets) in the
@@ -43,6 +45,41 @@ impl fmt::Display for ContentLength {
     }
 }
 
+impl FromStr for ContentLength {
+    type Err = String;
+
+    /// Parses sizes such as `512`, `64KB` or `2.5MB`, where a kilobyte is 1024 bytes as it
+    /// is when they are displayed.
+    fn from_str(size: &str) -> Result<ContentLength, String> {
+        let size = size.trim();
+        let invalid = || format!("Invalid size {}", size);
+        let split = size
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(size.len());
+        let scale: u64 = match size[split..].trim().to_uppercase().as_str() {
+            "" | "B" => 1,
+            "K" | "KB" => 1024,
+            "M" | "MB" => 1024 * 1024,
+            "G" | "GB" => 1024 * 1024 * 1024,
+            _ => return Err(invalid()),
+        };
+        let number = &size[..split];
+        let bytes = if number.contains('.') {
+            let number: f64 = number.parse().map_err(|_| invalid())?;
+            let bytes = (number * scale as f64).round();
+            if bytes.is_finite() && bytes < u64::MAX as f64 {
+                Some(bytes as u64)
+            } else {
+                None
+            }
+        } else {
+            let number: u64 = number.parse().map_err(|_| invalid())?;
+            number.checked_mul(scale)
+        };
+        bytes.map(ContentLength).ok_or_else(invalid)
+    }
+}
+
 impl Add for ContentLength {
     type Output = ContentLength;
 
@@ -51,7 +88,7 @@ impl Add for ContentLength {
     }
 }
 
-impl<'a> Add for &'a ContentLength {
+impl Add for &ContentLength {
     type Output = ContentLength;
 
     fn add(self, rhs: &ContentLength) -> ContentLength {
@@ -59,7 +96,7 @@ impl<'a> Add for &'a ContentLength {
     }
 }
 
-impl<'a> Add<&'a ContentLength> for ContentLength {
+impl Add<&ContentLength> for ContentLength {
     type Output = ContentLength;
 
     fn add(self, rhs: &ContentLength) -> ContentLength {
@@ -67,7 +104,7 @@ impl<'a> Add<&'a ContentLength> for ContentLength {
     }
 }
 
-impl<'a> Add<ContentLength> for &'a ContentLength {
+impl Add<ContentLength> for &ContentLength {
     type Output = ContentLength;
 
     fn add(self, rhs: ContentLength) -> ContentLength {
@@ -75,6 +112,47 @@ impl<'a> Add<ContentLength> for &'a ContentLength {
     }
 }
 
+impl AddAssign for ContentLength {
+    fn add_assign(&mut self, rhs: ContentLength) {
+        self.0 += rhs.0;
+    }
+}
+
+impl AddAssign<&ContentLength> for ContentLength {
+    fn add_assign(&mut self, rhs: &ContentLength) {
+        self.0 += rhs.0;
+    }
+}
+
+/// Subtracting a longer length leaves none rather than wrapping around.
+impl Sub for ContentLength {
+    type Output = ContentLength;
+
+    fn sub(self, rhs: ContentLength) -> ContentLength {
+        ContentLength(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Sub for &ContentLength {
+    type Output = ContentLength;
+
+    fn sub(self, rhs: &ContentLength) -> ContentLength {
+        ContentLength(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Sum for ContentLength {
+    fn sum<I: Iterator<Item = ContentLength>>(iter: I) -> ContentLength {
+        iter.fold(ContentLength::zero(), |sum, len| sum + len)
+    }
+}
+
+impl<'a> Sum<&'a ContentLength> for ContentLength {
+    fn sum<I: Iterator<Item = &'a ContentLength>>(iter: I) -> ContentLength {
+        iter.fold(ContentLength::zero(), |sum, len| sum + len)
+    }
+}
+
 #[cfg(test)]
 mod content_length_tests {
     use super::ContentLength;
@@ -87,6 +165,33 @@ mod content_length_tests {
         assert_eq!(&ContentLength(1) + ContentLength(2), ContentLength(3));
     }
 
+    #[test]
+    fn it_can_subtract_accumulate_and_sum_content_lengths() {
+        assert_eq!(ContentLength(3) - ContentLength(2), ContentLength(1));
+        assert_eq!(&ContentLength(3) - &ContentLength(3), ContentLength(0));
+        assert_eq!(ContentLength(2) - ContentLength(3), ContentLength(0));
+        let mut len = ContentLength(1);
+        len += ContentLength(2);
+        len += &ContentLength(3);
+        assert_eq!(len, ContentLength(6));
+        let lens = vec![ContentLength(1), ContentLength(2)];
+        assert_eq!(lens.iter().sum::<ContentLength>(), ContentLength(3));
+        assert_eq!(lens.into_iter().sum::<ContentLength>(), ContentLength(3));
+    }
+
+    #[test]
+    fn it_parses_human_units() {
+        assert_eq!("512".parse(), Ok(ContentLength(512)));
+        assert_eq!("512KB".parse(), Ok(ContentLength(512 * 1024)));
+        assert_eq!("2.5MB".parse(), Ok(ContentLength(2_621_440)));
+        assert_eq!(" 1 gb ".parse(), Ok(ContentLength(1024 * 1024 * 1024)));
+        assert!("MB".parse::<ContentLength>().is_err());
+        assert!("1.2.3KB".parse::<ContentLength>().is_err());
+        assert!("10 parsecs".parse::<ContentLength>().is_err());
+        assert!("99999999999999GB".parse::<ContentLength>().is_err());
+        assert!("99999999999999.5GB".parse::<ContentLength>().is_err());
+    }
+
     #[test]
     fn it_returns_bytes() {
         assert_eq!(ContentLength::new(1).bytes(), 1)
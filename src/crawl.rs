@@ -0,0 +1,190 @@
+use reqwest::Url;
+use sitemap;
+use std::collections::HashSet;
+
+const DEFAULT_DEPTH: usize = 2;
+const DEFAULT_LIMIT: usize = 100;
+
+/// How far to follow the links from the starting pages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crawl {
+    depth: usize,
+    limit: usize,
+}
+
+impl Crawl {
+    /// Parses the options given as `depth=2` and `limit=100`, either can be left out.
+    pub fn parse<'a, I>(specs: I) -> Result<Crawl, String>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut crawl = Crawl {
+            depth: DEFAULT_DEPTH,
+            limit: DEFAULT_LIMIT,
+        };
+        for spec in specs {
+            let mut parts = spec.splitn(2, '=');
+            let value = |value: &str| {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid number in {}", spec))
+            };
+            match (parts.next(), parts.next()) {
+                (Some("depth"), Some(depth)) => crawl.depth = value(depth)?,
+                (Some("limit"), Some(limit)) => crawl.limit = value(limit)?,
+                _ => {
+                    return Err(format!(
+                        "Expected depth=<links> or limit=<pages> but got {}",
+                        spec
+                    ))
+                }
+            }
+        }
+        if crawl.limit == 0 {
+            return Err("The crawl limit must be more than zero".to_string());
+        }
+        Ok(crawl)
+    }
+
+    /// Finds the pages of the same origin as the starting pages by following their links,
+    /// breadth first. Only the starting pages have to be fetched, links that cannot be
+    /// are still benchmarked but not followed.
+    pub fn pages(&self, start: &[String]) -> Result<Vec<String>, String> {
+        let mut seen = HashSet::new();
+        let mut pages = Vec::new();
+        for url in start {
+            if seen.insert(url.clone()) {
+                pages.push(url.clone());
+            }
+        }
+
+        let mut frontier = pages.clone();
+        for depth in 0..self.depth {
+            let mut next = Vec::new();
+            for url in &frontier {
+                let html = match sitemap::fetch(url) {
+                    Ok(html) => html,
+                    Err(e) if depth == 0 => return Err(e),
+                    Err(_) => continue,
+                };
+                for link in links(url, &html) {
+                    if pages.len() >= self.limit {
+                        return Ok(pages);
+                    }
+                    if seen.insert(link.clone()) {
+                        pages.push(link.clone());
+                        next.push(link);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        Ok(pages)
+    }
+}
+
+/// The links in the html that lead to pages of the same origin as the page it came from,
+/// without their fragments.
+fn links(page: &str, html: &str) -> Vec<String> {
+    let base = match Url::parse(page) {
+        Ok(base) => base,
+        Err(_) => return Vec::new(),
+    };
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut from = 0;
+    while let Some(start) = lower[from..].find("<a") {
+        let start = from + start + 2;
+        let end = lower[start..]
+            .find('>')
+            .map_or(lower.len(), |end| start + end);
+        from = end;
+        if !lower[start..].starts_with(|c: char| c.is_whitespace()) {
+            continue;
+        }
+        let href = match attribute(&html[start..end], &lower[start..end], "href") {
+            Some(href) => href.replace("&amp;", "&"),
+            None => continue,
+        };
+        let mut url = match base.join(&href) {
+            Ok(url) => url,
+            Err(_) => continue,
+        };
+        url.set_fragment(None);
+        if url.origin() == base.origin() {
+            links.push(url.to_string());
+        }
+    }
+    links
+}
+
+/// The value of an attribute in a tag, which may be quoted either way or not at all.
+fn attribute<'a>(tag: &'a str, lower: &str, name: &str) -> Option<&'a str> {
+    let mut from = 0;
+    while let Some(at) = lower[from..].find(name) {
+        let at = from + at;
+        from = at + name.len();
+        let preceded = lower[..at].ends_with(|c: char| c.is_whitespace());
+        let rest = lower[from..].trim_start();
+        if !preceded || !rest.starts_with('=') {
+            continue;
+        }
+        let value = tag[tag.len() - rest.len() + 1..].trim_start();
+        return Some(match value.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => {
+                let value = &value[1..];
+                &value[..value.find(quote).unwrap_or(value.len())]
+            }
+            _ => {
+                let end = value
+                    .find(|c: char| c.is_whitespace())
+                    .unwrap_or(value.len());
+                &value[..end]
+            }
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_the_options() {
+        assert_eq!(
+            Crawl::parse(vec!["depth=1", "limit=5"]),
+            Ok(Crawl { depth: 1, limit: 5 })
+        );
+        assert_eq!(
+            Crawl::parse(vec![]),
+            Ok(Crawl {
+                depth: DEFAULT_DEPTH,
+                limit: DEFAULT_LIMIT,
+            })
+        );
+        assert!(Crawl::parse(vec!["depth=two"]).is_err());
+        assert!(Crawl::parse(vec!["limit=0"]).is_err());
+        assert!(Crawl::parse(vec!["width=2"]).is_err());
+    }
+
+    #[test]
+    fn it_finds_links_of_the_same_origin() {
+        let html = r#"<html><body>
+            <a href="/about">About</a>
+            <A class="nav" HREF='blog/one#top'>One</A>
+            <a href=https://example.com/b?x=1&amp;y=2>B</a>
+            <a href="https://elsewhere.com/">Elsewhere</a>
+            <a name="anchor">No link</a>
+            <abbr href="/not-a-link">Abbr</abbr>
+        </body></html>"#;
+        assert_eq!(
+            links("https://example.com/docs/", html),
+            vec![
+                "https://example.com/about",
+                "https://example.com/docs/blog/one",
+                "https://example.com/b?x=1&y=2",
+            ]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use httparse;
+use serde_json::Value;
+use stats::Fact;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long each interval of the charts is.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// The most intervals kept, the oldest making way for new ones.
+const KEPT: usize = 600;
+
+/// The page of the dashboard, which polls `/data` and draws it, needing nothing else.
+const PAGE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>rench</title>
+<style>
+body { font: 14px sans-serif; margin: 2em; color: #222; }
+canvas { border: 1px solid #ddd; display: block; margin: 0.5em 0 1.5em; }
+table { border-collapse: collapse; }
+td { padding: 0.2em 1em 0.2em 0; }
+#state { color: #888; }
+</style>
+</head>
+<body>
+<h1>rench <span id="state">running</span></h1>
+<p><span id="requests">0</span> requests in <span id="elapsed">0</span> s</p>
+<h2>Requests per second</h2>
+<canvas id="rps" width="800" height="200"></canvas>
+<h2>Latency, ms <small>(p50 blue, p99 red)</small></h2>
+<canvas id="latency" width="800" height="200"></canvas>
+<h2>Responses</h2>
+<table id="outcomes"></table>
+<script>
+function draw(id, series) {
+  var canvas = document.getElementById(id), g = canvas.getContext("2d");
+  g.clearRect(0, 0, canvas.width, canvas.height);
+  var all = [].concat.apply([], series.map(function (s) { return s.values; }));
+  var max = Math.max.apply(null, all.concat([1e-9]));
+  g.fillStyle = "#888";
+  g.fillText(max.toFixed(1), 4, 12);
+  series.forEach(function (s) {
+    g.strokeStyle = s.colour;
+    g.beginPath();
+    s.values.forEach(function (v, i) {
+      var x = s.values.length < 2 ? 0 : i * canvas.width / (s.values.length - 1);
+      var y = canvas.height - v / max * (canvas.height - 16);
+      if (i === 0) { g.moveTo(x, y); } else { g.lineTo(x, y); }
+    });
+    g.stroke();
+  });
+}
+function update() {
+  fetch("/data").then(function (r) { return r.json(); }).then(function (d) {
+    document.getElementById("requests").textContent = d.requests;
+    document.getElementById("elapsed").textContent = d.elapsed.toFixed(0);
+    var pick = function (k) { return d.intervals.map(function (i) { return i[k]; }); };
+    draw("rps", [{ values: pick("rps"), colour: "#2a2" }]);
+    draw("latency", [{ values: pick("p50"), colour: "#22a" },
+                     { values: pick("p99"), colour: "#a22" }]);
+    var rows = Object.keys(d.outcomes).map(function (k) {
+      return "<tr><td>" + k + "</td><td>" + d.outcomes[k] + "</td></tr>";
+    });
+    document.getElementById("outcomes").innerHTML = rows.join("");
+    if (d.finished) { document.getElementById("state").textContent = "finished"; }
+    else { setTimeout(update, 1000); }
+  }).catch(function () {
+    document.getElementById("state").textContent = "finished";
+  });
+}
+update();
+</script>
+</body>
+</html>
+"##;
+
+/// A page served while the benchmark runs, charting the throughput and latencies of each
+/// second of it along with how many responses there were of each status.
+#[derive(Clone)]
+pub struct Dashboard {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    started: Instant,
+    since: Instant,
+    window: Vec<Duration>,
+    intervals: Vec<Value>,
+    outcomes: BTreeMap<String, u32>,
+    requests: u32,
+    finished: bool,
+}
+
+impl Dashboard {
+    /// Serves the dashboard at the address, such as `127.0.0.1:8080`, on a thread of its own.
+    pub fn serve(address: &str) -> io::Result<Dashboard> {
+        let listener = TcpListener::bind(address)?;
+        let dashboard = Dashboard::new();
+        let served = dashboard.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                let dashboard = served.clone();
+                // A browser going away mid request is no concern of the run's.
+                thread::spawn(move || dashboard.handle(stream));
+            }
+        });
+        Ok(dashboard)
+    }
+
+    fn new() -> Dashboard {
+        let now = Instant::now();
+        Dashboard {
+            state: Arc::new(Mutex::new(State {
+                started: now,
+                since: now,
+                window: Vec::new(),
+                intervals: Vec::new(),
+                outcomes: BTreeMap::new(),
+                requests: 0,
+                finished: false,
+            })),
+        }
+    }
+
+    /// Counts a request into the interval it finished in.
+    pub fn record(&self, fact: &Fact) {
+        let mut state = self.state.lock().expect("Expected the dashboard lock");
+        if state.since.elapsed() >= INTERVAL {
+            state.close_interval();
+        }
+        state.window.push(fact.duration());
+        *state.outcomes.entry(fact.outcome()).or_insert(0) += 1;
+        state.requests += 1;
+    }
+
+    /// Closes the last interval, and tells the page that no more are coming.
+    pub fn finish(&self) {
+        let mut state = self.state.lock().expect("Expected the dashboard lock");
+        state.close_interval();
+        state.finished = true;
+    }
+
+    /// What the page draws.
+    fn data(&self) -> Value {
+        let state = self.state.lock().expect("Expected the dashboard lock");
+        json!({
+            "elapsed": state.started.elapsed().as_secs_f64(),
+            "requests": state.requests,
+            "intervals": state.intervals,
+            "outcomes": state.outcomes,
+            "finished": state.finished,
+        })
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut buffer = Vec::new();
+        let path = loop {
+            let mut headers = [httparse::EMPTY_HEADER; 64];
+            let mut request = httparse::Request::new(&mut headers);
+            if let httparse::Status::Complete(_) = request
+                .parse(&buffer)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            {
+                break request.path.unwrap_or("/").to_string();
+            }
+            let mut chunk = [0; 4096];
+            let len = stream.read(&mut chunk)?;
+            if len == 0 {
+                return Ok(());
+            }
+            buffer.extend_from_slice(&chunk[..len]);
+        };
+        let (status, content_type, body) = match path.as_str() {
+            "/" => ("200 OK", "text/html; charset=utf-8", PAGE.to_string()),
+            "/data" => ("200 OK", "application/json", self.data().to_string()),
+            _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+             Cache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )
+    }
+}
+
+impl State {
+    /// Turns the requests of the interval so far into a point of the charts.
+    fn close_interval(&mut self) {
+        let elapsed = self.since.elapsed();
+        self.since = Instant::now();
+        let mut window: Vec<Duration> = self.window.drain(..).collect();
+        if window.is_empty() {
+            return;
+        }
+        window.sort();
+        let ms = |percentage: usize| {
+            window[(window.len() - 1) * percentage / 100].as_secs_f64() * 1_000f64
+        };
+        let point = json!({
+            "at": self.started.elapsed().as_secs_f64(),
+            "rps": window.len() as f64 / elapsed.as_secs_f64(),
+            "p50": ms(50),
+            "p99": ms(99),
+        });
+        if self.intervals.len() == KEPT {
+            self.intervals.remove(0);
+        }
+        self.intervals.push(point);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content_length::ContentLength;
+    use failure::Failure;
+
+    #[test]
+    fn it_counts_requests_into_intervals() {
+        let dashboard = Dashboard::new();
+        dashboard.record(&Fact::record(ContentLength::zero(), 200, Duration::from_millis(10)));
+        dashboard.record(&Fact::record(ContentLength::zero(), 200, Duration::from_millis(30)));
+        dashboard.record(&Fact::elapsed(Duration::from_millis(5)).with_failure(Failure::Dns));
+        dashboard.finish();
+        let data = dashboard.data();
+        assert_eq!(data["requests"], 3);
+        assert_eq!(data["finished"], true);
+        assert_eq!(data["outcomes"]["200"], 2);
+        assert_eq!(data["outcomes"]["dns errors"], 1);
+        let intervals = data["intervals"].as_array().unwrap();
+        assert_eq!(intervals.len(), 1);
+        assert_eq!(intervals[0]["p50"], 10f64);
+        assert_eq!(intervals[0]["p99"], 10f64);
+    }
+
+    #[test]
+    fn it_serves_the_page_and_its_data() {
+        let served = Dashboard::new();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            served.handle(stream).unwrap();
+        });
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /data HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\"requests\":0}"));
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Reads a time such as `2024-05-01T13:00:00Z` or `2024-05-01T15:00:00+02:00`, as rfc 3339
+/// writes them, with fractions of a second left out.
+pub fn parse(time: &str) -> Result<SystemTime, String> {
+    let invalid = || format!("Expected a time such as 2024-05-01T13:00:00Z but got {}", time);
+    if time.len() < 20 || !time.is_char_boundary(19) {
+        return Err(invalid());
+    }
+    let (local, offset) = time.split_at(19);
+    let number = |range: ::std::ops::Range<usize>| -> Result<i64, String> {
+        local[range].parse::<i64>().map_err(|_| invalid())
+    };
+    let separators = local.as_bytes();
+    if separators[4] != b'-'
+        || separators[7] != b'-'
+        || !b"Tt ".contains(&separators[10])
+        || separators[13] != b':'
+        || separators[16] != b':'
+    {
+        return Err(invalid());
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return Err(invalid());
+    }
+    // Fractions of a second are dropped, as deadlines need no finer.
+    let offset = offset.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match offset {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match offset.chars().next() {
+                Some('+') => 1,
+                Some('-') => -1,
+                _ => return Err(invalid()),
+            };
+            let (hours, minutes) = offset[1..].split_once(':').ok_or_else(invalid)?;
+            let hours = hours.parse::<i64>().map_err(|_| invalid())?;
+            let minutes = minutes.parse::<i64>().map_err(|_| invalid())?;
+            sign * (hours * 3600 + minutes * 60)
+        }
+    };
+    let seconds =
+        days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    if seconds < 0 {
+        return Err(format!("{} is before 1970", time));
+    }
+    Ok(UNIX_EPOCH + Duration::from_secs(seconds as u64))
+}
+
+/// The instant of a time on the clock, now when it has passed.
+pub fn instant(at: SystemTime) -> Instant {
+    let now = Instant::now();
+    match at.duration_since(SystemTime::now()) {
+        Ok(left) => now + left,
+        Err(_) => now,
+    }
+}
+
+/// Writes a time as rfc 3339 does in utc, to the second, such as `2024-05-01T13:00:00Z`.
+pub fn format(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs() as i64)
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let second = seconds.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        second / 3600,
+        second % 3600 / 60,
+        second % 60
+    )
+}
+
+/// The days since 1970-01-01 of a day of the proleptic gregorian calendar, by Howard
+/// Hinnant's algorithm.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The day of the proleptic gregorian calendar so many days after 1970-01-01, the inverse
+/// of `days_from_civil`.
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = (if days >= 0 { days } else { days - 146_096 }) / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_times() {
+        let since = |time| {
+            parse(time)
+                .unwrap()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        };
+        assert_eq!(since("1970-01-01T00:00:00Z"), 0);
+        assert_eq!(since("2024-05-01T13:00:00Z"), 1_714_568_400);
+        assert_eq!(since("2024-05-01T15:00:00.250+02:00"), 1_714_568_400);
+        assert_eq!(since("2024-02-29 00:00:00z"), 1_709_164_800);
+        assert!(parse("2024-05-01T13:00:00").is_err());
+        assert!(parse("2024-13-01T13:00:00Z").is_err());
+        assert!(parse("tomorrow").is_err());
+    }
+
+    #[test]
+    fn it_formats_times() {
+        for time in &["1970-01-01T00:00:00Z", "2024-02-29T23:59:59Z", "2024-05-01T13:00:00Z"] {
+            assert_eq!(format(parse(time).unwrap()), *time);
+        }
+    }
+}
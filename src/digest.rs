@@ -0,0 +1,260 @@
+use openssl::hash::{hash2, MessageDigest};
+use rand::{self, Rng};
+
+/// How the round trip that fetches a digest challenge is measured.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Handshake {
+    /// The challenge is fetched before the request is timed.
+    Excluded,
+    /// The time taken to fetch the challenge is added to the request that needed it.
+    Counted,
+}
+
+/// Credentials for http digest authentication, as servers ask for with a 401 carrying a
+/// `WWW-Authenticate: Digest` challenge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Digest {
+    user: String,
+    password: String,
+    handshake: Handshake,
+}
+
+impl Digest {
+    /// Parses credentials given as `user:password`.
+    pub fn parse(credentials: &str) -> Result<Digest, String> {
+        match credentials.split_once(':') {
+            Some((user, password)) if !user.is_empty() => Ok(Digest {
+                user: user.to_string(),
+                password: password.to_string(),
+                handshake: Handshake::Excluded,
+            }),
+            _ => Err(format!("Expected user:password but got {}", credentials)),
+        }
+    }
+
+    /// Measures the challenge round trips as the handshake says.
+    pub fn with_handshake(mut self, handshake: Handshake) -> Self {
+        self.handshake = handshake;
+        self
+    }
+
+    /// How the challenge round trips are measured.
+    pub fn handshake(&self) -> Handshake {
+        self.handshake
+    }
+
+    /// A session that answers the challenges of each of the targets.
+    pub fn session(&self, targets: usize) -> Session {
+        Session {
+            digest: self.clone(),
+            challenged: vec![false; targets],
+            challenges: (0..targets).map(|_| None).collect(),
+        }
+    }
+}
+
+/// The challenges a thread has been given, one for each target, along with how many times
+/// each nonce was used.
+pub struct Session {
+    digest: Digest,
+    challenged: Vec<bool>,
+    challenges: Vec<Option<(Challenge, u32)>>,
+}
+
+impl Session {
+    /// Whether the target has yet to be asked for its challenge.
+    pub fn needs_challenge(&self, index: usize) -> bool {
+        !self.challenged[index]
+    }
+
+    /// Takes up the challenge of a `WWW-Authenticate` header, as a target sends with its
+    /// 401s, including when the nonce it was given has gone stale. A target that sends none
+    /// is not asked again.
+    pub fn challenge(&mut self, index: usize, header: Option<&str>) -> Result<(), String> {
+        self.challenged[index] = true;
+        if let Some(header) = header {
+            self.challenges[index] = Some((Challenge::parse(header)?, 0));
+        }
+        Ok(())
+    }
+
+    /// The `Authorization` header for a request to the target, once it has challenged.
+    pub fn authorization(&mut self, index: usize, method: &str, uri: &str) -> Option<String> {
+        let digest = &self.digest;
+        self.challenges[index]
+            .as_mut()
+            .map(|&mut (ref challenge, ref mut count)| {
+                *count += 1;
+                let cnonce = format!("{:016x}", rand::thread_rng().gen::<u64>());
+                challenge.authorization(digest, method, uri, *count, &cnonce)
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    qop: bool,
+    algorithm: String,
+}
+
+impl Challenge {
+    fn parse(header: &str) -> Result<Challenge, String> {
+        let header = header.trim();
+        if header.len() < 7 || !header[..7].eq_ignore_ascii_case("digest ") {
+            return Err(format!("Expected a digest challenge but got {}", header));
+        }
+        let params = params(&header[7..]);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        let algorithm = param("algorithm").unwrap_or_else(|| "MD5".to_string());
+        if algorithm_digest(&algorithm).is_none() {
+            return Err(format!("Unsupported digest algorithm {}", algorithm));
+        }
+        Ok(Challenge {
+            realm: param("realm").unwrap_or_default(),
+            nonce: param("nonce").ok_or("The digest challenge has no nonce")?,
+            opaque: param("opaque"),
+            qop: param("qop").is_some_and(|qop| qop.split(',').any(|qop| qop.trim() == "auth")),
+            algorithm,
+        })
+    }
+
+    fn authorization(
+        &self,
+        digest: &Digest,
+        method: &str,
+        uri: &str,
+        count: u32,
+        cnonce: &str,
+    ) -> String {
+        let hash = |text: String| {
+            let bytes = hash2(
+                algorithm_digest(&self.algorithm).expect("Expected a supported algorithm"),
+                text.as_bytes(),
+            )
+            .expect("Expected the digest to hash");
+            bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        };
+        let mut secret = hash(format!(
+            "{}:{}:{}",
+            digest.user, self.realm, digest.password
+        ));
+        if self.algorithm.to_ascii_lowercase().ends_with("-sess") {
+            secret = hash(format!("{}:{}:{}", secret, self.nonce, cnonce));
+        }
+        let request = hash(format!("{}:{}", method, uri));
+        let nc = format!("{:08x}", count);
+
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}",
+            digest.user, self.realm, self.nonce, uri, self.algorithm
+        );
+        let response = if self.qop {
+            header.push_str(&format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce));
+            hash(format!(
+                "{}:{}:{}:{}:auth:{}",
+                secret, self.nonce, nc, cnonce, request
+            ))
+        } else {
+            hash(format!("{}:{}:{}", secret, self.nonce, request))
+        };
+        header.push_str(&format!(", response=\"{}\"", response));
+        if let Some(ref opaque) = self.opaque {
+            header.push_str(&format!(", opaque=\"{}\"", opaque));
+        }
+        header
+    }
+}
+
+fn algorithm_digest(algorithm: &str) -> Option<MessageDigest> {
+    match algorithm.to_ascii_uppercase().trim_end_matches("-SESS") {
+        "MD5" => Some(MessageDigest::md5()),
+        "SHA-256" => Some(MessageDigest::sha256()),
+        _ => None,
+    }
+}
+
+/// The `name=value` and `name="value"` pairs of a challenge.
+fn params(text: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut rest = text.trim_start_matches([' ', ',']);
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim().to_string();
+        rest = &rest[eq + 1..];
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            let value = quoted[..end].to_string();
+            rest = &quoted[(end + 1).min(quoted.len())..];
+            value
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let value = rest[..end].trim().to_string();
+            rest = &rest[end..];
+            value
+        };
+        params.push((name, value));
+        rest = rest.trim_start_matches([' ', ',']);
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_answers_the_rfc_2617_example() {
+        let challenge = Challenge::parse(
+            "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+             nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+             opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"",
+        )
+        .unwrap();
+        let digest = Digest::parse("Mufasa:Circle Of Life").unwrap();
+        let header = challenge.authorization(&digest, "GET", "/dir/index.html", 1, "0a4f113b");
+        assert!(header.contains("response=\"6629fae49393a05397450978507c4ef1\""));
+        assert!(header.contains("nc=00000001"));
+        assert!(header.ends_with("opaque=\"5ccc069c403ebaf9f0171e9517f40e41\""));
+    }
+
+    #[test]
+    fn it_counts_the_uses_of_a_nonce() {
+        let mut session = Digest::parse("user:pass").unwrap().session(2);
+        assert!(session.needs_challenge(1));
+        assert_eq!(session.authorization(1, "GET", "/"), None);
+        session
+            .challenge(
+                1,
+                Some("Digest realm=r, nonce=n, qop=auth, algorithm=SHA-256"),
+            )
+            .unwrap();
+        session.authorization(1, "GET", "/");
+        assert!(session
+            .authorization(1, "GET", "/")
+            .unwrap()
+            .contains("nc=00000002"));
+        assert!(!session.needs_challenge(1));
+        assert!(session.needs_challenge(0));
+        session.challenge(0, None).unwrap();
+        assert!(!session.needs_challenge(0));
+        assert_eq!(session.authorization(0, "GET", "/"), None);
+    }
+
+    #[test]
+    fn it_rejects_what_it_cannot_answer() {
+        assert!(Digest::parse("nobody").is_err());
+        assert!(Challenge::parse("Basic realm=\"r\"").is_err());
+        assert!(Challenge::parse("Digest realm=\"r\"").is_err());
+        assert!(Challenge::parse("Digest nonce=n, algorithm=SHA-512-256").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+const A: u16 = 1;
+const IN: u16 = 1;
+/// The longest a label may be, as its length has to fit in the six bits left of its byte.
+const MAX_LABEL: usize = 63;
+/// The longest a name may be once encoded, with the length of each label and the root.
+const MAX_NAME: usize = 255;
+
+/// Builds a recursive query for the A records of a name, which fails when the name cannot
+/// be encoded.
+pub fn query(id: u16, name: &str) -> io::Result<Vec<u8>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let mut encoded = Vec::with_capacity(name.len() + 2);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > MAX_LABEL {
+            return Err(invalid(format!("Invalid label {:?} in {}", label, name)));
+        }
+        encoded.push(label.len() as u8);
+        encoded.extend_from_slice(label.as_bytes());
+    }
+    encoded.push(0);
+    if encoded.len() > MAX_NAME {
+        return Err(invalid(format!("Name {} is longer than {} bytes", name, MAX_NAME)));
+    }
+
+    let mut query = Vec::with_capacity(encoded.len() + 16);
+    query.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired with a single question.
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    query.extend_from_slice(&encoded);
+    query.extend_from_slice(&A.to_be_bytes());
+    query.extend_from_slice(&IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Reads the response code from a reply, or none if it does not answer the query.
+pub fn response_code(id: u16, reply: &[u8]) -> Option<u8> {
+    if reply.len() < 12 || reply[..2] != id.to_be_bytes() || reply[2] & 0x80 == 0 {
+        return None;
+    }
+    Some(reply[3] & 0x0f)
+}
+
+/// Asks the resolver for the A records of a name, waiting for the matching reply and
+/// returning its response code.
+pub fn lookup(socket: &UdpSocket, resolver: SocketAddr, id: u16, name: &str) -> io::Result<u8> {
+    socket.send_to(&query(id, name)?, resolver)?;
+    let mut reply = [0; 512];
+    loop {
+        let (len, from) = socket.recv_from(&mut reply)?;
+        if from != resolver {
+            continue;
+        }
+        if let Some(code) = response_code(id, &reply[..len]) {
+            return Ok(code);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_builds_a_query() {
+        assert_eq!(
+            query(0xabcd, "a.io.").unwrap(),
+            vec![0xab, 0xcd, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 1, b'a', 2, b'i', b'o', 0, 0, 1, 0, 1]
+        );
+    }
+
+    #[test]
+    fn it_rejects_names_that_cannot_be_encoded() {
+        let label = "a".repeat(63);
+        assert!(query(1, &format!("{}.io", label)).is_ok());
+        assert!(query(1, &format!("a{}.io", label)).is_err());
+        assert!(query(1, "a..io").is_err());
+
+        // Four labels of 63 bytes take 257 bytes with their lengths and the root.
+        let name = [label.as_str(); 4].join(".");
+        assert!(query(1, &name[2..]).is_ok());
+        assert!(query(1, &name[1..]).is_err());
+    }
+
+    #[test]
+    fn it_reads_the_response_code() {
+        let mut reply = query(7, "a.io").unwrap();
+        assert_eq!(response_code(7, &reply), None);
+
+        reply[2] |= 0x80;
+        reply[3] |= 3;
+        assert_eq!(response_code(7, &reply), Some(3));
+        assert_eq!(response_code(8, &reply), None);
+        assert_eq!(response_code(7, &reply[..4]), None);
+    }
+}
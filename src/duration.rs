@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+/// Parses durations such as `500ms`, `30s`, `5m`, `1h` or `7d`. Plain numbers are seconds.
+pub fn parse(duration: &str) -> Result<Duration, String> {
+    let duration = duration.trim();
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(duration.len());
+    let number: f64 = duration[..split]
+        .parse()
+        .map_err(|_| format!("Invalid duration {}", duration))?;
+    let seconds = match duration[split..].trim() {
+        "ms" => number / 1_000f64,
+        "" | "s" => number,
+        "m" => number * 60f64,
+        "h" => number * 3_600f64,
+        "d" => number * 86_400f64,
+        _ => return Err(format!("Invalid duration {}", duration)),
+    };
+    Ok(Duration::new(
+        seconds.trunc() as u64,
+        (seconds.fract() * 1_000_000_000f64).round() as u32,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_durations() {
+        assert_eq!(parse("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse("2d"), Ok(Duration::from_secs(172_800)));
+        assert!(parse("s").is_err());
+        assert!(parse("5 fortnights").is_err());
+    }
+}
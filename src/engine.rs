@@ -1,6 +1,45 @@
+use address;
+use assertion::HeaderAssertion;
 use bench;
+use cache;
+use connector::{Connection, HttpsConnector, TimedConnector};
+use body_stream::BodyStream;
+use dns;
+use failure::{self, Failure};
+use graphql;
+use grpc;
+use hyper::header::Headers;
+use jwt::Jwt;
+use multipart::Form;
+use mix::Operation;
+use multiplex::{self, Pool};
+use ntlm;
+use oversize;
+use pause;
+use session::Sessions;
+use oauth2::OAuth2;
+use plugin::{self, Plugin};
+use pool;
+use protocol::{self, Iterations, Protocol, Target};
+use proxy::{Proxies, Proxy};
+use ramp::RampDown;
+use signature::Signature;
+use sigv4::SigV4;
+use socket;
+use status::StatusCheck;
+use sse;
 use stats::Fact;
+use template::Template;
+use timeout;
+use tls;
+use tungstenite::{self, WebSocket};
 use content_length::ContentLength;
+use digest::{self, Digest, Handshake};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime};
 
 /// The engine of making requests. The engine implements making the requests and producing
 /// facts for the stats collector to process.
@@ -8,32 +47,164 @@ use content_length::ContentLength;
 pub struct Engine {
     urls: Vec<String>,
     method: Method,
+    version: Option<Version>,
     kind: Kind,
+    message: String,
+    call: Option<grpc::Call>,
+    body: Option<Body>,
+    form: Option<Form>,
+    body_stream: Option<BodyStream>,
+    expect_continue: bool,
+    requests_per_conn: Option<usize>,
+    pool_max_idle: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+    socket: socket::Options,
+    graphql: bool,
+    resolver: Option<SocketAddr>,
+    group_by_header: Option<String>,
+    group_by_url: bool,
+    plugin: Option<Plugin>,
+    body_template: Option<Template>,
+    url_templates: Option<Vec<Template>>,
+    headers: Vec<(String, Template)>,
+    digest: Option<Digest>,
+    negotiate: Option<ntlm::Credentials>,
+    oauth2: Option<OAuth2>,
+    sigv4: Option<SigV4>,
+    signature: Option<Signature>,
+    jwt: Option<Jwt>,
+    proxies: Proxies,
+    tls: tls::Options,
+    h2: multiplex::Settings,
+    h2_pool: Pool,
+    mix: Option<Vec<(Method, u32)>>,
+    sessions: Option<Sessions>,
+    first_user: usize,
+    iterations: bool,
+    ramp_down: Option<RampDown>,
+    deadline: Option<Instant>,
+    max_response_size: Option<u64>,
+    timeout: Option<Duration>,
+    status_check: Option<StatusCheck>,
+    header_assertions: Vec<HeaderAssertion>,
 }
 
-/// The methods that are supported by the current implementations. These are currently
-/// body-less methods so that we don't need to load up any additional content.
-#[derive(Clone, Copy)]
+/// The methods that are supported by the current implementations. Only post requests
+/// carry the body the engine was given.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Method {
     Get,
     Head,
+    Post,
+    Delete,
+}
+
+impl Method {
+    /// The name requests are made with.
+    pub fn name(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Delete => "DELETE",
+        }
+    }
 }
-const DEFAULT_METHOD: Method = Method::Get;
 
+/// The http versions requests can be forced to use.
 #[derive(Clone, Copy)]
+pub enum Version {
+    Http10,
+    Http11,
+}
+
+/// A request body which is sent as is with every request.
+#[derive(Clone)]
+struct Body {
+    content_type: String,
+    bytes: Vec<u8>,
+}
+const DEFAULT_METHOD: Method = Method::Get;
+
+#[derive(Clone, Copy, PartialEq)]
 enum Kind {
     Reqwest,
     Hyper,
+    Http2,
+    WebSocket,
+    Grpc,
+    Sse,
+    Connect,
+    TlsHandshake,
+    Dns,
 }
 const DEFAULT_KIND: Kind = Kind::Reqwest;
 
+/// How much of a body is shown when describing the requests.
+const PREVIEW: usize = 200;
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Reqwest => "reqwest",
+            Kind::Hyper => "hyper",
+            Kind::Http2 => "http/2",
+            Kind::WebSocket => "websocket",
+            Kind::Grpc => "grpc",
+            Kind::Sse => "server sent events",
+            Kind::Connect => "tcp connect",
+            Kind::TlsHandshake => "tls handshake",
+            Kind::Dns => "dns",
+        }
+    }
+}
+
 impl Engine {
     /// Creates a new engine. The engine will default to using `reqwest`
     pub fn new(urls: Vec<String>) -> Engine {
         Engine {
             urls,
             method: DEFAULT_METHOD,
+            version: None,
             kind: DEFAULT_KIND,
+            message: String::new(),
+            call: None,
+            body: None,
+            form: None,
+            body_stream: None,
+            expect_continue: false,
+            requests_per_conn: None,
+            pool_max_idle: None,
+            pool_idle_timeout: None,
+            socket: socket::Options::default(),
+            graphql: false,
+            resolver: None,
+            group_by_header: None,
+            group_by_url: false,
+            plugin: None,
+            body_template: None,
+            url_templates: None,
+            headers: Vec::new(),
+            digest: None,
+            negotiate: None,
+            oauth2: None,
+            sigv4: None,
+            signature: None,
+            jwt: None,
+            proxies: Proxies::default(),
+            tls: tls::Options::default(),
+            h2: multiplex::Settings::default(),
+            h2_pool: Pool::default(),
+            mix: None,
+            sessions: None,
+            first_user: 0,
+            iterations: false,
+            ramp_down: None,
+            deadline: None,
+            max_response_size: None,
+            timeout: None,
+            status_check: None,
+            header_assertions: Vec::new(),
         }
     }
 
@@ -43,110 +214,2209 @@ impl Engine {
         self
     }
 
+    /// Forces the http version of every request. Only the hyper engine can choose one.
+    pub fn with_version(mut self, version: Version) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Sets the body sent with post requests along with its content type
+    pub fn with_body(mut self, bytes: Vec<u8>, content_type: &str) -> Self {
+        self.body = Some(Body {
+            content_type: content_type.to_string(),
+            bytes,
+        });
+        self
+    }
+
+    /// Posts a body rendered from the template for every request, for the hyper and
+    /// reqwest engines.
+    pub fn with_body_template(mut self, template: Template) -> Self {
+        self.body_template = Some(template);
+        self.with_method(Method::Post)
+    }
+
+    /// Renders each url afresh for every request from its template, given in the order of
+    /// the urls, for the hyper and reqwest engines.
+    pub fn with_url_templates(mut self, templates: Vec<Template>) -> Self {
+        self.url_templates = Some(templates);
+        self
+    }
+
+    /// Sends the header with every request, its value rendered from the template, for the
+    /// hyper and reqwest engines.
+    pub fn with_header(mut self, name: String, value: Template) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Answers the digest challenges of the urls, for the hyper and reqwest engines.
+    pub fn with_digest(mut self, digest: Digest) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// Authenticates each connection with an ntlm handshake, for the hyper and reqwest
+    /// engines. The handshakes are reported apart from the requests.
+    pub fn with_negotiate(mut self, credentials: ntlm::Credentials) -> Self {
+        self.negotiate = Some(credentials);
+        self
+    }
+
+    /// Authorizes every request with the grant's bearer token, for the hyper and reqwest
+    /// engines. A 401 drops the token so that the next request fetches another.
+    pub fn with_oauth2(mut self, oauth2: OAuth2) -> Self {
+        self.oauth2 = Some(oauth2);
+        self
+    }
+
+    /// Signs each request with aws signature version 4.
+    pub fn with_aws_sigv4(mut self, sigv4: SigV4) -> Self {
+        self.sigv4 = Some(sigv4);
+        self
+    }
+
+    /// Sends an hmac of each request in a header.
+    pub fn with_signature(mut self, signature: Signature) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+
+    /// Sends a freshly minted json web token with each request.
+    pub fn with_jwt(mut self, jwt: Jwt) -> Self {
+        self.jwt = Some(jwt);
+        self
+    }
+
+    /// Sends the requests through the http proxies.
+    pub fn with_proxies(mut self, proxies: Proxies) -> Self {
+        self.proxies = proxies;
+        self
+    }
+
+    /// Sets how connections are secured, such as the tls versions they may negotiate.
+    pub fn with_tls(mut self, tls: tls::Options) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Makes the requests of the mix in proportion to their weights, in place of the urls
+    /// the engine was given.
+    pub fn with_mix(mut self, mix: &[Operation]) -> Self {
+        self.urls = mix.iter().map(|operation| operation.url.clone()).collect();
+        self.mix = Some(
+            mix.iter()
+                .map(|operation| (operation.method, operation.weight))
+                .collect(),
+        );
+        self
+    }
+
+    /// The names of the requests the engine makes, the urls with the method when they are
+    /// a mix.
+    pub fn target_names(&self) -> Vec<String> {
+        match self.mix {
+            Some(ref mix) => mix
+                .iter()
+                .zip(&self.urls)
+                .map(|(&(method, _), url)| format!("{} {}", method.name(), url))
+                .collect(),
+            None => self.urls.clone(),
+        }
+    }
+
+    /// The method of the requests to the target.
+    fn method(&self, index: usize) -> Method {
+        match self.mix {
+            Some(ref mix) => mix[index].0,
+            None => self.method,
+        }
+    }
+
+    /// How the requests are shared between the targets.
+    fn weights(&self) -> Vec<u32> {
+        match self.mix {
+            Some(ref mix) => mix.iter().map(|&(_, weight)| weight).collect(),
+            None => vec![1; self.urls.len()],
+        }
+    }
+
+    /// The proxies the requests to the urls go through, each named once.
+    pub fn proxies_used(&self) -> Vec<String> {
+        let mut used: Vec<String> = Vec::new();
+        for proxy in self.urls.iter().filter_map(|url| self.proxies.for_url(url)) {
+            if !used.iter().any(|url| url == proxy.url()) {
+                used.push(proxy.url().to_string());
+            }
+        }
+        used
+    }
+
+    /// The settings of the engine that say what its requests are, as names and values for the
+    /// configuration printed at the start of a run.
+    pub fn configuration(&self) -> Vec<(&'static str, String)> {
+        let names = self.target_names();
+        let targets = if names.len() > 3 {
+            format!("{} and {} more", names[..3].join(", "), names.len() - 3)
+        } else {
+            names.join(", ")
+        };
+        let method = match self.mix {
+            Some(_) => "mixed".to_string(),
+            None => self.method.name().to_string(),
+        };
+        let timeout = match (self.timeout, self.kind) {
+            (Some(timeout), _) => format!("{} ms", timeout.as_millis()),
+            (None, Kind::Reqwest) => "30000 ms, reqwest's default".to_string(),
+            (None, _) => "none".to_string(),
+        };
+        vec![
+            ("Targets", targets),
+            ("Method", method),
+            ("Engine", self.kind.name().to_string()),
+            ("Timeout", timeout),
+            ("TLS", self.tls.describe()),
+        ]
+    }
+
+    /// What the requests of a run of so many would be, as lines to print before running it:
+    /// the engine, the targets with the share of the requests each gets, the headers and the
+    /// body, rendered as they would be for the first request.
+    pub fn describe(&self, requests: usize) -> Vec<String> {
+        let mut lines = vec![format!("Engine:  {}", self.kind.name())];
+        if self.mix.is_none() {
+            lines.push(format!("Method:  {}", self.method.name()));
+        }
+        lines.push("Targets:".to_string());
+        let weights = self.weights();
+        let total: u32 = weights.iter().sum();
+        for (name, weight) in self.target_names().iter().zip(weights) {
+            let share = requests as f64 * weight as f64 / total.max(1) as f64;
+            lines.push(format!("  {} (~{:.0} requests)", name, share));
+        }
+        let url = self.urls.first().map(String::as_str).unwrap_or("");
+        let render = |template: &Template| {
+            template
+                .render(0, url)
+                .unwrap_or_else(|e| format!("<could not render: {}>", e))
+        };
+        if !self.headers.is_empty() {
+            lines.push("Headers:".to_string());
+            for (name, value) in &self.headers {
+                lines.push(format!("  {}: {}", name, render(value)));
+            }
+        }
+        let posts = match self.mix {
+            Some(ref mix) => mix.iter().any(|&(method, _)| method == Method::Post),
+            None => self.method == Method::Post,
+        };
+        let body = match (self.kind, posts) {
+            (Kind::WebSocket, _) => Some(("text".to_string(), self.message.clone().into_bytes())),
+            (_, false) => None,
+            _ => match (&self.body_template, &self.body, &self.form, &self.body_stream) {
+                (Some(template), _, _, _) => {
+                    Some((template.content_type().to_string(), render(template).into_bytes()))
+                }
+                (_, Some(body), _, _) => Some((body.content_type.clone(), body.bytes.clone())),
+                (_, _, Some(form), _) => {
+                    lines.push(format!("Body:    a form, as {}", form.content_type()));
+                    None
+                }
+                (_, _, _, Some(stream)) => {
+                    lines.push(format!(
+                        "Body:    {} streamed in chunks of {}",
+                        ContentLength::new(stream.size()),
+                        ContentLength::new(stream.chunk() as u64)
+                    ));
+                    None
+                }
+                _ => None,
+            },
+        };
+        if let Some((content_type, bytes)) = body {
+            lines.push(format!(
+                "Body:    {} of {}",
+                ContentLength::new(bytes.len() as u64),
+                content_type
+            ));
+            let preview = String::from_utf8_lossy(&bytes[..bytes.len().min(PREVIEW)]);
+            let more = if bytes.len() > PREVIEW { "..." } else { "" };
+            lines.push(format!("  {}{}", preview, more));
+            if self.kind != Kind::WebSocket {
+                let sent = bytes.len() as u64 * requests as u64;
+                lines.push(format!("Upload:  ~{} in all", ContentLength::new(sent)));
+            }
+        }
+        lines
+    }
+
+    /// Posts the form with every request, reading any files in it as the request is sent.
+    pub fn with_form(mut self, form: Form) -> Self {
+        self.form = Some(form);
+        self.with_method(Method::Post)
+    }
+
+    /// Posts a generated body with every request, sent with chunked transfer encoding.
+    pub fn with_body_stream(mut self, body_stream: BodyStream) -> Self {
+        self.body_stream = Some(body_stream);
+        self.with_method(Method::Post)
+    }
+
+    /// Sends each body only once the server answers `Expect: 100-continue`, timing the wait
+    /// as its own phase. Requests are posted over http/1.1 whichever http engine is used.
+    pub fn with_expect_continue(mut self) -> Self {
+        self.expect_continue = true;
+        self.with_method(Method::Post)
+    }
+
+    /// Closes each http connection after it has carried the given number of requests.
+    pub fn with_requests_per_conn(mut self, requests: usize) -> Self {
+        self.requests_per_conn = Some(requests);
+        self
+    }
+
+    /// Limits the idle connections the hyper engine keeps for reuse on each thread, none
+    /// disabling reuse.
+    pub fn with_pool_max_idle(mut self, max_idle: usize) -> Self {
+        self.pool_max_idle = Some(max_idle);
+        self
+    }
+
+    /// Closes connections the hyper engine has left idle for longer than the timeout.
+    pub fn with_pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Groups the facts by the value of a response header, for the hyper and reqwest engines.
+    pub fn with_group_by_header(mut self, header: String) -> Self {
+        self.group_by_header = Some(header);
+        self
+    }
+
+    /// Groups the facts by the url they requested, for the hyper and reqwest engines.
+    pub fn with_group_by_url(mut self) -> Self {
+        self.group_by_url = true;
+        self
+    }
+
+    /// Runs the plugin's hooks around each request, for the hyper and reqwest engines.
+    pub fn with_plugin(mut self, plugin: Plugin) -> Self {
+        self.plugin = Some(plugin);
+        self
+    }
+
+    /// Sets up every socket the engine opens with the options.
+    pub fn with_socket_options(mut self, options: socket::Options) -> Self {
+        self.socket = options;
+        self
+    }
+
+    /// Posts the graphql request body and counts responses that carry errors as failures
+    pub fn with_graphql(self, body: String) -> Self {
+        let mut eng = self
+            .with_method(Method::Post)
+            .with_body(body.into_bytes(), "application/json");
+        eng.graphql = true;
+        eng
+    }
+
     /// Sets the engine to be a hyper engine
     pub fn with_hyper(mut self) -> Self {
         self.kind = Kind::Hyper;
         self
     }
 
+    /// Sets the engine to make its requests over http/2, which servers of https urls are
+    /// offered by alpn. Those that choose http/1.1, and cleartext urls unless the servers are
+    /// known to speak http/2, are left to hyper.
+    pub fn with_http2(mut self) -> Self {
+        self.kind = Kind::Http2;
+        self
+    }
+
+    /// Sets how requests are multiplexed over the http/2 connections.
+    pub fn with_h2(mut self, h2: multiplex::Settings) -> Self {
+        self.h2 = h2;
+        self
+    }
+
+    /// Gives each thread a session of its own that its requests keep to, for the hyper,
+    /// reqwest and http/2 engines.
+    pub fn with_sessions(mut self, sessions: Sessions) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Numbers the virtual users from the first on, as those of a child process come after
+    /// the users of the processes before it.
+    pub fn with_first_user(mut self, first: usize) -> Self {
+        self.first_user = first;
+        self
+    }
+
+    /// Times the iterations of the virtual users, each going through the targets once in
+    /// turn, or through the weights of a mix once.
+    pub fn with_iterations(mut self) -> Self {
+        self.iterations = true;
+        self
+    }
+
+    /// Ramps the virtual users down once the first is through its iterations, leaving the
+    /// others only to finish the iteration they are on.
+    pub fn with_ramp_down(mut self, ramp_down: RampDown) -> Self {
+        self.ramp_down = Some(ramp_down);
+        self
+    }
+
+    /// Starts no requests once the deadline has passed.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stops reading response bodies larger than so many bytes, counting them as failures,
+    /// for the reqwest, hyper and http/2 engines.
+    pub fn with_max_response_size(mut self, max: u64) -> Self {
+        self.max_response_size = Some(max);
+        self
+    }
+
+    /// Gives up on requests that have not been answered within the timeout, counting them as
+    /// timeouts, for the reqwest, hyper and http/2 engines.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Counts responses as failures by their status, for the reqwest, hyper and http/2
+    /// engines.
+    pub fn with_status_check(mut self, check: StatusCheck) -> Self {
+        self.status_check = Some(check);
+        self
+    }
+
+    /// Checks a header of every response, counting those it does not hold for as failures,
+    /// for the reqwest, hyper and http/2 engines.
+    pub fn with_header_assertion(mut self, assertion: HeaderAssertion) -> Self {
+        self.header_assertions.push(assertion);
+        self
+    }
+
+    /// How many requests make up an iteration.
+    pub fn iteration_len(&self) -> usize {
+        self.weights().iter().map(|&weight| weight as usize).sum()
+    }
+
+    /// Makes the engine that of the virtual user run by the nth thread, which its templates
+    /// render for.
+    pub fn with_user(mut self, worker: usize) -> Self {
+        let user = self.first_user + worker;
+        self.body_template = self.body_template.map(|template| template.with_user(user));
+        self.url_templates = self.url_templates.map(|templates| {
+            templates
+                .into_iter()
+                .map(|template| template.with_user(user))
+                .collect()
+        });
+        self.headers = self
+            .headers
+            .into_iter()
+            .map(|(name, value)| (name, value.with_user(user)))
+            .collect();
+        self
+    }
+
+    /// Sets the engine to be a websocket engine. Every url gets one connection per worker
+    /// and each request is a round trip of the message to the server and back.
+    pub fn with_websocket(mut self, message: String) -> Self {
+        self.kind = Kind::WebSocket;
+        self.message = message;
+        self
+    }
+
+    /// Sets the engine to be a grpc engine. The urls are `host:port` pairs that each get
+    /// a plaintext http/2 connection per worker which the unary call is made over.
+    pub fn with_grpc(mut self, call: grpc::Call) -> Self {
+        self.kind = Kind::Grpc;
+        self.call = Some(call);
+        self
+    }
+
+    /// Sets the engine to be a server sent events engine. Every url gets one stream per
+    /// worker and each event received is a request, timed from the event before it. The
+    /// first event is timed from opening the stream and is reported as its setup time.
+    pub fn with_sse(mut self) -> Self {
+        self.kind = Kind::Sse;
+        self
+    }
+
+    /// Sets the engine to only open tcp connections. Each request is a connection to the
+    /// host and port of a url which is closed as soon as it is established.
+    pub fn with_connect(mut self) -> Self {
+        self.kind = Kind::Connect;
+        self
+    }
+
+    /// Sets the engine to only complete tls handshakes. A new connection is opened for each
+    /// request, resuming the last session with the url when the server allows it.
+    pub fn with_tls_handshake(mut self) -> Self {
+        self.kind = Kind::TlsHandshake;
+        self
+    }
+
+    /// Sets the engine to only resolve the host of each url. Names are resolved the way the
+    /// system would unless a resolver is given to query directly.
+    pub fn with_dns(mut self, resolver: Option<SocketAddr>) -> Self {
+        self.kind = Kind::Dns;
+        self.resolver = resolver;
+        self
+    }
+
     /// Consumes self to start up the engine and begins making requests. It will callback
     /// to the collector to allow the caller to capture requests.
-    pub fn run<F>(self, requests: usize, collect: F)
+    pub fn run<F>(self, requests: usize, mut collect: F)
     where
         F: FnMut(Fact),
     {
+        let iterations = RefCell::new(if self.iterations {
+            Some(Iterations::new(self.iteration_len()))
+        } else {
+            None
+        });
+        let collect = |fact: Fact| {
+            let fact = match *iterations.borrow_mut() {
+                Some(ref mut iterations) => iterations.record(fact),
+                None => fact,
+            };
+            collect(match self.ramp_down {
+                Some(ref ramp) if ramp.is_ramping_down() => fact.with_ramping_down(),
+                _ => fact,
+            })
+        };
+        let weights = self.weights();
+        let targets = &weights[..];
+        let more = || {
+            pause::wait();
+            if !(self.keeps_going(&iterations.borrow()) && self.take_row()) {
+                return false;
+            }
+            if let Some(ref mut iterations) = *iterations.borrow_mut() {
+                iterations.begin();
+            }
+            true
+        };
         match self.kind {
-            Kind::Reqwest => self.run_reqwest(requests, collect),
-            Kind::Hyper => self.run_hyper(requests, collect),
+            Kind::Reqwest | Kind::Hyper if self.expect_continue => {
+                protocol::run(self.expect_continue(), requests, targets, more, collect)
+            }
+            Kind::Reqwest => protocol::run(self.reqwest(), requests, targets, more, collect),
+            Kind::Hyper => protocol::run(self.hyper(), requests, targets, more, collect),
+            Kind::Http2 => protocol::run(self.http2(), requests, targets, more, collect),
+            Kind::WebSocket => protocol::run(self.websocket(), requests, targets, more, collect),
+            Kind::Grpc => protocol::run(self.grpc(), requests, targets, more, collect),
+            // Events arrive on whichever stream has one next rather than in turn.
+            Kind::Sse => self.run_sse(requests, more, collect),
+            Kind::Connect => protocol::run(self.connect(), requests, targets, more, collect),
+            Kind::TlsHandshake => {
+                protocol::run(self.tls_handshake(), requests, targets, more, collect)
+            }
+            Kind::Dns => protocol::run(self.dns(), requests, targets, more, collect),
         };
+        if let Some(ref ramp) = self.ramp_down {
+            ramp.begin();
+        }
     }
 
-    fn run_reqwest<F>(&self, requests: usize, mut collect: F)
-    where
-        F: FnMut(Fact),
-    {
+    /// Whether the virtual user goes on to its next request, which it only does before the
+    /// deadline, and while the run ramps down to finish the iteration it is on before the
+    /// window runs out.
+    fn keeps_going(&self, iterations: &Option<Iterations>) -> bool {
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return false;
+        }
+        match self.ramp_down {
+            Some(ref ramp) if ramp.is_ramping_down() => {
+                let between = iterations.as_ref().is_none_or(Iterations::is_between);
+                !between && !ramp.is_over()
+            }
+            _ => true,
+        }
+    }
+
+    fn reqwest(&self) -> impl Protocol + '_ {
         use reqwest::{self, Client, Request};
-        let client = Client::new();
+        let mut builder = Client::builder();
+        if !self.proxies.is_empty() {
+            let proxies = self.proxies.clone();
+            builder.proxy(reqwest::Proxy::custom(move |url| {
+                proxies
+                    .for_url(url.as_str())
+                    .map(|proxy| proxy.url().parse().expect("Expected a valid proxy"))
+            }));
+        }
+        // Reqwest gives up after 30 seconds unless told otherwise.
+        if let Some(timeout) = self.timeout {
+            builder.timeout(timeout);
+        }
+        let client = builder.build().expect("Expected a client for the options");
 
-        let method = match self.method {
-            Method::Get => reqwest::Method::Get,
-            Method::Head => reqwest::Method::Head,
-        };
+        let mut plugin = self.instantiate_plugin();
+        let mut session = self.digest.as_ref().map(|digest| digest.session(self.urls.len()));
+        let mut negotiation = self
+            .negotiate
+            .as_ref()
+            .map(|credentials| credentials.session(self.urls.len()));
+        let mut sticky = self.sessions.as_ref().map(Sessions::start);
 
-        for n in 0..requests {
-            let url = &self.urls[n % self.urls.len()];
+        move |target: Target| {
+            let url = self.url(target);
+            let method = self.method(target.index);
 
-            let request = Request::new(method.clone(), url.parse().expect("Invalid url"));
-            let mut len = 0;
+            let request_method = match method {
+                Method::Get => reqwest::Method::Get,
+                Method::Head => reqwest::Method::Head,
+                Method::Post => reqwest::Method::Post,
+                Method::Delete => reqwest::Method::Delete,
+            };
+            let mut request =
+                Request::new(request_method.clone(), url.parse().expect("Invalid url"));
+            if self.closes_connection(target.n) {
+                request
+                    .headers_mut()
+                    .set(reqwest::header::Connection::close());
+            }
+            self.add_headers(target.n, &url, request.headers_mut());
+            if let Some(ref sticky) = sticky {
+                sticky.add_to(&url, request.headers_mut());
+            }
+            self.add_proxy_authorization(&url, request.headers_mut());
+            let body = self.body(target.n, &url);
+            let mut sent = 0;
+            if let (Method::Post, Some(body)) = (method, body.as_deref()) {
+                request
+                    .headers_mut()
+                    .set_raw("Content-Type", body.content_type.clone());
+                *request.body_mut() = Some(body.bytes.clone().into());
+                sent = body.bytes.len() as u64;
+            } else if let (Method::Post, Some(form)) = (method, self.form.as_ref()) {
+                let (reader, len) = form.open().expect("Expected readable form files");
+                request
+                    .headers_mut()
+                    .set_raw("Content-Type", form.content_type());
+                *request.body_mut() = Some(reqwest::Body::sized(reader, len));
+                sent = len;
+            } else if let (Method::Post, Some(stream)) = (method, self.body_stream) {
+                request
+                    .headers_mut()
+                    .set_raw("Content-Type", "application/octet-stream");
+                *request.body_mut() = Some(reqwest::Body::new(stream.reader()));
+                sent = stream.size();
+            }
+            if let Some(ref mut plugin) = plugin {
+                self.before_request(plugin, method, &url, body.as_deref(), request.headers_mut());
+            }
+            self.sign(method, &url, body.as_deref(), request.headers_mut());
+            let challenged = match session {
+                Some(ref mut session) => {
+                    let challenged =
+                        self.authorize(session, target, &url, request.headers_mut(), || {
+                            let url = url.parse().expect("Invalid url");
+                            let request = Request::new(request_method.clone(), url);
+                            let resp = client
+                                .execute(request)
+                                .map_err(|ref e| failure::of_reqwest(e))?;
+                            Ok(challenge_of(resp.headers()))
+                        });
+                    match challenged {
+                        Ok(challenged) => challenged,
+                        Err(fact) => return *fact,
+                    }
+                }
+                None => Duration::new(0, 0),
+            };
+            let negotiated = match negotiation {
+                Some(ref mut negotiation) => {
+                    let negotiated = negotiate(negotiation, target, request.headers_mut(), |token| {
+                        let mut request =
+                            Request::new(request_method.clone(), url.parse().expect("Invalid url"));
+                        request.headers_mut().set_raw("Authorization", token);
+                        let mut resp = client
+                            .execute(request)
+                            .map_err(|ref e| failure::of_reqwest(e))?;
+                        // Read to the end so that the connection can carry the next request.
+                        let _ = resp.text();
+                        Ok(negotiate_challenge(resp.headers()))
+                    });
+                    match negotiated {
+                        Ok(negotiated) => negotiated,
+                        Err(fact) => return *fact,
+                    }
+                }
+                None => None,
+            };
+            let mut text = String::new();
+            let mut failure = None;
             let (resp, duration) = bench::time_it(|| {
-                let mut resp = client
-                    .execute(request)
-                    .expect("Failure to even connect is no good");
-                if let Ok(body) = resp.text() {
-                    len = body.len();
+                let mut resp = client.execute(request)?;
+                let body = match self.max_response_size {
+                    Some(max) => match oversize::read_at_most(&mut resp, max) {
+                        Ok(body) => Some(String::from_utf8_lossy(&body).into_owned()),
+                        Err(ref e) => {
+                            failure = Some(failure::of_io(e));
+                            None
+                        }
+                    },
+                    None => match resp.text() {
+                        Ok(body) => Some(body),
+                        Err(ref e) => {
+                            failure = Some(failure::of_reqwest(e));
+                            None
+                        }
+                    },
+                };
+                if let Some(body) = body {
+                    if self.graphql && graphql::has_errors(body.as_bytes()) {
+                        failure = Some(Failure::GraphQl);
+                    }
+                    text = body;
                 }
-                resp
+                Ok(resp)
             });
+            let duration = duration + challenged;
+            let resp = match resp {
+                Ok(resp) => resp,
+                Err(ref e) => return Fact::elapsed(duration).with_failure(failure::of_reqwest(e)),
+            };
+            if let Some(ref mut sticky) = sticky {
+                sticky.store(&url, resp.headers().get_raw("Set-Cookie").into_iter().flatten());
+            }
+            failure = self.proxy_failure(resp.status().as_u16(), failure);
+            failure = self.status_failure(resp.status().as_u16(), failure);
+            failure = self.header_failure(|name| first_value(resp.headers(), name), failure);
+            if let (Some(session), 401) = (session.as_mut(), resp.status().as_u16()) {
+                renew(session, target, resp.headers());
+            }
+            if let (Some(oauth2), 401) = (self.oauth2.as_ref(), resp.status().as_u16()) {
+                oauth2.expire();
+            }
+            // A new connection has to be authenticated afresh.
+            if let Some(ref mut negotiation) = negotiation {
+                if resp.status().as_u16() == 401 || self.closes_connection(target.n) {
+                    negotiation.set_authenticated(target.index, false);
+                }
+            }
+            if let Some(ref mut plugin) = plugin {
+                let status = resp.status().as_u16();
+                failure = after_response(plugin, status, resp.headers(), text.as_bytes(), failure);
+            }
 
-            collect(Fact::record(
-                ContentLength::new(len as u64),
+            let fact = Fact::record(
+                ContentLength::new(text.len() as u64),
                 resp.status().as_u16(),
                 duration,
-            ));
+            ).with_sent(ContentLength::new(sent));
+            let fact = match negotiated {
+                Some(negotiated) => fact.with_authentication(negotiated),
+                None => fact,
+            };
+            let fact = match cache_hit(resp.headers()) {
+                Some(hit) => fact.with_cache_hit(hit),
+                None => fact,
+            };
+            let fact = match self.group_by_header {
+                Some(ref header) => {
+                    let value = resp.headers().get_raw(header).and_then(|raw| raw.one());
+                    fact.with_group(group_of(value))
+                }
+                None if self.group_by_url => fact.with_group(self.urls[target.index].clone()),
+                None => fact,
+            };
+            match failure {
+                Some(failure) => fact.with_failure(failure),
+                None => fact,
+            }
+        }
+    }
+
+    fn hyper(&self) -> impl Protocol + '_ {
+        use hyper::{self, Client, Request, Uri};
+        use tokio_core::reactor::Core;
+        use futures::{Future, Stream};
+
+        let mut core = Core::new().expect("Setting up tokio core failed");
+        let handle = core.handle();
+        let connector = TimedConnector::new(self.https_connector(&handle, &[]));
+        let connected = connector.connected();
+        let mut config = Client::configure()
+            .connector(connector)
+            .keep_alive(self.pool_max_idle != Some(0));
+        if let Some(timeout) = self.pool_idle_timeout {
+            config = config.keep_alive_timeout(Some(timeout));
+        }
+        let client = config.build(&handle);
+        let mut idle = self.pool_max_idle.map(pool::IdleLimit::new);
+
+        let urls: Vec<Uri> = match self.url_templates {
+            Some(_) => Vec::new(),
+            None => self.urls.iter().map(|url| url.parse().unwrap()).collect(),
+        };
+
+        let graphql = self.graphql;
+        let max_response_size = self.max_response_size;
+        let group_by_header = self.group_by_header.clone();
+        let mut plugin = self.instantiate_plugin();
+        let mut session = self.digest.as_ref().map(|digest| digest.session(self.urls.len()));
+        let mut negotiation = self
+            .negotiate
+            .as_ref()
+            .map(|credentials| credentials.session(self.urls.len()));
+        let mut sticky = self.sessions.as_ref().map(Sessions::start);
+
+        move |target: Target| {
+            let uri: Uri = match self.url_templates {
+                Some(_) => self
+                    .url(target)
+                    .parse()
+                    .expect("Expected the url template to render a valid url"),
+                None => urls[target.index].clone(),
+            };
+            let method = self.method(target.index);
+            let request_method = match method {
+                Method::Get => hyper::Method::Get,
+                Method::Head => hyper::Method::Head,
+                Method::Post => hyper::Method::Post,
+                Method::Delete => hyper::Method::Delete,
+            };
+            let mut request = Request::new(request_method.clone(), uri.clone());
+            match self.version {
+                Some(Version::Http10) => request.set_version(hyper::HttpVersion::Http10),
+                Some(Version::Http11) => request.set_version(hyper::HttpVersion::Http11),
+                None => (),
+            }
+            let kept = match idle {
+                Some(ref mut idle) => idle.keeps(&format!(
+                    "{}://{}",
+                    uri.scheme().unwrap_or("http"),
+                    uri.authority().unwrap_or_default()
+                )),
+                None => true,
+            };
+            if self.closes_connection(target.n) || !kept {
+                request
+                    .headers_mut()
+                    .set(hyper::header::Connection::close());
+            }
+            self.add_headers(target.n, uri.as_ref(), request.headers_mut());
+            if let Some(ref sticky) = sticky {
+                sticky.add_to(uri.as_ref(), request.headers_mut());
+            }
+            if self.proxies.for_url(uri.as_ref()).is_some() && uri.scheme() == Some("http") {
+                request.set_proxy(true);
+                self.add_proxy_authorization(uri.as_ref(), request.headers_mut());
+            }
+            let body = self.body(target.n, uri.as_ref());
+            let mut sent = 0;
+            if let (Method::Post, Some(body)) = (method, body.as_deref()) {
+                request
+                    .headers_mut()
+                    .set_raw("Content-Type", body.content_type.clone());
+                request
+                    .headers_mut()
+                    .set(hyper::header::ContentLength(body.bytes.len() as u64));
+                request.set_body(body.bytes.clone());
+                sent = body.bytes.len() as u64;
+            } else if let (Method::Post, Some(form)) = (method, self.form.as_ref()) {
+                let (reader, len) = form.open().expect("Expected readable form files");
+                request
+                    .headers_mut()
+                    .set_raw("Content-Type", form.content_type());
+                request
+                    .headers_mut()
+                    .set(hyper::header::ContentLength(len));
+                request.set_body(streamed_body(&handle, reader, 16 * 1024));
+                sent = len;
+            } else if let (Method::Post, Some(stream)) = (method, self.body_stream) {
+                request
+                    .headers_mut()
+                    .set_raw("Content-Type", "application/octet-stream");
+                let reader = Box::new(stream.reader());
+                request.set_body(streamed_body(&handle, reader, stream.chunk()));
+                sent = stream.size();
+            }
+            if let Some(ref mut plugin) = plugin {
+                let headers = request.headers_mut();
+                self.before_request(plugin, method, uri.as_ref(), body.as_deref(), headers);
+            }
+            self.sign(method, uri.as_ref(), body.as_deref(), request.headers_mut());
+            let challenged = match session {
+                Some(ref mut session) => {
+                    let headers = request.headers_mut();
+                    let challenged = self.authorize(session, target, uri.as_ref(), headers, || {
+                        let challenge = client
+                            .request(Request::new(request_method.clone(), uri.clone()))
+                            .and_then(|response| {
+                                let challenge = challenge_of(response.headers());
+                                response.body().concat2().map(move |_| challenge)
+                            });
+                        core.run(challenge).map_err(|ref e| failure::of_hyper(e))
+                    });
+                    match challenged {
+                        Ok(challenged) => challenged,
+                        Err(fact) => return *fact,
+                    }
+                }
+                None => Duration::new(0, 0),
+            };
+            let negotiated = match negotiation {
+                Some(ref mut negotiation) => {
+                    let negotiated = negotiate(negotiation, target, request.headers_mut(), |token| {
+                        let mut request = Request::new(request_method.clone(), uri.clone());
+                        request.headers_mut().set_raw("Authorization", token);
+                        let challenge = client.request(request).and_then(|response| {
+                            let challenge = negotiate_challenge(response.headers());
+                            response.body().concat2().map(move |_| challenge)
+                        });
+                        core.run(challenge).map_err(|ref e| failure::of_hyper(e))
+                    });
+                    match negotiated {
+                        Ok(negotiated) => negotiated,
+                        Err(fact) => return *fact,
+                    }
+                }
+                None => None,
+            };
+            let judged = plugin.is_some();
+            let authenticated = session.is_some();
+            let keeps_cookies = sticky.is_some();
+            let request = client.request(request).and_then(|response| {
+                let status = response.status().as_u16();
+                let group = group_by_header.as_ref().map(|header| {
+                    group_of(response.headers().get_raw(header).and_then(|raw| raw.one()))
+                });
+                let hit = cache_hit(response.headers());
+                let header_failure =
+                    self.header_failure(|name| first_value(response.headers(), name), None);
+                let cookies = if keeps_cookies {
+                    set_cookies(response.headers())
+                } else {
+                    Vec::new()
+                };
+                // The plugin judges the response, and digest challenges are taken up, once
+                // it has been timed.
+                let headers = if judged || (authenticated && status == 401) {
+                    Some(response.headers().clone())
+                } else {
+                    None
+                };
+                let version = match response.version() {
+                    hyper::HttpVersion::Http09 => "HTTP/0.9",
+                    hyper::HttpVersion::Http10 => "HTTP/1.0",
+                    hyper::HttpVersion::Http11 => "HTTP/1.1",
+                    _ => "HTTP/2",
+                };
+                let body = response.body().fold(Vec::new(), move |mut body, chunk| {
+                    body.extend_from_slice(&chunk);
+                    oversize::check(body.len(), max_response_size)
+                        .map(|_| body)
+                        .map_err(hyper::Error::Io)
+                });
+                body.then(move |body| {
+                    // A body that is too large still came with the response's status, as
+                    // reqwest's does.
+                    let (body, too_large) = match body {
+                        Ok(body) => (body, false),
+                        Err(hyper::Error::Io(ref e)) if oversize::is_too_large(e) => {
+                            (Vec::new(), true)
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    let failure = if too_large {
+                        Some(Failure::TooLarge)
+                    } else if graphql && graphql::has_errors(&body) {
+                        Some(Failure::GraphQl)
+                    } else {
+                        header_failure
+                    };
+                    let len = body.len() as u64;
+                    let received = headers.map(|headers| (headers, body));
+                    Ok((status, version, len, failure, group, hit, received, cookies))
+                })
+            });
+            let request = timeout::within(request, self.timeout, &handle);
+            let (response, duration) = bench::time_it(|| core.run(request));
+            let (status, version, content_length, failure, group, hit, received, cookies) =
+                match response {
+                    Ok(response) => response,
+                    Err(ref e) => {
+                        return Fact::elapsed(duration + challenged)
+                            .with_failure(failure::of_hyper(e));
+                    }
+                };
+            let failure = self.proxy_failure(status, failure);
+            let failure = self.status_failure(status, failure);
+            let duration = duration + challenged;
+            if let Some(ref mut sticky) = sticky {
+                sticky.store(uri.as_ref(), cookies.iter().map(Vec::as_slice));
+            }
+            if let (Some(session), Some((headers, _)), 401) =
+                (session.as_mut(), received.as_ref(), status)
+            {
+                renew(session, target, headers);
+            }
+            if let (Some(oauth2), 401) = (self.oauth2.as_ref(), status) {
+                oauth2.expire();
+            }
+            if let Some(ref mut negotiation) = negotiation {
+                if status == 401 || self.closes_connection(target.n) {
+                    negotiation.set_authenticated(target.index, false);
+                }
+            }
+            let failure = match (plugin.as_mut(), received) {
+                (Some(plugin), Some((headers, body))) => {
+                    after_response(plugin, status, &headers, &body, failure)
+                }
+                _ => failure,
+            };
+            let fact = Fact::record(ContentLength::new(content_length), status, duration)
+                .with_sent(ContentLength::new(sent))
+                .with_version(version);
+            let fact = match negotiated {
+                Some(negotiated) => fact.with_authentication(negotiated),
+                None => fact,
+            };
+            let fact = match group {
+                Some(group) => fact.with_group(group),
+                None if self.group_by_url => fact.with_group(self.urls[target.index].clone()),
+                None => fact,
+            };
+            let fact = match hit {
+                Some(hit) => fact.with_cache_hit(hit),
+                None => fact,
+            };
+            let fact = match connected.take() {
+                Some(connection) => with_connection(fact, connection),
+                None => fact,
+            };
+            match failure {
+                Some(failure) => fact.with_failure(failure),
+                None => fact,
+            }
+        }
+    }
+
+    fn http2(&self) -> impl Protocol + '_ {
+        use bytes::Bytes;
+        use futures::{Future, Stream};
+        use http::{self, Request};
+        use hyper::header::ContentLength as ContentLengthHeader;
+        use std::time::Instant;
+        use tokio_core::reactor::Core;
+
+        let mut core = Core::new().expect("Setting up tokio core failed");
+        let handle = core.handle();
+        let connector = self.https_connector(&handle, HTTP2_ALPN);
+        let mut http1 = self.hyper();
+        let mut connections: Vec<Http2> = self.urls.iter().map(|_| Http2::Closed).collect();
+
+        let graphql = self.graphql;
+        let mut sticky = self.sessions.as_ref().map(Sessions::start);
+
+        move |target: Target| {
+            let index = target.index;
+            let url = self.url(target);
+            let mut opened = None;
+            let mut shared = None;
+            let client = match (self.h2.streams(), &connections[index]) {
+                (_, Http2::Http1) => return http1.run_one(target),
+                (_, Http2::Open(client)) => client.clone(),
+                (Some(most), Http2::Closed) => {
+                    let open = || self.open_shared_http2(&url);
+                    match self.h2_pool.stream(index, most, open) {
+                        Ok((stream, connection)) => {
+                            opened = connection;
+                            let client = stream.client();
+                            shared = Some(stream);
+                            client
+                        }
+                        Err(not) => {
+                            let connection = &mut connections[index];
+                            return self.not_http2(not, connection, &mut http1, target);
+                        }
+                    }
+                }
+                (None, Http2::Closed) => match self.open_http2(&mut core, &connector, &url) {
+                    Ok((client, driver, connection)) => {
+                        handle.spawn(driver.map_err(|_| ()));
+                        connections[index] = Http2::Open(client.clone());
+                        opened = Some(connection);
+                        client
+                    }
+                    Err(not) => {
+                        let connection = &mut connections[index];
+                        return self.not_http2(not, connection, &mut http1, target);
+                    }
+                },
+            };
+
+            let mut headers = Headers::new();
+            self.add_headers(target.n, &url, &mut headers);
+            if let Some(ref sticky) = sticky {
+                sticky.add_to(&url, &mut headers);
+            }
+            let body = self.body(target.n, &url);
+            let method = self.method(index);
+            let data = match (method, body.as_deref()) {
+                (Method::Post, Some(body)) => {
+                    headers.set_raw("Content-Type", body.content_type.clone());
+                    headers.set(ContentLengthHeader(body.bytes.len() as u64));
+                    Some(Bytes::from(body.bytes.clone()))
+                }
+                _ => None,
+            };
+            self.sign(method, &url, body.as_deref(), &mut headers);
+            let mut request = Request::builder();
+            let request_method = match method {
+                Method::Get => http::Method::GET,
+                Method::Head => http::Method::HEAD,
+                Method::Post => http::Method::POST,
+                Method::Delete => http::Method::DELETE,
+            };
+            request.method(request_method).uri(url.as_ref());
+            for header in headers.iter() {
+                request.header(header.name(), header.value_string().as_str());
+            }
+            let request = request.body(()).expect("To build the request");
+            let sent = data.as_ref().map_or(0, |data| data.len() as u64);
+
+            let asked = Instant::now();
+            let sending = core.run(client.ready()).and_then(|mut client| {
+                let start = Instant::now();
+                let (response, mut stream) = client.send_request(request, data.is_none())?;
+                if let Some(data) = data {
+                    stream.send_data(data, true)?;
+                }
+                Ok((response, start))
+            });
+            let (mut response, start) = match sending {
+                Ok(sending) => sending,
+                Err(ref e) => {
+                    close_http2(shared.as_ref(), &mut connections[index]);
+                    return Fact::elapsed(asked.elapsed()).with_failure(failure::of_h2(e));
+                }
+            };
+            // Pushed streams are read alongside the response, but are left out of its
+            // duration and size.
+            let pushes = response
+                .push_promises()
+                .and_then(|promise| {
+                    let (_, pushed) = promise.into_parts();
+                    pushed.and_then(|pushed| {
+                        let mut body = pushed.into_body();
+                        let mut flow = body.release_capacity().clone();
+                        body.fold(0, move |len, chunk| {
+                            flow.release_capacity(chunk.len())?;
+                            Ok::<_, h2::Error>(len + chunk.len() as u64)
+                        })
+                    })
+                })
+                .fold((0, 0), |(count, bytes), len| {
+                    Ok::<_, h2::Error>((count + 1, bytes + len))
+                });
+            let keeps_cookies = sticky.is_some();
+            let reply = response.and_then(|response| {
+                let status = response.status().as_u16();
+                let cookies: Vec<Vec<u8>> = if keeps_cookies {
+                    let set = response.headers().get_all("set-cookie");
+                    set.iter().map(|value| value.as_bytes().to_vec()).collect()
+                } else {
+                    Vec::new()
+                };
+                let group = self.group_by_header.as_ref().map(|header| {
+                    let value = response.headers().get(header.as_str());
+                    group_of(value.map(|value| value.as_bytes()))
+                });
+                let headers = response.headers();
+                let header_failure = self.header_failure(
+                    |name| headers.get(name).map(|value| value.as_bytes()),
+                    None,
+                );
+                let mut body = response.into_body();
+                let mut flow = body.release_capacity().clone();
+                body.fold((0, Vec::new()), move |(len, mut received), chunk| {
+                    flow.release_capacity(chunk.len())?;
+                    oversize::check(len as usize + chunk.len(), self.max_response_size)?;
+                    if graphql {
+                        received.extend_from_slice(&chunk);
+                    }
+                    Ok::<_, h2::Error>((len + chunk.len() as u64, received))
+                })
+                .then(move |body| {
+                    // A body that is too large still came with the response's status, as
+                    // reqwest's does.
+                    let (len, received, failure) = match body {
+                        Ok((len, received)) => (len, received, header_failure),
+                        Err(ref e) if e.get_io().is_some_and(oversize::is_too_large) => {
+                            (0, Vec::new(), Some(Failure::TooLarge))
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    let elapsed = start.elapsed();
+                    Ok((status, group, len, received, cookies, failure, elapsed))
+                })
+            });
+            let replied = core.run(timeout::within(reply.join(pushes), self.timeout, &handle));
+            if replied.is_err() || self.closes_connection(target.n) {
+                close_http2(shared.as_ref(), &mut connections[index]);
+            }
+            drop(shared);
+            let (response, (pushed, pushed_bytes)) = match replied {
+                Ok(replied) => replied,
+                Err(ref e) => {
+                    return Fact::elapsed(start.elapsed()).with_failure(failure::of_h2(e));
+                }
+            };
+            let (status, group, content_length, received, cookies, failure, duration) = response;
+            if let Some(ref mut sticky) = sticky {
+                sticky.store(&url, cookies.iter().map(Vec::as_slice));
+            }
+            if let (Some(oauth2), 401) = (self.oauth2.as_ref(), status) {
+                oauth2.expire();
+            }
+
+            let fact = Fact::record(ContentLength::new(content_length), status, duration)
+                .with_sent(ContentLength::new(sent))
+                .with_version("HTTP/2");
+            let fact = if pushed > 0 {
+                fact.with_pushed(pushed, ContentLength::new(pushed_bytes))
+            } else {
+                fact
+            };
+            let fact = match group {
+                Some(group) => fact.with_group(group),
+                None if self.group_by_url => fact.with_group(self.urls[index].clone()),
+                None => fact,
+            };
+            let fact = match opened {
+                Some(connection) => with_connection(fact, connection),
+                None => fact,
+            };
+            let failure = if graphql && graphql::has_errors(&received) {
+                Some(Failure::GraphQl)
+            } else {
+                failure
+            };
+            match self.status_failure(status, failure) {
+                Some(failure) => fact.with_failure(failure),
+                None => fact,
+            }
         }
     }
 
-    fn run_hyper<F>(&self, requests: usize, mut collect: F)
+    /// Opens a connection to the url on the core, handing back what drives it once the
+    /// server has chosen http/2 for it by alpn, or when it is cleartext and the server is
+    /// known to speak http/2.
+    fn open_http2(
+        &self,
+        core: &mut ::tokio_core::reactor::Core,
+        connector: &HttpsConnector<socket::Connector>,
+        url: &str,
+    ) -> Result<(::h2::client::SendRequest<::bytes::Bytes>, Http2Driver, Connection), NotHttp2> {
+        use hyper::server::Service;
+        use hyper::Uri;
+
+        let uri: Uri = url.parse().expect("Invalid url");
+        let secured = uri.scheme() == Some("https");
+        if !secured && !self.h2.prior_knowledge() {
+            return Err(NotHttp2::Http1(None));
+        }
+        let (stream, duration) = bench::time_it(|| core.run(connector.call(uri)));
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(ref e) => {
+                let fact = Fact::elapsed(duration).with_failure(failure::of_io(e));
+                return Err(NotHttp2::Failed(Box::new(fact)));
+            }
+        };
+        let connection = Connection::new(duration, &stream);
+        match connection.alpn {
+            Some(ref alpn) if alpn == "h2" => {}
+            None if !secured => {}
+            alpn => return Err(NotHttp2::Http1(alpn)),
+        }
+        let (handshake, duration) =
+            bench::time_it(|| core.run(self.h2.builder().handshake(stream)));
+        match handshake {
+            Ok((client, driver)) => Ok((client, driver, connection)),
+            Err(ref e) => {
+                let fact = Fact::elapsed(connection.duration + duration)
+                    .with_failure(failure::of_h2(e));
+                Err(NotHttp2::Failed(Box::new(fact)))
+            }
+        }
+    }
+
+    /// Opens a connection to the url for the threads to share, which is driven on a thread
+    /// of its own as the threads only turn their reactors while they wait on their streams.
+    fn open_shared_http2(
+        &self,
+        url: &str,
+    ) -> Result<(::h2::client::SendRequest<::bytes::Bytes>, Connection), NotHttp2> {
+        use std::sync::mpsc;
+        use std::thread;
+        use tokio_core::reactor::Core;
+
+        let eng = self.clone();
+        let url = url.to_string();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut core = Core::new().expect("Setting up tokio core failed");
+            let connector = eng.https_connector(&core.handle(), HTTP2_ALPN);
+            match eng.open_http2(&mut core, &connector, &url) {
+                Ok((client, driver, connection)) => {
+                    let _ = sender.send(Ok((client, connection)));
+                    let _ = core.run(driver);
+                }
+                Err(not) => {
+                    let _ = sender.send(Err(not));
+                }
+            }
+        });
+        receiver.recv().expect("Expected the connection to open")
+    }
+
+    /// The fact of a request to a target that could not be made over http/2, which is left
+    /// to hyper from then on when the server speaks http/1.1.
+    fn not_http2<P: Protocol>(
+        &self,
+        not: NotHttp2,
+        connection: &mut Http2,
+        http1: &mut P,
+        target: Target,
+    ) -> Fact {
+        match not {
+            NotHttp2::Failed(fact) => *fact,
+            NotHttp2::Http1(alpn) => {
+                *connection = Http2::Http1;
+                // Hyper speaks http/1.1 to the server on connections of its own.
+                let fact = http1.run_one(target);
+                match alpn {
+                    Some(alpn) => fact.with_alpn(alpn),
+                    None => fact,
+                }
+            }
+        }
+    }
+
+    fn expect_continue(&self) -> impl Protocol + '_ {
+        use expect_continue::Connection;
+        use std::io::Cursor;
+
+        let mut connections: Vec<Option<Connection>> = self.urls.iter().map(|_| None).collect();
+
+        move |target: Target| {
+            let index = target.index;
+            let connected = if connections[index].is_none() {
+                let (connection, duration) =
+                    bench::time_it(|| Connection::open(&self.urls[index], &self.socket));
+                match connection {
+                    Ok(connection) => connections[index] = Some(connection),
+                    Err(ref e) => {
+                        return Fact::elapsed(duration).with_failure(failure::of_io(e));
+                    }
+                }
+                Some(duration)
+            } else {
+                None
+            };
+            let connection = connections[index].as_mut().expect("Connected above");
+
+            let (content_type, mut body, len): (String, Box<dyn Read + Send>, Option<u64>) =
+                if let Some(ref body) = self.body {
+                    let len = body.bytes.len() as u64;
+                    let reader = Box::new(Cursor::new(body.bytes.clone()));
+                    (body.content_type.clone(), reader, Some(len))
+                } else if let Some(ref form) = self.form {
+                    let (reader, len) = form.open().expect("Expected readable form files");
+                    (form.content_type(), reader, Some(len))
+                } else if let Some(stream) = self.body_stream {
+                    let reader = Box::new(stream.reader());
+                    ("application/octet-stream".to_string(), reader, None)
+                } else {
+                    ("application/octet-stream".to_string(), Box::new(Cursor::new(vec![])), Some(0))
+                };
+            let sent = match (len, self.body_stream) {
+                (Some(len), _) => len,
+                (None, Some(stream)) => stream.size(),
+                (None, None) => 0,
+            };
+
+            let (response, duration) =
+                bench::time_it(|| connection.post(&content_type, &mut body, len));
+            let response = match response {
+                Ok(response) => response,
+                Err(ref e) => {
+                    // The connection is in no state to carry another request.
+                    connections[index] = None;
+                    return Fact::elapsed(duration).with_failure(failure::of_io(e));
+                }
+            };
+            if !response.reusable || self.closes_connection(target.n) {
+                connections[index] = None;
+            }
+
+            let mut fact = Fact::record(
+                ContentLength::new(response.content_length),
+                response.status,
+                duration,
+            ).with_version(response.version);
+            if response.sent {
+                fact = fact.with_sent(ContentLength::new(sent));
+            }
+            if let Some(continued) = response.continued {
+                fact = fact.with_continue(continued);
+            }
+            if let Some(connect) = connected {
+                fact = fact.with_connect(connect);
+            }
+            fact
+        }
+    }
+
+    fn websocket(&self) -> WebSockets<'_> {
+        WebSockets {
+            eng: self,
+            sockets: self.urls.iter().map(|_| None).collect(),
+        }
+    }
+
+    fn grpc(&self) -> impl Protocol + '_ {
+        use bytes::Bytes;
+        use futures::{future, Async, Future, Stream};
+        use h2::client::{self, SendRequest};
+        use http::{self, Request};
+        use std::net::ToSocketAddrs;
+        use tokio_core::reactor::Core;
+
+        let call = self
+            .call
+            .as_ref()
+            .expect("Grpc engine is set up with a call");
+        let frame = Bytes::from(call.frame());
+
+        let mut core = Core::new().expect("Setting up tokio core failed");
+        let handle = core.handle();
+
+        let mut clients: Vec<Option<SendRequest<Bytes>>> = self.urls.iter().map(|_| None).collect();
+
+        move |target: Target| {
+            let index = target.index;
+            let authority = &self.urls[index];
+
+            let connected = if clients[index].is_none() {
+                let (addr, resolving) = bench::time_it(|| authority.to_socket_addrs());
+                let addr = match addr.map(|mut addrs| addrs.next()) {
+                    Ok(Some(addr)) => addr,
+                    Ok(None) => return Fact::elapsed(resolving).with_failure(Failure::Dns),
+                    Err(ref e) => return Fact::elapsed(resolving).with_failure(failure::of_io(e)),
+                };
+                let connect = self
+                    .socket
+                    .connect_async(&addr, &handle)
+                    .map_err(h2::Error::from)
+                    .and_then(client::handshake);
+                let (connection, duration) = bench::time_it(|| core.run(connect));
+                let (client, connection) = match connection {
+                    Ok(connection) => connection,
+                    Err(ref e) => return Fact::elapsed(duration).with_failure(failure::of_h2(e)),
+                };
+                handle.spawn(connection.map_err(|_| ()));
+                clients[index] = Some(client);
+                Some(duration)
+            } else {
+                None
+            };
+
+            let client = clients[index].take().expect("Connected above");
+            let request = Request::builder()
+                .method(http::Method::POST)
+                .uri(format!("http://{}{}", authority, call.path()).as_str())
+                .header("content-type", "application/grpc")
+                .header("te", "trailers")
+                .body(())
+                .expect("To build the grpc request");
+
+            let (called, duration) = bench::time_it(|| {
+                let mut client = core.run(client.ready())?;
+                let (response, mut stream) = client.send_request(request, false)?;
+                stream.send_data(frame.clone(), true)?;
+
+                let reply = response.and_then(|response| {
+                    let (parts, mut body) = response.into_parts();
+                    let http_status = parts.status.as_u16();
+                    let mut status = grpc::status(&parts.headers);
+                    let mut len = 0;
+                    future::poll_fn(move || {
+                        while let Some(chunk) = try_ready!(body.poll()) {
+                            len += chunk.len() as u64;
+                            body.release_capacity().release_capacity(chunk.len())?;
+                        }
+                        if let Some(trailers) = try_ready!(body.poll_trailers()) {
+                            status = status.or_else(|| grpc::status(&trailers));
+                        }
+                        Ok(Async::Ready((http_status, status, len)))
+                    })
+                });
+                let (http_status, status, len) = core.run(reply)?;
+                // A missing status is reported as UNKNOWN, as grpc clients do.
+                Ok((client, http_status, status.unwrap_or(2), len))
+            });
+            // A call that failed leaves the connection to be opened afresh.
+            let (client, http_status, status, content_length) = match called {
+                Ok(called) => called,
+                Err(ref e) => return Fact::elapsed(duration).with_failure(failure::of_h2(e)),
+            };
+            clients[index] = Some(client);
+
+            let fact = Fact::record(ContentLength::new(content_length), http_status, duration)
+                .with_grpc_status(status);
+            match connected {
+                Some(connect) => fact.with_connect(connect),
+                None => fact,
+            }
+        }
+    }
+
+    /// Times the events of the streams as they arrive, asking `more` before waiting for each
+    /// as the other engines do before each request.
+    fn run_sse<M, F>(&self, requests: usize, mut more: M, mut collect: F)
     where
+        M: FnMut() -> bool,
         F: FnMut(Fact),
     {
-        use hyper::{self, Client, Request, Uri};
-        use hyper_tls::HttpsConnector;
+        use futures::{stream, Future, Stream};
+        use hyper::{self, Chunk, Client, Request};
+        use std::collections::VecDeque;
+        use std::time::Instant;
         use tokio_core::reactor::Core;
-        use futures::{Future, Stream};
 
         let mut core = Core::new().expect("Setting up tokio core failed");
         let handle = core.handle();
         let client = Client::configure()
-            .connector(HttpsConnector::new(1, &handle).expect("To set up a http connector"))
+            .connector(self.https_connector(&handle, &[]))
             .build(&handle);
 
-        let urls: Vec<Uri> = self.urls.iter().map(|url| url.parse().unwrap()).collect();
+        let started = Instant::now();
+        let streams = self.urls.iter().enumerate().map(|(index, url)| {
+            let mut request = Request::new(hyper::Method::Get, url.parse().expect("Invalid url"));
+            request.headers_mut().set_raw("Accept", "text/event-stream");
+            client
+                .request(request)
+                .map(move |response| {
+                    let status = response.status().as_u16();
+                    response.body().map(move |chunk| (index, status, chunk))
+                })
+                .flatten_stream()
+        });
+        type Events = Box<dyn Stream<Item = (usize, u16, Chunk), Error = hyper::Error>>;
+        let mut events: Events = streams.fold(Box::new(stream::empty()), |events, s| {
+            Box::new(events.select(s))
+        });
+
+        let mut buffers: Vec<Vec<u8>> = self.urls.iter().map(|_| Vec::new()).collect();
+        let mut last_event: Vec<Option<Instant>> = self.urls.iter().map(|_| None).collect();
+        // The facts of the events of a chunk, and of failed streams, yet to be counted.
+        let mut pending = VecDeque::new();
+        let mut received = 0;
+
+        while received < requests && more() {
+            while pending.is_empty() {
+                let waiting = Instant::now();
+                match core.run(events.into_future()) {
+                    Ok((Some((index, status, chunk)), rest)) => {
+                        events = rest;
+                        let arrived = Instant::now();
+                        buffers[index].extend_from_slice(&chunk);
+                        for len in sse::drain_events(&mut buffers[index]) {
+                            let fact = match last_event[index] {
+                                Some(last) => {
+                                    Fact::record(ContentLength::new(len), status, arrived - last)
+                                }
+                                None => {
+                                    Fact::record(ContentLength::new(len), status, arrived - started)
+                                        .with_connect(arrived - started)
+                                }
+                            };
+                            last_event[index] = Some(arrived);
+                            pending.push_back(fact);
+                        }
+                    }
+                    Ok((None, _)) => return,
+                    // The stream that failed ends there, while the others carry on.
+                    Err((e, rest)) => {
+                        events = rest;
+                        let failure = failure::of_hyper(&e);
+                        pending.push_back(Fact::elapsed(waiting.elapsed()).with_failure(failure));
+                    }
+                }
+            }
+            collect(pending.pop_front().expect("Expected an event to be pending"));
+            received += 1;
+        }
+    }
+
+    fn connect(&self) -> impl Protocol + '_ {
+        // Resolve up front so that only establishing the connection is timed.
+        let targets = self.resolve_targets();
+
+        move |target: Target| {
+            let (_, ref addr) = targets[target.index];
+            let (stream, duration) = bench::time_it(|| self.socket.connect(addr));
+            match stream {
+                Ok(_) => Fact::elapsed(duration),
+                Err(ref e) => Fact::elapsed(duration).with_failure(failure::of_io(e)),
+            }
+        }
+    }
+
+    fn tls_handshake(&self) -> impl Protocol + '_ {
+        use openssl::ssl::{SslConnectorBuilder, SslMethod, SslSession};
+
+        let mut builder =
+            SslConnectorBuilder::new(SslMethod::tls()).expect("To build a tls connector");
+        self.tls
+            .apply(&mut builder)
+            .expect("Expected the tls options to be supported");
+        let connector = builder.build();
+        let targets = self.resolve_targets();
+        let mut sessions: Vec<Option<SslSession>> = vec![None; targets.len()];
+
+        move |target: Target| {
+            let index = target.index;
+            let (ref host, ref addr) = targets[index];
+            let (tcp, connect) = bench::time_it(|| self.socket.connect(addr));
+            let tcp = match tcp {
+                Ok(tcp) => tcp,
+                Err(ref e) => return Fact::elapsed(connect).with_failure(failure::of_io(e)),
+            };
+            let mut config = connector.configure().expect("To configure the tls session");
+            if let Some(ref session) = sessions[index] {
+                // Every session was negotiated with this connector's context.
+                unsafe { config.set_session(session) }.expect("To offer the last session");
+            }
+
+            let (stream, duration) =
+                bench::time_it(|| config.connect(self.tls.server_name().unwrap_or(host), tcp));
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => return Fact::elapsed(duration).with_failure(Failure::Tls),
+            };
+            let resumed = stream.ssl().session_reused();
+            let version = stream.ssl().version();
+            let cipher = stream.ssl().current_cipher().map(|cipher| cipher.name().to_string());
+            let expires_in = tls::expires_in(stream.ssl());
+            let pinned = tls::check_pins(self.tls.pins(), stream.ssl()).is_ok();
+
+            // Tls 1.3 tickets arrive after the handshake, closing cleanly reads them in.
+            let _ = stream.get_ref().set_read_timeout(Some(Duration::from_secs(1)));
+            let _ = stream.shutdown().and_then(|_| stream.shutdown());
+            sessions[index] = stream.ssl().session().map(|session| session.to_owned());
+
+            let fact = Fact::elapsed(duration)
+                .with_resumed(resumed)
+                .with_tls_version(version);
+            let fact = match cipher {
+                Some(cipher) => fact.with_cipher(cipher),
+                None => fact,
+            };
+            let fact = match expires_in {
+                Some(seconds) => fact.with_expires_in(seconds),
+                None => fact,
+            };
+            if pinned {
+                fact
+            } else {
+                fact.with_failure(Failure::PinMismatch)
+            }
+        }
+    }
+
+    fn dns(&self) -> impl Protocol + '_ {
+        use std::net::{ToSocketAddrs, UdpSocket};
+
+        let hosts: Vec<String> = self.urls.iter().map(|url| address::host(url)).collect();
+        let socket = self.resolver.map(|resolver| {
+            let local = if resolver.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+            let socket = UdpSocket::bind(local).expect("To bind a socket for queries");
+            socket
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .expect("To set the query timeout");
+            (socket, resolver)
+        });
 
-        let method = match self.method {
-            Method::Get => hyper::Method::Get,
-            Method::Head => hyper::Method::Head,
+        move |target: Target| {
+            let host = &hosts[target.index];
+            let (resolved, duration) = bench::time_it(|| match socket {
+                Some((ref socket, resolver)) => {
+                    dns::lookup(socket, resolver, target.n as u16, host).map(|code| code == 0)
+                }
+                None => (host.as_str(), 0).to_socket_addrs().map(|_| true),
+            });
+
+            let fact = Fact::elapsed(duration);
+            match resolved {
+                Ok(true) => fact,
+                _ => fact.with_failure(Failure::Dns),
+            }
+        }
+    }
+
+    /// A connector for the hyper client that opens its sockets with the engine's options,
+    /// offering the protocols by alpn when there are any.
+    fn https_connector(
+        &self,
+        handle: &::tokio_core::reactor::Handle,
+        alpn: &[&[u8]],
+    ) -> HttpsConnector<socket::Connector> {
+        use native_tls::backend::openssl::TlsConnectorBuilderExt;
+        use native_tls::TlsConnector;
+
+        let mut builder = TlsConnector::builder().expect("To set up a tls connector");
+        self.tls
+            .apply(builder.builder_mut())
+            .expect("Expected the tls options to be supported");
+        if !alpn.is_empty() {
+            builder
+                .builder_mut()
+                .set_alpn_protocols(alpn)
+                .expect("To offer the protocols by alpn");
+        }
+        let tls = builder.build().expect("To set up a tls connector");
+        let connector =
+            socket::Connector::new(self.socket.clone(), handle).with_proxies(self.proxies.clone());
+        let server_name = self.tls.server_name().map(String::from);
+        HttpsConnector::new(connector, tls)
+            .with_server_name(server_name)
+            .with_pins(self.tls.pins().to_vec())
+    }
+
+    /// An instance of the plugin for a thread's requests, if the engine has one.
+    fn instantiate_plugin(&self) -> Option<plugin::Instance> {
+        self.plugin
+            .as_ref()
+            .map(|plugin| plugin.instantiate().expect("Expected the plugin to start"))
+    }
+
+    /// The url of the request, rendered afresh when the urls are templates.
+    fn url(&self, target: Target) -> Cow<'_, str> {
+        match self.url_templates {
+            Some(ref templates) => Cow::Owned(
+                templates[target.index]
+                    .render(target.n, &self.urls[target.index])
+                    .expect("Expected the url template to render"),
+            ),
+            None => Cow::Borrowed(&self.urls[target.index]),
+        }
+    }
+
+    /// Sets the headers the engine sends with every request, rendered for the nth one, along
+    /// with the oauth2 token or json web token when there is one.
+    fn add_headers(&self, n: usize, url: &str, headers: &mut Headers) {
+        for (name, value) in &self.headers {
+            let value = value
+                .render(n, url)
+                .expect("Expected the header template to render");
+            headers.set_raw(name.clone(), value);
+        }
+        if let Some(ref oauth2) = self.oauth2 {
+            let token = oauth2.bearer().expect("Expected an oauth2 token");
+            headers.set_raw("Authorization", format!("Bearer {}", token));
+        }
+        if let Some(ref jwt) = self.jwt {
+            headers.set_raw("Authorization", format!("Bearer {}", jwt.bearer()));
+        }
+    }
+
+    /// Authenticates a plain http request with the proxy, which it is sent to whole. Https
+    /// requests are tunnelled, so the proxy never sees their headers.
+    fn add_proxy_authorization(&self, url: &str, headers: &mut Headers) {
+        if let Some(authorization) = self.proxies.for_url(url).and_then(Proxy::authorization) {
+            if url.starts_with("http:") {
+                headers.set_raw("Proxy-Authorization", authorization.to_string());
+            }
+        }
+    }
+
+    /// The failure of a response that may have gone through a proxy, which is the proxy
+    /// asking for authentication when it answers with a 407.
+    fn proxy_failure(&self, status: u16, failure: Option<Failure>) -> Option<Failure> {
+        match failure {
+            None if !self.proxies.is_empty() && status == 407 => {
+                Some(Failure::ProxyAuthentication)
+            }
+            failure => failure,
+        }
+    }
+
+    /// Counts a response whose status the benchmark does not expect as a failure, unless it
+    /// failed for another reason.
+    fn status_failure(&self, status: u16, failure: Option<Failure>) -> Option<Failure> {
+        match (failure, self.status_check.as_ref()) {
+            (None, Some(check)) if check.fails(status) => Some(Failure::Status),
+            (failure, _) => failure,
+        }
+    }
+
+    /// Counts a response with a header that does not hold as asserted as a failure, unless it
+    /// failed for another reason, given how to look up the first value of a header.
+    fn header_failure<'h, F>(&self, header: F, failure: Option<Failure>) -> Option<Failure>
+    where
+        F: Fn(&str) -> Option<&'h [u8]>,
+    {
+        let holds = |assertion: &HeaderAssertion| assertion.holds(header(assertion.name()));
+        match failure {
+            None if !self.header_assertions.iter().all(holds) => Some(Failure::Header),
+            failure => failure,
+        }
+    }
+
+    /// Adds the digest authorization to a request, first fetching the target's challenge if
+    /// the session has yet to. The time the fetch took is returned when it counts, and the
+    /// failed fetch as the fact of the request when it failed.
+    fn authorize<F>(
+        &self,
+        session: &mut digest::Session,
+        target: Target,
+        url: &str,
+        headers: &mut Headers,
+        fetch_challenge: F,
+    ) -> Result<Duration, Box<Fact>>
+    where
+        F: FnOnce() -> Result<Option<String>, Failure>,
+    {
+        let mut took = Duration::new(0, 0);
+        if session.needs_challenge(target.index) {
+            let (challenge, duration) = bench::time_it(fetch_challenge);
+            let challenge = challenge
+                .map_err(|failure| Box::new(Fact::elapsed(duration).with_failure(failure)))?;
+            session
+                .challenge(target.index, challenge.as_deref())
+                .expect("Expected a digest challenge rench can answer");
+            if self.digest.as_ref().map(Digest::handshake) == Some(Handshake::Counted) {
+                took = duration;
+            }
+        }
+        let uri = match reqwest::Url::parse(url) {
+            Ok(url) => match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            },
+            Err(_) => url.to_string(),
         };
+        if let Some(authorization) = session.authorization(target.index, self.method(target.index).name(), &uri) {
+            headers.set_raw("Authorization", authorization);
+        }
+        Ok(took)
+    }
 
-        for n in 0..requests {
-            let uri = &urls[n % urls.len()];
-            let request = client
-                .request(Request::new(method.clone(), uri.clone()))
-                .and_then(|response| {
-                    let status = response.status().as_u16();
-                    response
-                        .body()
-                        .concat2()
-                        .map(move |body| (status, body.len() as u64))
-                });
-            let ((status, content_length), duration) =
-                bench::time_it(|| core.run(request).expect("reactor run"));
-            collect(Fact::record(
-                ContentLength::new(content_length),
-                status,
-                duration,
-            ));
+    /// Signs a request to the url with aws signature version 4 and with the hmac signature,
+    /// if the engine signs them. Forms and streamed bodies are left out of the aws signature,
+    /// as they are only read as they go out.
+    fn sign(&self, method: Method, url: &str, body: Option<&Body>, headers: &mut Headers) {
+        let payload = match (method, body) {
+            (Method::Post, Some(body)) => Some(&body.bytes[..]),
+            (Method::Post, None) if self.form.is_some() || self.body_stream.is_some() => None,
+            _ => Some(&[][..]),
+        };
+        if let Some(ref signature) = self.signature {
+            let (name, value) = signature.sign(url, payload.unwrap_or_default());
+            headers.set_raw(name, value);
+        }
+        if let Some(ref sigv4) = self.sigv4 {
+            let signed = sigv4
+                .sign(method.name(), url, payload, SystemTime::now())
+                .expect("Expected the request to be signed");
+            for (name, value) in signed {
+                headers.set_raw(name, value);
+            }
+        }
+    }
+
+    /// The body of the nth request to the url, rendered afresh when there is a template.
+    fn body(&self, n: usize, url: &str) -> Option<Cow<'_, Body>> {
+        match self.body_template {
+            Some(ref template) => Some(Cow::Owned(Body {
+                content_type: template.content_type().to_string(),
+                bytes: template
+                    .render(n, url)
+                    .expect("Expected the body template to render")
+                    .into_bytes(),
+            })),
+            None => self.body.as_ref().map(Cow::Borrowed),
+        }
+    }
+
+    /// Takes a row of the body template's data for the next request, false once the rows that
+    /// go to one request each have run out.
+    fn take_row(&self) -> bool {
+        self.body_template.as_ref().is_none_or(Template::take_row)
+    }
+
+    /// Adds the headers the plugin asks for to a request about to be sent to the url.
+    fn before_request(
+        &self,
+        plugin: &mut plugin::Instance,
+        method: Method,
+        url: &str,
+        body: Option<&Body>,
+        headers: &mut Headers,
+    ) {
+        let body: &[u8] = match (method, body) {
+            (Method::Post, Some(body)) => &body.bytes,
+            _ => b"",
+        };
+        let added = plugin
+            .before_request(method.name(), url, body)
+            .expect("Expected the plugin's before_request to run");
+        for (name, value) in added {
+            headers.set_raw(name, value);
+        }
+    }
+
+    /// Whether the nth request is the last its connection should carry. Each url keeps a
+    /// single connection open, so that is every so many of the requests made to the url.
+    fn closes_connection(&self, n: usize) -> bool {
+        match self.requests_per_conn {
+            Some(limit) => (n / self.urls.len() + 1).is_multiple_of(limit),
+            None => false,
+        }
+    }
+
+    fn resolve_targets(&self) -> Vec<(String, SocketAddr)> {
+        self.urls
+            .iter()
+            .map(|url| address::resolve(url).expect("To resolve the target"))
+            .collect()
+    }
+}
+
+trait Socket: Read + Write {}
+impl<S: Read + Write> Socket for S {}
+
+/// The protocols servers are offered by alpn, http/2 first.
+const HTTP2_ALPN: &[&[u8]] = &[b"h2", b"http/1.1"];
+
+/// The connection the http/2 engine holds to a target.
+enum Http2 {
+    /// There is none, and the server is asked again which protocols it speaks when one is
+    /// opened. When the threads share connections, every request takes a stream from the
+    /// pool instead.
+    Closed,
+    Open(::h2::client::SendRequest<::bytes::Bytes>),
+    /// The target is left to hyper, as the server only speaks http/1.1 or the url is not
+    /// secured for alpn to be offered and the server not known to speak http/2.
+    Http1,
+}
+
+/// The first value of a header, the one assertions are checked against.
+fn first_value<'h>(headers: &'h Headers, name: &str) -> Option<&'h [u8]> {
+    headers.get_raw(name).and_then(|raw| raw.into_iter().next())
+}
+
+/// Closes the connection a request failed on or was to close, so that the next request opens
+/// another.
+fn close_http2(shared: Option<&multiplex::Stream>, connection: &mut Http2) {
+    match shared {
+        Some(stream) => stream.close(),
+        None => *connection = Http2::Closed,
+    }
+}
+
+/// What drives an http/2 connection, reading its frames and writing those of its streams.
+type Http2Driver = ::h2::client::Connection<
+    ::hyper_tls::MaybeHttpsStream<::tokio_core::net::TcpStream>,
+    ::bytes::Bytes,
+>;
+
+/// Why a connection was not opened over http/2.
+enum NotHttp2 {
+    /// The server chose the protocol by alpn, or was offered none.
+    Http1(Option<String>),
+    Failed(Box<Fact>),
+}
+
+/// Round trips of the engine's message over a websocket to each url, which is opened with
+/// the first message and kept open for the rest.
+struct WebSockets<'a> {
+    eng: &'a Engine,
+    sockets: Vec<Option<WebSocket<Box<dyn Socket>>>>,
+}
+
+impl<'a> WebSockets<'a> {
+    /// Opens a websocket to the url, or says how it failed to.
+    fn connect(&self, url: &str) -> Result<WebSocket<Box<dyn Socket>>, Failure> {
+        use native_tls::TlsConnector;
+        use std::net::ToSocketAddrs;
+        use tungstenite::handshake::HandshakeError;
+        use tungstenite::http::Uri;
+
+        let uri: Uri = url.parse().expect("Invalid url");
+        let host = uri.host().expect("Url must have a host").to_string();
+        let secure = uri.scheme_str() == Some("wss");
+        let port = uri.port_u16().unwrap_or(if secure { 443 } else { 80 });
+
+        let addr = (host.as_str(), port)
+            .to_socket_addrs()
+            .map_err(|ref e| failure::of_io(e))?
+            .next()
+            .ok_or(Failure::Dns)?;
+        let stream = self
+            .eng
+            .socket
+            .connect(&addr)
+            .map_err(|ref e| failure::of_io(e))?;
+        let stream: Box<dyn Socket> = if secure {
+            let tls = TlsConnector::builder()
+                .and_then(|builder| builder.build())
+                .expect("To set up a tls connector");
+            Box::new(tls.connect(&host, stream).map_err(|_| Failure::Tls)?)
+        } else {
+            Box::new(stream)
+        };
+        match tungstenite::client(uri, stream) {
+            Ok((socket, _)) => Ok(socket),
+            Err(HandshakeError::Failure(ref e)) => Err(failure::of_tungstenite(e)),
+            // The handshake is only interrupted by the read timeout of the socket.
+            Err(HandshakeError::Interrupted(_)) => Err(Failure::Timeout),
         }
     }
 }
 
+impl<'a> Protocol for WebSockets<'a> {
+    fn run_one(&mut self, target: Target) -> Fact {
+        use tungstenite::Message;
+
+        let index = target.index;
+        let connected = if self.sockets[index].is_none() {
+            let (socket, duration) = bench::time_it(|| self.connect(&self.eng.urls[index]));
+            match socket {
+                Ok(socket) => self.sockets[index] = Some(socket),
+                Err(failure) => return Fact::elapsed(duration).with_failure(failure),
+            }
+            Some(duration)
+        } else {
+            None
+        };
+        let message = &self.eng.message;
+        let socket = self.sockets[index].as_mut().expect("Connected above");
+
+        let (len, duration) = bench::time_it(|| -> Result<usize, Failure> {
+            let failed = |ref e| failure::of_tungstenite(e);
+            socket.send(Message::Text(message.clone())).map_err(failed)?;
+            loop {
+                match socket.read().map_err(failed)? {
+                    Message::Text(reply) => break Ok(reply.len()),
+                    Message::Binary(reply) => break Ok(reply.len()),
+                    _ => continue,
+                }
+            }
+        });
+        let len = match len {
+            Ok(len) => len,
+            Err(failure) => {
+                // A websocket that failed is opened afresh for the next message.
+                self.sockets[index] = None;
+                return Fact::elapsed(duration).with_failure(failure);
+            }
+        };
+
+        // The handshake status is the only status a websocket has.
+        let fact = Fact::record(ContentLength::new(len as u64), 101, duration);
+        match connected {
+            Some(connect) => fact.with_connect(connect),
+            None => fact,
+        }
+    }
+
+    fn finish(&mut self) {
+        for socket in self.sockets.iter_mut().filter_map(|s| s.as_mut()) {
+            let _ = socket.close(None);
+            let _ = socket.flush();
+        }
+    }
+}
+
+/// The digest challenge of a response, if it has one.
+fn challenge_of(headers: &Headers) -> Option<String> {
+    headers
+        .get_raw("WWW-Authenticate")
+        .and_then(|raw| raw.one())
+        .map(|challenge| String::from_utf8_lossy(challenge).into_owned())
+}
+
+/// Authenticates the connection to the target with an ntlm handshake if it has yet to be,
+/// sending the negotiate token with `send` and answering the challenge it gets back on the
+/// request about to be made. Returns how long the handshake took, if there was one, or the
+/// failed handshake as the fact of the request.
+fn negotiate<F>(
+    negotiation: &mut ntlm::Session,
+    target: Target,
+    headers: &mut Headers,
+    send: F,
+) -> Result<Option<Duration>, Box<Fact>>
+where
+    F: FnOnce(String) -> Result<Option<String>, Failure>,
+{
+    if !negotiation.needs_handshake(target.index) {
+        return Ok(None);
+    }
+    let token = negotiation.credentials().negotiate();
+    let (challenge, took) = bench::time_it(|| send(token));
+    let challenge = challenge
+        .map_err(|failure| Box::new(Fact::elapsed(took).with_failure(failure)))?
+        .expect("Expected the server to answer with an ntlm challenge");
+    let authorization = negotiation
+        .credentials()
+        .authenticate(&challenge)
+        .expect("Expected an ntlm challenge rench can answer");
+    headers.set_raw("Authorization", authorization);
+    negotiation.set_authenticated(target.index, true);
+    Ok(Some(took))
+}
+
+/// The ntlm challenge of a response, among the `WWW-Authenticate` headers it may have.
+fn negotiate_challenge(headers: &Headers) -> Option<String> {
+    headers
+        .get_raw("WWW-Authenticate")?
+        .iter()
+        .map(|challenge| String::from_utf8_lossy(challenge).trim().to_string())
+        .find(|challenge| {
+            let scheme = challenge.split(' ').next().unwrap_or("");
+            (scheme.eq_ignore_ascii_case("negotiate") || scheme.eq_ignore_ascii_case("ntlm"))
+                && challenge.contains(' ')
+        })
+}
+
+/// Takes up the fresh challenge of a 401, as servers send when a nonce has gone stale.
+fn renew(session: &mut digest::Session, target: Target, headers: &Headers) {
+    if let Some(challenge) = challenge_of(headers) {
+        session
+            .challenge(target.index, Some(&challenge))
+            .expect("Expected a digest challenge rench can answer");
+    }
+}
+
+/// The failure of a response, which is the plugin rejecting it when nothing else failed.
+fn after_response(
+    plugin: &mut plugin::Instance,
+    status: u16,
+    headers: &Headers,
+    body: &[u8],
+    failure: Option<Failure>,
+) -> Option<Failure> {
+    let accepted = plugin
+        .after_response(status, headers, body)
+        .expect("Expected the plugin's after_response to run");
+    match failure {
+        None if !accepted => Some(Failure::Plugin),
+        failure => failure,
+    }
+}
+
+/// The lines of the `Set-Cookie` headers of a response.
+fn set_cookies(headers: &Headers) -> Vec<Vec<u8>> {
+    headers
+        .get_raw("Set-Cookie")
+        .map(|raw| raw.iter().map(<[u8]>::to_vec).collect())
+        .unwrap_or_default()
+}
+
+/// Whether a cache in front of the server answered, judging by the headers it added.
+fn cache_hit(headers: &Headers) -> Option<bool> {
+    let header = |name: &str| {
+        headers
+            .get_raw(name)
+            .and_then(|raw| raw.one())
+            .and_then(|value| ::std::str::from_utf8(value).ok())
+    };
+    cache::is_hit(header("CF-Cache-Status"), header("X-Cache"), header("Age"))
+}
+
+/// Attaches what is known of the connection the fact was the first to use.
+fn with_connection(fact: Fact, connection: Connection) -> Fact {
+    let fact = fact.with_connect(connection.duration);
+    let fact = match connection.tls_version {
+        Some(version) => fact.with_tls_version(version),
+        None => fact,
+    };
+    let fact = match connection.cipher {
+        Some(cipher) => fact.with_cipher(cipher),
+        None => fact,
+    };
+    let fact = match connection.alpn {
+        Some(alpn) => fact.with_alpn(alpn),
+        None => fact,
+    };
+    match connection.expires_in {
+        Some(seconds) => fact.with_expires_in(seconds),
+        None => fact,
+    }
+}
+
+/// The group a response falls into, by the value of the header it is grouped by.
+fn group_of(value: Option<&[u8]>) -> String {
+    match value {
+        Some(value) => String::from_utf8_lossy(value).trim().to_string(),
+        None => "(none)".to_string(),
+    }
+}
+
+/// Feeds the reader into a hyper body a chunk at a time, as fast as the connection takes it.
+fn streamed_body(
+    handle: &::tokio_core::reactor::Handle,
+    mut reader: Box<dyn Read + Send>,
+    chunk: usize,
+) -> ::hyper::Body {
+    use futures::sync::mpsc::SendError;
+    use futures::{stream, Async, Future, Poll, Sink};
+    use hyper::{Body, Chunk, Error};
+
+    type Item = Result<Chunk, Error>;
+
+    let (tx, body) = Body::pair();
+    let chunks = stream::poll_fn(move || -> Poll<Option<Item>, SendError<Item>> {
+        let mut buffer = vec![0; chunk];
+        let chunk = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(Async::Ready(None)),
+            Ok(len) => {
+                buffer.truncate(len);
+                Ok(Chunk::from(buffer))
+            }
+            Err(e) => Err(Error::from(e)),
+        };
+        Ok(Async::Ready(Some(chunk)))
+    });
+    handle.spawn(tx.send_all(chunks).map(|_| ()).map_err(|_| ()));
+    body
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn serve() -> String {
+        use serve::Server;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        Server::default().spawn(listener);
+        url
+    }
+
     #[test]
     fn reqwest_engine_can_collect_facts() {
-        let eng = Engine::new(vec!["https://www.google.com".to_string()]);
+        let eng = Engine::new(vec![serve()]);
         let mut fact: Option<Fact> = None;
         eng.run(1, |f| fact = Some(f));
         assert!(fact.is_some());
@@ -154,9 +2424,246 @@ mod tests {
 
     #[test]
     fn hyper_engine_can_collect_facts() {
-        let eng = Engine::new(vec!["https://www.google.com".to_string()]).with_hyper();
+        let eng = Engine::new(vec![serve()]).with_hyper();
         let mut fact: Option<Fact> = None;
         eng.run(1, |f| fact = Some(f));
         assert!(fact.is_some());
     }
+
+    #[test]
+    fn responses_too_large_keep_their_status() {
+        for eng in [
+            Engine::new(vec![serve()]).with_max_response_size(5),
+            Engine::new(vec![serve()]).with_max_response_size(5).with_hyper(),
+        ] {
+            let mut fact: Option<Fact> = None;
+            eng.run(1, |f| fact = Some(f));
+            let json = fact.unwrap().to_json();
+            assert_eq!(json["status"], 200);
+            assert_eq!(json["failure"], "too-large");
+        }
+    }
+
+    #[test]
+    fn plugins_can_reject_responses() {
+        use std::env;
+        use std::fs;
+        use wat;
+
+        let path = env::temp_dir().join("rench_rejecting_plugin.wasm");
+        let module = wat::parse_str(
+            r#"(module
+                 (memory (export "memory") 1)
+                 (func (export "alloc") (param i32) (result i32) (i32.const 0))
+                 (func (export "after_response") (param i32 i32) (result i32) (i32.const 1)))"#,
+        )
+        .unwrap();
+        fs::write(&path, module).unwrap();
+        let plugin = Plugin::load(path.to_str().unwrap()).unwrap();
+
+        for eng in [
+            Engine::new(vec![serve()]).with_plugin(plugin.clone()),
+            Engine::new(vec![serve()]).with_plugin(plugin).with_hyper(),
+        ] {
+            let mut fact: Option<Fact> = None;
+            eng.run(1, |f| fact = Some(f));
+            assert_eq!(fact.unwrap().to_json()["failure"], "plugin");
+        }
+    }
+
+    #[test]
+    fn websocket_engine_can_collect_facts() {
+        use std::net::TcpListener;
+        use std::thread;
+        use tungstenite;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            while let Ok(message) = socket.read() {
+                if message.is_text() {
+                    socket.send(message).unwrap();
+                }
+            }
+        });
+
+        let eng = Engine::new(vec![url]).with_websocket("ping".to_string());
+        let mut facts: Vec<Fact> = Vec::new();
+        eng.run(3, |f| facts.push(f));
+        server.join().unwrap();
+
+        assert_eq!(facts.len(), 3);
+    }
+
+    #[test]
+    fn websocket_and_sse_engines_count_refused_connections() {
+        use std::net::TcpListener;
+
+        // Nothing listens on the port once the listener is dropped.
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        for eng in [
+            Engine::new(vec![format!("ws://{}", addr)]).with_websocket("ping".to_string()),
+            Engine::new(vec![format!("http://{}", addr)]).with_sse(),
+        ] {
+            let mut facts: Vec<Fact> = Vec::new();
+            eng.run(2, |f| facts.push(f));
+            assert!(!facts.is_empty());
+            for fact in &facts {
+                assert_eq!(fact.to_json()["failure"], "connect-refused");
+            }
+        }
+    }
+
+    #[test]
+    fn grpc_engine_can_collect_facts() {
+        use futures::{Future, Stream};
+        use h2::server;
+        use http::{HeaderMap, Response};
+        use std::env;
+        use std::fs::File;
+        use std::io::Write;
+        use std::net::TcpListener;
+        use std::thread;
+        use tokio_core::net::TcpStream;
+        use tokio_core::reactor::Core;
+
+        let proto = env::temp_dir().join("rench_engine_grpc.proto");
+        File::create(&proto)
+            .and_then(|mut f| {
+                f.write_all(
+                    b"syntax = \"proto3\"; message E {} service S { rpc M (E) returns (E); }",
+                )
+            })
+            .unwrap();
+        let call = grpc::Call::load(&proto, "S/M", "{}").unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut core = Core::new().unwrap();
+            let stream = TcpStream::from_stream(stream, &core.handle()).unwrap();
+            let serve = server::handshake(stream).and_then(|connection| {
+                connection.for_each(|(_, mut respond)| {
+                    let mut stream = respond.send_response(Response::new(()), false)?;
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert("grpc-status", "0".parse().unwrap());
+                    stream.send_trailers(trailers)
+                })
+            });
+            let _ = core.run(serve);
+        });
+
+        let eng = Engine::new(vec![addr]).with_grpc(call);
+        let mut facts: Vec<Fact> = Vec::new();
+        eng.run(3, |f| facts.push(f));
+        server.join().unwrap();
+
+        assert_eq!(facts.len(), 3);
+        let json = facts[0].to_json();
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["grpc_status"], 0);
+    }
+
+    #[test]
+    fn sse_engine_can_collect_facts() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n")
+                .unwrap();
+            stream
+                .write_all(b": hello\n\ndata: 1\n\ndata: 2\n\ndata: 3\n\n")
+                .unwrap();
+        });
+
+        let eng = Engine::new(vec![url]).with_sse();
+        let mut facts: Vec<Fact> = Vec::new();
+        eng.run(3, |f| facts.push(f));
+        server.join().unwrap();
+
+        assert_eq!(facts.len(), 3);
+    }
+
+    #[test]
+    fn connect_engine_can_collect_facts() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = listener.local_addr().unwrap().to_string();
+
+        let eng = Engine::new(vec![target]).with_connect();
+        let mut facts: Vec<Fact> = Vec::new();
+        eng.run(3, |f| facts.push(f));
+
+        assert_eq!(facts.len(), 3);
+    }
+
+    #[test]
+    fn dns_engine_can_collect_facts() {
+        use std::net::UdpSocket;
+        use std::thread;
+
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = server.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut query = [0; 512];
+            while let Ok((len, from)) = server.recv_from(&mut query) {
+                let mut reply = query[..len].to_vec();
+                reply[2] |= 0x80;
+                server.send_to(&reply, from).unwrap();
+            }
+        });
+
+        let eng = Engine::new(vec!["localhost".to_string()]).with_dns(Some(resolver));
+        let mut facts: Vec<Fact> = Vec::new();
+        eng.run(3, |f| facts.push(f));
+        assert_eq!(facts.len(), 3);
+
+        let eng = Engine::new(vec!["localhost".to_string()]).with_dns(None);
+        let mut facts: Vec<Fact> = Vec::new();
+        eng.run(2, |f| facts.push(f));
+        assert_eq!(facts.len(), 2);
+    }
+
+    #[test]
+    fn closes_connections_after_the_requests_per_conn() {
+        let urls = vec!["http://a".to_string(), "http://b".to_string()];
+        let eng = Engine::new(urls.clone()).with_requests_per_conn(2);
+        let closes: Vec<bool> = (0..8).map(|n| eng.closes_connection(n)).collect();
+        assert_eq!(
+            closes,
+            vec![false, false, true, true, false, false, true, true]
+        );
+
+        let eng = Engine::new(urls);
+        assert!(!(0..8).any(|n| eng.closes_connection(n)));
+    }
+
+    #[test]
+    fn describes_the_requests_of_a_run() {
+        let urls = vec!["http://a".to_string(), "http://b".to_string()];
+        let eng = Engine::new(urls)
+            .with_method(Method::Post)
+            .with_body(vec![b'x'; 300], "text/plain")
+            .with_header("X-Run".to_string(), Template::parse("run {{ n }}").unwrap());
+        let lines = eng.describe(10);
+        assert_eq!(lines[0], "Engine:  reqwest");
+        assert_eq!(lines[1], "Method:  POST");
+        assert_eq!(lines[3], "  http://a (~5 requests)");
+        assert_eq!(lines[6], "  X-Run: run 0");
+        assert!(lines[7].starts_with("Body:    300 B"));
+        assert!(lines[8].ends_with("x..."));
+        assert_eq!(lines.len(), 10);
+    }
 }
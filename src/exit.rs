@@ -0,0 +1,62 @@
+use std::fmt;
+use std::panic;
+use std::process;
+
+/// The run finished but did not meet its thresholds, or a gate stopped it.
+pub const THRESHOLDS: i32 = 1;
+
+/// The options, or the files they name, were not valid.
+pub const CONFIG: i32 = 2;
+
+/// None of the requests got a response, such as when the targets refuse connections or
+/// their names cannot be resolved.
+pub const UNREACHABLE: i32 = 3;
+
+/// Something went wrong within rench itself.
+pub const INTERNAL: i32 = 4;
+
+/// Exits with `INTERNAL` on a panic, once it has been printed as usual, rather than with the
+/// code every panic has. Options that are not valid exit with `CONFIG` where they are read
+/// instead of panicking.
+pub fn on_panic() {
+    let print = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        print(info);
+        process::exit(INTERNAL);
+    }));
+}
+
+/// Reads an option, or a file it names, exiting with `CONFIG` when it is not valid.
+pub trait OrInvalid<T> {
+    /// The value, or else an exit with `CONFIG` once the message is printed with why.
+    fn or_invalid(self, message: &str) -> T;
+}
+
+impl<T, E: fmt::Display> OrInvalid<T> for Result<T, E> {
+    fn or_invalid(self, message: &str) -> T {
+        self.unwrap_or_else(|e| invalid(&format!("{}: {}", message, e)))
+    }
+}
+
+impl<T> OrInvalid<T> for Option<T> {
+    fn or_invalid(self, message: &str) -> T {
+        self.unwrap_or_else(|| invalid(message))
+    }
+}
+
+/// Prints why the options are not valid and exits with `CONFIG`.
+pub fn invalid(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(CONFIG);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_valid_options() {
+        assert_eq!("4".parse::<usize>().or_invalid("Expected valid number for threads"), 4);
+        assert_eq!(Some(95f64).or_invalid("Expected a valid confidence"), 95f64);
+    }
+}
@@ -0,0 +1,365 @@
+use httparse;
+use hyper::Uri;
+use native_tls::TlsConnector;
+use socket::Options;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// How long to wait for the server to ask for the body before sending it anyway.
+const CONTINUE_TIMEOUT: Duration = Duration::from_secs(1);
+
+trait Socket: Read + Write {}
+impl<S: Read + Write> Socket for S {}
+
+/// A http/1.1 connection that sends a request's head with `Expect: 100-continue` and only
+/// sends the body once the server asks for it. Hyper takes the interim response as the
+/// final one, so the exchange is written out by hand.
+pub struct Connection {
+    tcp: TcpStream,
+    io: Box<dyn Socket>,
+    buffer: Vec<u8>,
+    host: String,
+    path: String,
+}
+
+/// The outcome of a single request.
+pub struct Response {
+    pub status: u16,
+    pub version: &'static str,
+    pub content_length: u64,
+    /// How long the server took to ask for the body, none if it never did.
+    pub continued: Option<Duration>,
+    /// Whether the body was sent, which it is not when the server answers without it.
+    pub sent: bool,
+    /// Whether the connection can carry another request.
+    pub reusable: bool,
+}
+
+/// The parts of a response head that decide how to read what follows it.
+struct Head {
+    status: u16,
+    version: &'static str,
+    length: Option<u64>,
+    chunked: bool,
+    close: bool,
+}
+
+impl Connection {
+    /// Connects to the url, completing a tls handshake for https.
+    pub fn open(url: &str, options: &Options) -> io::Result<Connection> {
+        let uri: Uri = url
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let host = uri
+            .host()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Url must have a host"))?
+            .to_string();
+        let secure = uri.scheme() == Some("https");
+        let port = uri.port().unwrap_or(if secure { 443 } else { 80 });
+
+        let addr = (host.as_str(), port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No addresses for the host"))?;
+        let tcp = options.connect(&addr)?;
+        let stream = tcp.try_clone()?;
+        let io: Box<dyn Socket> = if secure {
+            let tls = TlsConnector::builder()
+                .and_then(|builder| builder.build())
+                .map_err(io::Error::other)?;
+            Box::new(tls.connect(&host, stream).map_err(io::Error::other)?)
+        } else {
+            Box::new(stream)
+        };
+
+        Ok(Connection {
+            tcp,
+            io,
+            buffer: Vec::new(),
+            host: match uri.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host,
+            },
+            path: uri.path().to_string()
+                + &uri.query().map(|q| format!("?{}", q)).unwrap_or_default(),
+        })
+    }
+
+    /// Posts the body, which is sent chunked when its length is not known.
+    pub fn post(
+        &mut self,
+        content_type: &str,
+        body: &mut dyn Read,
+        len: Option<u64>,
+    ) -> io::Result<Response> {
+        let start = Instant::now();
+        let framing = match len {
+            Some(len) => format!("Content-Length: {}", len),
+            None => "Transfer-Encoding: chunked".to_string(),
+        };
+        write!(
+            self.io,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\n{}\r\n\
+             Expect: 100-continue\r\n\r\n",
+            self.path, self.host, content_type, framing
+        )?;
+        self.io.flush()?;
+
+        let mut continued = None;
+        loop {
+            let remaining = CONTINUE_TIMEOUT
+                .checked_sub(start.elapsed())
+                .unwrap_or_default();
+            if remaining == Duration::new(0, 0) {
+                break;
+            }
+            self.tcp.set_read_timeout(Some(remaining))?;
+            match self.read_head() {
+                Ok(ref head) if head.status == 100 => {
+                    continued = Some(start.elapsed());
+                    break;
+                }
+                Ok(ref head) if head.status < 200 => continue,
+                // The server answered without wanting the body, so the connection is
+                // left in a state that is not worth recovering.
+                Ok(head) => {
+                    self.tcp.set_read_timeout(None)?;
+                    let content_length = self.read_body(&head)?;
+                    return Ok(Response {
+                        status: head.status,
+                        version: head.version,
+                        content_length,
+                        continued,
+                        sent: false,
+                        reusable: false,
+                    });
+                }
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    break
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        self.tcp.set_read_timeout(None)?;
+
+        self.write_body(body, len.is_none())?;
+        let head = loop {
+            let head = self.read_head()?;
+            if head.status >= 200 {
+                break head;
+            }
+        };
+        let content_length = self.read_body(&head)?;
+        Ok(Response {
+            status: head.status,
+            version: head.version,
+            content_length,
+            continued,
+            sent: true,
+            reusable: !head.close && (head.chunked || head.length.is_some()),
+        })
+    }
+
+    fn write_body(&mut self, body: &mut dyn Read, chunked: bool) -> io::Result<()> {
+        if !chunked {
+            io::copy(body, &mut self.io)?;
+            return self.io.flush();
+        }
+
+        let mut chunk = vec![0; 64 * 1024];
+        loop {
+            let len = body.read(&mut chunk)?;
+            write!(self.io, "{:x}\r\n", len)?;
+            self.io.write_all(&chunk[..len])?;
+            self.io.write_all(b"\r\n")?;
+            if len == 0 {
+                return self.io.flush();
+            }
+        }
+    }
+
+    fn read_head(&mut self) -> io::Result<Head> {
+        loop {
+            let parsed = {
+                let mut headers = [httparse::EMPTY_HEADER; 64];
+                let mut response = httparse::Response::new(&mut headers);
+                match response.parse(&self.buffer) {
+                    Ok(httparse::Status::Complete(end)) => {
+                        let mut head = Head {
+                            status: response.code.unwrap_or(0),
+                            version: if response.version == Some(0) {
+                                "HTTP/1.0"
+                            } else {
+                                "HTTP/1.1"
+                            },
+                            length: None,
+                            chunked: false,
+                            close: response.version == Some(0),
+                        };
+                        for header in response.headers.iter() {
+                            let value = String::from_utf8_lossy(header.value).to_lowercase();
+                            if header.name.eq_ignore_ascii_case("content-length") {
+                                head.length = value.trim().parse().ok();
+                            } else if header.name.eq_ignore_ascii_case("transfer-encoding") {
+                                head.chunked = value.trim().ends_with("chunked");
+                            } else if header.name.eq_ignore_ascii_case("connection") {
+                                head.close = value.contains("close");
+                            }
+                        }
+                        Some((head, end))
+                    }
+                    Ok(httparse::Status::Partial) => None,
+                    Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                }
+            };
+            if let Some((head, end)) = parsed {
+                self.buffer.drain(..end);
+                return Ok(head);
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Reads and discards the body, returning its length.
+    fn read_body(&mut self, head: &Head) -> io::Result<u64> {
+        if head.status < 200 || head.status == 204 || head.status == 304 {
+            return Ok(0);
+        }
+        if head.chunked {
+            let mut total = 0;
+            loop {
+                let line = self.read_line()?;
+                let size = line.split(';').next().unwrap_or("").trim();
+                let size = u64::from_str_radix(size, 16)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if size == 0 {
+                    while !self.read_line()?.is_empty() {}
+                    return Ok(total);
+                }
+                self.skip(size + 2)?;
+                total += size;
+            }
+        }
+        match head.length {
+            Some(length) => {
+                self.skip(length)?;
+                Ok(length)
+            }
+            None => {
+                let mut total = self.buffer.len() as u64;
+                self.buffer.clear();
+                while self.fill().is_ok() {
+                    total += self.buffer.len() as u64;
+                    self.buffer.clear();
+                }
+                Ok(total)
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                return Ok(String::from_utf8_lossy(&line).trim_end().to_string());
+            }
+            self.fill()?;
+        }
+    }
+
+    fn skip(&mut self, mut len: u64) -> io::Result<()> {
+        while len > 0 {
+            if self.buffer.is_empty() {
+                self.fill()?;
+            }
+            let taken = ::std::cmp::min(len, self.buffer.len() as u64);
+            self.buffer.drain(..taken as usize);
+            len -= taken;
+        }
+        Ok(())
+    }
+
+    fn fill(&mut self) -> io::Result<usize> {
+        let mut chunk = [0; 16 * 1024];
+        let len = self.io.read(&mut chunk)?;
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection closed",
+            ));
+        }
+        self.buffer.extend_from_slice(&chunk[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn serve<F>(respond: F) -> String
+    where
+        F: Fn(&mut BufReader<TcpStream>, &mut TcpStream) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/upload", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            respond(&mut reader, &mut stream);
+        });
+        url
+    }
+
+    #[test]
+    fn it_sends_the_body_when_asked() {
+        let url = serve(|reader, stream| {
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+            let mut body = [0; 5];
+            reader.read_exact(&mut body).unwrap();
+            assert_eq!(&body, b"hello");
+            stream
+                .write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok")
+                .unwrap();
+        });
+
+        let mut connection = Connection::open(&url, &Options::default()).unwrap();
+        let response = connection
+            .post("text/plain", &mut &b"hello"[..], Some(5))
+            .unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(response.content_length, 2);
+        assert!(response.continued.is_some());
+        assert!(response.sent);
+        assert!(response.reusable);
+    }
+
+    #[test]
+    fn it_stops_when_the_server_answers_early() {
+        let url = serve(|_, stream| {
+            stream
+                .write_all(b"HTTP/1.1 417 Expectation Failed\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+
+        let mut connection = Connection::open(&url, &Options::default()).unwrap();
+        let response = connection
+            .post("text/plain", &mut &b"hello"[..], None)
+            .unwrap();
+        assert_eq!(response.status, 417);
+        assert!(response.continued.is_none());
+        assert!(!response.sent);
+        assert!(!response.reusable);
+    }
+}
@@ -0,0 +1,234 @@
+use h2;
+use hyper;
+use native_tls;
+use oversize;
+use proxy;
+use reqwest;
+use socket;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use tls;
+use tungstenite;
+
+/// The reasons a request can be counted as failed without stopping the benchmark.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum Failure {
+    /// A graphql response that carried a non-empty `errors` array.
+    GraphQl,
+    /// A name that could not be resolved, or whose resolver answered with an error.
+    Dns,
+    /// A connection that could not be opened for want of a free local port.
+    PortExhaustion,
+    /// A response that a plugin's `after_response` hook rejected.
+    Plugin,
+    /// A proxy that answered with a 407, asking for credentials it was not given or did not
+    /// accept.
+    ProxyAuthentication,
+    /// A server whose certificate did not have any of the pinned public keys.
+    PinMismatch,
+    /// A response whose body was larger than the most the requests read of one.
+    TooLarge,
+    /// A connection the server refused, such as to a port nothing listens on.
+    ConnectRefused,
+    /// A tls handshake that failed, such as on a certificate that could not be verified.
+    Tls,
+    /// A connection or a response that took longer than was allowed.
+    Timeout,
+    /// A connection that the server or something in between reset or closed early.
+    Reset,
+    /// Any other error of reading a response or writing a request.
+    Read,
+    /// A response with a status the benchmark was told to count as a failure.
+    Status,
+    /// A response with a header that was missing or did not hold as asserted.
+    Header,
+}
+
+impl Failure {
+    /// A short name for the failure that can be read back with `parse`.
+    pub fn key(&self) -> &'static str {
+        match *self {
+            Failure::GraphQl => "graphql",
+            Failure::Dns => "dns",
+            Failure::PortExhaustion => "port-exhaustion",
+            Failure::Plugin => "plugin",
+            Failure::ProxyAuthentication => "proxy-auth",
+            Failure::PinMismatch => "pin-mismatch",
+            Failure::TooLarge => "too-large",
+            Failure::ConnectRefused => "connect-refused",
+            Failure::Tls => "tls",
+            Failure::Timeout => "timeout",
+            Failure::Reset => "reset",
+            Failure::Read => "read",
+            Failure::Status => "status",
+            Failure::Header => "header",
+        }
+    }
+}
+
+impl FromStr for Failure {
+    type Err = String;
+
+    fn from_str(key: &str) -> Result<Failure, String> {
+        match key {
+            "graphql" => Ok(Failure::GraphQl),
+            "dns" => Ok(Failure::Dns),
+            "port-exhaustion" => Ok(Failure::PortExhaustion),
+            "plugin" => Ok(Failure::Plugin),
+            "proxy-auth" => Ok(Failure::ProxyAuthentication),
+            "pin-mismatch" => Ok(Failure::PinMismatch),
+            "too-large" => Ok(Failure::TooLarge),
+            "connect-refused" => Ok(Failure::ConnectRefused),
+            "tls" => Ok(Failure::Tls),
+            "timeout" => Ok(Failure::Timeout),
+            "reset" => Ok(Failure::Reset),
+            "read" => Ok(Failure::Read),
+            "status" => Ok(Failure::Status),
+            "header" => Ok(Failure::Header),
+            _ => Err(format!("Unknown failure {}", key)),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Failure::GraphQl => write!(f, "graphql errors"),
+            Failure::Dns => write!(f, "dns errors"),
+            Failure::PortExhaustion => write!(f, "local ports exhausted"),
+            Failure::Plugin => write!(f, "plugin rejections"),
+            Failure::ProxyAuthentication => write!(f, "proxy authentication failures"),
+            Failure::PinMismatch => write!(f, "certificate pin mismatches"),
+            Failure::TooLarge => write!(f, "responses over the size limit"),
+            Failure::ConnectRefused => write!(f, "connections refused"),
+            Failure::Tls => write!(f, "tls errors"),
+            Failure::Timeout => write!(f, "timeouts"),
+            Failure::Reset => write!(f, "connections reset"),
+            Failure::Read => write!(f, "read errors"),
+            Failure::Status => write!(f, "unexpected statuses"),
+            Failure::Header => write!(f, "header assertions failed"),
+        }
+    }
+}
+
+/// The failure an error of a connection or of its reads and writes stands for.
+pub fn of_io(e: &io::Error) -> Failure {
+    if socket::is_port_exhaustion(e) {
+        return Failure::PortExhaustion;
+    }
+    if proxy::is_authentication_required(e) {
+        return Failure::ProxyAuthentication;
+    }
+    if tls::is_pin_mismatch(e) {
+        return Failure::PinMismatch;
+    }
+    if oversize::is_too_large(e) {
+        return Failure::TooLarge;
+    }
+    if e.get_ref().is_some_and(|inner| inner.is::<native_tls::Error>()) {
+        return Failure::Tls;
+    }
+    match e.kind() {
+        io::ErrorKind::ConnectionRefused => Failure::ConnectRefused,
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Failure::Timeout,
+        io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::UnexpectedEof => Failure::Reset,
+        // Names that could not be looked up are only told apart by the message.
+        _ if e.to_string().contains("lookup address") => Failure::Dns,
+        _ => Failure::Read,
+    }
+}
+
+/// The failure an error of a hyper client stands for.
+pub fn of_hyper(e: &hyper::Error) -> Failure {
+    match *e {
+        hyper::Error::Io(ref e) => of_io(e),
+        hyper::Error::Timeout => Failure::Timeout,
+        hyper::Error::Incomplete => Failure::Reset,
+        _ => Failure::Read,
+    }
+}
+
+/// The failure an error of an http/2 connection or stream stands for, a stream the server
+/// reset when it is not an error of the connection.
+pub fn of_h2(e: &h2::Error) -> Failure {
+    match e.get_io() {
+        Some(e) => of_io(e),
+        None => Failure::Reset,
+    }
+}
+
+/// The failure an error of a reqwest client stands for.
+pub fn of_reqwest(e: &reqwest::Error) -> Failure {
+    let inner = match e.get_ref() {
+        Some(inner) => inner,
+        None => return Failure::Read,
+    };
+    if let Some(e) = inner.downcast_ref::<hyper::Error>() {
+        of_hyper(e)
+    } else if let Some(e) = inner.downcast_ref::<io::Error>() {
+        of_io(e)
+    } else if inner.is::<native_tls::Error>() {
+        Failure::Tls
+    } else {
+        Failure::Read
+    }
+}
+
+/// The failure an error of a websocket stands for.
+pub fn of_tungstenite(e: &tungstenite::Error) -> Failure {
+    match *e {
+        tungstenite::Error::Io(ref e) => of_io(e),
+        tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => Failure::Reset,
+        _ => Failure::Read,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_back_its_keys() {
+        for failure in &[
+            Failure::GraphQl,
+            Failure::Dns,
+            Failure::PortExhaustion,
+            Failure::Plugin,
+            Failure::ProxyAuthentication,
+            Failure::PinMismatch,
+            Failure::TooLarge,
+            Failure::ConnectRefused,
+            Failure::Tls,
+            Failure::Timeout,
+            Failure::Reset,
+            Failure::Read,
+            Failure::Status,
+            Failure::Header,
+        ] {
+            assert_eq!(failure.key().parse(), Ok(*failure));
+        }
+        assert!("refused".parse::<Failure>().is_err());
+    }
+
+    #[test]
+    fn it_classifies_errors() {
+        let of_kind = |kind| of_io(&io::Error::new(kind, "error"));
+        assert_eq!(of_kind(io::ErrorKind::ConnectionRefused), Failure::ConnectRefused);
+        assert_eq!(of_kind(io::ErrorKind::TimedOut), Failure::Timeout);
+        assert_eq!(of_kind(io::ErrorKind::ConnectionReset), Failure::Reset);
+        assert_eq!(of_kind(io::ErrorKind::AddrNotAvailable), Failure::PortExhaustion);
+        assert_eq!(of_kind(io::ErrorKind::InvalidData), Failure::Read);
+        assert_eq!(
+            of_io(&io::Error::other("failed to lookup address information")),
+            Failure::Dns
+        );
+        assert_eq!(of_io(&oversize::too_large(1)), Failure::TooLarge);
+        assert_eq!(of_hyper(&hyper::Error::Timeout), Failure::Timeout);
+        assert_eq!(of_tungstenite(&tungstenite::Error::ConnectionClosed), Failure::Reset);
+        assert_eq!(of_hyper(&hyper::Error::Status), Failure::Read);
+    }
+}
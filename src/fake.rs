@@ -0,0 +1,152 @@
+use rand::{self, Rng};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use tera::{self, Tera};
+
+const FIRST_NAMES: [&str; 16] = [
+    "Ada",
+    "Alan",
+    "Barbara",
+    "Charles",
+    "Donald",
+    "Edsger",
+    "Frances",
+    "Grace",
+    "Hedy",
+    "John",
+    "Katherine",
+    "Ken",
+    "Linus",
+    "Margaret",
+    "Niklaus",
+    "Radia",
+];
+const LAST_NAMES: [&str; 16] = [
+    "Allen",
+    "Backus",
+    "Dijkstra",
+    "Hamilton",
+    "Hopper",
+    "Johnson",
+    "Kernighan",
+    "Knuth",
+    "Lamarr",
+    "Liskov",
+    "Lovelace",
+    "McCarthy",
+    "Perlman",
+    "Ritchie",
+    "Shannon",
+    "Turing",
+];
+const DOMAINS: [&str; 3] = ["example.com", "example.net", "example.org"];
+
+/// Adds the functions that make up plausible data to the templates:
+///
+/// * `name()`, a first and last name
+/// * `email()`, an address at one of the example domains
+/// * `uuid()`, a random version 4 uuid
+/// * `int(min, max)`, a whole number between the two, both included
+pub fn register(tera: &mut Tera) {
+    tera.register_function("name", |_: &HashMap<String, Value>| {
+        let (first, last) = name();
+        Ok(Value::String(format!("{} {}", first, last)))
+    });
+    tera.register_function("email", |_: &HashMap<String, Value>| {
+        let (first, last) = name();
+        let mut rng = rand::thread_rng();
+        Ok(Value::String(format!(
+            "{}.{}{}@{}",
+            first.to_lowercase(),
+            last.to_lowercase(),
+            rng.gen_range(1..1000),
+            DOMAINS[rng.gen_range(0..DOMAINS.len())]
+        )))
+    });
+    tera.register_function("uuid", |_: &HashMap<String, Value>| {
+        Ok(Value::String(uuid()))
+    });
+    tera.register_function("int", |args: &HashMap<String, Value>| {
+        let bound = |name: &str, default: i64| match args.get(name) {
+            Some(value) => value
+                .as_i64()
+                .ok_or_else(|| tera::Error::msg(format!("int expects {} to be a number", name))),
+            None => Ok(default),
+        };
+        let (min, max) = (bound("min", 0)?, bound("max", 100)?);
+        if min > max {
+            return Err(tera::Error::msg("int expects min to be no more than max"));
+        }
+        Ok(Value::from(rand::thread_rng().gen_range(min..=max)))
+    });
+}
+
+/// Lets `int(1, 1000)` be written without naming the bounds, which tera always needs.
+pub fn name_arguments(template: &str) -> Cow<'_, str> {
+    let int = Regex::new(r"\bint\(\s*(-?\d+)\s*,\s*(-?\d+)\s*\)").expect("Expected a valid regex");
+    int.replace_all(template, |captures: &Captures| {
+        format!("int(min={}, max={})", &captures[1], &captures[2])
+    })
+}
+
+fn name() -> (&'static str, &'static str) {
+    let mut rng = rand::thread_rng();
+    (
+        FIRST_NAMES[rng.gen_range(0..FIRST_NAMES.len())],
+        LAST_NAMES[rng.gen_range(0..LAST_NAMES.len())],
+    )
+}
+
+/// A random version 4 uuid.
+pub fn uuid() -> String {
+    let mut bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tera::Context;
+
+    fn render(template: &str) -> Result<String, tera::Error> {
+        let mut tera = Tera::default();
+        register(&mut tera);
+        tera.add_raw_template("fake", &name_arguments(template))?;
+        tera.render("fake", &Context::new())
+    }
+
+    #[test]
+    fn it_names_int_arguments() {
+        assert_eq!(
+            name_arguments("{{ int(1, 1000) }}"),
+            "{{ int(min=1, max=1000) }}"
+        );
+        assert_eq!(name_arguments("{{ int(min=1) }}"), "{{ int(min=1) }}");
+    }
+
+    #[test]
+    fn it_makes_up_data() {
+        let uuid = render("{{ uuid() }}").unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert!(render("{{ email() }}").unwrap().contains("@example."));
+        assert_eq!(render("{{ name() }}").unwrap().split(' ').count(), 2);
+        for _ in 0..20 {
+            let int: i64 = render("{{ int(-2,2) }}").unwrap().parse().unwrap();
+            assert!((-2..=2).contains(&int));
+        }
+        assert!(render("{{ int(5, 1) }}").is_err());
+    }
+}
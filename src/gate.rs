@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+/// Fails a run early when the tail latency of its first requests is already over a limit, so
+/// that a build that has obviously regressed does not take a whole benchmark to find out.
+#[derive(Debug)]
+pub struct Gate {
+    p99: Duration,
+    after: usize,
+    durations: Vec<Duration>,
+}
+
+impl Gate {
+    /// Gates on the p99 of the first `after` requests.
+    pub fn new(p99: Duration, after: usize) -> Gate {
+        Gate {
+            p99,
+            after,
+            durations: Vec::with_capacity(after),
+        }
+    }
+
+    /// Records how long a request took, handing back the p99 of the first requests once they
+    /// have all been made if it is over the limit.
+    pub fn record(&mut self, duration: Duration) -> Option<Duration> {
+        if self.durations.len() >= self.after {
+            return None;
+        }
+        self.durations.push(duration);
+        if self.durations.len() < self.after {
+            return None;
+        }
+        self.durations.sort();
+        let rank = (self.durations.len() * 99).div_ceil(100);
+        let p99 = self.durations[rank.max(1) - 1];
+        if p99 > self.p99 {
+            Some(p99)
+        } else {
+            None
+        }
+    }
+
+    /// How many requests the gate is on.
+    pub fn after(&self) -> usize {
+        self.after
+    }
+
+    /// The most the p99 of the first requests can be.
+    pub fn p99(&self) -> Duration {
+        self.p99
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_fails_once_the_first_requests_are_too_slow() {
+        let ms = Duration::from_millis;
+        let mut gate = Gate::new(ms(50), 100);
+        for _ in 0..98 {
+            assert_eq!(gate.record(ms(10)), None);
+        }
+        assert_eq!(gate.record(ms(60)), None);
+        assert_eq!(gate.record(ms(70)), Some(ms(60)));
+        assert_eq!(gate.record(ms(500)), None);
+
+        let mut gate = Gate::new(ms(50), 100);
+        for _ in 0..99 {
+            gate.record(ms(10));
+        }
+        assert_eq!(gate.record(ms(500)), None);
+    }
+}
@@ -0,0 +1,71 @@
+use stats::Summary;
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long carbon has to take a connection, so that it cannot hold up the run.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The metrics of a summary in graphite's plaintext protocol, one `path value timestamp`
+/// to a line, with latencies in milliseconds.
+pub fn lines(prefix: &str, summary: &Summary, rps: f64, at: SystemTime) -> String {
+    let timestamp = at
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+    let mut metrics = vec![
+        ("requests", summary.requests() as f64),
+        ("errors", summary.errors() as f64),
+        ("error_rate", summary.error_rate()),
+        ("rps", rps),
+    ];
+    if summary.has_latencies() {
+        for &(name, percentage) in &[("p50", 50f64), ("p90", 90f64), ("p99", 99f64)] {
+            let latency = summary.latency_at(percentage).expect("Expected latencies");
+            metrics.push((name, latency.as_secs_f64() * 1_000f64));
+        }
+        metrics.push(("max", summary.max().as_secs_f64() * 1_000f64));
+    }
+    metrics
+        .iter()
+        .map(|&(name, value)| format!("{}.{} {} {}\n", prefix, name, value, timestamp))
+        .collect()
+}
+
+/// Sends lines of the plaintext protocol to the carbon at the address, such as
+/// `graphite:2003`, on a connection of their own.
+pub fn send(address: &str, lines: &str) -> io::Result<()> {
+    let addr = address.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("{} has no address", address))
+    })?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(lines.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content_length::ContentLength;
+    use stats::Fact;
+
+    #[test]
+    fn it_writes_the_plaintext_protocol() {
+        let facts = [
+            Fact::record(ContentLength::zero(), 200, Duration::from_millis(10)),
+            Fact::record(ContentLength::zero(), 500, Duration::from_millis(30)),
+        ];
+        let at = UNIX_EPOCH + Duration::from_secs(1_714_568_400);
+        let lines = lines("bench.api", &Summary::from_facts(&facts), 2.5, at);
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(lines[0], "bench.api.requests 2 1714568400");
+        assert_eq!(lines[1], "bench.api.errors 1 1714568400");
+        assert_eq!(lines[2], "bench.api.error_rate 0.5 1714568400");
+        assert_eq!(lines[3], "bench.api.rps 2.5 1714568400");
+        assert_eq!(lines[7], "bench.api.max 30 1714568400");
+        assert_eq!(lines.len(), 8);
+
+        let empty = super::lines("bench", &Summary::from_facts(&[]), 0f64, at);
+        assert_eq!(empty.lines().count(), 4);
+    }
+}
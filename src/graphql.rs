@@ -0,0 +1,55 @@
+use serde_json::{self, Map, Value};
+
+/// Builds the json body of a graphql request from the query and optional variables.
+pub fn body(query: &str, variables: Option<&str>) -> Result<String, String> {
+    let mut body = Map::new();
+    body.insert("query".to_string(), Value::String(query.to_string()));
+    if let Some(variables) = variables {
+        let variables: Value = serde_json::from_str(variables)
+            .map_err(|e| format!("Invalid json variables: {}", e))?;
+        body.insert("variables".to_string(), variables);
+    }
+    serde_json::to_string(&Value::Object(body)).map_err(|e| e.to_string())
+}
+
+/// A graphql server answers with a 200 even when the query failed, so the body has to
+/// be checked for errors instead.
+pub fn has_errors(body: &[u8]) -> bool {
+    serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|response| {
+            response
+                .get("errors")
+                .and_then(|errors| errors.as_array())
+                .map(|errors| !errors.is_empty())
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_builds_the_request_body() {
+        assert_eq!(
+            body("{ me { id } }", None).unwrap(),
+            r#"{"query":"{ me { id } }"}"#
+        );
+        assert_eq!(
+            body("query($id: ID) { user(id: $id) }", Some(r#"{"id": 1}"#)).unwrap(),
+            r#"{"query":"query($id: ID) { user(id: $id) }","variables":{"id":1}}"#
+        );
+        assert!(body("{ me }", Some("nope")).is_err());
+    }
+
+    #[test]
+    fn it_detects_errors_in_the_response() {
+        assert!(has_errors(
+            br#"{"data": null, "errors": [{"message": "bad"}]}"#
+        ));
+        assert!(!has_errors(br#"{"data": {"me": null}, "errors": []}"#));
+        assert!(!has_errors(br#"{"data": {"me": null}}"#));
+        assert!(!has_errors(b"not json"));
+    }
+}
@@ -0,0 +1,283 @@
+use base64;
+use http::HeaderMap;
+use protobuf::reflect::{
+    FileDescriptor, MessageDescriptor, ReflectValueBox, RuntimeFieldType, RuntimeType,
+};
+use protobuf::MessageDyn;
+use protobuf_parse::Parser;
+use serde_json::{self, Value};
+use std::fs;
+use std::path::Path;
+
+/// A unary grpc call. The request message is built once from the proto definition and
+/// the json data so every request sends the exact same frame.
+#[derive(Clone)]
+pub struct Call {
+    path: String,
+    frame: Vec<u8>,
+}
+
+impl Call {
+    /// Loads the call from a proto file. The method is given as `pkg.Service/Method` and
+    /// the data is the json representation of the method's input message.
+    pub fn load(proto: &Path, method: &str, data: &str) -> Result<Call, String> {
+        let include = proto.parent().unwrap_or_else(|| Path::new("."));
+        let parsed = Parser::new()
+            .pure()
+            .include(include)
+            .input(proto)
+            .parse_and_typecheck()
+            .map_err(|e| format!("Could not parse {}: {}", proto.display(), e))?;
+        let files = FileDescriptor::new_dynamic_fds(parsed.file_descriptors, &[])
+            .map_err(|e| format!("Could not load {}: {}", proto.display(), e))?;
+
+        let descriptor = input_type(&files, method)?;
+        let json: Value =
+            serde_json::from_str(data).map_err(|e| format!("Invalid json data: {}", e))?;
+        let message = message_from_json(&descriptor, &json)?;
+        let bytes = message
+            .write_to_bytes_dyn()
+            .map_err(|e| format!("Could not encode the request: {}", e))?;
+
+        Ok(Call {
+            path: format!("/{}", method),
+            frame: frame(&bytes),
+        })
+    }
+
+    /// Reads the json data from a file before loading the call.
+    pub fn load_with_data_file(proto: &Path, method: &str, data: &Path) -> Result<Call, String> {
+        let data = fs::read_to_string(data)
+            .map_err(|e| format!("Could not read {}: {}", data.display(), e))?;
+        Self::load(proto, method, &data)
+    }
+
+    /// The http/2 path the call is made on.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The length prefixed request message.
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+}
+
+/// Extracts the grpc status from a set of headers or trailers.
+pub fn status(headers: &HeaderMap) -> Option<u16> {
+    headers
+        .get("grpc-status")
+        .and_then(|status| status.to_str().ok())
+        .and_then(|status| status.parse().ok())
+}
+
+fn input_type(files: &[FileDescriptor], method: &str) -> Result<MessageDescriptor, String> {
+    let mut parts = method.splitn(2, '/');
+    let (service, name) = match (parts.next(), parts.next()) {
+        (Some(service), Some(name)) => (service, name),
+        _ => return Err(format!("Expected pkg.Service/Method but got {}", method)),
+    };
+
+    for file in files {
+        for candidate in file.services() {
+            let full_name = if file.package().is_empty() {
+                candidate.proto().name().to_string()
+            } else {
+                format!("{}.{}", file.package(), candidate.proto().name())
+            };
+            if full_name != service {
+                continue;
+            }
+            return candidate
+                .methods()
+                .find(|m| m.proto().name() == name)
+                .map(|m| m.input_type())
+                .ok_or_else(|| format!("No method {} on {}", name, service));
+        }
+    }
+    Err(format!("No service named {}", service))
+}
+
+/// Every grpc message is prefixed with a compression flag and its length.
+fn frame(message: &[u8]) -> Vec<u8> {
+    let len = message.len() as u32;
+    let mut frame = Vec::with_capacity(message.len() + 5);
+    frame.push(0);
+    frame.extend_from_slice(&[
+        (len >> 24) as u8,
+        (len >> 16) as u8,
+        (len >> 8) as u8,
+        len as u8,
+    ]);
+    frame.extend_from_slice(message);
+    frame
+}
+
+fn message_from_json(
+    descriptor: &MessageDescriptor,
+    json: &Value,
+) -> Result<Box<dyn MessageDyn>, String> {
+    let object = json
+        .as_object()
+        .ok_or_else(|| format!("Expected an object for {}", descriptor.full_name()))?;
+    let mut message = descriptor.new_instance();
+
+    for (key, value) in object {
+        let field = descriptor
+            .field_by_name_or_json_name(key)
+            .ok_or_else(|| format!("No field {} on {}", key, descriptor.full_name()))?;
+        if value.is_null() {
+            continue;
+        }
+        match field.runtime_field_type() {
+            RuntimeFieldType::Singular(ref t) => {
+                field.set_singular_field(&mut *message, value_from_json(t, value)?);
+            }
+            RuntimeFieldType::Repeated(ref t) => {
+                let values = value
+                    .as_array()
+                    .ok_or_else(|| format!("Expected an array for {}", key))?;
+                let mut repeated = field.mut_repeated(&mut *message);
+                for value in values {
+                    repeated.push(value_from_json(t, value)?);
+                }
+            }
+            RuntimeFieldType::Map(ref k, ref v) => {
+                let entries = value
+                    .as_object()
+                    .ok_or_else(|| format!("Expected an object for {}", key))?;
+                let mut map = field.mut_map(&mut *message);
+                for (entry_key, entry_value) in entries {
+                    let entry_key = value_from_json(k, &Value::String(entry_key.clone()))?;
+                    map.insert(entry_key, value_from_json(v, entry_value)?);
+                }
+            }
+        }
+    }
+    Ok(message)
+}
+
+fn value_from_json(t: &RuntimeType, json: &Value) -> Result<ReflectValueBox, String> {
+    // Json mapping allows any number to be quoted, 64 bit ones usually are.
+    let number = |json: &Value| -> Option<f64> {
+        json.as_f64()
+            .or_else(|| json.as_str().and_then(|s| s.parse().ok()))
+    };
+    let integer = |json: &Value| -> Option<i64> {
+        json.as_i64()
+            .or_else(|| json.as_str().and_then(|s| s.parse().ok()))
+    };
+    let unsigned = |json: &Value| -> Option<u64> {
+        json.as_u64()
+            .or_else(|| json.as_str().and_then(|s| s.parse().ok()))
+    };
+
+    let value = match *t {
+        RuntimeType::I32 => integer(json).map(|n| ReflectValueBox::I32(n as i32)),
+        RuntimeType::I64 => integer(json).map(ReflectValueBox::I64),
+        RuntimeType::U32 => unsigned(json).map(|n| ReflectValueBox::U32(n as u32)),
+        RuntimeType::U64 => unsigned(json).map(ReflectValueBox::U64),
+        RuntimeType::F32 => number(json).map(|n| ReflectValueBox::F32(n as f32)),
+        RuntimeType::F64 => number(json).map(ReflectValueBox::F64),
+        RuntimeType::Bool => json
+            .as_bool()
+            .or_else(|| json.as_str().and_then(|s| s.parse().ok()))
+            .map(ReflectValueBox::Bool),
+        RuntimeType::String => json
+            .as_str()
+            .map(|s| ReflectValueBox::String(s.to_string())),
+        RuntimeType::VecU8 => json
+            .as_str()
+            .and_then(|s| base64::decode(s).ok())
+            .map(ReflectValueBox::Bytes),
+        RuntimeType::Enum(ref e) => {
+            let number = match json.as_str() {
+                Some(name) => e.value_by_name(name).map(|v| v.value()),
+                None => json.as_i64().map(|n| n as i32),
+            };
+            number.map(|n| ReflectValueBox::Enum(e.clone(), n))
+        }
+        RuntimeType::Message(ref m) => {
+            return message_from_json(m, json).map(ReflectValueBox::Message);
+        }
+    };
+    value.ok_or_else(|| format!("Expected a {} but got {}", t, json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+    use std::env;
+    use std::fs::File;
+    use std::io::Write;
+
+    const PROTO: &str = r#"
+        syntax = "proto3";
+        package test;
+
+        message Point {
+            int32 x = 1;
+            int32 y = 2;
+        }
+
+        message Request {
+            string name = 1;
+            int64 id = 2;
+            repeated Point points = 3;
+            map<string, int32> labels = 4;
+        }
+
+        message Reply {}
+
+        service Tester {
+            rpc Check (Request) returns (Reply);
+        }
+    "#;
+
+    fn proto_file(name: &str) -> ::std::path::PathBuf {
+        let path = env::temp_dir().join(name);
+        File::create(&path)
+            .and_then(|mut f| f.write_all(PROTO.as_bytes()))
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn it_frames_the_message_with_its_length() {
+        assert_eq!(frame(&[1, 2, 3]), vec![0, 0, 0, 0, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn it_encodes_the_request_from_json() {
+        let proto = proto_file("rench_grpc_encodes.proto");
+        let call = Call::load(
+            &proto,
+            "test.Tester/Check",
+            r#"{"name": "a", "id": "300", "points": [{"x": 1}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(call.path(), "/test.Tester/Check");
+        assert_eq!(
+            call.frame(),
+            &[0, 0, 0, 0, 10, 10, 1, b'a', 16, 172, 2, 26, 2, 8, 1][..]
+        );
+    }
+
+    #[test]
+    fn it_rejects_unknown_methods_and_fields() {
+        let proto = proto_file("rench_grpc_rejects.proto");
+        assert!(Call::load(&proto, "test.Tester/Nope", "{}").is_err());
+        assert!(Call::load(&proto, "test.Nope/Check", "{}").is_err());
+        assert!(Call::load(&proto, "test.Tester/Check", r#"{"nope": 1}"#).is_err());
+    }
+
+    #[test]
+    fn it_reads_the_grpc_status() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(status(&headers), None);
+        headers.insert("grpc-status", HeaderValue::from_static("14"));
+        assert_eq!(status(&headers), Some(14));
+    }
+}
@@ -0,0 +1,136 @@
+use base64;
+use hdrhistogram::serialization::{Serializer, V2DeflateSerializer};
+use hdrhistogram::Histogram;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long each histogram in the log covers.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// The longest latency the histograms can hold, an hour in microseconds.
+const HIGHEST: u64 = 3_600_000_000;
+
+/// Writes latencies as an HdrHistogram interval log, one compressed histogram of
+/// microseconds per second of the run, for tools such as HdrHistogram's plotter to read.
+pub struct HdrLog<W: Write> {
+    out: W,
+    serializer: V2DeflateSerializer,
+    histogram: Histogram<u64>,
+    start: SystemTime,
+    interval_start: SystemTime,
+}
+
+impl<W: Write> HdrLog<W> {
+    /// Begins the log with the headers, counting intervals from the start.
+    pub fn new(mut out: W, start: SystemTime) -> io::Result<HdrLog<W>> {
+        let since = start.duration_since(UNIX_EPOCH).unwrap_or_default();
+        writeln!(out, "#[Histogram log format version 1.3]")?;
+        writeln!(
+            out,
+            "#[StartTime: {:.3} (seconds since epoch)]",
+            since.as_secs_f64()
+        )?;
+        writeln!(out, "#[MaxValueDivisor: 1000.000]")?;
+        writeln!(
+            out,
+            "\"StartTimestamp\",\"Interval_Length\",\"Interval_Max\",\"Interval_Compressed_Histogram\""
+        )?;
+        Ok(HdrLog {
+            out,
+            serializer: V2DeflateSerializer::new(),
+            histogram: Histogram::new_with_bounds(1, HIGHEST, 3)
+                .expect("Expected valid histogram bounds"),
+            start,
+            interval_start: start,
+        })
+    }
+
+    /// Records a latency that completed at the time, writing out the intervals before it.
+    pub fn record(&mut self, latency: Duration, at: SystemTime) -> io::Result<()> {
+        while at.duration_since(self.interval_start).unwrap_or_default() >= INTERVAL {
+            self.write_interval(INTERVAL)?;
+            self.interval_start += INTERVAL;
+        }
+        let micros = latency.as_secs() * 1_000_000 + u64::from(latency.subsec_micros());
+        self.histogram.saturating_record(micros.max(1));
+        Ok(())
+    }
+
+    /// Writes out the interval that is still open, which may be shorter than the rest.
+    pub fn finish(&mut self, at: SystemTime) -> io::Result<()> {
+        let length = at.duration_since(self.interval_start).unwrap_or_default();
+        if !self.histogram.is_empty() {
+            self.write_interval(length)?;
+        }
+        self.out.flush()
+    }
+
+    fn write_interval(&mut self, length: Duration) -> io::Result<()> {
+        let offset = self
+            .interval_start
+            .duration_since(self.start)
+            .unwrap_or_default();
+        let mut encoded = Vec::new();
+        self.serializer
+            .serialize(&self.histogram, &mut encoded)
+            .map_err(|e| io::Error::other(format!("{:?}", e)))?;
+        writeln!(
+            self.out,
+            "{:.3},{:.3},{:.3},{}",
+            offset.as_secs_f64(),
+            length.as_secs_f64(),
+            self.histogram.max() as f64 / 1_000f64,
+            base64::encode(&encoded)
+        )?;
+        self.histogram.reset();
+        Ok(())
+    }
+}
+
+/// The last interval is written when the log goes out of scope, as the run ends.
+impl<W: Write> Drop for HdrLog<W> {
+    fn drop(&mut self) {
+        let _ = self.finish(SystemTime::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hdrhistogram::serialization::Deserializer;
+
+    #[test]
+    fn it_writes_a_histogram_for_each_interval() {
+        let start = UNIX_EPOCH + Duration::from_secs(100);
+        let mut out = Vec::new();
+        let mut log = HdrLog::new(&mut out, start).unwrap();
+        log.record(Duration::from_millis(5), start).unwrap();
+        log.record(Duration::from_millis(7), start + Duration::from_millis(500))
+            .unwrap();
+        log.record(
+            Duration::from_millis(3),
+            start + Duration::from_millis(2_500),
+        )
+        .unwrap();
+        log.finish(start + Duration::from_millis(2_750)).unwrap();
+        drop(log);
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out
+            .lines()
+            .filter(|line| !line.starts_with(['#', '"']))
+            .collect();
+        assert!(out.contains("#[StartTime: 100.000 (seconds since epoch)]"));
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("0.000,1.000,7.003,"));
+        // An interval without latencies is still written so the timeline has no gaps.
+        assert!(lines[1].starts_with("1.000,1.000,0.000,"));
+        assert!(lines[2].starts_with("2.000,0.750,3.00"));
+
+        let encoded = lines[0].rsplit(',').next().unwrap();
+        let decoded: Histogram<u64> = Deserializer::new()
+            .deserialize(&mut &base64::decode(encoded).unwrap()[..])
+            .unwrap();
+        assert_eq!(decoded.len(), 2);
+    }
+}
@@ -0,0 +1,258 @@
+use base64;
+use fake;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde_json::{self, Map, Value};
+use std::cmp;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long tokens last when no ttl is given.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// How long before a shared token expires that another is minted, so that no request is sent
+/// with a token that runs out on its way. Short lived tokens are reminted halfway.
+const REMINT_MARGIN: Duration = Duration::from_secs(30);
+
+/// Mints json web tokens signed with a private key, for services that reject tokens that
+/// are long lived or used more than once. Every token is given its own `iat`, `exp` and,
+/// unless the claims name one, `jti`.
+#[derive(Clone)]
+pub struct Jwt {
+    key: Arc<PKey>,
+    algorithm: Algorithm,
+    claims: Map<String, Value>,
+    ttl: Duration,
+    mint: Mint,
+    token: Arc<Mutex<Option<(String, Instant)>>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Algorithm {
+    Rs256,
+    Es256,
+}
+
+/// How often tokens are minted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mint {
+    /// Every request is sent with a token of its own.
+    Request,
+    /// The threads share a token until it is about to expire.
+    Window,
+}
+
+impl Jwt {
+    /// Reads the rsa or p-256 private key of the pem file, which sets the algorithm tokens
+    /// are signed with, and the json object of claims the tokens carry.
+    pub fn load(key: &str, claims: Option<&str>) -> Result<Jwt, String> {
+        let pem = fs::read(key).map_err(|e| format!("Could not read {}: {}", key, e))?;
+        let key = PKey::private_key_from_pem(&pem)
+            .map_err(|e| format!("Expected a private key in {}: {}", key, e))?;
+        let claims = match claims {
+            Some(path) => {
+                let text = fs::read_to_string(path)
+                    .map_err(|e| format!("Could not read {}: {}", path, e))?;
+                match serde_json::from_str(&text) {
+                    Ok(Value::Object(claims)) => claims,
+                    Ok(_) => return Err(format!("Expected a json object of claims in {}", path)),
+                    Err(e) => return Err(format!("Invalid claims in {}: {}", path, e)),
+                }
+            }
+            None => Map::new(),
+        };
+        Jwt::new(key, claims)
+    }
+
+    fn new(key: PKey, claims: Map<String, Value>) -> Result<Jwt, String> {
+        let algorithm = if key.rsa().is_ok() {
+            Algorithm::Rs256
+        } else if key.ec_key().is_ok() {
+            Algorithm::Es256
+        } else {
+            return Err("Expected an rsa or ec private key to sign tokens with".to_string());
+        };
+        Ok(Jwt {
+            key: Arc::new(key),
+            algorithm,
+            claims,
+            ttl: DEFAULT_TTL,
+            mint: Mint::Request,
+            token: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Sets how long tokens last.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets how often tokens are minted.
+    pub fn with_mint(mut self, mint: Mint) -> Self {
+        self.mint = mint;
+        self
+    }
+
+    /// The bearer token to authorize a request with.
+    pub fn bearer(&self) -> String {
+        if self.mint == Mint::Request {
+            return self.sign(SystemTime::now());
+        }
+        let mut token = self.token.lock().expect("Expected the token lock");
+        let now = Instant::now();
+        match *token {
+            Some((_, remint_at)) if now < remint_at => {}
+            _ => {
+                let remint_at = now + self.ttl - cmp::min(REMINT_MARGIN, self.ttl / 2);
+                *token = Some((self.sign(SystemTime::now()), remint_at));
+            }
+        }
+        token
+            .as_ref()
+            .map(|(token, _)| token.clone())
+            .expect("Expected a token once minted")
+    }
+
+    /// A token issued at the time.
+    fn sign(&self, now: SystemTime) -> String {
+        let issued = now
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or(0);
+        let mut claims = self.claims.clone();
+        claims.insert("iat".to_string(), Value::from(issued));
+        claims.insert("exp".to_string(), Value::from(issued + self.ttl.as_secs()));
+        if !claims.contains_key("jti") {
+            claims.insert("jti".to_string(), Value::String(fake::uuid()));
+        }
+        let header = match self.algorithm {
+            Algorithm::Rs256 => json!({"alg": "RS256", "typ": "JWT"}),
+            Algorithm::Es256 => json!({"alg": "ES256", "typ": "JWT"}),
+        };
+        let signed = format!(
+            "{}.{}",
+            encode(header.to_string().as_bytes()),
+            encode(Value::Object(claims).to_string().as_bytes())
+        );
+
+        let mut signer =
+            Signer::new(MessageDigest::sha256(), &self.key).expect("Expected a token signer");
+        signer
+            .update(signed.as_bytes())
+            .expect("Expected the token to sign");
+        let signature = signer.sign_to_vec().expect("Expected the token to sign");
+        let signature = match self.algorithm {
+            Algorithm::Rs256 => signature,
+            Algorithm::Es256 => {
+                raw_signature(&signature).expect("Expected a der encoded ecdsa signature")
+            }
+        };
+        format!("{}.{}", signed, encode(&signature))
+    }
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// The `r` and `s` of a der encoded ecdsa signature side by side, each 32 bytes long, as
+/// jws has them.
+fn raw_signature(der: &[u8]) -> Option<Vec<u8>> {
+    // A sequence of two integers, none of them long enough for a long form length.
+    if der.first() != Some(&0x30) {
+        return None;
+    }
+    let mut rest = der.get(2..)?;
+    let mut raw = Vec::with_capacity(64);
+    for _ in 0..2 {
+        if rest.first() != Some(&0x02) {
+            return None;
+        }
+        let len = *rest.get(1)? as usize;
+        let int = rest.get(2..2 + len)?;
+        let int = &int[int.iter().take_while(|&&byte| byte == 0).count()..];
+        if int.len() > 32 {
+            return None;
+        }
+        raw.extend(std::iter::repeat_n(0, 32 - int.len()));
+        raw.extend_from_slice(int);
+        rest = &rest[2 + len..];
+    }
+    Some(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid;
+    use openssl::rsa::Rsa;
+    use openssl::sign::Verifier;
+
+    fn decode(part: &str) -> Value {
+        let bytes = base64::decode_config(part, base64::URL_SAFE_NO_PAD).unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn it_mints_rs256_tokens() {
+        let pem = Rsa::generate(2048).unwrap().private_key_to_pem().unwrap();
+        let key = PKey::private_key_from_pem(&pem).unwrap();
+        let mut claims = Map::new();
+        claims.insert("sub".to_string(), Value::from("bench"));
+        let jwt = Jwt::new(PKey::private_key_from_pem(&pem).unwrap(), claims)
+            .unwrap()
+            .with_ttl(Duration::from_secs(90));
+
+        let token = jwt.sign(UNIX_EPOCH + Duration::from_secs(1000));
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(decode(parts[0])["alg"], "RS256");
+        let claims = decode(parts[1]);
+        assert_eq!(claims["sub"], "bench");
+        assert_eq!(claims["iat"], 1000);
+        assert_eq!(claims["exp"], 1090);
+
+        let signature = base64::decode_config(parts[2], base64::URL_SAFE_NO_PAD).unwrap();
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &key).unwrap();
+        verifier
+            .update(format!("{}.{}", parts[0], parts[1]).as_bytes())
+            .unwrap();
+        assert!(verifier.verify(&signature).unwrap());
+    }
+
+    #[test]
+    fn it_mints_es256_tokens_as_often_as_asked() {
+        let group = EcGroup::from_curve_name(nid::X9_62_PRIME256V1).unwrap();
+        let ec = EcKey::generate(&group).unwrap();
+        let jwt = Jwt::new(PKey::from_ec_key(ec).unwrap(), Map::new()).unwrap();
+        let token = jwt.bearer();
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(decode(parts[0])["alg"], "ES256");
+        assert_eq!(
+            base64::decode_config(parts[2], base64::URL_SAFE_NO_PAD)
+                .unwrap()
+                .len(),
+            64
+        );
+        assert_ne!(
+            decode(parts[1])["jti"],
+            decode(jwt.bearer().split('.').nth(1).unwrap())["jti"]
+        );
+
+        let shared = jwt.with_mint(Mint::Window);
+        assert_eq!(shared.bearer(), shared.bearer());
+    }
+
+    #[test]
+    fn it_unwraps_der_signatures() {
+        let der = [0x30, 0x08, 0x02, 0x02, 0x00, 0x81, 0x02, 0x02, 0x01, 0x02];
+        let raw = raw_signature(&der).unwrap();
+        assert_eq!(raw.len(), 64);
+        assert_eq!(&raw[30..32], &[0x00, 0x81]);
+        assert_eq!(&raw[62..], &[0x01, 0x02]);
+        assert_eq!(raw_signature(&[0x31]), None);
+    }
+}
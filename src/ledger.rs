@@ -0,0 +1,245 @@
+use deadline;
+use stats::Summary;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// The columns of a ledger, written once when it is started.
+const HEADER: &str = "timestamp,tag,concurrency,requests,rps,p50,p99,error_rate";
+
+/// A run as a row of a ledger, with latencies in milliseconds and the tags of the run
+/// joined by semicolons.
+pub fn row(
+    at: SystemTime,
+    tags: &[&str],
+    concurrency: usize,
+    rps: f64,
+    summary: &Summary,
+) -> String {
+    let ms = |percentage| {
+        summary
+            .latency_at(percentage)
+            .map(|latency| format!("{:.3}", latency.as_secs_f64() * 1_000f64))
+            .unwrap_or_default()
+    };
+    format!(
+        "{},{},{},{},{:.3},{},{},{:.6}",
+        deadline::format(at),
+        quote(&tags.join(";")),
+        concurrency,
+        summary.requests(),
+        rps,
+        ms(50f64),
+        ms(99f64),
+        summary.error_rate()
+    )
+}
+
+/// Appends a row to the ledger at the path, starting it with the header when it is new.
+pub fn append(path: &Path, row: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", HEADER)?;
+    }
+    writeln!(file, "{}", row)
+}
+
+/// A run as a ledger recorded it, with its latencies in milliseconds.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    timestamp: String,
+    tags: Vec<String>,
+    requests: u64,
+    rps: f64,
+    p50: Option<f64>,
+    p99: Option<f64>,
+    error_rate: f64,
+}
+
+impl Entry {
+    /// When the run started, as `2024-05-01T13:00:00Z`.
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+}
+
+/// Reads back the runs of a ledger, oldest first.
+pub fn read(path: &Path) -> Result<Vec<Entry>, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    text.lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields = unquote(line);
+            let invalid = || format!("Expected a row of {} but got {}", HEADER, line);
+            if fields.len() != 8 {
+                return Err(invalid());
+            }
+            let number = |i: usize| fields[i].parse::<f64>().map_err(|_| invalid());
+            let latency = |i: usize| match fields[i].as_str() {
+                "" => Ok(None),
+                _ => number(i).map(Some),
+            };
+            Ok(Entry {
+                timestamp: fields[0].clone(),
+                tags: fields[1]
+                    .split(';')
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                requests: fields[3].parse::<u64>().map_err(|_| invalid())?,
+                rps: number(4)?,
+                p50: latency(5)?,
+                p99: latency(6)?,
+                error_rate: number(7)?,
+            })
+        })
+        .collect()
+}
+
+/// Checks that none of the tags has a semicolon, as a ledger joins them with semicolons
+/// and would read such a tag back as several.
+pub fn check_tags<'a, I: IntoIterator<Item = &'a str>>(tags: I) -> Result<(), String> {
+    match tags.into_iter().find(|tag| tag.contains(';')) {
+        Some(tag) => Err(format!("Expected tags without semicolons but got {}", tag)),
+        None => Ok(()),
+    }
+}
+
+/// The latest run of the ledger with the tag.
+pub fn latest<'a>(entries: &'a [Entry], tag: &str) -> Option<&'a Entry> {
+    entries.iter().rev().find(|entry| entry.tags.iter().any(|t| t == tag))
+}
+
+/// How a run recorded in a ledger compares with a baseline run, laid out as the comparison
+/// of streamed facts is.
+pub fn compare(baseline: &Entry, candidate: &Entry) -> String {
+    let mut lines = vec![
+        format!("{:<10} {:>14} {:>14} {:>9}", "", "Baseline", "Candidate", "Change"),
+        format!("{:<10} {:>14} {:>14}", "Requests", baseline.requests, candidate.requests),
+        format!(
+            "{:<10} {:>14.1} {:>14.1} {:>+8.1}%",
+            "RPS",
+            baseline.rps,
+            candidate.rps,
+            change(baseline.rps, candidate.rps)
+        ),
+    ];
+    for &(name, base, cand) in &[
+        ("p50", baseline.p50, candidate.p50),
+        ("p99", baseline.p99, candidate.p99),
+    ] {
+        if let (Some(base), Some(cand)) = (base, cand) {
+            lines.push(format!(
+                "{:<10} {:>11.3} ms {:>11.3} ms {:>+8.1}%",
+                name,
+                base,
+                cand,
+                change(base, cand)
+            ));
+        }
+    }
+    lines.push(format!(
+        "{:<10} {:>13.2}% {:>13.2}%",
+        "Errors",
+        baseline.error_rate * 100f64,
+        candidate.error_rate * 100f64
+    ));
+    lines.join("\n")
+}
+
+fn change(baseline: f64, candidate: f64) -> f64 {
+    (candidate - baseline) / baseline * 100f64
+}
+
+/// The fields of a row, quoted ones read back as `quote` wrote them.
+fn unquote(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().expect("Expected a field").push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => fields.last_mut().expect("Expected a field").push(c),
+        }
+    }
+    fields
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content_length::ContentLength;
+    use stats::Fact;
+    use std::env;
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn it_appends_rows_under_a_header() {
+        let facts: Vec<Fact> = [10, 20, 30]
+            .iter()
+            .map(|&ms| Fact::record(ContentLength::zero(), 200, Duration::from_millis(ms)))
+            .chain(vec![Fact::record(ContentLength::zero(), 500, Duration::from_millis(40))])
+            .collect();
+        let summary = Summary::from_facts(&facts);
+        let at = UNIX_EPOCH + Duration::from_secs(1_714_568_400);
+        let row = row(at, &["release-1.4", "a,b"], 8, 123.4567, &summary);
+        assert_eq!(
+            row,
+            "2024-05-01T13:00:00Z,\"release-1.4;a,b\",8,4,123.457,20.000,40.000,0.250000"
+        );
+
+        let path = env::temp_dir().join("rench_ledger.csv");
+        let _ = fs::remove_file(&path);
+        append(&path, &row).unwrap();
+        append(&path, &row).unwrap();
+        let ledger = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(ledger, format!("{}\n{}\n{}\n", HEADER, row, row));
+    }
+
+    #[test]
+    fn it_reads_back_runs_by_tag() {
+        let facts = [Fact::record(ContentLength::zero(), 200, Duration::from_millis(10))];
+        let summary = Summary::from_facts(&facts);
+        let at = UNIX_EPOCH + Duration::from_secs(1_714_568_400);
+        let path = env::temp_dir().join("rench_ledger_tags.csv");
+        let _ = fs::remove_file(&path);
+        append(&path, &row(at, &["release-1.3", "a,\"b\""], 8, 100f64, &summary)).unwrap();
+        append(&path, &row(at, &["release-1.4"], 8, 110f64, &summary)).unwrap();
+        append(&path, &row(at, &["release-1.3"], 8, 90f64, &summary)).unwrap();
+        let entries = read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].tags, vec!["release-1.3", "a,\"b\""]);
+        assert_eq!(entries[0].p50, Some(10f64));
+        let baseline = latest(&entries, "release-1.3").unwrap();
+        assert_eq!(baseline.rps, 90f64);
+        assert!(latest(&entries, "release-1.5").is_none());
+        let comparison = compare(baseline, latest(&entries, "release-1.4").unwrap());
+        assert!(comparison.contains("RPS                  90.0          110.0    +22.2%"));
+    }
+
+    #[test]
+    fn it_rejects_tags_it_would_split() {
+        assert!(check_tags(vec!["release-1.3", "a,\"b\""]).is_ok());
+        assert!(check_tags(vec!["release-1.3", "env=a;b"]).is_err());
+    }
+}
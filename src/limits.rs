@@ -0,0 +1,71 @@
+use libc;
+use std::io;
+
+/// Files rench keeps open besides its connections, such as stdio and the collector.
+const RESERVED: u64 = 32;
+
+/// Each worker holds a connection, its reactor and a connection that may still be closing.
+const PER_WORKER: u64 = 3;
+
+/// The number of open files needed to run the workers without running out.
+pub fn open_files_needed(workers: usize) -> u64 {
+    workers as u64 * PER_WORKER + RESERVED
+}
+
+/// Makes sure the process can open enough files for the workers, raising its limit when
+/// it is too low. Fails with the limit when it cannot be raised far enough.
+#[cfg(unix)]
+pub fn ensure_open_files(workers: usize) -> Result<(), String> {
+    let needed = open_files_needed(workers);
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return Err(format!(
+            "Could not read the open file limit: {}",
+            io::Error::last_os_error()
+        ));
+    }
+    if limit.rlim_cur >= needed as libc::rlim_t {
+        return Ok(());
+    }
+
+    let raised = libc::rlimit {
+        rlim_cur: needed as libc::rlim_t,
+        rlim_max: ::std::cmp::max(limit.rlim_max, needed as libc::rlim_t),
+    };
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } != 0 {
+        return Err(format!(
+            "{} concurrent requests need {} open files but the limit is {} and could not \
+             be raised ({}). Raise it with `ulimit -n {}` or lower the concurrency.",
+            workers,
+            needed,
+            limit.rlim_cur,
+            io::Error::last_os_error(),
+            needed
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn ensure_open_files(_workers: usize) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_files_for_every_worker() {
+        assert_eq!(open_files_needed(0), RESERVED);
+        assert_eq!(open_files_needed(100), 300 + RESERVED);
+    }
+
+    #[test]
+    fn it_allows_limits_that_are_already_high_enough() {
+        assert!(ensure_open_files(1).is_ok());
+    }
+}
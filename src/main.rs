@@ -1,51 +1,1312 @@
+extern crate base64;
+extern crate bytes;
 extern crate clap;
+extern crate foreign_types;
+#[macro_use]
 extern crate futures;
+extern crate h2;
+extern crate hdrhistogram;
+extern crate http;
+extern crate httparse;
 extern crate hyper;
 extern crate hyper_tls;
+extern crate libc;
+extern crate net2;
+extern crate native_tls;
+extern crate openssl;
+extern crate openssl_sys;
+extern crate protobuf;
+extern crate protobuf_parse;
+extern crate rand;
+extern crate regex;
 extern crate reqwest;
+#[macro_use]
+extern crate serde_json;
+extern crate tera;
 extern crate tokio_core;
+extern crate tokio_tls;
+extern crate tungstenite;
+extern crate wasmi;
+#[cfg(test)]
+extern crate wat;
 
-use clap::{App, Arg};
+use std::cmp;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::path::Path;
+use std::process;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
+use regex::Regex;
 
+mod address;
+mod assertion;
+mod affinity;
 mod bench;
+mod bootstrap;
+mod cache;
+mod body_stream;
 mod chart;
+mod cloudwatch;
 mod collector;
+mod compat;
+mod connector;
 mod content_length;
+mod dashboard;
+mod crawl;
+mod deadline;
+mod digest;
+mod dns;
+mod duration;
 mod engine;
+mod exit;
+mod expect_continue;
+mod failure;
+mod fake;
+mod gate;
+mod graphite;
+mod graphql;
+mod hdr_log;
+mod grpc;
+mod jwt;
+mod ledger;
+mod limits;
 mod message;
+mod metadata;
+mod monitor;
+mod mix;
+mod multipart;
+mod multiplex;
+mod notify;
+mod ntlm;
+mod oauth2;
+mod ndjson;
+mod oversize;
+mod pause;
 mod plan;
+mod plugin;
+mod pool;
+mod processes;
+mod report;
+mod protocol;
+mod proxy;
+mod ramp;
+mod reservoir;
 mod runner;
+mod serve;
+mod session;
+mod signature;
+mod significance;
+mod sigv4;
+mod sitemap;
+mod socket;
+mod spectrum;
+mod sse;
 mod stats;
-use stats::{ChartSize, Fact, Summary};
+mod status;
+mod template;
+mod timeout;
+mod threshold;
+mod throughput;
+mod tls;
+use content_length::ContentLength;
+use stats::{ChartSize, Comparison, Fact, Summary};
 use plan::Plan;
+use message::Message;
 use runner::Runner;
+use hdr_log::HdrLog;
+use monitor::Monitor;
+use ndjson::FactWriter;
+use report::Reporter;
+use exit::OrInvalid;
+
+/// How many of the pages of a sitemap are reported on.
+const SLOWEST_PAGES: usize = 10;
 
 fn main() {
-    let matches = App::new("Git Release Names")
+    exit::on_panic();
+    let args = compat::translate(env::args().collect()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(exit::CONFIG);
+    });
+    let matches = app()
+        .get_matches_from_safe(args)
+        .unwrap_or_else(|e| {
+            if !e.use_stderr() {
+                e.exit();
+            }
+            eprintln!("{}", e);
+            process::exit(exit::CONFIG);
+        });
+
+    match matches.subcommand() {
+        ("serve", Some(matches)) => serve(matches),
+        ("report", Some(matches)) => report(matches),
+        ("compare", Some(matches)) => compare(matches),
+        ("merge", Some(matches)) => merge(matches),
+        ("ws", Some(matches)) => {
+            let message = matches
+                .value_of("message")
+                .expect("Message is required")
+                .to_string();
+            let eng = engine::Engine::new(urls(matches)).with_websocket(message);
+            run(matches, eng);
+        }
+        ("sse", Some(matches)) => {
+            let eng = engine::Engine::new(urls(matches)).with_sse();
+            run(matches, eng);
+        }
+        ("grpc", Some(matches)) => {
+            let proto = Path::new(matches.value_of("proto").expect("Proto is required"));
+            let method = matches.value_of("call").expect("Call is required");
+            let call = match matches.value_of("data") {
+                Some(data) => grpc::Call::load_with_data_file(proto, method, Path::new(data)),
+                None => grpc::Call::load(proto, method, "{}"),
+            }.or_invalid("Expected a valid grpc call");
+            let eng = engine::Engine::new(urls(matches)).with_grpc(call);
+            run(matches, eng);
+        }
+        ("run", Some(matches)) => run_http(matches),
+        _ => run_http(&matches),
+    }
+}
+
+/// Every option and subcommand of rench.
+fn app<'a, 'b>() -> App<'a, 'b> {
+    let app = App::new("Git Release Names")
         .author("Kevin Choubacha <chewbacha@gmail.com>")
+        .setting(AppSettings::SubcommandsNegateReqs);
+    http_args(app)
+        .arg(
+            Arg::with_name("compat")
+                .long("compat")
+                .takes_value(true)
+                .possible_values(&["hey", "ab"])
+                .help("Reads the other arguments as hey or ab would, such as -disable-keepalive or -k"),
+        )
+        .subcommand(http_args(
+            SubCommand::with_name("run")
+                .about("Benchmarks http requests, which is what rench does without a command"),
+        ))
+        .subcommand(
+            SubCommand::with_name("ws")
+                .about("Benchmarks websocket round trips instead of http requests")
+                .arg(url_arg())
+                .arg(concurrency_arg())
+                .arg(pin_cores_arg())
+                .arg(per_worker_arg())
+                .arg(sample_arg())
+                .arg(stream_facts_arg())
+                .arg(hdr_out_arg())
+                .arg(latency_spectrum_arg())
+                .arg(latency_arg())
+                .arg(format_arg())
+                .arg(dry_run_arg())
+                .args(&process_args())
+                .args(&deadline_args())
+                .args(&results_args())
+                .arg(requests_arg())
+                .arg(
+                    Arg::with_name("message")
+                        .long("message")
+                        .short("m")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The message to send, each reply completes a request"),
+                )
+                .args(&socket_args())
+                .arg(chart_size_arg())
+                .arg(chart_style_arg())
+                .arg(chart_height_arg())
+                .arg(hist_bucket_arg())
+                .arg(chart_clamp_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("sse")
+                .about("Benchmarks server sent event streams, each event is a request")
+                .after_help(
+                    "Events are timed from the event before them on the same stream. The \
+                     connections section reports the time from opening a stream to its \
+                     first event.",
+                )
+                .arg(url_arg())
+                .arg(concurrency_arg())
+                .arg(pin_cores_arg())
+                .arg(per_worker_arg())
+                .arg(sample_arg())
+                .arg(stream_facts_arg())
+                .arg(hdr_out_arg())
+                .arg(latency_spectrum_arg())
+                .arg(latency_arg())
+                .arg(format_arg())
+                .arg(dry_run_arg())
+                .args(&process_args())
+                .args(&deadline_args())
+                .args(&results_args())
+                .arg(requests_arg())
+                .args(&socket_args())
+                .arg(chart_size_arg())
+                .arg(chart_style_arg())
+                .arg(chart_height_arg())
+                .arg(hist_bucket_arg())
+                .arg(chart_clamp_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("grpc")
+                .about("Benchmarks a unary grpc call over plaintext http/2")
+                .arg(
+                    Arg::with_name("URL")
+                        .required(true)
+                        .multiple(true)
+                        .help("Each host:port specified will be round robined."),
+                )
+                .arg(concurrency_arg())
+                .arg(pin_cores_arg())
+                .arg(per_worker_arg())
+                .arg(sample_arg())
+                .arg(stream_facts_arg())
+                .arg(hdr_out_arg())
+                .arg(latency_spectrum_arg())
+                .arg(latency_arg())
+                .arg(format_arg())
+                .arg(dry_run_arg())
+                .args(&process_args())
+                .args(&deadline_args())
+                .args(&results_args())
+                .arg(requests_arg())
+                .arg(
+                    Arg::with_name("proto")
+                        .long("proto")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The proto file defining the service"),
+                )
+                .arg(
+                    Arg::with_name("call")
+                        .long("call")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The method to call, as pkg.Service/Method"),
+                )
+                .arg(
+                    Arg::with_name("data")
+                        .long("data")
+                        .takes_value(true)
+                        .help("A json file with the request message, empty if not given"),
+                )
+                .args(&socket_args())
+                .arg(chart_size_arg())
+                .arg(chart_style_arg())
+                .arg(chart_height_arg())
+                .arg(hist_bucket_arg())
+                .arg(chart_clamp_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Serves http requests to benchmark against, to try out a benchmark locally")
+                .arg(
+                    Arg::with_name("host")
+                        .long("host")
+                        .takes_value(true)
+                        .help("The address to listen on, defaults to 127.0.0.1"),
+                )
+                .arg(
+                    Arg::with_name("port")
+                        .long("port")
+                        .short("p")
+                        .takes_value(true)
+                        .help("The port to listen on, defaults to 8080"),
+                )
+                .arg(
+                    Arg::with_name("latency")
+                        .long("latency")
+                        .takes_value(true)
+                        .help("How long to wait before answering each request, such as 20ms"),
+                )
+                .arg(
+                    Arg::with_name("jitter")
+                        .long("jitter")
+                        .takes_value(true)
+                        .help("How much the wait varies by either way, such as 5ms"),
+                )
+                .arg(
+                    Arg::with_name("status-mix")
+                        .long("status-mix")
+                        .takes_value(true)
+                        .help("The statuses to answer with by weight, such as 200:95,500:5"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("report")
+                .about("Summarizes the facts a run streamed to a file")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .help("The file written with --stream-facts"),
+                )
+                .arg(output_arg())
+                .arg(per_worker_arg())
+                .arg(latency_spectrum_arg())
+                .arg(latency_arg())
+                .arg(format_arg())
+                .arg(chart_size_arg())
+                .arg(chart_style_arg())
+                .arg(chart_height_arg())
+                .arg(hist_bucket_arg())
+                .arg(chart_clamp_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("Compares the latencies and errors of a run with those of a baseline run")
+                .arg(
+                    Arg::with_name("BASELINE")
+                        .required_unless("tag")
+                        .help("The file the baseline run wrote with --stream-facts"),
+                )
+                .arg(
+                    Arg::with_name("CANDIDATE")
+                        .required_unless("tag")
+                        .help("The file the run to compare wrote with --stream-facts"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .long("tag")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .requires("ledger")
+                        .conflicts_with_all(&["BASELINE", "CANDIDATE"])
+                        .help(
+                            "Compares the latest runs in the ledger with these tags, the \
+                             baseline's given first, rather than files of facts",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("test")
+                        .long("test")
+                        .takes_value(true)
+                        .possible_values(&significance::TESTS)
+                        .conflicts_with("tag")
+                        .help(
+                            "Also tests whether the latencies differ by more than chance, \
+                             with Welch's t-test of the means or the Mann-Whitney U test of \
+                             the ranks, reporting its p-value and effect size",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("ledger")
+                        .long("ledger")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("The ledger the runs were appended to with --append-csv"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("merge")
+                .about("Summarizes the facts that one or more runs streamed to files")
+                .arg(
+                    Arg::with_name("FILE")
+                        .required(true)
+                        .multiple(true)
+                        .help("The files written with --stream-facts"),
+                )
+                .arg(output_arg())
+                .arg(format_arg())
+                .arg(chart_size_arg())
+                .arg(chart_style_arg())
+                .arg(chart_height_arg())
+                .arg(hist_bucket_arg())
+                .arg(chart_clamp_arg()),
+        )
+}
+
+/// Runs the http benchmark, or one of the modes that only time part of a request.
+fn run_http(matches: &ArgMatches) {
+    match matches.value_of("mode") {
+        Some("connect") => {
+            let eng = engine::Engine::new(urls(matches)).with_connect();
+            run(matches, eng);
+        }
+        Some("tls-handshake") => {
+            let eng = engine::Engine::new(urls(matches))
+                .with_tls_handshake()
+                .with_tls(tls_options(matches));
+            run(matches, eng);
+        }
+        Some("dns") => {
+            let resolver = matches.value_of("resolver").map(|resolver| {
+                resolver
+                    .parse::<SocketAddr>()
+                    .or_else(|_| resolver.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+                    .or_invalid("Expected the resolver to be an ip address")
+            });
+            let eng = engine::Engine::new(urls(matches)).with_dns(resolver);
+            run(matches, eng);
+        }
+        _ => {
+            let urls = urls(matches);
+            let mix = matches.value_of("mix").map(|mix| {
+                mix::parse(mix, &urls[0]).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(exit::CONFIG);
+                })
+            });
+            let urls = match mix {
+                Some(ref mix) => mix.iter().map(|operation| operation.url.clone()).collect(),
+                None => urls,
+            };
+            let templated = urls.iter().any(|url| url.contains("{{") || url.contains("{%"));
+            let https_urls: Vec<String> = urls
+                .iter()
+                .filter(|url| url.starts_with("https:"))
+                .cloned()
+                .collect();
+            let templates = if templated {
+                let templates = urls.iter().map(|url| template::Template::parse(url));
+                Some(templates.collect::<Result<Vec<_>, String>>().unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(exit::CONFIG);
+                }))
+            } else {
+                None
+            };
+            let eng = match matches.value_of("engine").unwrap_or("hyper") {
+                "hyper" if matches.is_present("any-http2") => engine::Engine::new(urls).with_http2(),
+                "hyper" => engine::Engine::new(urls).with_hyper(),
+                _ if matches.is_present("any-http2") => {
+                    eprintln!("Only the hyper engine can make requests over http/2");
+                    process::exit(exit::CONFIG);
+                }
+                _ => engine::Engine::new(urls),
+            };
+
+            let eng = match templates {
+                Some(templates) => eng.with_url_templates(templates),
+                None => eng,
+            };
+
+            let eng = match mix {
+                Some(ref mix) => eng.with_mix(mix),
+                None => eng,
+            };
+
+            let eng = matches
+                .values_of("header")
+                .into_iter()
+                .flatten()
+                .fold(eng, |eng, header| {
+                    let (name, value) = header
+                        .split_once(':')
+                        .or_invalid("Expected headers given as Name: value");
+                    let value = template::Template::parse(value.trim()).unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        process::exit(exit::CONFIG);
+                    });
+                    eng.with_header(name.trim().to_string(), value)
+                });
+
+            let eng = matches
+                .values_of("assert-header")
+                .into_iter()
+                .flatten()
+                .map(assertion::HeaderAssertion::equals)
+                .chain(
+                    matches
+                        .values_of("assert-header-regex")
+                        .into_iter()
+                        .flatten()
+                        .map(assertion::HeaderAssertion::matches),
+                )
+                .fold(eng, |eng, assertion| {
+                    eng.with_header_assertion(assertion.unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        process::exit(exit::CONFIG);
+                    }))
+                });
+
+            if (matches.is_present("http1.0") || matches.is_present("http1.1"))
+                && matches.value_of("engine") == Some("reqwest")
+            {
+                eprintln!("Only the hyper engine can force the http version");
+                process::exit(exit::CONFIG);
+            }
+            let eng = if matches.is_present("http1.0") {
+                eng.with_version(engine::Version::Http10)
+            } else if matches.is_present("http1.1") {
+                eng.with_version(engine::Version::Http11)
+            } else {
+                eng
+            };
+
+            let eng = match matches.value_of("requests-per-conn") {
+                Some(requests) => eng.with_requests_per_conn(
+                    requests
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&requests| requests > 0)
+                        .or_invalid("Expected a positive number of requests per connection"),
+                ),
+                None => eng,
+            };
+
+            if matches.is_present("pool-max-idle") && matches.value_of("engine") == Some("reqwest")
+            {
+                eprintln!("Only the hyper engine can limit the idle connections");
+                process::exit(exit::CONFIG);
+            }
+            let eng = match matches.value_of("pool-max-idle") {
+                Some(max_idle) => eng.with_pool_max_idle(
+                    max_idle
+                        .parse::<usize>()
+                        .or_invalid("Expected a valid number of idle connections"),
+                ),
+                None => eng,
+            };
+
+            let eng = match matches.value_of("pool-idle-timeout") {
+                Some(timeout) => eng.with_pool_idle_timeout(
+                    duration::parse(timeout).or_invalid("Expected a valid idle timeout"),
+                ),
+                None => eng,
+            };
+
+            let eng = match matches.value_of("timeout") {
+                Some(timeout) => eng.with_timeout(
+                    duration::parse(timeout).or_invalid("Expected a valid timeout"),
+                ),
+                None => eng,
+            };
+
+            let statuses = (matches.value_of("fail-on-status"), matches.value_of("expect-status"));
+            let eng = match statuses {
+                (Some(list), _) => eng.with_status_check(status::StatusCheck::FailOn(
+                    status::parse(list).or_invalid("Expected a list of statuses"),
+                )),
+                (_, Some(list)) => eng.with_status_check(status::StatusCheck::Expect(
+                    status::parse(list).or_invalid("Expected a list of statuses"),
+                )),
+                _ => eng,
+            };
+
+            let eng = match matches.value_of("max-response-size") {
+                Some(max) => eng.with_max_response_size(
+                    max.parse::<ContentLength>()
+                        .or_invalid("Expected a valid response size")
+                        .bytes(),
+                ),
+                None => eng,
+            };
+
+            let eng = eng.with_h2(h2_settings(matches));
+
+            let eng = if matches.is_present("sticky-sessions") {
+                let sessions = session::Sessions::default();
+                eng.with_sessions(match matches.value_of("session-header") {
+                    Some(header) => sessions.with_header(header.to_string()),
+                    None => sessions,
+                })
+            } else {
+                eng
+            };
+
+            let eng = if matches.is_present("head-requests") {
+                eng.with_method(engine::Method::Head)
+            } else {
+                eng
+            };
+
+            let eng = match matches.value_of("graphql") {
+                Some(query) => {
+                    let query =
+                        fs::read_to_string(query).or_invalid("Expected a readable query file");
+                    let variables = matches.value_of("variables").map(|variables| {
+                        fs::read_to_string(variables)
+                            .or_invalid("Expected a readable variables file")
+                    });
+                    let body = graphql::body(&query, variables.as_deref())
+                        .or_invalid("Expected valid graphql variables");
+                    eng.with_graphql(body)
+                }
+                None => eng,
+            };
+
+            let eng = match matches.value_of("digest") {
+                Some(credentials) => {
+                    let digest = digest::Digest::parse(credentials)
+                        .or_invalid("Expected digest credentials");
+                    eng.with_digest(match matches.value_of("digest-handshake") {
+                        Some("counted") => digest.with_handshake(digest::Handshake::Counted),
+                        _ => digest,
+                    })
+                }
+                None => eng,
+            };
+
+            let eng = match matches.value_of("negotiate") {
+                Some(credentials) => eng.with_negotiate(
+                    ntlm::Credentials::parse(credentials).or_invalid("Expected ntlm credentials"),
+                ),
+                None => eng,
+            };
+
+            let eng = match matches.value_of("oauth2") {
+                Some(spec) => {
+                    let oauth2 = oauth2::OAuth2::parse(spec).or_invalid("Expected an oauth2 grant");
+                    // The first token is fetched up front so that a bad grant fails the run
+                    // before it starts.
+                    if let Err(e) = oauth2.bearer() {
+                        eprintln!("{}", e);
+                        process::exit(exit::CONFIG);
+                    }
+                    eng.with_oauth2(oauth2)
+                }
+                None => eng,
+            };
+
+            let proxies = match matches.value_of("proxy") {
+                Some(url) => proxy::Proxy::parse(url).map(proxy::Proxies::all),
+                None if matches.is_present("no-proxy-env") => Ok(proxy::Proxies::default()),
+                None => proxy::Proxies::from_env(),
+            };
+            let proxies = proxies.unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(exit::CONFIG);
+            });
+            let proxies = match matches.value_of("proxy-auth") {
+                Some(_) if proxies.is_empty() => {
+                    eprintln!("There is no proxy to authenticate with");
+                    process::exit(exit::CONFIG);
+                }
+                Some(credentials) => match credentials.split_once(':') {
+                    Some((user, password)) => proxies.with_basic_auth(user, password),
+                    None => proxies.with_basic_auth(credentials, ""),
+                },
+                None => proxies,
+            };
+            // reqwest opens its tunnels itself, without the proxy's credentials.
+            let tunnelled = https_urls.iter().any(|url| {
+                proxies
+                    .for_url(url)
+                    .and_then(proxy::Proxy::authorization)
+                    .is_some()
+            });
+            if tunnelled && matches.value_of("engine") == Some("reqwest") {
+                eprintln!("Only the hyper engine can authenticate https tunnels with a proxy");
+                process::exit(exit::CONFIG);
+            }
+            let eng = eng.with_proxies(proxies);
+
+            let tls = tls_options(matches);
+            if tls != tls::Options::default() && matches.value_of("engine") == Some("reqwest") {
+                eprintln!("Only the hyper engine supports the tls options");
+                process::exit(exit::CONFIG);
+            }
+            let eng = eng.with_tls(tls);
+
+            let eng = match matches.value_of("aws-sigv4") {
+                Some(scope) => eng.with_aws_sigv4(sigv4::SigV4::parse(scope).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(exit::CONFIG);
+                })),
+                None => eng,
+            };
+
+            let eng = match matches.value_of("jwt") {
+                Some(key) => {
+                    let jwt = jwt::Jwt::load(key, matches.value_of("jwt-claims"))
+                        .unwrap_or_else(|e| {
+                            eprintln!("{}", e);
+                            process::exit(exit::CONFIG);
+                        });
+                    let jwt = match matches.value_of("jwt-ttl") {
+                        Some(ttl) => {
+                            jwt.with_ttl(
+                                duration::parse(ttl).or_invalid("Expected a valid jwt ttl"),
+                            )
+                        }
+                        None => jwt,
+                    };
+                    eng.with_jwt(match matches.value_of("jwt-mint") {
+                        Some("window") => jwt.with_mint(jwt::Mint::Window),
+                        _ => jwt,
+                    })
+                }
+                None => eng,
+            };
+
+            let eng = match matches.value_of("sign") {
+                Some(spec) => {
+                    let signature = signature::Signature::parse(spec).unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        process::exit(exit::CONFIG);
+                    });
+                    if signature.signs_body()
+                        && (matches.is_present("form") || matches.is_present("body-stream"))
+                    {
+                        eprintln!("Forms and streamed bodies cannot be signed, sign the url instead");
+                        process::exit(exit::CONFIG);
+                    }
+                    eng.with_signature(signature)
+                }
+                None => eng,
+            };
+
+            let eng = match matches.value_of("body-template") {
+                Some(path) => {
+                    let template = template::Template::load(path).unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        process::exit(exit::CONFIG);
+                    });
+                    let template = match matches.value_of("data-file") {
+                        Some(path) => {
+                            let rows =
+                                template::read_rows(path).or_invalid("Expected a valid data file");
+                            if matches.value_of("data-policy") == Some("unique") {
+                                unique_rows(matches, template, rows)
+                            } else if matches.is_present("sticky-sessions") {
+                                template.with_rows(rows).with_sticky_rows()
+                            } else {
+                                template.with_rows(rows)
+                            }
+                        }
+                        None => template,
+                    };
+                    eng.with_body_template(template)
+                }
+                None => eng,
+            };
+
+            let eng = match matches.values_of("form") {
+                Some(fields) => {
+                    eng.with_form(
+                        multipart::Form::parse(fields).or_invalid("Expected valid form fields"),
+                    )
+                }
+                None => eng,
+            };
+
+            let eng = if matches.is_present("body-stream") {
+                let specs = matches.values_of("body-stream").into_iter().flatten();
+                eng.with_body_stream(
+                    body_stream::BodyStream::parse(specs)
+                        .or_invalid("Expected a valid body stream"),
+                )
+            } else {
+                eng
+            };
+
+            let eng = match matches.value_of("group-by-header") {
+                Some(header) => eng.with_group_by_header(header.to_string()),
+                None if matches.is_present("sitemap") || matches.is_present("crawl") => {
+                    eng.with_group_by_url()
+                }
+                None => eng,
+            };
+
+            let eng = if matches.is_present("expect-continue") {
+                eng.with_expect_continue()
+            } else {
+                eng
+            };
+
+            let eng = match matches.value_of("plugin") {
+                Some(path) => eng.with_plugin(plugin::Plugin::load(path).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(exit::CONFIG);
+                })),
+                None => eng,
+            };
+            run(matches, eng);
+        }
+    }
+}
+
+/// The options of an http benchmark, which rench takes with or without the run command.
+fn http_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    let http2_conflicts = [
+        "http1.0",
+        "http1.1",
+        "expect-continue",
+        "form",
+        "body-stream",
+        "digest",
+        "negotiate",
+        "plugin",
+        "mode",
+    ];
+    app.arg(url_arg().required_unless("sitemap"))
+        .arg(concurrency_arg())
+        .arg(pin_cores_arg())
+        .arg(per_worker_arg())
+        .arg(sample_arg())
+        .arg(stream_facts_arg())
+        .arg(hdr_out_arg())
+        .arg(latency_spectrum_arg())
+        .arg(latency_arg())
+        .arg(format_arg())
+        .arg(dry_run_arg())
+        .args(&process_args())
+        .args(&deadline_args())
+        .args(&results_args())
+        .arg(requests_arg())
+        .args(&vu_args())
+        .arg(
+            Arg::with_name("head-requests")
+                .short("i")
+                .long("head")
+                .help("The issue head requests instead of get"),
+        )
         .arg(
-            Arg::with_name("URL")
-                .required(true)
+            Arg::with_name("mix")
+                .long("mix")
+                .takes_value(true)
+                .value_name("MIX")
+                .conflicts_with_all(&["head-requests", "sitemap", "crawl", "mode"])
+                .help(
+                    "Makes a mix of requests by weight in place of the urls, such as \
+                     'GET /items:80,POST /items:15,DELETE /items/{{ n }}:5', the paths \
+                     joined to the first url. Post requests carry the body, and the summary \
+                     is broken down by request",
+                ),
+        )
+        .arg(
+            Arg::with_name("graphql")
+                .long("graphql")
+                .takes_value(true)
+                .conflicts_with("head-requests")
+                .help("Posts the graphql query in the file, responses with errors are failures"),
+        )
+        .arg(
+            Arg::with_name("variables")
+                .long("variables")
+                .takes_value(true)
+                .requires("graphql")
+                .help("A json file with the variables for the graphql query"),
+        )
+        .arg(
+            Arg::with_name("header")
+                .long("header")
+                .short("H")
+                .takes_value(true)
                 .multiple(true)
-                .help("Each url specified will be round robined."),
+                .number_of_values(1)
+                .help(
+                    "Sends a header given as Name: value with every request. Values, urls and \
+                     body templates can call name(), email(), uuid() and int(min, max) for data",
+                ),
         )
         .arg(
-            Arg::with_name("concurrency")
-                .short("c")
+            Arg::with_name("digest")
+                .long("digest")
                 .takes_value(true)
-                .help("The number of concurrent requests to make"),
+                .value_name("USER:PASSWORD")
+                .help("Answers the http digest authentication challenges of the urls"),
         )
         .arg(
-            Arg::with_name("requests")
-                .short("n")
+            Arg::with_name("digest-handshake")
+                .long("digest-handshake")
                 .takes_value(true)
-                .help("The number of requests in total to make"),
+                .possible_values(&["excluded", "counted"])
+                .requires("digest")
+                .help(
+                    "Whether the 401 that first challenges each thread is left out of the \
+                     latency, the default, or counted in the request that needed it",
+                ),
         )
         .arg(
-            Arg::with_name("head-requests")
-                .short("i")
-                .long("head")
-                .help("The issue head requests instead of get"),
+            Arg::with_name("negotiate")
+                .long("negotiate")
+                .takes_value(true)
+                .value_name("DOMAIN\\USER:PASSWORD")
+                .conflicts_with("digest")
+                .help(
+                    "Authenticates each connection with windows integrated authentication over \
+                     ntlm, reporting the handshakes apart from the requests",
+                ),
+        )
+        .arg(
+            Arg::with_name("oauth2")
+                .long("oauth2")
+                .takes_value(true)
+                .value_name("TOKEN_URL,CLIENT_ID,CLIENT_SECRET,SCOPE")
+                .conflicts_with_all(&["digest", "negotiate"])
+                .help(
+                    "Sends a bearer token from an oauth2 client credentials grant with every \
+                     request, refreshing it before it expires. The scope can be left out",
+                ),
+        )
+        .arg(
+            Arg::with_name("proxy")
+                .long("proxy")
+                .takes_value(true)
+                .value_name("URL")
+                .conflicts_with_all(&["mode", "expect-continue"])
+                .help(
+                    "Sends the requests through the http proxy, tunnelling https ones with \
+                     CONNECT, in place of any proxies of the environment. Credentials can be \
+                     given in the url",
+                ),
+        )
+        .arg(
+            Arg::with_name("proxy-auth")
+                .long("proxy-auth")
+                .takes_value(true)
+                .value_name("USER:PASSWORD")
+                .help(
+                    "Authenticates with the proxy using basic authentication. Requests the \
+                     proxy answers with a 407 are counted as proxy authentication failures",
+                ),
+        )
+        .arg(
+            Arg::with_name("no-proxy-env")
+                .long("no-proxy-env")
+                .conflicts_with("proxy")
+                .help(
+                    "Connects directly rather than through the proxies of the http_proxy, \
+                     https_proxy, all_proxy and no_proxy environment variables",
+                ),
+        )
+        .arg(
+            Arg::with_name("aws-sigv4")
+                .long("aws-sigv4")
+                .takes_value(true)
+                .value_name("REGION/SERVICE")
+                .conflicts_with_all(&["digest", "negotiate", "oauth2"])
+                .help(
+                    "Signs every request with aws signature version 4, using the credentials \
+                     of the environment or the shared credentials file",
+                ),
+        )
+        .arg(
+            Arg::with_name("jwt")
+                .long("jwt")
+                .takes_value(true)
+                .value_name("KEY.PEM")
+                .conflicts_with_all(&["digest", "negotiate", "oauth2", "aws-sigv4"])
+                .help(
+                    "Sends a json web token signed with the rsa (RS256) or p-256 (ES256) private \
+                     key as the bearer token of each request",
+                ),
+        )
+        .arg(
+            Arg::with_name("jwt-claims")
+                .long("jwt-claims")
+                .takes_value(true)
+                .value_name("JSON")
+                .requires("jwt")
+                .help(
+                    "A json file of the claims the tokens carry, to which iat, exp and jti are \
+                     added",
+                ),
+        )
+        .arg(
+            Arg::with_name("jwt-ttl")
+                .long("jwt-ttl")
+                .takes_value(true)
+                .value_name("DURATION")
+                .requires("jwt")
+                .help("How long the tokens last, 60s by default"),
+        )
+        .arg(
+            Arg::with_name("jwt-mint")
+                .long("jwt-mint")
+                .takes_value(true)
+                .possible_values(&["request", "window"])
+                .requires("jwt")
+                .help(
+                    "Whether each request gets a token of its own, the default, or the requests \
+                     share one until it is about to expire",
+                ),
+        )
+        .arg(
+            Arg::with_name("sign")
+                .long("sign")
+                .takes_value(true)
+                .value_name("SCHEME")
+                .help(
+                    "Sends an hmac of each request in a header, given as the algorithm and \
+                     options such as hmac-sha256:header=X-Signature:key=env:API_KEY:payload=body",
+                ),
+        )
+        .arg(
+            Arg::with_name("body-template")
+                .long("body-template")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with_all(&["head-requests", "graphql"])
+                .help(
+                    "Posts a body rendered from the tera template for each request, which can \
+                     use n, request, url, timestamp, env, vu and the row of the data file",
+                ),
+        )
+        .arg(
+            Arg::with_name("data-file")
+                .long("data-file")
+                .takes_value(true)
+                .value_name("CSV")
+                .requires("body-template")
+                .help("A csv file with a header row whose rows the requests take in turn as row"),
+        )
+        .arg(
+            Arg::with_name("data-policy")
+                .long("data-policy")
+                .takes_value(true)
+                .value_name("POLICY")
+                .possible_values(&["cycle", "unique"])
+                .requires("data-file")
+                .help(
+                    "Whether the requests cycle through the rows of the data file or take each \
+                     once at most, stopping when they run out",
+                ),
+        )
+        .arg(
+            Arg::with_name("sticky-sessions")
+                .long("sticky-sessions")
+                .conflicts_with("data-policy")
+                .help(
+                    "Makes each thread a virtual user that keeps the cookies it is given and one \
+                     row of the data file for all of its requests",
+                ),
+        )
+        .arg(
+            Arg::with_name("session-header")
+                .long("session-header")
+                .takes_value(true)
+                .value_name("NAME")
+                .requires("sticky-sessions")
+                .help("Sends each virtual user's own session id in the header"),
+        )
+        .arg(
+            Arg::with_name("form")
+                .long("form")
+                .short("F")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .conflicts_with_all(&["head-requests", "graphql", "body-template"])
+                .help("Posts a multipart form field as name=value, or name=@path to upload a file"),
+        )
+        .arg(
+            Arg::with_name("body-stream")
+                .long("body-stream")
+                .takes_value(true)
+                .min_values(0)
+                .max_values(2)
+                .conflicts_with_all(&["head-requests", "graphql", "form", "body-template"])
+                .help(
+                    "Posts a generated body in chunks, given as size=1MB chunk=64KB which are \
+                     the defaults. Only the hyper engine writes chunks of the given size",
+                ),
+        )
+        .arg(
+            Arg::with_name("expect-continue")
+                .long("expect-continue")
+                .conflicts_with_all(&["head-requests", "body-template"])
+                .help("Waits for the server to answer Expect: 100-continue before sending bodies"),
+        )
+        .arg(
+            Arg::with_name("http1.0")
+                .long("http1.0")
+                .conflicts_with_all(&["http1.1", "expect-continue"])
+                .help("Forces requests to use http/1.0, only the hyper engine can"),
+        )
+        .arg(
+            Arg::with_name("http1.1")
+                .long("http1.1")
+                .help("Forces requests to use http/1.1, only the hyper engine can"),
+        )
+        .arg(
+            Arg::with_name("http2")
+                .long("http2")
+                .conflicts_with_all(&http2_conflicts)
+                .help(
+                    "Makes requests over http/2, offered to the servers of https urls by alpn. \
+                     Servers that choose http/1.1, and cleartext urls, are sent http/1.1 \
+                     requests, which the summary warns of",
+                ),
+        )
+        .arg(
+            Arg::with_name("http2-prior-knowledge")
+                .long("http2-prior-knowledge")
+                .conflicts_with_all(&http2_conflicts)
+                .help(
+                    "Makes requests over http/2 as --http2 does, and speaks it to the servers \
+                     of cleartext urls from the start, for services that take h2c",
+                ),
+        )
+        .group(
+            ArgGroup::with_name("any-http2")
+                .args(&["http2", "http2-prior-knowledge"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("h2-streams")
+                .long("h2-streams")
+                .takes_value(true)
+                .value_name("N")
+                .requires("any-http2")
+                .help(
+                    "Shares http/2 connections between the threads, each carrying up to N \
+                     streams at once, rather than giving every thread connections of its own",
+                ),
+        )
+        .arg(
+            Arg::with_name("reject-push")
+                .long("reject-push")
+                .requires("any-http2")
+                .help(
+                    "Tells servers not to push streams over http/2, which are otherwise counted \
+                     apart from the responses asked for",
+                ),
+        )
+        .arg(
+            Arg::with_name("h2-window")
+                .long("h2-window")
+                .takes_value(true)
+                .value_name("BYTES")
+                .requires("any-http2")
+                .help("The window servers are given to send each http/2 stream's body in"),
+        )
+        .arg(
+            Arg::with_name("h2-connection-window")
+                .long("h2-connection-window")
+                .takes_value(true)
+                .value_name("BYTES")
+                .requires("any-http2")
+                .help(
+                    "The window servers are given to send the bodies of all of an http/2 \
+                     connection's streams in",
+                ),
+        )
+        .arg(
+            Arg::with_name("requests-per-conn")
+                .long("requests-per-conn")
+                .takes_value(true)
+                .help("The number of requests each connection carries before it is closed"),
+        )
+        .arg(
+            Arg::with_name("pool-max-idle")
+                .long("pool-max-idle")
+                .takes_value(true)
+                .help(
+                    "The idle connections each thread keeps for reuse with the hyper engine, 0 \
+                     opening one for every request",
+                ),
+        )
+        .arg(
+            Arg::with_name("pool-idle-timeout")
+                .long("pool-idle-timeout")
+                .takes_value(true)
+                .help("How long a connection can sit idle before it is closed, such as 30s"),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .value_name("DURATION")
+                .help(
+                    "Gives up on requests not answered within this, such as 5s, counting them \
+                     as timeouts rather than in the latencies",
+                ),
+        )
+        .arg(
+            Arg::with_name("pessimistic-percentiles")
+                .long("pessimistic-percentiles")
+                .requires("timeout")
+                .help("Also reports percentiles that count timeouts as having taken the timeout"),
+        )
+        .arg(
+            Arg::with_name("confidence")
+                .long("confidence")
+                .takes_value(true)
+                .value_name("PERCENT")
+                .help(
+                    "Also reports bootstrapped intervals the mean and p99 latencies fall within \
+                     with this confidence, such as 95",
+                ),
+        )
+        .arg(
+            Arg::with_name("mean")
+                .long("mean")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("MEAN")
+                .help(
+                    "Also reports a mean of the latencies less swayed by outliers, trimmed:5% \
+                     leaving out 5% at each end or geometric",
+                ),
+        )
+        .arg(
+            Arg::with_name("fail-on-status")
+                .long("fail-on-status")
+                .takes_value(true)
+                .value_name("STATUSES")
+                .conflicts_with("expect-status")
+                .help("Counts responses with these statuses as failures, such as 4xx,5xx,302"),
+        )
+        .arg(
+            Arg::with_name("expect-status")
+                .long("expect-status")
+                .takes_value(true)
+                .value_name("STATUSES")
+                .help("Counts responses with any status but these as failures, such as 200,204"),
+        )
+        .arg(
+            Arg::with_name("assert-header")
+                .long("assert-header")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("HEADER")
+                .help(
+                    "Counts responses as failures unless they have the header with exactly \
+                     this value, given as Name: value",
+                ),
+        )
+        .arg(
+            Arg::with_name("assert-header-regex")
+                .long("assert-header-regex")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("HEADER")
+                .help(
+                    "Counts responses as failures unless they have the header with a value \
+                     the regex matches, given as Name: regex",
+                ),
+        )
+        .arg(
+            Arg::with_name("gate-p99")
+                .long("gate-p99")
+                .takes_value(true)
+                .value_name("DURATION")
+                .help(
+                    "Ends the run with a failure as soon as the p99 of its first requests is \
+                     over this, such as 500ms",
+                ),
+        )
+        .arg(
+            Arg::with_name("gate-after")
+                .long("gate-after")
+                .takes_value(true)
+                .value_name("REQUESTS")
+                .requires("gate-p99")
+                .help("How many requests the p99 of --gate-p99 is taken over, 1000 by default"),
+        )
+        .arg(
+            Arg::with_name("max-response-size")
+                .long("max-response-size")
+                .takes_value(true)
+                .value_name("SIZE")
+                .help(
+                    "Stops reading response bodies larger than this, such as 10MB, counting \
+                     them as failures",
+                ),
         )
         .arg(
             Arg::with_name("engine")
@@ -56,69 +1317,1564 @@ fn main() {
                 .help("The engine to use"),
         )
         .arg(
-            Arg::with_name("chart-size")
-                .long("chart-size")
+            Arg::with_name("group-by-header")
+                .long("group-by-header")
+                .takes_value(true)
+                .value_name("HEADER")
+                .help("Breaks the summary down by the value of a response header, such as X-Backend"),
+        )
+        .arg(
+            Arg::with_name("sitemap")
+                .long("sitemap")
+                .takes_value(true)
+                .value_name("URL")
+                .conflicts_with("group-by-header")
+                .help(
+                    "Benchmarks the pages listed in a sitemap, or in the sitemaps of a sitemap \
+                     index, and reports the slowest of them",
+                ),
+        )
+        .arg(
+            Arg::with_name("sitemap-limit")
+                .long("sitemap-limit")
+                .takes_value(true)
+                .requires("sitemap")
+                .help("The most pages to take from the sitemap, defaults to 100"),
+        )
+        .arg(
+            Arg::with_name("sitemap-filter")
+                .long("sitemap-filter")
+                .takes_value(true)
+                .value_name("REGEX")
+                .requires("sitemap")
+                .help("Only benchmarks the pages of the sitemap whose url matches"),
+        )
+        .arg(
+            Arg::with_name("crawl")
+                .long("crawl")
+                .takes_value(true)
+                .min_values(0)
+                .max_values(2)
+                .conflicts_with_all(&["sitemap", "group-by-header"])
+                .help(
+                    "Adds the pages of the same origin linked from each url to the mix, given \
+                     as depth=2 limit=100 which are the defaults, and reports the slowest of them",
+                ),
+        )
+        .arg(
+            Arg::with_name("plugin")
+                .long("plugin")
+                .takes_value(true)
+                .value_name("FILE")
+                .help(
+                    "Runs the before_request and after_response functions of a wasm module \
+                     around each request, to add headers such as signatures or to reject \
+                     responses",
+                ),
+        )
+        .arg(
+            Arg::with_name("mode")
+                .long("mode")
+                .takes_value(true)
+                .possible_values(&["http", "connect", "tls-handshake", "dns"])
+                .help(
+                    "What to measure, connect only opens tcp connections to each host:port, \
+                     tls-handshake only completes a tls handshake on each and dns only \
+                     resolves each host",
+                ),
+        )
+        .arg(
+            Arg::with_name("resolver")
+                .long("resolver")
+                .takes_value(true)
+                .requires("mode")
+                .help("The ip of a resolver to query directly in dns mode, with an optional port"),
+        )
+        .arg(
+            Arg::with_name("tls-min")
+                .long("tls-min")
+                .takes_value(true)
+                .value_name("VERSION")
+                .conflicts_with("expect-continue")
+                .help("The lowest tls version connections may negotiate, such as 1.2"),
+        )
+        .arg(
+            Arg::with_name("tls-max")
+                .long("tls-max")
                 .takes_value(true)
-                .possible_values(&["none", "n", "small", "s", "medium", "m", "large", "l"])
-                .help("The size of the chart to render"),
+                .value_name("VERSION")
+                .conflicts_with("expect-continue")
+                .help("The highest tls version connections may negotiate, such as 1.3"),
         )
-        .get_matches();
+        .arg(
+            Arg::with_name("tls")
+                .long("tls")
+                .takes_value(true)
+                .value_name("VERSION")
+                .conflicts_with_all(&["tls-min", "tls-max", "expect-continue"])
+                .help(
+                    "Pins connections to the one tls version. The versions connections \
+                     negotiate are counted in the summary",
+                ),
+        )
+        .arg(
+            Arg::with_name("ciphers")
+                .long("ciphers")
+                .takes_value(true)
+                .value_name("LIST")
+                .conflicts_with("expect-continue")
+                .help(
+                    "The colon separated openssl cipher suites connections may negotiate. Tls \
+                     1.3 suites, named TLS_*, are set apart from those of earlier versions, so \
+                     either left out keeps its defaults. The suites negotiated are counted in \
+                     the summary",
+                ),
+        )
+        .arg(
+            Arg::with_name("sni")
+                .long("sni")
+                .takes_value(true)
+                .value_name("NAME")
+                .conflicts_with("expect-continue")
+                .help(
+                    "The server name sent in the tls handshake in place of the host of the \
+                     url, which the certificate is checked against. The connection is still \
+                     made to the url's host and the Host header left as is",
+                ),
+        )
+        .arg(
+            Arg::with_name("pin")
+                .long("pin")
+                .takes_value(true)
+                .value_name("sha256//BASE64")
+                .conflicts_with("expect-continue")
+                .help(
+                    "The sha256 digest of the public key the server's certificate is expected \
+                     to have, or several split by semicolons. Requests to servers with another \
+                     key are counted as pin mismatches",
+                ),
+        )
+        .arg(
+            Arg::with_name("expiry-window")
+                .long("expiry-window")
+                .takes_value(true)
+                .value_name("DURATION")
+                .help(
+                    "Warns when a server's certificate expires within the duration, such as 14d, \
+                     30d by default",
+                ),
+        )
+        .args(&socket_args())
+        .arg(chart_size_arg())
+        .arg(chart_style_arg())
+        .arg(chart_height_arg())
+        .arg(hist_bucket_arg())
+        .arg(chart_clamp_arg())
+}
 
-    let urls: Vec<String> = matches
-        .values_of("URL")
-        .expect("URLs are required")
-        .map(|v| v.to_string())
-        .collect();
+fn url_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("URL")
+        .required(true)
+        .multiple(true)
+        .help("Each url specified will be round robined.")
+}
 
-    let threads = matches
-        .value_of("concurrency")
-        .unwrap_or("1")
-        .parse::<usize>()
-        .expect("Expected valid number for threads");
+fn concurrency_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("concurrency")
+        .short("c")
+        .takes_value(true)
+        .help("The number of concurrent requests to make")
+}
 
-    let requests = matches
-        .value_of("requests")
-        .unwrap_or("1000")
-        .parse::<usize>()
-        .expect("Expected valid number for number of requests");
+fn sample_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("sample")
+        .long("sample")
+        .takes_value(true)
+        .help("Only keeps a random sample of this many requests, summarizing the run from it")
+}
+
+fn stream_facts_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("stream-facts")
+        .long("stream-facts")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("Writes each request to the file as a line of json as soon as it completes")
+}
+
+fn hdr_out_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("hdr-out")
+        .long("hdr-out")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("Writes a latency histogram for each second to the file as an HdrHistogram log")
+}
+
+fn latency_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("latency")
+        .long("latency")
+        .help(
+            "Also prints a finer grained distribution of the latencies, down to every tenth of \
+             a percent from p99",
+        )
+}
+
+fn latency_spectrum_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("latency-spectrum")
+        .long("latency-spectrum")
+        .help("Prints the latency percentile spectrum in the same format as wrk2")
+}
 
-    let chart_size = match matches.value_of("chart-size").unwrap_or("medium") {
+fn format_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("format")
+        .long("format")
+        .takes_value(true)
+        .possible_values(&report::FORMATS)
+        .help(
+            "The format to print the summary in, defaults to text. The progress of a run is \
+             printed to stderr for the other formats, leaving stdout to the summary",
+        )
+}
+
+fn dry_run_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("dry-run")
+        .long("dry-run")
+        .help(
+            "Prints the targets, headers, body and load the run would have, then makes a \
+             single request to check them rather than running",
+        )
+}
+
+fn pin_cores_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("pin-cores")
+        .long("pin-cores")
+        .takes_value(true)
+        .min_values(0)
+        .require_equals(true)
+        .help("Pins the threads to cores in turn, to every core or to a list such as --pin-cores=0,2-3")
+}
+
+fn per_worker_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("per-worker")
+        .long("per-worker")
+        .help("Breaks the summary down by the thread that made each request")
+}
+
+fn process_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("processes")
+            .long("processes")
+            .takes_value(true)
+            .help("Splits the requests between this many child processes"),
+        // Tells a child process which share of the requests to make.
+        Arg::with_name("child")
+            .long("child")
+            .takes_value(true)
+            .hidden(true),
+    ]
+}
+
+fn deadline_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("deadline")
+            .long("deadline")
+            .takes_value(true)
+            .value_name("TIME")
+            .help(
+                "Ends the run at a time such as 2024-05-01T13:00:00Z however far it got, \
+                 summarizing the requests made by then",
+            ),
+        Arg::with_name("max-runtime")
+            .long("max-runtime")
+            .takes_value(true)
+            .value_name("DURATION")
+            .conflicts_with("deadline")
+            .help("Ends the run once it has gone on this long, as --deadline does"),
+    ]
+}
+
+fn results_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("config")
+            .long("config")
+            .takes_value(true)
+            .value_name("FILE")
+            .help(
+                "Checks the run against the thresholds of the [thresholds] section of the \
+                 file, one to a line such as p95 < 150ms, error_rate < 0.5% or rps > 1000, \
+                 failing the run when any is not met",
+            ),
+        Arg::with_name("junit")
+            .long("junit")
+            .takes_value(true)
+            .value_name("FILE")
+            .requires("config")
+            .help("Writes how the run met its thresholds to the file as a JUnit report"),
+        Arg::with_name("append-csv")
+            .long("append-csv")
+            .takes_value(true)
+            .value_name("FILE")
+            .help(
+                "Appends a row for the run to a csv ledger of runs, with its time, tags, \
+                 concurrency, requests per second, p50, p99 and error rate",
+            ),
+        Arg::with_name("tag")
+            .long("tag")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help(
+                "Labels the run, such as release-1.4, in its json metadata, in the rows it \
+                 appends to a ledger, and as the dimensions of its cloudwatch metrics, such as env=staging",
+            ),
+        Arg::with_name("meta")
+            .long("meta")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("KEY=VALUE")
+            .help(
+                "Records what the run was of in the metadata of its json, such as \
+                 git_sha=4a85a85, along with the host, os and cpus it ran on",
+            ),
+        Arg::with_name("notify-url")
+            .long("notify-url")
+            .takes_value(true)
+            .value_name("URL")
+            .help(
+                "Posts the json summary to a webhook once the run finishes, or why it was \
+                 aborted when it was",
+            ),
+        Arg::with_name("slack-webhook")
+            .long("slack-webhook")
+            .takes_value(true)
+            .value_name("URL")
+            .help(
+                "Posts the throughput, p99, error rate and how the run met its thresholds \
+                 to a slack incoming webhook once it finishes",
+            ),
+        Arg::with_name("pushgateway")
+            .long("pushgateway")
+            .takes_value(true)
+            .value_name("URL")
+            .help(
+                "Pushes the metrics of the run to a prometheus pushgateway once it finishes, \
+                 given the url of a job such as http://host:9091/metrics/job/rench",
+            ),
+        Arg::with_name("push-interval")
+            .long("push-interval")
+            .takes_value(true)
+            .value_name("DURATION")
+            .requires("pushgateway")
+            .help(
+                "Also pushes the metrics of the requests since the last push this often, such \
+                 as 10s",
+            ),
+        Arg::with_name("cloudwatch")
+            .long("cloudwatch")
+            .takes_value(true)
+            .value_name("OPTIONS")
+            .help(
+                "Publishes the latency percentiles, throughput and errors of the run as \
+                 cloudwatch metrics, given as namespace=LoadTests with an optional \
+                 region= and endpoint=, with the tags of the run as dimensions",
+            ),
+        Arg::with_name("graphite")
+            .long("graphite")
+            .takes_value(true)
+            .value_name("HOST:PORT")
+            .help(
+                "Sends the metrics of each interval of the run, and of the whole run once it \
+                 finishes, to graphite in its plaintext protocol",
+            ),
+        Arg::with_name("graphite-prefix")
+            .long("graphite-prefix")
+            .takes_value(true)
+            .value_name("PREFIX")
+            .requires("graphite")
+            .help("Prefixes the paths of the graphite metrics [default: rench]"),
+        Arg::with_name("graphite-interval")
+            .long("graphite-interval")
+            .takes_value(true)
+            .value_name("DURATION")
+            .requires("graphite")
+            .help("How often to send the metrics of the run to graphite [default: 10s]"),
+        Arg::with_name("web")
+            .long("web")
+            .takes_value(true)
+            .value_name("ADDRESS")
+            .help(
+                "Serves a page at the address, such as 127.0.0.1:8080, charting the requests \
+                 per second, latencies and statuses of the run live",
+            ),
+        Arg::with_name("gha-summary")
+            .long("gha-summary")
+            .help(
+                "Appends the results and thresholds as markdown to the github actions job \
+                 summary, when GITHUB_STEP_SUMMARY is set",
+            ),
+    ]
+}
+
+fn vu_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("vus")
+            .long("vus")
+            .takes_value(true)
+            .value_name("N")
+            .conflicts_with("concurrency")
+            .requires("iterations")
+            .help("The number of virtual users, each a thread going through the iterations"),
+        Arg::with_name("iterations")
+            .long("iterations")
+            .takes_value(true)
+            .value_name("N")
+            .conflicts_with("requests")
+            .requires("vus")
+            .help(
+                "How many times each virtual user goes through the urls in turn, or the \
+                 weights of a mix, timing each time",
+            ),
+        Arg::with_name("ramp-down")
+            .long("ramp-down")
+            .takes_value(true)
+            .value_name("DURATION")
+            .requires("iterations")
+            .help(
+                "Once a virtual user is through its iterations, leaves the others this long to \
+                 finish the iteration they are on without starting another",
+            ),
+        Arg::with_name("exclude-ramp-down")
+            .long("exclude-ramp-down")
+            .requires("ramp-down")
+            .help("Leaves the requests made while ramping down out of the summary"),
+    ]
+}
+
+fn requests_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("requests")
+        .short("n")
+        .takes_value(true)
+        .help("The number of requests in total to make")
+}
+
+fn output_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .help("Writes the summary to the file as json")
+}
+
+fn chart_size_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("chart-size")
+        .long("chart-size")
+        .takes_value(true)
+        .possible_values(&["none", "n", "small", "s", "medium", "m", "large", "l"])
+        .help("The size of the chart to render")
+}
+
+fn chart_style_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("chart-style")
+        .long("chart-style")
+        .takes_value(true)
+        .possible_values(&["bars", "braille"])
+        .help(
+            "How to draw the charts, defaults to bars. Braille draws four levels to a row, \
+             showing smaller differences",
+        )
+}
+
+fn chart_height_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("chart-height")
+        .long("chart-height")
+        .takes_value(true)
+        .value_name("ROWS")
+        .help("How many rows to draw the charts with, rather than as many as their size has")
+}
+
+fn hist_bucket_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("hist-bucket")
+        .long("hist-bucket")
+        .takes_value(true)
+        .value_name("DURATION")
+        .help(
+            "Counts the latency histogram into buckets of this width, such as 10ms, rather \
+             than 2% of the longest latency, so that the histograms of runs line up",
+        )
+}
+
+fn chart_clamp_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("chart-clamp")
+        .long("chart-clamp")
+        .takes_value(true)
+        .value_name("PERCENTILE")
+        .help(
+            "Scales the charts to the latency at this percentile, such as p99.5, listing the \
+             latencies past it rather than letting a few outliers flatten the charts",
+        )
+}
+
+/// The options for the sockets every engine opens, shared by all of the commands.
+fn socket_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("bind")
+            .long("bind")
+            .takes_value(true)
+            .help("The source ips to connect from, comma separated ips are taken in turn"),
+        Arg::with_name("local-port-range")
+            .long("local-port-range")
+            .takes_value(true)
+            .help("The local ports to connect from, such as 20000-40000"),
+        Arg::with_name("tcp-nodelay")
+            .long("tcp-nodelay")
+            .help("Disables Nagle's algorithm on every connection"),
+        Arg::with_name("so-sndbuf")
+            .long("so-sndbuf")
+            .takes_value(true)
+            .help("The size of each socket's send buffer, such as 256KB"),
+        Arg::with_name("so-rcvbuf")
+            .long("so-rcvbuf")
+            .takes_value(true)
+            .help("The size of each socket's receive buffer, such as 256KB"),
+    ]
+}
+
+fn chart_size(matches: &ArgMatches) -> ChartSize {
+    match matches.value_of("chart-size").unwrap_or("medium") {
         "none" | "n" => ChartSize::None,
         "small" | "s" => ChartSize::Small,
         "medium" | "m" => ChartSize::Medium,
         "large" | "l" => ChartSize::Large,
         _ => unreachable!(),
+    }
+}
+
+fn chart_style(matches: &ArgMatches) -> chart::Style {
+    match matches.value_of("chart-style").unwrap_or("bars") {
+        "bars" => chart::Style::Bars,
+        "braille" => chart::Style::Braille,
+        _ => unreachable!(),
+    }
+}
+
+fn chart_height(matches: &ArgMatches) -> Option<u32> {
+    matches.value_of("chart-height").map(|rows| {
+        rows.parse::<u32>()
+            .ok()
+            .filter(|&rows| rows > 0)
+            .or_invalid("Expected a valid chart height, a positive number of rows")
+    })
+}
+
+fn hist_bucket(matches: &ArgMatches) -> Option<Duration> {
+    matches.value_of("hist-bucket").map(|width| {
+        duration::parse(width)
+            .ok()
+            .filter(|width| *width > Duration::new(0, 0))
+            .or_invalid("Expected a valid histogram bucket, a duration such as 10ms")
+    })
+}
+
+fn chart_clamp(matches: &ArgMatches) -> Option<f64> {
+    matches.value_of("chart-clamp").map(|percentile| {
+        percentile
+            .trim_start_matches('p')
+            .parse::<f64>()
+            .ok()
+            .filter(|&percentage| percentage > 0f64 && percentage <= 100f64)
+            .or_invalid("Expected a valid percentile to clamp the charts at, such as p99.5")
+    })
+}
+
+/// Serves requests until the process is stopped.
+fn serve(matches: &ArgMatches) {
+    let server = serve::Server::default();
+    let server = match matches.value_of("latency") {
+        Some(latency) => {
+            server.with_latency(duration::parse(latency).or_invalid("Expected a valid latency"))
+        }
+        None => server,
+    };
+    let server = match matches.value_of("jitter") {
+        Some(jitter) => {
+            server.with_jitter(duration::parse(jitter).or_invalid("Expected a valid jitter"))
+        }
+        None => server,
+    };
+    let server = match matches.value_of("status-mix") {
+        Some(mix) => {
+            server.with_status_mix(
+                serve::parse_status_mix(mix).or_invalid("Expected a valid status mix"),
+            )
+        }
+        None => server,
+    };
+
+    let host = matches.value_of("host").unwrap_or("127.0.0.1");
+    let port = matches
+        .value_of("port")
+        .unwrap_or("8080")
+        .parse::<u16>()
+        .or_invalid("Expected a valid port");
+    let listener = TcpListener::bind((host, port)).unwrap_or_else(|e| {
+        eprintln!("Could not listen on {}:{}: {}", host, port, e);
+        process::exit(exit::CONFIG);
+    });
+    println!("Serving on http://{}", listener.local_addr().expect("Expected a bound address"));
+    server.run(listener);
+}
+
+/// Reads the facts in a file written by `--stream-facts`.
+fn read_facts(path: &str) -> Vec<Fact> {
+    let file = File::open(path).or_invalid("Expected a readable file of facts");
+    ndjson::read_facts(BufReader::new(file))
+        .unwrap_or_else(|e| exit::invalid(&format!("Expected valid facts in {}: {}", path, e)))
+}
+
+/// Whether the summary is printed as text, which other lines can be printed alongside. A child
+/// process's stdout carries its facts to the parent, so its lines go to stderr.
+fn is_text(matches: &ArgMatches) -> bool {
+    !matches.is_present("child")
+        && matches.value_of("format").unwrap_or(report::FORMATS[0]) == report::FORMATS[0]
+}
+
+/// Prints a line about how things are going, to stderr when stdout is left to a summary
+/// in another format.
+fn note(text: bool, line: &str) {
+    if text {
+        println!("{}", line);
+    } else {
+        eprintln!("{}", line);
+    }
+}
+
+/// Prints the summary in the format asked for, also writing it out as json when asked to.
+fn print_summary(matches: &ArgMatches, summary: &Summary) {
+    print_summary_checked(matches, summary, &[]);
+}
+
+/// Prints the summary as `print_summary` does, along with how the run met its thresholds
+/// in formats that report them.
+fn print_summary_checked(matches: &ArgMatches, summary: &Summary, outcomes: &[threshold::Outcome]) {
+    if let Some(path) = matches.value_of("output") {
+        let json = serde_json::to_string_pretty(&summary.to_json())
+            .expect("Expected the summary to convert to json");
+        fs::write(path, json).expect("Expected to write the summary");
+    }
+    let reporter: Box<dyn Reporter> = match matches.value_of("format") {
+        Some("tap") => Box::new(outcomes.iter().fold(report::Tap::default(), |tap, outcome| {
+            tap.with_test(outcome.passed, outcome.describe())
+        })),
+        format => report::reporter(format.unwrap_or(report::FORMATS[0]))
+            .expect("Expected a known format"),
+    };
+    if is_text(matches) {
+        println!();
+    }
+    let stdout = io::stdout();
+    reporter
+        .report(summary, &mut stdout.lock())
+        .expect("Expected to print the summary");
+}
+
+/// Summarizes a run from the facts it streamed to a file, as it was summarized at the end
+/// of the run.
+fn report(matches: &ArgMatches) {
+    let path = matches.value_of("FILE").expect("File is required");
+    let facts = read_facts(path);
+    note(is_text(matches), &format!("{} requests from {}", facts.len(), path));
+
+    let summary = Summary::from_facts(&facts)
+        .with_chart_size(chart_size(matches))
+        .with_chart_style(chart_style(matches))
+        .with_chart_height(chart_height(matches))
+        .with_histogram_bucket(hist_bucket(matches))
+        .with_chart_clamp(chart_clamp(matches));
+    let summary = if matches.is_present("per-worker") {
+        summary.with_per_worker()
+    } else {
+        summary
+    };
+    let summary = if matches.is_present("latency") {
+        summary.with_latency_distribution()
+    } else {
+        summary
+    };
+    print_summary(matches, &summary);
+    if matches.is_present("latency-spectrum") {
+        print!("{}", spectrum::spectrum(facts.iter().filter_map(Fact::latency).collect()));
+    }
+}
+
+/// Compares two runs from the facts they streamed to files.
+fn compare(matches: &ArgMatches) {
+    if let Some(tags) = matches.values_of("tag") {
+        return compare_tagged(matches, &tags.collect::<Vec<_>>());
+    }
+    let baseline = read_facts(matches.value_of("BASELINE").expect("Baseline is required"));
+    let candidate = read_facts(matches.value_of("CANDIDATE").expect("Candidate is required"));
+    let comparison = Comparison::new(&baseline, &candidate)
+        .and_then(|comparison| match matches.value_of("test") {
+            Some(test) => comparison.with_test(significance::Test::parse(test)?),
+            None => Ok(comparison),
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(exit::CONFIG);
+        });
+    println!("{}", comparison);
+}
+
+/// Compares the latest runs of a ledger with the baseline's tag and the candidate's.
+fn compare_tagged(matches: &ArgMatches, tags: &[&str]) {
+    let fail = |message: String| -> ! {
+        eprintln!("{}", message);
+        process::exit(exit::CONFIG);
     };
+    if tags.len() != 2 {
+        fail(format!("Expected the tags of a baseline and a candidate but got {}", tags.len()));
+    }
+    ledger::check_tags(tags.iter().cloned()).unwrap_or_else(|e| fail(e));
+    let path = matches.value_of("ledger").expect("Ledger is required with tags");
+    let entries = ledger::read(Path::new(path)).unwrap_or_else(|e| fail(e));
+    let runs: Vec<&ledger::Entry> = tags
+        .iter()
+        .map(|tag| {
+            ledger::latest(&entries, tag)
+                .unwrap_or_else(|| fail(format!("No run in {} is tagged {}", path, tag)))
+        })
+        .collect();
+    for (tag, run) in tags.iter().zip(&runs) {
+        println!("{}: the run of {}", tag, run.timestamp());
+    }
+    println!();
+    println!("{}", ledger::compare(runs[0], runs[1]));
+}
+
+/// Summarizes the facts in files written by `--stream-facts` as though they came from a
+/// single run, optionally writing the joint summary out as json.
+fn merge(matches: &ArgMatches) {
+    let mut facts = Vec::new();
+    for path in matches.values_of("FILE").expect("Files are required") {
+        let read = read_facts(path);
+        note(is_text(matches), &format!("{} requests from {}", read.len(), path));
+        facts.extend(read);
+    }
 
+    let summary = Summary::from_facts(&facts)
+        .with_chart_size(chart_size(matches))
+        .with_chart_style(chart_style(matches))
+        .with_chart_height(chart_height(matches))
+        .with_histogram_bucket(hist_bucket(matches))
+        .with_chart_clamp(chart_clamp(matches));
+    print_summary(matches, &summary);
+}
+
+/// Makes this process's share of the requests, reporting each to the parent process.
+fn run_child(
+    eng: engine::Engine,
+    threads: usize,
+    requests: usize,
+    cores: &[usize],
+    deadline: Option<Instant>,
+) {
+    if let Err(e) = limits::ensure_open_files(threads) {
+        eprintln!("{}", e);
+        process::exit(exit::CONFIG);
+    }
     let plan = Plan::new(threads, requests);
+    let (collector, handle) = processes::forward(plan);
+    let runner = Runner::start(plan, &eng, &collector, cores);
+    if deadline.is_none() {
+        runner.join();
+    }
+    stop_at(deadline, &collector, plan.threads());
+    handle.join().expect("Forwarding thread to finish");
+}
 
-    let eng = match matches.value_of("engine").unwrap_or("hyper") {
-        "hyper" => engine::Engine::new(urls.clone()).with_hyper(),
-        "reqwest" | _ => engine::Engine::new(urls.clone()),
+/// When the run has to end, from `--deadline` or `--max-runtime`.
+fn deadline(matches: &ArgMatches) -> Option<Instant> {
+    if let Some(time) = matches.value_of("deadline") {
+        let at = deadline::parse(time).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(exit::CONFIG);
+        });
+        return Some(deadline::instant(at));
+    }
+    matches.value_of("max-runtime").map(|runtime| {
+        Instant::now() + duration::parse(runtime).or_invalid("Expected a valid maximum runtime")
+    })
+}
+
+/// Prints the settings of a run as it starts, so that its output says what it was of.
+fn print_configuration(
+    matches: &ArgMatches,
+    eng: &engine::Engine,
+    threads: usize,
+    requests: usize,
+    processes: usize,
+) {
+    let users = if matches.is_present("vus") { "virtual users" } else { "threads" };
+    let mut settings = eng.configuration();
+    let concurrency = match processes {
+        1 => format!("{} {}", threads, users),
+        _ => format!("{} {} in {} processes", threads, users, processes),
     };
+    settings.insert(3, ("Concurrency", concurrency));
+    settings.insert(4, ("Requests", requests.to_string()));
+    if let Some(runtime) = matches.value_of("max-runtime") {
+        settings.push(("Runtime", format!("{} at the most", runtime)));
+    }
+    if let Some(time) = matches.value_of("deadline") {
+        settings.push(("Deadline", time.to_string()));
+    }
+    let text = is_text(matches);
+    let width = settings.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    note(text, "Configuration:");
+    for (name, value) in settings {
+        note(text, &format!("  {:width$} {}", format!("{}:", name), value, width = width + 1));
+    }
+}
+
+/// Prints what a run would be and checks it with a single request, exiting as though the
+/// targets were unreachable when the request got no response.
+fn dry_run(
+    matches: &ArgMatches,
+    eng: engine::Engine,
+    threads: usize,
+    requests: usize,
+    processes: usize,
+) {
+    for line in eng.describe(requests) {
+        println!("{}", line);
+    }
+    println!("Load:");
+    let users = if matches.is_present("vus") { "virtual users" } else { "threads" };
+    println!("  {} {} in {} process(es)", threads, users, processes);
+    match matches.value_of("iterations") {
+        Some(iterations) => println!("  {} iterations each, {} requests", iterations, requests),
+        None => println!("  {} requests", requests),
+    }
+    if let Some(runtime) = matches.value_of("max-runtime") {
+        println!("  Stopping after {} at the most", runtime);
+    }
+    if let Some(time) = matches.value_of("deadline") {
+        println!("  Stopping at {} at the latest", time);
+    }
+    if let Some(timeout) = matches.value_of("timeout") {
+        println!("  Each request timing out after {}", timeout);
+    }
 
-    let eng = if matches.is_present("head-requests") {
-        eng.with_method(engine::Method::Head)
+    println!();
+    println!("Checking with a single request");
+    let mut facts = Vec::new();
+    eng.with_user(0).run(1, |fact| facts.push(fact));
+    for fact in &facts {
+        println!(
+            "  {} in {:.3} ms",
+            fact.outcome(),
+            fact.duration().as_secs_f64() * 1_000f64
+        );
+    }
+    if Summary::from_facts(&facts).responses() == 0 {
+        eprintln!("The request got no response");
+        process::exit(exit::UNREACHABLE);
+    }
+}
+
+/// Ends the collection of the facts at the deadline, as though every thread had finished,
+/// so that threads stalled on a request are left behind rather than waited on.
+fn stop_at(deadline: Option<Instant>, collector: &Sender<Message<Fact>>, threads: usize) {
+    if let Some(deadline) = deadline {
+        let collector = collector.clone();
+        thread::spawn(move || {
+            thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            for _ in 0..threads {
+                // The collector is gone when the threads finished first.
+                let _ = collector.send(Message::Eof);
+            }
+        });
+    }
+}
+
+/// Hands each row to one request at most. A child process only has its share of the rows, so
+/// that no two processes send the same one.
+fn unique_rows(
+    matches: &ArgMatches,
+    template: template::Template,
+    rows: Vec<serde_json::Value>,
+) -> template::Template {
+    let rows = match matches.value_of("child") {
+        Some(share) => processes::Share::parse(share)
+            .or_invalid("Expected a valid process share")
+            .part(rows),
+        None => rows,
+    };
+    let requests = matches
+        .value_of("requests")
+        .unwrap_or("1000")
+        .parse::<usize>()
+        .or_invalid("Expected valid number for number of requests");
+    if rows.len() < requests && !matches.is_present("child") && !matches.is_present("vus") {
+        note(
+            is_text(matches),
+            &format!(
+                "The data file has {} rows, so only {} of the {} requests will be made",
+                rows.len(),
+                rows.len(),
+                requests
+            ),
+        );
+    }
+    template.with_rows(rows).with_unique_rows()
+}
+
+/// The cores to pin the threads to, none unless `--pin-cores` was given.
+fn pinned_cores(matches: &ArgMatches) -> Vec<usize> {
+    if !matches.is_present("pin-cores") {
+        return Vec::new();
+    }
+    let cores = match matches.value_of("pin-cores") {
+        Some(cores) => affinity::parse_cores(cores).or_invalid("Expected a valid list of cores"),
+        None => (0..monitor::cores()).collect(),
+    };
+    if let Some(core) = cores.iter().find(|&&core| core >= monitor::cores()) {
+        exit::invalid(&format!("Core {} does not exist, there are {}", core, monitor::cores()));
+    }
+    cores
+}
+
+fn socket_options(matches: &ArgMatches) -> socket::Options {
+    // The reqwest client opens its sockets itself, without any of the options.
+    if matches.value_of("engine") == Some("reqwest") {
+        if let Some(option) = ["bind", "local-port-range", "tcp-nodelay", "so-sndbuf", "so-rcvbuf"]
+            .iter()
+            .find(|&&option| matches.is_present(option))
+        {
+            eprintln!("Only the hyper engine can set --{}", option);
+            process::exit(exit::CONFIG);
+        }
+    }
+    let options = socket::Options::default();
+    let options = match matches.value_of("bind") {
+        Some(bind) => {
+            let ips = bind
+                .split(',')
+                .map(|ip| ip.trim().parse::<IpAddr>())
+                .collect::<Result<Vec<IpAddr>, _>>()
+                .or_invalid("Expected the ips to bind to");
+            if let Some(ip) = ips.iter().find(|&&ip| !socket::is_local(ip)) {
+                exit::invalid(&format!("Cannot bind {}, which is not an address of this host", ip));
+            }
+            options.with_bind(ips)
+        }
+        None => options,
+    };
+    let options = match matches.value_of("local-port-range") {
+        Some(range) => {
+            let mut ports = range.splitn(2, '-').map(|port| port.trim().parse::<u16>());
+            match (ports.next(), ports.next()) {
+                (Some(Ok(first)), Some(Ok(last))) if first <= last => {
+                    options.with_port_range(first, last)
+                }
+                _ => exit::invalid("Expected a local port range such as 20000-40000"),
+            }
+        }
+        None => options,
+    };
+    let options = if matches.is_present("tcp-nodelay") {
+        options.with_nodelay()
+    } else {
+        options
+    };
+    let options = match matches.value_of("so-sndbuf") {
+        Some(size) => options.with_send_buffer(
+            size.parse::<ContentLength>()
+                .or_invalid("Expected a valid send buffer size")
+                .bytes() as usize,
+        ),
+        None => options,
+    };
+    match matches.value_of("so-rcvbuf") {
+        Some(size) => options.with_recv_buffer(
+            size.parse::<ContentLength>()
+                .or_invalid("Expected a valid receive buffer size")
+                .bytes() as usize,
+        ),
+        None => options,
+    }
+}
+
+/// How requests are multiplexed over http/2 connections.
+fn h2_settings(matches: &ArgMatches) -> multiplex::Settings {
+    let h2 = multiplex::Settings::default();
+    let h2 = if matches.is_present("http2-prior-knowledge") {
+        h2.with_prior_knowledge()
+    } else {
+        h2
+    };
+    let h2 = if matches.is_present("reject-push") {
+        h2.with_reject_push()
+    } else {
+        h2
+    };
+    let h2 = match matches.value_of("h2-streams") {
+        Some(streams) => h2.with_streams(
+            streams
+                .parse::<usize>()
+                .ok()
+                .filter(|&streams| streams > 0)
+                .or_invalid("Expected a positive number of streams per connection"),
+        ),
+        None => h2,
+    };
+    let h2 = match matches.value_of("h2-window") {
+        Some(window) => {
+            h2.with_window(window.parse::<u32>().or_invalid("Expected a valid stream window"))
+        }
+        None => h2,
+    };
+    match matches.value_of("h2-connection-window") {
+        Some(window) => h2.with_connection_window(
+            window
+                .parse::<u32>()
+                .or_invalid("Expected a valid connection window"),
+        ),
+        None => h2,
+    }
+}
+
+/// How connections are secured, checked against what openssl supports.
+fn tls_options(matches: &ArgMatches) -> tls::Options {
+    let version = |name| {
+        matches.value_of(name).map(|version| {
+            tls::Version::parse(version).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(exit::CONFIG);
+            })
+        })
+    };
+    let versions = match version("tls") {
+        Some(version) => Ok(tls::Versions::only(version)),
+        None => tls::Versions::new(version("tls-min"), version("tls-max")),
+    };
+    let options = versions.map(|versions| tls::Options::default().with_versions(versions));
+    let options = options.map(|options| match matches.value_of("ciphers") {
+        Some(ciphers) => options.with_ciphers(ciphers),
+        None => options,
+    });
+    let options = options.map(|options| match matches.value_of("sni") {
+        Some(name) => options.with_server_name(name),
+        None => options,
+    });
+    let options = options.and_then(|options| match matches.value_of("pin") {
+        Some(pins) => tls::Pin::parse(pins).map(|pins| options.with_pins(pins)),
+        None => Ok(options),
+    });
+    match options.and_then(|options| options.check().map(|_| options)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(exit::CONFIG);
+        }
+    }
+}
+
+fn urls(matches: &ArgMatches) -> Vec<String> {
+    if matches.is_present("child") && (matches.is_present("sitemap") || matches.is_present("crawl"))
+    {
+        return processes::pages().expect("Expected the pages from the parent process");
+    }
+    if let Some(url) = matches.value_of("sitemap") {
+        let limit = matches
+            .value_of("sitemap-limit")
+            .unwrap_or("100")
+            .parse::<usize>()
+            .or_invalid("Expected valid number for the sitemap limit");
+        let filter = matches
+            .value_of("sitemap-filter")
+            .map(|filter| Regex::new(filter).or_invalid("Expected a valid sitemap filter"));
+        let pages = sitemap::pages(url, limit, filter.as_ref()).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(exit::UNREACHABLE);
+        });
+        note(is_text(matches), &format!("Benchmarking {} pages from {}", pages.len(), url));
+        return pages;
+    }
+    let urls: Vec<String> = matches
+        .values_of("URL")
+        .expect("URLs are required")
+        .map(|v| v.to_string())
+        .collect();
+    if matches.is_present("crawl") {
+        let specs = matches.values_of("crawl").into_iter().flatten();
+        let crawl = crawl::Crawl::parse(specs).or_invalid("Expected valid crawl options");
+        let pages = crawl.pages(&urls).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(exit::UNREACHABLE);
+        });
+        note(is_text(matches), &format!("Benchmarking {} crawled pages", pages.len()));
+        return pages;
+    }
+    urls
+}
+
+fn run(matches: &ArgMatches, eng: engine::Engine) {
+    pause::listen();
+    let started = SystemTime::now();
+    let thresholds = matches.value_of("config").map(|path| {
+        fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path, e))
+            .and_then(|config| threshold::parse_config(&config))
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(exit::CONFIG);
+            })
+    });
+    let meta = matches
+        .values_of("meta")
+        .into_iter()
+        .flatten()
+        .map(metadata::parse_meta)
+        .collect::<Result<Vec<(String, String)>, String>>()
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(exit::CONFIG);
+        });
+    if matches.is_present("append-csv") {
+        ledger::check_tags(matches.values_of("tag").into_iter().flatten()).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(exit::CONFIG);
+        });
+    }
+    let cloudwatch = matches.value_of("cloudwatch").map(|options| {
+        cloudwatch::CloudWatch::parse(options).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(exit::CONFIG);
+        })
+    });
+    let deadline = deadline(matches);
+    let eng = match deadline {
+        Some(deadline) => eng.with_deadline(deadline),
+        None => eng,
+    };
+    let eng = eng.with_socket_options(socket_options(matches));
+
+    let threads = matches
+        .value_of("vus")
+        .or_else(|| matches.value_of("concurrency"))
+        .unwrap_or("1")
+        .parse::<usize>()
+        .or_invalid("Expected valid number for threads");
+
+    // Each virtual user makes every request of its iterations.
+    let per_user = matches.value_of("iterations").map(|iterations| {
+        let iterations = iterations
+            .parse::<usize>()
+            .or_invalid("Expected valid number for iterations");
+        iterations * eng.iteration_len()
+    });
+    let eng = if per_user.is_some() {
+        eng.with_iterations()
     } else {
         eng
     };
+    let eng = match matches.value_of("ramp-down") {
+        Some(window) => eng.with_ramp_down(ramp::RampDown::new(
+            duration::parse(window).or_invalid("Expected a valid ramp down"),
+        )),
+        None => eng,
+    };
+
+    let requests = match per_user {
+        Some(per_user) => threads * per_user,
+        None => matches
+            .value_of("requests")
+            .unwrap_or("1000")
+            .parse::<usize>()
+            .or_invalid("Expected valid number for number of requests"),
+    };
+
+    let processes = matches
+        .value_of("processes")
+        .unwrap_or("1")
+        .parse::<usize>()
+        .or_invalid("Expected valid number for processes");
+    if processes == 0 || processes > threads {
+        exit::invalid(&format!(
+            "Expected between 1 and {} processes, one for each thread at most",
+            threads
+        ));
+    }
+
+    if matches.is_present("dry-run") {
+        dry_run(matches, eng, threads, requests, processes);
+        return;
+    }
+
+    if let Some(share) = matches.value_of("child") {
+        let share = processes::Share::parse(share).or_invalid("Expected a valid process share");
+        let eng = eng.with_first_user(share.offset(threads));
+        let requests = match per_user {
+            Some(per_user) => share.of(threads) * per_user,
+            None => share.of(requests),
+        };
+        run_child(eng, share.of(threads), requests, &pinned_cores(matches), deadline);
+        return;
+    }
+
+    if let Err(e) = limits::ensure_open_files(threads) {
+        eprintln!("{}", e);
+        process::exit(exit::CONFIG);
+    }
+
+    let cores = pinned_cores(matches);
+    let plan = Plan::new(threads, requests);
+    // Only the parent serves the dashboard, the children's facts reaching it through the
+    // parent's collector.
+    let dashboard = matches.value_of("web").map(|address| {
+        let dashboard = dashboard::Dashboard::serve(address).unwrap_or_else(|e| {
+            eprintln!("Could not serve the dashboard at {}: {}", address, e);
+            process::exit(exit::CONFIG);
+        });
+        note(is_text(matches), &format!("Serving the dashboard at http://{}/", address));
+        dashboard
+    });
 
-    let (collector, rec_handle) = collector::start::<Fact>(plan);
-    let runner = Runner::start(plan, &eng, &collector);
+    let sample = matches.value_of("sample").map(|size| {
+        size.parse::<usize>()
+            .ok()
+            .filter(|&size| size > 0)
+            .or_invalid("Expected a valid sample size, a positive number of requests")
+    });
+    let mut stream = matches.value_of("stream-facts").map(|path| {
+        let file = File::create(path).expect("Expected to create the file to stream facts to");
+        FactWriter::new(BufWriter::new(file))
+    });
+    let mut hdr = matches.value_of("hdr-out").map(|path| {
+        let file = File::create(path).expect("Expected to create the histogram log");
+        HdrLog::new(BufWriter::new(file), SystemTime::now())
+            .expect("Expected to write the histogram log")
+    });
+    let notify_url = matches.value_of("notify-url").map(str::to_string);
+    let mut pushes = matches.value_of("push-interval").map(|interval| {
+        let interval = duration::parse(interval).or_invalid("Expected a valid push interval");
+        let url = matches.value_of("pushgateway").expect("Required by the interval").to_string();
+        (url, interval, Instant::now(), Vec::new())
+    });
+    let web = dashboard.clone();
+    let throughput = throughput::Throughput::start();
+    let per_second = throughput.clone();
+    let mut graphite = matches.value_of("graphite").map(|address| {
+        let interval = matches.value_of("graphite-interval").unwrap_or("10s");
+        let interval = duration::parse(interval).or_invalid("Expected a valid graphite interval");
+        let prefix = matches.value_of("graphite-prefix").unwrap_or("rench");
+        (address.to_string(), prefix.to_string(), interval, Instant::now(), Vec::new())
+    });
+    let mut gate = matches.value_of("gate-p99").map(|p99| {
+        gate::Gate::new(
+            duration::parse(p99).or_invalid("Expected a valid p99 to gate on"),
+            matches
+                .value_of("gate-after")
+                .unwrap_or("1000")
+                .parse::<usize>()
+                .ok()
+                .filter(|&after| after > 0)
+                .or_invalid("Expected a positive number of requests to gate on"),
+        )
+    });
+    let text = is_text(matches);
+    let progress = cmp::max(requests / 10, 1);
+    let mut observed = 0;
+    let observe = move |fact: &Fact| {
+        observed += 1;
+        if observed % progress == 0 {
+            note(text, &format!("{} requests", observed));
+        }
+        let now = SystemTime::now();
+        if let Some(ref mut stream) = stream {
+            stream
+                .write(fact, now)
+                .expect("Expected to stream the fact to the file");
+        }
+        if let (Some(hdr), Some(latency)) = (hdr.as_mut(), fact.latency()) {
+            hdr.record(latency, now)
+                .expect("Expected to write the histogram log");
+        }
+        if let Some(ref mut gate) = gate {
+            if let Some(p99) = gate.record(fact.duration()) {
+                let reason = format!(
+                    "The p99 of the first {} requests was {} ms, over the gate of {} ms",
+                    gate.after(),
+                    p99.as_millis(),
+                    gate.p99().as_millis()
+                );
+                eprintln!("{}", reason);
+                if let Some(ref url) = notify_url {
+                    if let Err(e) = notify::aborted(url, &reason) {
+                        eprintln!("{}", e);
+                    }
+                }
+                // Exiting skips the writers' destructors, so they are finished first.
+                if let Some(ref mut stream) = stream {
+                    stream.flush().expect("Expected to stream the facts to the file");
+                }
+                if let Some(ref mut hdr) = hdr {
+                    hdr.finish(now).expect("Expected to write the histogram log");
+                }
+                process::exit(exit::THRESHOLDS);
+            }
+        }
+        if let Some(ref web) = web {
+            web.record(fact);
+        }
+        per_second.record(fact);
+        if let Some((ref address, ref prefix, interval, ref mut since, ref mut window)) = graphite {
+            window.push(fact.headline());
+            let elapsed = since.elapsed();
+            if elapsed >= interval {
+                let rps = window.len() as f64 / elapsed.as_secs_f64();
+                let lines = graphite::lines(prefix, &Summary::from_facts(window), rps, now);
+                if let Err(e) = graphite::send(address, &lines) {
+                    eprintln!("Could not send to graphite at {}: {}", address, e);
+                }
+                *since = Instant::now();
+                window.clear();
+            }
+        }
+        // Each push is of the window since the last, as graphite's are, so that the facts
+        // of the whole run are not kept and summarized again and again.
+        if let Some((ref url, interval, ref mut pushed, ref mut window)) = pushes {
+            window.push(fact.headline());
+            if pushed.elapsed() >= interval {
+                *pushed = Instant::now();
+                if let Err(e) = notify::push_gateway(url, &Summary::from_facts(window)) {
+                    eprintln!("{}", e);
+                }
+                window.clear();
+            }
+        }
+    };
+    let collect_plan = if processes > 1 {
+        Plan::new(processes, requests)
+    } else {
+        plan
+    };
+    // Printed before any request is made, so that no progress comes before it.
+    print_configuration(matches, &eng, threads, requests, processes);
+    let (collector, rec_handle) = collector::start(collect_plan, sample, observe);
+    let runner = if processes > 1 {
+        // The children make requests of the pages the parent found rather than finding them.
+        let pages = if matches.is_present("sitemap") || matches.is_present("crawl") {
+            eng.target_names()
+        } else {
+            Vec::new()
+        };
+        Runner::start_processes(plan, processes, &pages, &collector)
+    } else {
+        Runner::start(plan, &eng, &collector, &cores)
+    };
 
-    println!("Beginning requests");
-    let ((), duration) = bench::time_it(|| runner.join());
-    let facts = rec_handle.join().expect("Receiving thread to finish");
+    let monitor = Monitor::start(threads);
+    note(text, "Beginning requests");
+    stop_at(deadline, &collector, collect_plan.threads());
+    let ((facts, seen), duration) = bench::time_it(|| {
+        if deadline.is_none() {
+            runner.join();
+        }
+        rec_handle.join().expect("Receiving thread to finish")
+    });
+    // The children's usage is their own, so only a single process is worth reporting.
+    let usage = monitor.stop().filter(|_| processes == 1);
+    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        note(text, &format!("Stopped at the deadline after {} of {} requests", seen, requests));
+    }
+    let paused = pause::paused_for();
+    let duration = duration.checked_sub(paused).unwrap_or_default();
     let seconds =
         duration.as_secs() as f64 + (f64::from(duration.subsec_nanos()) / 1_000_000_000f64);
 
-    println!("Finished!");
-    println!();
-    println!("Took {} seconds", seconds);
-    println!("{} requests / second", requests as f64 / seconds);
-    println!();
-    println!(
-        "{}",
-        Summary::from_facts(&facts).with_chart_size(chart_size)
-    );
+    note(text, "Finished!");
+    note(text, "");
+    if paused > Duration::new(0, 0) {
+        let seconds = paused.as_secs_f64();
+        note(text, &format!("Paused for {} seconds, left out of the time taken", seconds));
+    }
+    note(text, &format!("Took {} seconds", seconds));
+    note(text, &format!("{} requests / second", seen as f64 / seconds));
+    let facts = if matches.is_present("exclude-ramp-down") {
+        let made = facts.len();
+        let kept: Vec<Fact> = facts.into_iter().filter(|f| !f.is_ramping_down()).collect();
+        note(text, &format!("{} requests made while ramping down left out", made - kept.len()));
+        kept
+    } else {
+        facts
+    };
+    let summary = Summary::from_facts(&facts)
+        .with_chart_size(chart_size(matches))
+        .with_chart_style(chart_style(matches))
+        .with_chart_height(chart_height(matches))
+        .with_histogram_bucket(hist_bucket(matches))
+        .with_chart_clamp(chart_clamp(matches))
+        .with_proxies(eng.proxies_used())
+        .with_http2_asked(matches.is_present("any-http2"))
+        .with_expiry_window(match matches.value_of("expiry-window") {
+            Some(window) => duration::parse(window).or_invalid("Expected a valid expiry window"),
+            None => stats::DEFAULT_EXPIRY_WINDOW,
+        })
+        .with_usage(usage)
+        .with_throughput(throughput.rates())
+        .with_concurrency(threads, seen as f64 / seconds)
+        .with_sampled_from(seen)
+        .with_metadata(
+            metadata::Metadata::new(started, eng.target_names())
+                .with_meta(meta)
+                .with_tags(
+                    matches.values_of("tag").into_iter().flatten().map(String::from).collect(),
+                ),
+        );
+    let summary = match matches.value_of("timeout") {
+        Some(timeout) if matches.is_present("pessimistic-percentiles") => summary
+            .with_pessimistic_percentiles(
+                duration::parse(timeout).or_invalid("Expected a valid timeout"),
+            ),
+        _ => summary,
+    };
+    let means: Vec<stats::Mean> = matches
+        .values_of("mean")
+        .into_iter()
+        .flatten()
+        .map(|mean| stats::Mean::parse(mean).or_invalid("Expected a valid mean"))
+        .collect();
+    let summary = summary.with_means(&means);
+    let summary = match matches.value_of("confidence") {
+        Some(level) => summary.with_confidence(
+            level
+                .parse::<f64>()
+                .ok()
+                .filter(|&level| level > 0f64 && level < 100f64)
+                .or_invalid("Expected a valid confidence, a percentage such as 95"),
+        ),
+        None => summary,
+    };
+    // The pages of a sitemap or crawl are already grouped by url.
+    let summary = if matches.is_present("sitemap") || matches.is_present("crawl") {
+        summary
+    } else {
+        summary.with_urls(&eng.target_names())
+    };
+    let summary = match matches.value_of("group-by-header") {
+        Some(header) => summary.with_group_header(header),
+        None if matches.is_present("sitemap") || matches.is_present("crawl") => {
+            summary.with_group_header("page").with_slowest_groups(SLOWEST_PAGES)
+        }
+        None => summary,
+    };
+    let summary = if matches.is_present("per-worker") {
+        summary.with_per_worker()
+    } else {
+        summary
+    };
+    let summary = if matches.is_present("latency") {
+        summary.with_latency_distribution()
+    } else {
+        summary
+    };
+    if summary.sent().bytes() > 0 {
+        let rate = summary.sent().bytes() as f64 / seconds;
+        note(text, &format!("{} / second uploaded", ContentLength::new(rate as u64)));
+    }
+    let thresholds = thresholds.unwrap_or_default();
+    let outcomes: Vec<threshold::Outcome> = thresholds
+        .iter()
+        .map(|threshold| threshold.check(&summary, seen as f64 / seconds))
+        .collect();
+    print_summary_checked(matches, &summary, &outcomes);
+    if matches.is_present("latency-spectrum") {
+        print!("{}", spectrum::spectrum(facts.iter().filter_map(Fact::latency).collect()));
+    }
+    if let Some(dashboard) = dashboard {
+        dashboard.finish();
+    }
+    if matches.is_present("gha-summary") {
+        write_job_summary(&summary, &outcomes);
+    }
+    if let Some(url) = matches.value_of("notify-url") {
+        if let Err(e) = notify::finished(url, summary.to_json()) {
+            eprintln!("{}", e);
+        }
+    }
+    if let Some(url) = matches.value_of("pushgateway") {
+        if let Err(e) = notify::push_gateway(url, &summary) {
+            eprintln!("{}", e);
+        }
+    }
+    if let Some(address) = matches.value_of("graphite") {
+        let prefix = format!("{}.total", matches.value_of("graphite-prefix").unwrap_or("rench"));
+        let lines = graphite::lines(&prefix, &summary, seen as f64 / seconds, SystemTime::now());
+        if let Err(e) = graphite::send(address, &lines) {
+            eprintln!("Could not send to graphite at {}: {}", address, e);
+        }
+    }
+    if let Some(url) = matches.value_of("slack-webhook") {
+        if let Err(e) = notify::slack(url, &summary, seen as f64 / seconds, &outcomes) {
+            eprintln!("{}", e);
+        }
+    }
+    let tags: Vec<&str> = matches.values_of("tag").into_iter().flatten().collect();
+    if let Some(cloudwatch) = cloudwatch {
+        if let Err(e) = cloudwatch.publish(&summary, seen as f64 / seconds, &tags) {
+            eprintln!("{}", e);
+        }
+    }
+    if let Some(path) = matches.value_of("append-csv") {
+        let row = ledger::row(SystemTime::now(), &tags, threads, seen as f64 / seconds, &summary);
+        ledger::append(Path::new(path), &row).expect("Expected to append to the ledger");
+    }
+    let passed = !matches.is_present("config") || check_thresholds(matches, &outcomes);
+    if summary.requests() > 0 && summary.responses() == 0 {
+        eprintln!("None of the requests got a response");
+        process::exit(exit::UNREACHABLE);
+    }
+    if !passed {
+        process::exit(exit::THRESHOLDS);
+    }
+}
+
+/// Appends the summary and how the run met its thresholds to the summary of the github
+/// actions job it runs in, when it runs in one.
+fn write_job_summary(summary: &Summary, outcomes: &[threshold::Outcome]) {
+    let path = match env::var_os("GITHUB_STEP_SUMMARY") {
+        Some(path) => path,
+        None => return,
+    };
+    let mut markdown = Vec::new();
+    report::Markdown
+        .report(summary, &mut markdown)
+        .expect("Expected to write the summary as markdown");
+    if !outcomes.is_empty() {
+        markdown.push(b'\n');
+        markdown.extend_from_slice(threshold::markdown(outcomes).as_bytes());
+    }
+    markdown.push(b'\n');
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(&markdown))
+        .expect("Expected to append to the job summary");
+}
+
+/// Reports how the run met its thresholds, and whether all of them were.
+fn check_thresholds(matches: &ArgMatches, outcomes: &[threshold::Outcome]) -> bool {
+    let text = is_text(matches);
+    note(text, "");
+    note(text, "Thresholds:");
+    for outcome in outcomes {
+        note(text, &format!("  {}", outcome));
+    }
+    if let Some(path) = matches.value_of("junit") {
+        fs::write(path, threshold::junit(outcomes)).expect("Expected to write the JUnit report");
+    }
+    outcomes.iter().all(|outcome| outcome.passed)
 }
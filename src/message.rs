@@ -6,5 +6,5 @@ where
     T: 'static + Send,
 {
     Body(T),
-    EOF,
+    Eof,
 }
@@ -0,0 +1,229 @@
+use deadline;
+#[cfg(unix)]
+use libc;
+use monitor;
+use serde_json::{Map, Value};
+use std::env;
+use std::time::SystemTime;
+
+/// The options whose values can carry credentials, which the command line leaves out.
+const SECRET_OPTIONS: [&str; 10] = [
+    "--digest",
+    "--negotiate",
+    "--oauth2",
+    "--proxy",
+    "--proxy-auth",
+    "--sign",
+    "--jwt",
+    "--jwt-claims",
+    "--header",
+    "-H",
+];
+
+/// The headers whose values are credentials, the values of the others being kept.
+const SECRET_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "x-api-key",
+    "x-auth-token",
+];
+
+const REDACTED: &str = "<redacted>";
+
+/// What a run was, where and when, so that results read back later say what they are of.
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    version: &'static str,
+    command_line: Vec<String>,
+    hostname: Option<String>,
+    os: &'static str,
+    arch: &'static str,
+    cpus: usize,
+    started: SystemTime,
+    ended: SystemTime,
+    targets: Vec<String>,
+    meta: Vec<(String, String)>,
+    tags: Vec<String>,
+}
+
+impl Metadata {
+    /// The metadata of this process's run of the targets, which started at the time and has
+    /// just ended.
+    pub fn new(started: SystemTime, targets: Vec<String>) -> Metadata {
+        Metadata {
+            version: env!("CARGO_PKG_VERSION"),
+            command_line: redact(env::args()),
+            hostname: hostname(),
+            os: env::consts::OS,
+            arch: env::consts::ARCH,
+            cpus: monitor::cores(),
+            started,
+            ended: SystemTime::now(),
+            targets,
+            meta: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Adds what the run was told of itself, such as the git sha of the build under test.
+    pub fn with_meta(mut self, meta: Vec<(String, String)>) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    /// Adds the labels of the run, such as `release-1.4` or `region=us-east`.
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "rench_version": self.version,
+            "command_line": self.command_line,
+            "hostname": self.hostname,
+            "os": self.os,
+            "arch": self.arch,
+            "cpus": self.cpus,
+            "started": deadline::format(self.started),
+            "ended": deadline::format(self.ended),
+            "targets": self.targets,
+            "meta": self
+                .meta
+                .iter()
+                .map(|(key, value)| (key.clone(), Value::from(value.as_str())))
+                .collect::<Map<String, Value>>(),
+            "tags": self.tags,
+        })
+    }
+}
+
+/// Parses metadata given as `key=value`, such as `git_sha=4a85a85`.
+pub fn parse_meta(pair: &str) -> Result<(String, String), String> {
+    match pair.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("Expected metadata as key=value but got {}", pair)),
+    }
+}
+
+/// The arguments with the values of the options that can carry credentials redacted, whether
+/// given as `--option value` or `--option=value`, so that the results can be shared.
+fn redact<I: Iterator<Item = String>>(args: I) -> Vec<String> {
+    let mut redacted = Vec::new();
+    let mut secret: Option<String> = None;
+    for arg in args {
+        if let Some(option) = secret.take() {
+            redacted.push(redact_value(&option, &arg));
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((option, value)) if SECRET_OPTIONS.contains(&option) => {
+                redacted.push(format!("{}={}", option, redact_value(option, value)));
+            }
+            _ => {
+                if SECRET_OPTIONS.contains(&arg.as_str()) {
+                    secret = Some(arg.clone());
+                }
+                redacted.push(arg);
+            }
+        }
+    }
+    redacted
+}
+
+fn redact_value(option: &str, value: &str) -> String {
+    if option != "--header" && option != "-H" {
+        return REDACTED.to_string();
+    }
+    match value.split_once(':') {
+        Some((name, _)) if SECRET_HEADERS.contains(&name.trim().to_lowercase().as_str()) => {
+            format!("{}: {}", name, REDACTED)
+        }
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut name = [0u8; 256];
+    let result = unsafe { libc::gethostname(name.as_mut_ptr() as *mut libc::c_char, name.len()) };
+    if result != 0 {
+        return None;
+    }
+    let len = name.iter().position(|&byte| byte == 0).unwrap_or(name.len());
+    Some(String::from_utf8_lossy(&name[..len]).into_owned())
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    env::var("COMPUTERNAME").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn it_says_what_the_run_was() {
+        let started = UNIX_EPOCH + Duration::from_secs(1_714_568_400);
+        let json = Metadata::new(started, vec!["http://localhost/".to_string()])
+            .with_meta(vec![("git_sha".to_string(), "4a85a85".to_string())])
+            .with_tags(vec!["release-1.4".to_string()])
+            .to_json();
+        assert_eq!(json["rench_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["started"], "2024-05-01T13:00:00Z");
+        assert_eq!(json["targets"][0], "http://localhost/");
+        assert!(json["command_line"].as_array().is_some_and(|args| !args.is_empty()));
+        assert!(json["hostname"].is_string());
+        assert_eq!(json["os"], env::consts::OS);
+        assert!(json["cpus"].as_u64().is_some_and(|cpus| cpus > 0));
+        assert_eq!(json["meta"]["git_sha"], "4a85a85");
+        assert_eq!(json["tags"][0], "release-1.4");
+    }
+
+    #[test]
+    fn it_redacts_credentials_from_the_command_line() {
+        let args = vec![
+            "rench",
+            "--digest",
+            "admin:s3cret",
+            "--proxy-auth=user:pass",
+            "-H",
+            "Authorization: Bearer abc",
+            "--header=Accept: text/html",
+            "-c",
+            "4",
+            "http://localhost/",
+        ];
+        assert_eq!(
+            redact(args.into_iter().map(String::from)),
+            vec![
+                "rench",
+                "--digest",
+                "<redacted>",
+                "--proxy-auth=<redacted>",
+                "-H",
+                "Authorization: <redacted>",
+                "--header=Accept: text/html",
+                "-c",
+                "4",
+                "http://localhost/",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_meta() {
+        assert_eq!(
+            parse_meta("build = 17"),
+            Ok(("build".to_string(), "17".to_string()))
+        );
+        assert_eq!(parse_meta("empty="), Ok(("empty".to_string(), String::new())));
+        assert!(parse_meta("=value").is_err());
+        assert!(parse_meta("flag").is_err());
+    }
+}
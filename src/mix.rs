@@ -0,0 +1,84 @@
+use engine::Method;
+
+/// One of the requests of a traffic mix, made in proportion to its weight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation {
+    pub method: Method,
+    pub url: String,
+    pub weight: u32,
+}
+
+/// Parses a mix of requests with their weights, such as
+/// `GET /items:80,POST /items:15,DELETE /items/{{ n }}:5`. Paths are joined to the origin of
+/// the base url, while full urls are taken as they are.
+pub fn parse(mix: &str, base: &str) -> Result<Vec<Operation>, String> {
+    let origin = match base.find("://") {
+        Some(scheme) => match base[scheme + 3..].find('/') {
+            Some(path) => &base[..scheme + 3 + path],
+            None => base,
+        },
+        None => return Err(format!("Expected a url to join the paths of the mix to, got {}", base)),
+    };
+    mix.split(',')
+        .map(|part| {
+            let invalid = || format!("Expected METHOD PATH:WEIGHT but got {}", part);
+            let (request, weight) = part.trim().rsplit_once(':').ok_or_else(invalid)?;
+            let (method, target) = request.trim().split_once(' ').ok_or_else(invalid)?;
+            let method = match method.to_ascii_uppercase().as_str() {
+                "GET" => Method::Get,
+                "HEAD" => Method::Head,
+                "POST" => Method::Post,
+                "DELETE" => Method::Delete,
+                _ => return Err(format!("Expected GET, HEAD, POST or DELETE but got {}", method)),
+            };
+            let target = target.trim();
+            let url = if target.starts_with('/') {
+                format!("{}{}", origin, target)
+            } else if target.contains("://") {
+                target.to_string()
+            } else {
+                return Err(invalid());
+            };
+            match weight.trim().parse() {
+                Ok(weight) if weight > 0 => Ok(Operation {
+                    method,
+                    url,
+                    weight,
+                }),
+                _ => Err(format!("Expected a positive weight but got {}", weight)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_mixes() {
+        let mix = parse(
+            "GET /items:80, post /items:15,DELETE /items/{{ n }}:5",
+            "http://localhost:8080/api",
+        )
+        .unwrap();
+        assert_eq!(
+            mix[0],
+            Operation {
+                method: Method::Get,
+                url: "http://localhost:8080/items".to_string(),
+                weight: 80,
+            }
+        );
+        assert_eq!(mix[1].method, Method::Post);
+        assert_eq!(mix[2].url, "http://localhost:8080/items/{{ n }}");
+        assert_eq!(
+            parse("GET http://other:81/x:1", "http://localhost").unwrap()[0].url,
+            "http://other:81/x"
+        );
+        assert!(parse("GET /items", "http://localhost").is_err());
+        assert!(parse("GET /items:0", "http://localhost").is_err());
+        assert!(parse("PATCH /items:1", "http://localhost").is_err());
+        assert!(parse("GET items:1", "http://localhost").is_err());
+    }
+}
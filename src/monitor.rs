@@ -0,0 +1,170 @@
+use libc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use ContentLength;
+
+/// How often the process's own usage is sampled.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// Above this share of the cpu it can use, rench is probably slowing down the benchmark.
+const SATURATED: f64 = 0.9;
+
+/// What the benchmark cost rench itself to run.
+#[derive(Debug)]
+pub struct Usage {
+    /// The share of the cpu the workers could use that was used, over the whole run.
+    pub average_cpu: f64,
+    /// The highest share used over any one sample.
+    pub peak_cpu: f64,
+    pub peak_memory: ContentLength,
+}
+
+impl Usage {
+    /// Whether the client was too busy for the results to be trusted.
+    pub fn is_saturated(&self) -> bool {
+        self.average_cpu > SATURATED
+    }
+}
+
+/// Samples the cpu and memory of the process on a thread of its own.
+pub struct Monitor {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Option<Usage>>,
+}
+
+impl Monitor {
+    /// Starts sampling. Workers cannot use more cores than there are of them, so the
+    /// cpu is measured against whichever there are fewer of.
+    pub fn start(workers: usize) -> Monitor {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let capacity = ::std::cmp::max(1, ::std::cmp::min(workers, cores()));
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+            let first = cpu_time()?;
+            let mut last = (start, first);
+            let mut peak_cpu = 0f64;
+            while !stopped.load(Ordering::Relaxed) {
+                thread::sleep(INTERVAL);
+                let now = (Instant::now(), cpu_time()?);
+                let cpu = utilization(now.1 - last.1, now.0 - last.0, capacity);
+                peak_cpu = peak_cpu.max(cpu);
+                last = now;
+            }
+            Some(Usage {
+                average_cpu: utilization(last.1 - first, last.0 - start, capacity),
+                peak_cpu,
+                peak_memory: peak_memory()?,
+            })
+        });
+        Monitor { stop, handle }
+    }
+
+    /// Stops sampling, returning nothing when usage cannot be read on this platform.
+    pub fn stop(self) -> Option<Usage> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.join().expect("Monitor thread to finish")
+    }
+}
+
+/// The share of the capacity used when spending the cpu time over the wall time.
+fn utilization(cpu: Duration, wall: Duration, capacity: usize) -> f64 {
+    let wall = wall.as_secs_f64() * capacity as f64;
+    if wall == 0f64 {
+        0f64
+    } else {
+        cpu.as_secs_f64() / wall
+    }
+}
+
+/// The number of cores online.
+#[cfg(unix)]
+pub fn cores() -> usize {
+    let cores = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) };
+    if cores < 1 {
+        1
+    } else {
+        cores as usize
+    }
+}
+
+#[cfg(unix)]
+fn usage() -> Option<libc::rusage> {
+    let mut usage: libc::rusage = unsafe { ::std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } == 0 {
+        Some(usage)
+    } else {
+        None
+    }
+}
+
+/// The user and system time spent by every thread of the process so far.
+#[cfg(unix)]
+fn cpu_time() -> Option<Duration> {
+    let usage = usage()?;
+    let time = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1_000);
+    Some(time(usage.ru_utime) + time(usage.ru_stime))
+}
+
+#[cfg(unix)]
+fn peak_memory() -> Option<ContentLength> {
+    let max = usage()?.ru_maxrss as u64;
+    // Linux counts the resident set in kilobytes where macOS counts bytes.
+    if cfg!(target_os = "macos") {
+        Some(ContentLength::new(max))
+    } else {
+        Some(ContentLength::new(max * 1024))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn cores() -> usize {
+    1
+}
+
+#[cfg(not(unix))]
+fn cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(not(unix))]
+fn peak_memory() -> Option<ContentLength> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_measures_utilization_against_the_capacity() {
+        let second = Duration::from_secs(1);
+        assert_eq!(utilization(second, second, 1), 1f64);
+        assert_eq!(utilization(second, second, 4), 0.25);
+        assert_eq!(utilization(second, Duration::new(0, 0), 4), 0f64);
+    }
+
+    #[test]
+    fn it_samples_its_own_usage() {
+        let usage = Monitor::start(1).stop().unwrap();
+        assert!(usage.peak_memory.bytes() > 0);
+        assert!(usage.average_cpu >= 0f64);
+    }
+
+    #[test]
+    fn it_is_saturated_above_the_threshold() {
+        let usage = Usage {
+            average_cpu: 0.95,
+            peak_cpu: 1f64,
+            peak_memory: ContentLength::zero(),
+        };
+        assert!(usage.is_saturated());
+        assert!(!Usage {
+            average_cpu: 0.5,
+            ..usage
+        }
+        .is_saturated());
+    }
+}
@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A multipart/form-data body. Files are only opened when a request is made and are read
+/// as the body is sent, so large uploads never sit in memory.
+#[derive(Clone, Debug)]
+pub struct Form {
+    boundary: String,
+    fields: Vec<Field>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Field {
+    Text { name: String, value: String },
+    File { name: String, path: PathBuf },
+}
+
+impl Form {
+    /// Parses fields given as `name=value`, or `name=@path` to upload the file at path.
+    pub fn parse<'a, I>(specs: I) -> Result<Form, String>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let fields = specs
+            .into_iter()
+            .map(|spec| {
+                let split = spec
+                    .find('=')
+                    .ok_or_else(|| format!("Expected name=value but got {}", spec))?;
+                let name = spec[..split].to_string();
+                let value = &spec[split + 1..];
+                Ok(if let Some(path) = value.strip_prefix('@') {
+                    Field::File {
+                        name,
+                        path: PathBuf::from(path),
+                    }
+                } else {
+                    Field::Text {
+                        name,
+                        value: value.to_string(),
+                    }
+                })
+            })
+            .collect::<Result<Vec<Field>, String>>()?;
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        Ok(Form {
+            boundary: format!("------------------------rench{:08x}", nanos),
+            fields,
+        })
+    }
+
+    /// The content type header, which carries the boundary between the fields.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Opens every file in the form, returning a reader over the whole body and its length.
+    pub fn open(&self) -> io::Result<(Box<dyn Read + Send>, u64)> {
+        let mut len = 0;
+        let mut body: Box<dyn Read + Send> = Box::new(io::empty());
+        for field in &self.fields {
+            let (head, content): (String, Box<dyn Read + Send>) = match *field {
+                Field::Text {
+                    ref name,
+                    ref value,
+                } => {
+                    len += value.len() as u64;
+                    let head = format!(
+                        "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n",
+                        self.boundary,
+                        escape(name)
+                    );
+                    (head, Box::new(Cursor::new(value.clone().into_bytes())))
+                }
+                Field::File { ref name, ref path } => {
+                    let file = File::open(path)?;
+                    len += file.metadata()?.len();
+                    let head = format!(
+                        "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+                         Content-Type: application/octet-stream\r\n\r\n",
+                        self.boundary,
+                        escape(name),
+                        escape(&file_name(path))
+                    );
+                    (head, Box::new(file))
+                }
+            };
+            len += head.len() as u64 + 2;
+            body = Box::new(
+                body.chain(Cursor::new(head.into_bytes()))
+                    .chain(content)
+                    .chain(&b"\r\n"[..]),
+            );
+        }
+
+        let tail = format!("--{}--\r\n", self.boundary);
+        len += tail.len() as u64;
+        Ok((Box::new(body.chain(Cursor::new(tail.into_bytes()))), len))
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Quotes are percent encoded in names, the same as browsers do.
+fn escape(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::Write;
+
+    #[test]
+    fn it_parses_fields() {
+        let form = Form::parse(vec!["name=a=b", "upload=@/tmp/file.png"]).unwrap();
+        assert_eq!(
+            form.fields,
+            vec![
+                Field::Text {
+                    name: "name".to_string(),
+                    value: "a=b".to_string(),
+                },
+                Field::File {
+                    name: "upload".to_string(),
+                    path: PathBuf::from("/tmp/file.png"),
+                },
+            ]
+        );
+        assert!(Form::parse(vec!["name"]).is_err());
+    }
+
+    #[test]
+    fn it_streams_the_body_with_its_length() {
+        let path = env::temp_dir().join("rench_multipart_upload.txt");
+        File::create(&path)
+            .and_then(|mut f| f.write_all(b"file contents"))
+            .unwrap();
+        let spec = format!("upload=@{}", path.display());
+        let mut form = Form::parse(vec!["name=value", spec.as_str()]).unwrap();
+        form.boundary = "XX".to_string();
+
+        let (mut reader, len) = form.open().unwrap();
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+
+        assert_eq!(
+            body,
+            "--XX\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nvalue\r\n\
+             --XX\r\nContent-Disposition: form-data; name=\"upload\"; \
+             filename=\"rench_multipart_upload.txt\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\nfile contents\r\n--XX--\r\n"
+        );
+        assert_eq!(len, body.len() as u64);
+    }
+}
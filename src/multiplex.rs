@@ -0,0 +1,217 @@
+use bytes::Bytes;
+use h2::client::{Builder, SendRequest};
+use std::sync::{Arc, Mutex};
+
+/// How requests are multiplexed over http/2 connections, h2's defaults for anything not set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settings {
+    streams: Option<usize>,
+    window: Option<u32>,
+    connection_window: Option<u32>,
+    prior_knowledge: bool,
+    reject_push: bool,
+}
+
+impl Settings {
+    /// Shares connections between the threads, each carrying up to so many streams at once,
+    /// rather than giving every thread connections of its own.
+    pub fn with_streams(mut self, streams: usize) -> Self {
+        self.streams = Some(streams);
+        self
+    }
+
+    /// Sets the window the server is given to send each stream's body in.
+    pub fn with_window(mut self, window: u32) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Sets the window the server is given to send the bodies of all of a connection's
+    /// streams in.
+    pub fn with_connection_window(mut self, window: u32) -> Self {
+        self.connection_window = Some(window);
+        self
+    }
+
+    /// Speaks http/2 to the servers of cleartext urls from the start, rather than leaving
+    /// them to http/1.1 with no alpn to ask them by.
+    pub fn with_prior_knowledge(mut self) -> Self {
+        self.prior_knowledge = true;
+        self
+    }
+
+    /// Whether http/2 is spoken to the servers of cleartext urls.
+    pub fn prior_knowledge(&self) -> bool {
+        self.prior_knowledge
+    }
+
+    /// Tells servers not to push streams the requests did not ask for.
+    pub fn with_reject_push(mut self) -> Self {
+        self.reject_push = true;
+        self
+    }
+
+    /// The most streams a shared connection carries at once, when connections are shared.
+    pub fn streams(&self) -> Option<usize> {
+        self.streams
+    }
+
+    /// Sets up the handshake of a connection with these settings.
+    pub fn builder(&self) -> Builder {
+        let mut builder = Builder::new();
+        if let Some(window) = self.window {
+            builder.initial_window_size(window);
+        }
+        if let Some(window) = self.connection_window {
+            builder.initial_connection_window_size(window);
+        }
+        if self.reject_push {
+            builder.enable_push(false);
+        }
+        builder
+    }
+}
+
+/// The http/2 connections the threads share, per target.
+#[derive(Clone, Default)]
+pub struct Pool {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    targets: Vec<Vec<Shared>>,
+    opened: usize,
+}
+
+struct Shared {
+    id: usize,
+    client: SendRequest<Bytes>,
+    streams: usize,
+}
+
+/// A stream taken on one of the pool's connections, given back when dropped.
+pub struct Stream {
+    pool: Pool,
+    index: usize,
+    id: usize,
+    client: SendRequest<Bytes>,
+}
+
+impl Pool {
+    /// A stream on a connection to the target that carries fewer than the most streams,
+    /// opening a connection when none does, whatever the opening tells of it being handed
+    /// back with the stream. The pool is held while a connection opens so that the threads
+    /// waiting on it share it rather than opening their own.
+    pub fn stream<F, T, E>(
+        &self,
+        index: usize,
+        most: usize,
+        open: F,
+    ) -> Result<(Stream, Option<T>), E>
+    where
+        F: FnOnce() -> Result<(SendRequest<Bytes>, T), E>,
+    {
+        let mut inner = self.inner.lock().expect("Expected the connection pool lock");
+        if inner.targets.len() <= index {
+            inner.targets.resize_with(index + 1, Vec::new);
+        }
+        let free = inner.targets[index]
+            .iter_mut()
+            .find(|shared| shared.streams < most);
+        let (id, client, opened) = match free {
+            Some(shared) => {
+                shared.streams += 1;
+                (shared.id, shared.client.clone(), None)
+            }
+            None => {
+                let (client, opened) = open()?;
+                let id = inner.opened;
+                inner.opened += 1;
+                inner.targets[index].push(Shared {
+                    id,
+                    client: client.clone(),
+                    streams: 1,
+                });
+                (id, client, Some(opened))
+            }
+        };
+        let stream = Stream {
+            pool: self.clone(),
+            index,
+            id,
+            client,
+        };
+        Ok((stream, opened))
+    }
+
+    fn release(&self, index: usize, id: usize) {
+        let mut inner = self.inner.lock().expect("Expected the connection pool lock");
+        if let Some(shared) = inner.targets[index].iter_mut().find(|shared| shared.id == id) {
+            shared.streams -= 1;
+        }
+    }
+
+    fn close(&self, index: usize, id: usize) {
+        let mut inner = self.inner.lock().expect("Expected the connection pool lock");
+        inner.targets[index].retain(|shared| shared.id != id);
+    }
+}
+
+impl Stream {
+    /// The handle to send the stream's request with.
+    pub fn client(&self) -> SendRequest<Bytes> {
+        self.client.clone()
+    }
+
+    /// Opens no more streams on the stream's connection, which closes once the streams it
+    /// carries are done.
+    pub fn close(&self) {
+        self.pool.close(self.index, self.id);
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.pool.release(self.index, self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use tokio_core::net::TcpStream;
+    use tokio_core::reactor::Core;
+
+    #[test]
+    fn it_shares_connections_up_to_the_most_streams() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut core = Core::new().unwrap();
+        let mut open = || {
+            let tcp = core.run(TcpStream::connect(&addr, &core.handle())).unwrap();
+            let (client, _) = core.run(Settings::default().builder().handshake(tcp)).unwrap();
+            Ok::<_, ()>((client, ()))
+        };
+
+        let pool = Pool::default();
+        let (first, opened) = pool.stream(0, 2, &mut open).unwrap();
+        assert!(opened.is_some());
+        let (second, opened) = pool.stream(0, 2, &mut open).unwrap();
+        assert!(opened.is_none());
+        let (third, opened) = pool.stream(0, 2, &mut open).unwrap();
+        assert!(opened.is_some());
+        assert_ne!(first.id, third.id);
+        assert_eq!(first.id, second.id);
+
+        drop(second);
+        let (fourth, opened) = pool.stream(0, 2, &mut open).unwrap();
+        assert!(opened.is_none());
+        assert_eq!(fourth.id, first.id);
+
+        third.close();
+        let (_, opened) = pool.stream(0, 2, &mut open).unwrap();
+        assert!(opened.is_some());
+    }
+}
@@ -0,0 +1,84 @@
+use serde_json::{self, Value};
+use stats::Fact;
+use std::io::{self, BufRead, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Writes facts as they arrive, one json object per line, each stamped with the time it
+/// was written in milliseconds since the unix epoch.
+pub struct FactWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> FactWriter<W> {
+    pub fn new(out: W) -> FactWriter<W> {
+        FactWriter { out }
+    }
+
+    pub fn write(&mut self, fact: &Fact, at: SystemTime) -> io::Result<()> {
+        let mut json = fact.to_json();
+        if let Value::Object(ref mut json) = json {
+            let since = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+            json.insert(
+                "timestamp".to_string(),
+                Value::from(since.as_secs_f64() * 1_000f64),
+            );
+        }
+        writeln!(self.out, "{}", json)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Reads back facts written one per line, skipping blank lines.
+pub fn read_facts<R: BufRead>(input: R) -> Result<Vec<Fact>, String> {
+    let mut facts = Vec::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let json: Value = serde_json::from_str(&line)
+            .map_err(|e| format!("Invalid json on line {}: {}", number + 1, e))?;
+        facts.push(Fact::from_json(&json).map_err(|e| format!("Line {}: {}", number + 1, e))?);
+    }
+    Ok(facts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content_length::ContentLength;
+    use serde_json;
+    use std::time::Duration;
+
+    #[test]
+    fn it_writes_a_line_for_each_fact() {
+        let mut writer = FactWriter::new(Vec::new());
+        let at = UNIX_EPOCH + Duration::from_millis(1_500);
+        let fact = Fact::record(ContentLength::new(2), 200, Duration::from_millis(3));
+        writer.write(&fact, at).unwrap();
+        writer.write(&fact, at).unwrap();
+
+        let out = String::from_utf8(writer.out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let json: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(json["timestamp"], Value::from(1_500f64));
+        assert_eq!(json["status"], Value::from(200));
+    }
+
+    #[test]
+    fn it_reads_back_what_it_wrote() {
+        let mut writer = FactWriter::new(Vec::new());
+        let fact = Fact::record(ContentLength::new(2), 200, Duration::from_millis(3));
+        writer.write(&fact, UNIX_EPOCH).unwrap();
+        writer.out.extend_from_slice(b"\n");
+        writer.write(&fact, UNIX_EPOCH).unwrap();
+
+        let facts = read_facts(&writer.out[..]).unwrap();
+        assert_eq!(facts.len(), 2);
+        assert!(read_facts(&b"{}\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use report::{Prometheus, Reporter};
+use reqwest::header::Headers;
+use reqwest::{self, Client};
+use serde_json::Value;
+use stats::Summary;
+use std::time::Duration;
+use threshold::Outcome;
+
+/// How long a webhook has to take a notification, so that it cannot hold up the end of a run.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Posts a run's json summary to a webhook, such as one of a chat or of automation, noting
+/// whether it finished or was aborted.
+pub fn finished(url: &str, summary: Value) -> Result<(), String> {
+    post(url, &with_status(summary, "finished", None))
+}
+
+/// Posts why a run was aborted to a webhook.
+pub fn aborted(url: &str, reason: &str) -> Result<(), String> {
+    post(url, &with_status(json!({}), "aborted", Some(reason)))
+}
+
+/// Posts the headline numbers of a run and how it met its thresholds to a slack incoming
+/// webhook, as a message of its own.
+pub fn slack(url: &str, summary: &Summary, rps: f64, outcomes: &[Outcome]) -> Result<(), String> {
+    post(url, &slack_message(summary, rps, outcomes))
+}
+
+/// Pushes the metrics of a run, or of the run so far, to a prometheus pushgateway, replacing
+/// those pushed before under the job of the url.
+pub fn push_gateway(url: &str, summary: &Summary) -> Result<(), String> {
+    let mut metrics = Vec::new();
+    Prometheus
+        .report(summary, &mut metrics)
+        .map_err(|e| format!("Could not write the metrics: {}", e))?;
+    let failed = |e: reqwest::Error| format!("Could not push to the gateway: {}", e);
+    let mut headers = Headers::new();
+    headers.set_raw("Content-Type", "text/plain; version=0.0.4");
+    let client = Client::builder().timeout(TIMEOUT).build().map_err(failed)?;
+    let response = client
+        .post(url)
+        .headers(headers)
+        .body(metrics)
+        .send()
+        .map_err(failed)?;
+    answered(url, response.status())
+}
+
+fn slack_message(summary: &Summary, rps: f64, outcomes: &[Outcome]) -> Value {
+    let passed = outcomes.iter().all(|outcome| outcome.passed);
+    let p99 = summary
+        .latency_at(99f64)
+        .map(|p99| format!("{:.1} ms", p99.as_secs_f64() * 1_000f64))
+        .unwrap_or_else(|| "none".to_string());
+    let errors = format!("{:.2}%", summary.error_rate() * 100f64);
+    let headline = format!(
+        "rench: {} requests, {:.1} requests / second, p99 {}, {} errors",
+        summary.requests(),
+        rps,
+        p99,
+        errors
+    );
+    let mut blocks = vec![json!({
+        "type": "section",
+        "fields": [
+            {"type": "mrkdwn", "text": format!("*Requests*\n{}", summary.requests())},
+            {"type": "mrkdwn", "text": format!("*Throughput*\n{:.1} / second", rps)},
+            {"type": "mrkdwn", "text": format!("*p99*\n{}", p99)},
+            {"type": "mrkdwn", "text": format!("*Errors*\n{}", errors)},
+        ],
+    })];
+    if !outcomes.is_empty() {
+        let lines: Vec<String> = outcomes
+            .iter()
+            .map(|outcome| {
+                let mark = if outcome.passed { ":white_check_mark:" } else { ":x:" };
+                format!("{} {}", mark, outcome.describe())
+            })
+            .collect();
+        blocks.push(json!({
+            "type": "section",
+            "text": {"type": "mrkdwn", "text": lines.join("\n")},
+        }));
+    }
+    let title = if outcomes.is_empty() {
+        "*rench results*"
+    } else if passed {
+        "*rench results* :white_check_mark: thresholds met"
+    } else {
+        "*rench results* :x: thresholds not met"
+    };
+    blocks.insert(0, json!({"type": "section", "text": {"type": "mrkdwn", "text": title}}));
+    json!({"text": headline, "blocks": blocks})
+}
+
+fn with_status(mut json: Value, status: &str, reason: Option<&str>) -> Value {
+    if let Some(object) = json.as_object_mut() {
+        object.insert("status".to_string(), Value::from(status));
+        if let Some(reason) = reason {
+            object.insert("reason".to_string(), Value::from(reason));
+        }
+    }
+    json
+}
+
+fn post(url: &str, json: &Value) -> Result<(), String> {
+    let failed = |e: reqwest::Error| format!("Could not notify the webhook: {}", e);
+    let client = Client::builder().timeout(TIMEOUT).build().map_err(failed)?;
+    let response = client.post(url).json(json).send().map_err(failed)?;
+    answered(url, response.status())
+}
+
+fn answered(url: &str, status: reqwest::StatusCode) -> Result<(), String> {
+    if status.is_success() {
+        Ok(())
+    } else {
+        Err(format!("{} answered with {}", url, status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_marks_how_the_run_ended() {
+        let json = with_status(json!({"requests": 3}), "finished", None);
+        assert_eq!(json, json!({"requests": 3, "status": "finished"}));
+        let json = with_status(json!({}), "aborted", Some("too slow"));
+        assert_eq!(json, json!({"status": "aborted", "reason": "too slow"}));
+    }
+
+    #[test]
+    fn it_formats_slack_messages() {
+        use content_length::ContentLength;
+        use stats::Fact;
+        use threshold;
+
+        let facts = vec![
+            Fact::record(ContentLength::zero(), 200, Duration::from_millis(10)),
+            Fact::record(ContentLength::zero(), 500, Duration::from_millis(20)),
+        ];
+        let summary = Summary::from_facts(&facts);
+        let thresholds = threshold::parse_config("[thresholds]\nerror_rate < 1%").unwrap();
+        let outcomes: Vec<Outcome> = thresholds.iter().map(|t| t.check(&summary, 5f64)).collect();
+        let message = slack_message(&summary, 5f64, &outcomes);
+        assert_eq!(
+            message["text"],
+            "rench: 2 requests, 5.0 requests / second, p99 20.0 ms, 50.00% errors"
+        );
+        assert_eq!(message["blocks"][0]["text"]["text"], "*rench results* :x: thresholds not met");
+        assert_eq!(message["blocks"][1]["fields"][2]["text"], "*p99*\n20.0 ms");
+        assert_eq!(
+            message["blocks"][2]["text"]["text"],
+            ":x: error_rate < 1% (measured 50.00%)"
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use base64;
+use openssl::hash::{hash2, MessageDigest};
+use rand::{self, Rng};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+/// Unicode, oem, request target, ntlm, always sign and extended session security.
+const NEGOTIATE_FLAGS: u32 = 0x0008_8207;
+/// The flags of a challenge that would need a session key, which http has no use for.
+const SESSION_FLAGS: u32 = 0x4000_0000 | 0x20 | 0x10;
+/// The attribute of a challenge's target info that carries the server's time.
+const AV_TIMESTAMP: u16 = 7;
+/// The seconds between 1601, when windows starts counting, and the unix epoch.
+const WINDOWS_EPOCH: u64 = 11_644_473_600;
+
+/// Credentials for the ntlm leg of windows integrated authentication, which servers ask for
+/// with `WWW-Authenticate: Negotiate` or `NTLM`. Ntlm authenticates the connection rather
+/// than the request, so it takes a round trip of its own on every new connection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Credentials {
+    domain: String,
+    user: String,
+    password: String,
+}
+
+impl Credentials {
+    /// Parses credentials given as `DOMAIN\user:password`, the domain being optional.
+    pub fn parse(credentials: &str) -> Result<Credentials, String> {
+        let (account, password) = match credentials.split_once(':') {
+            Some((account, password)) if !account.is_empty() => (account, password),
+            _ => {
+                return Err(format!(
+                    "Expected DOMAIN\\user:password but got {}",
+                    credentials
+                ))
+            }
+        };
+        let (domain, user) = account.split_once('\\').unwrap_or(("", account));
+        Ok(Credentials {
+            domain: domain.to_string(),
+            user: user.to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    /// A session that keeps track of which targets' connections are authenticated.
+    pub fn session(&self, targets: usize) -> Session {
+        Session {
+            credentials: self.clone(),
+            authenticated: vec![false; targets],
+        }
+    }
+
+    /// The `Authorization` header that opens the handshake.
+    pub fn negotiate(&self) -> String {
+        let mut message = SIGNATURE.to_vec();
+        message.extend_from_slice(&1u32.to_le_bytes());
+        message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+        // The domain and workstation are left empty.
+        message.extend_from_slice(&[0; 16]);
+        format!("Negotiate {}", base64::encode(&message))
+    }
+
+    /// The `Authorization` header that answers the `WWW-Authenticate` challenge the server
+    /// sent back, under the same scheme.
+    pub fn authenticate(&self, challenge: &str) -> Result<String, String> {
+        let (scheme, token) = challenge
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| format!("Expected an ntlm challenge but got {}", challenge))?;
+        let challenge = base64::decode(token.trim())
+            .map_err(|e| format!("Invalid ntlm challenge: {}", e))
+            .and_then(|bytes| Challenge::parse(&bytes))?;
+        let client_challenge: [u8; 8] = rand::thread_rng().gen();
+        let timestamp = challenge.timestamp().unwrap_or_else(|| {
+            let since = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            (since.as_secs() + WINDOWS_EPOCH) * 10_000_000 + u64::from(since.subsec_nanos()) / 100
+        });
+        let message = self.answer(&challenge, client_challenge, timestamp);
+        Ok(format!("{} {}", scheme, base64::encode(&message)))
+    }
+
+    /// The authenticate message, with ntlmv2 responses to the challenge.
+    fn answer(&self, challenge: &Challenge, client_challenge: [u8; 8], timestamp: u64) -> Vec<u8> {
+        let key = self.key();
+        let mut blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+        blob.extend_from_slice(&timestamp.to_le_bytes());
+        blob.extend_from_slice(&client_challenge);
+        blob.extend_from_slice(&[0; 4]);
+        blob.extend_from_slice(&challenge.target_info);
+        blob.extend_from_slice(&[0; 4]);
+
+        let mut nt = hmac_md5(&key, &[&challenge.server_challenge[..], &blob].concat()).to_vec();
+        nt.extend_from_slice(&blob);
+        let mut lm = hmac_md5(
+            &key,
+            &[challenge.server_challenge, client_challenge].concat(),
+        )
+        .to_vec();
+        lm.extend_from_slice(&client_challenge);
+
+        let fields = [
+            lm,
+            nt,
+            utf16(&self.domain),
+            utf16(&self.user),
+            Vec::new(),
+            Vec::new(),
+        ];
+        let mut message = SIGNATURE.to_vec();
+        message.extend_from_slice(&3u32.to_le_bytes());
+        let mut payload = Vec::new();
+        let header = 12 + 8 * fields.len() + 4;
+        for field in &fields {
+            message.extend_from_slice(&(field.len() as u16).to_le_bytes());
+            message.extend_from_slice(&(field.len() as u16).to_le_bytes());
+            message.extend_from_slice(&((header + payload.len()) as u32).to_le_bytes());
+            payload.extend_from_slice(field);
+        }
+        message.extend_from_slice(&(challenge.flags & !SESSION_FLAGS).to_le_bytes());
+        message.extend_from_slice(&payload);
+        message
+    }
+
+    /// The ntlmv2 key, a hash of the password keyed on the user and domain.
+    fn key(&self) -> [u8; 16] {
+        let account = utf16(&format!("{}{}", self.user.to_uppercase(), self.domain));
+        hmac_md5(&md4(&utf16(&self.password)), &account)
+    }
+}
+
+/// Which of the targets' connections a thread has authenticated.
+pub struct Session {
+    credentials: Credentials,
+    authenticated: Vec<bool>,
+}
+
+impl Session {
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    /// Whether the connection to the target has yet to be authenticated.
+    pub fn needs_handshake(&self, index: usize) -> bool {
+        !self.authenticated[index]
+    }
+
+    /// Counts the connection to the target as authenticated, or not once it answers a 401.
+    pub fn set_authenticated(&mut self, index: usize, authenticated: bool) {
+        self.authenticated[index] = authenticated;
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Challenge {
+    flags: u32,
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+}
+
+impl Challenge {
+    fn parse(bytes: &[u8]) -> Result<Challenge, String> {
+        if bytes.len() < 32 || &bytes[..8] != SIGNATURE || u32_at(bytes, 8) != 2 {
+            return Err("Expected an ntlm challenge message".to_string());
+        }
+        let mut server_challenge = [0; 8];
+        server_challenge.copy_from_slice(&bytes[24..32]);
+        let target_info = if bytes.len() >= 48 {
+            let len = u16_at(bytes, 40) as usize;
+            let offset = u32_at(bytes, 44) as usize;
+            bytes
+                .get(offset..offset + len)
+                .ok_or("The ntlm challenge's target info is out of bounds")?
+                .to_vec()
+        } else {
+            Vec::new()
+        };
+        Ok(Challenge {
+            flags: u32_at(bytes, 20),
+            server_challenge,
+            target_info,
+        })
+    }
+
+    /// The server's time from the target info, which the response should be stamped with.
+    fn timestamp(&self) -> Option<u64> {
+        let mut info = &self.target_info[..];
+        while info.len() >= 4 {
+            let (id, len) = (u16_at(info, 0), u16_at(info, 2) as usize);
+            let value = info.get(4..4 + len)?;
+            if id == AV_TIMESTAMP && len == 8 {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(value);
+                return Some(u64::from_le_bytes(bytes));
+            }
+            info = &info[4 + len..];
+        }
+        None
+    }
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn utf16(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn hmac_md5(key: &[u8], data: &[u8]) -> [u8; 16] {
+    let md5 = |data: &[u8]| hash2(MessageDigest::md5(), data).expect("Expected md5 to hash");
+    let mut padded = [0u8; 64];
+    padded[..key.len()].copy_from_slice(key);
+    let inner: Vec<u8> = padded
+        .iter()
+        .map(|byte| byte ^ 0x36)
+        .chain(data.iter().cloned())
+        .collect();
+    let mut outer: Vec<u8> = padded.iter().map(|byte| byte ^ 0x5c).collect();
+    outer.extend_from_slice(&md5(&inner));
+    let mut mac = [0; 16];
+    mac.copy_from_slice(&md5(&outer));
+    mac
+}
+
+/// Md4, which ntlm hashes passwords with but openssl no longer provides by default.
+fn md4(input: &[u8]) -> [u8; 16] {
+    let mut message = input.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(input.len() as u64 * 8).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for block in message.chunks(64) {
+        let x: Vec<u32> = (0..16).map(|i| u32_at(block, i * 4)).collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+        let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+        let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+        for i in 0..4 {
+            a = a
+                .wrapping_add(f(b, c, d))
+                .wrapping_add(x[4 * i])
+                .rotate_left(3);
+            d = d
+                .wrapping_add(f(a, b, c))
+                .wrapping_add(x[4 * i + 1])
+                .rotate_left(7);
+            c = c
+                .wrapping_add(f(d, a, b))
+                .wrapping_add(x[4 * i + 2])
+                .rotate_left(11);
+            b = b
+                .wrapping_add(f(c, d, a))
+                .wrapping_add(x[4 * i + 3])
+                .rotate_left(19);
+        }
+        for i in 0..4 {
+            let k = 0x5a82_7999;
+            a = a
+                .wrapping_add(g(b, c, d))
+                .wrapping_add(x[i])
+                .wrapping_add(k)
+                .rotate_left(3);
+            d = d
+                .wrapping_add(g(a, b, c))
+                .wrapping_add(x[i + 4])
+                .wrapping_add(k)
+                .rotate_left(5);
+            c = c
+                .wrapping_add(g(d, a, b))
+                .wrapping_add(x[i + 8])
+                .wrapping_add(k)
+                .rotate_left(9);
+            b = b
+                .wrapping_add(g(c, d, a))
+                .wrapping_add(x[i + 12])
+                .wrapping_add(k)
+                .rotate_left(13);
+        }
+        for &i in &[0, 2, 1, 3] {
+            let k = 0x6ed9_eba1;
+            a = a
+                .wrapping_add(h(b, c, d))
+                .wrapping_add(x[i])
+                .wrapping_add(k)
+                .rotate_left(3);
+            d = d
+                .wrapping_add(h(a, b, c))
+                .wrapping_add(x[i + 8])
+                .wrapping_add(k)
+                .rotate_left(9);
+            c = c
+                .wrapping_add(h(d, a, b))
+                .wrapping_add(x[i + 4])
+                .wrapping_add(k)
+                .rotate_left(11);
+            b = b
+                .wrapping_add(h(c, d, a))
+                .wrapping_add(x[i + 12])
+                .wrapping_add(k)
+                .rotate_left(15);
+        }
+        for (word, add) in state.iter_mut().zip(&[a, b, c, d]) {
+            *word = word.wrapping_add(*add);
+        }
+    }
+    let mut digest = [0; 16];
+    for (i, word) in state.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn it_hashes_with_md4() {
+        assert_eq!(hex(&md4(b"")), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(hex(&md4(b"abc")), "a448017aaf21d8525fc10ae87aa6729d");
+        assert_eq!(
+            hex(&md4(&utf16("Password"))),
+            "a4f49c406510bdcab6824ee7c30fd852"
+        );
+    }
+
+    /// The ntlmv2 example of section 4.2.4 of MS-NLMP.
+    #[test]
+    fn it_answers_the_ms_nlmp_example() {
+        let credentials = Credentials::parse("Domain\\User:Password").unwrap();
+        assert_eq!(hex(&credentials.key()), "0c868a403bfd7a93a3001ef22ef02e3f");
+
+        let mut target_info = vec![2, 0, 12, 0];
+        target_info.extend_from_slice(&utf16("Domain"));
+        target_info.extend_from_slice(&[1, 0, 12, 0]);
+        target_info.extend_from_slice(&utf16("Server"));
+        target_info.extend_from_slice(&[0, 0, 0, 0]);
+        let challenge = Challenge {
+            flags: 0xe28a_8233,
+            server_challenge: [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef],
+            target_info,
+        };
+        let message = credentials.answer(&challenge, [0xaa; 8], 0);
+        let nt_offset = u32_at(&message, 24) as usize;
+        assert_eq!(
+            hex(&message[nt_offset..nt_offset + 16]),
+            "68cd0ab851e51c96aabc927bebef6a1c"
+        );
+        let lm_offset = u32_at(&message, 16) as usize;
+        assert_eq!(
+            hex(&message[lm_offset..lm_offset + 16]),
+            "86c35097ac9cec102554764a57cccc19"
+        );
+        assert_eq!(u32_at(&message, 60), 0xa28a_8203);
+    }
+
+    #[test]
+    fn it_reads_challenges() {
+        let mut message = SIGNATURE.to_vec();
+        message.extend_from_slice(&2u32.to_le_bytes());
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&0x0088_0205u32.to_le_bytes());
+        message.extend_from_slice(&[7; 8]);
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&[12, 0, 12, 0, 48, 0, 0, 0]);
+        message.extend_from_slice(&[7, 0, 8, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        let challenge = Challenge::parse(&message).unwrap();
+        assert_eq!(challenge.server_challenge, [7; 8]);
+        assert_eq!(challenge.timestamp(), Some(1));
+
+        let header = format!("NTLM {}", base64::encode(&message));
+        assert!(Credentials::parse("user:pass")
+            .unwrap()
+            .authenticate(&header)
+            .unwrap()
+            .starts_with("NTLM TlRMTVNTUAADAAAA"));
+        assert!(Challenge::parse(&message[..20]).is_err());
+        assert!(Credentials::parse("nobody").is_err());
+    }
+}
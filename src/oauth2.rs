@@ -0,0 +1,153 @@
+use reqwest::{self, Client};
+use serde_json::{self, Value};
+use std::cmp;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long before a token expires that it is refreshed, so that no request is sent with a
+/// token that runs out on its way to the server. Short lived tokens are refreshed halfway.
+const REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+/// An oauth2 client credentials grant, whose bearer token is shared between the threads of
+/// a run and refreshed by whichever of them first finds it about to expire.
+#[derive(Clone, Debug)]
+pub struct OAuth2 {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    token: Arc<Mutex<Option<Token>>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    access_token: String,
+    refresh_at: Option<Instant>,
+}
+
+impl Token {
+    fn is_fresh(&self, now: Instant) -> bool {
+        self.refresh_at.is_none_or(|refresh_at| now < refresh_at)
+    }
+}
+
+impl OAuth2 {
+    /// Parses the grant given as `token_url,client_id,client_secret` with an optional
+    /// `,scope` after them.
+    pub fn parse(spec: &str) -> Result<OAuth2, String> {
+        let parts: Vec<&str> = spec.splitn(4, ',').map(str::trim).collect();
+        match parts[..] {
+            [token_url, client_id, client_secret] | [token_url, client_id, client_secret, _]
+                if !token_url.is_empty() && !client_id.is_empty() =>
+            {
+                Ok(OAuth2 {
+                    token_url: token_url.to_string(),
+                    client_id: client_id.to_string(),
+                    client_secret: client_secret.to_string(),
+                    scope: parts.get(3).map(|scope| scope.to_string()),
+                    token: Arc::new(Mutex::new(None)),
+                })
+            }
+            _ => Err(format!(
+                "Expected token_url,client_id,client_secret,scope but got {}",
+                spec
+            )),
+        }
+    }
+
+    /// The bearer token to authorize a request with, fetched afresh when there is none yet
+    /// or the last one is about to expire.
+    pub fn bearer(&self) -> Result<String, String> {
+        let mut token = self.token.lock().expect("Expected the token lock");
+        match *token {
+            Some(ref token) if token.is_fresh(Instant::now()) => {}
+            _ => *token = Some(self.fetch()?),
+        }
+        Ok(token
+            .as_ref()
+            .map(|token| token.access_token.clone())
+            .expect("Expected a token once fetched"))
+    }
+
+    /// Drops the token, as after the server rejects it, so the next request fetches another.
+    pub fn expire(&self) {
+        *self.token.lock().expect("Expected the token lock") = None;
+    }
+
+    fn fetch(&self) -> Result<Token, String> {
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+        ];
+        if let Some(ref scope) = self.scope {
+            form.push(("scope", scope.as_str()));
+        }
+        let fetched = Instant::now();
+        let mut response = Client::new()
+            .post(self.token_url.as_str())
+            .form(&form)
+            .send()
+            .map_err(|e| format!("Could not fetch an oauth2 token: {}", e))?;
+        let body = response
+            .text()
+            .map_err(|e| format!("Could not read the oauth2 token: {}", e))?;
+        if response.status() != reqwest::StatusCode::Ok {
+            return Err(format!(
+                "The oauth2 token endpoint answered {}: {}",
+                response.status(),
+                body
+            ));
+        }
+        parse_token(&body, fetched)
+    }
+}
+
+/// Reads a token response, whose `expires_in` counts from when the token was asked for.
+fn parse_token(body: &str, fetched: Instant) -> Result<Token, String> {
+    let json: Value =
+        serde_json::from_str(body).map_err(|e| format!("Invalid oauth2 token response: {}", e))?;
+    let access_token = json["access_token"]
+        .as_str()
+        .ok_or("The oauth2 token response has no access_token")?;
+    Ok(Token {
+        access_token: access_token.to_string(),
+        refresh_at: json["expires_in"].as_u64().map(|seconds| {
+            let lifetime = Duration::from_secs(seconds);
+            fetched + lifetime - cmp::min(REFRESH_MARGIN, lifetime / 2)
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_grants() {
+        let grant = OAuth2::parse("https://auth/token,id,secret,read write").unwrap();
+        assert_eq!(grant.token_url, "https://auth/token");
+        assert_eq!(grant.scope, Some("read write".to_string()));
+        assert_eq!(
+            OAuth2::parse("https://auth/token,id,secret").unwrap().scope,
+            None
+        );
+        assert!(OAuth2::parse("https://auth/token,id").is_err());
+    }
+
+    #[test]
+    fn it_refreshes_tokens_before_they_expire() {
+        let now = Instant::now();
+        let token = parse_token(r#"{"access_token": "abc", "expires_in": 60}"#, now).unwrap();
+        assert_eq!(token.access_token, "abc");
+        assert!(token.is_fresh(now));
+        assert!(!token.is_fresh(now + Duration::from_secs(31)));
+
+        let lasting = parse_token(r#"{"access_token": "abc"}"#, now).unwrap();
+        assert!(lasting.is_fresh(now + Duration::from_secs(3600)));
+        let short = parse_token(r#"{"access_token": "abc", "expires_in": 10}"#, now).unwrap();
+        assert!(short.is_fresh(now + Duration::from_secs(4)));
+        assert!(!short.is_fresh(now + Duration::from_secs(5)));
+        assert!(parse_token(r#"{"error": "invalid_client"}"#, now).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+/// A response body that went over the most the requests are to read of one.
+#[derive(Debug)]
+struct TooLarge(u64);
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The response was larger than {} bytes", self.0)
+    }
+}
+
+impl Error for TooLarge {}
+
+/// The error that aborts reading a body once it is larger than the most allowed.
+pub fn too_large(max: u64) -> io::Error {
+    io::Error::other(TooLarge(max))
+}
+
+/// Whether an error came from a body that was larger than the most allowed.
+pub fn is_too_large(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|inner| inner.is::<TooLarge>())
+}
+
+/// Fails with `too_large` once the bytes of a body read so far are more than the most, for
+/// bodies that arrive in chunks.
+pub fn check(read: usize, max: Option<u64>) -> io::Result<()> {
+    match max {
+        Some(max) if read as u64 > max => Err(too_large(max)),
+        _ => Ok(()),
+    }
+}
+
+/// Reads a body to its end, stopping as soon as it is larger than the most rather than
+/// reading the rest.
+pub fn read_at_most<R: Read>(reader: R, max: u64) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    reader.take(max + 1).read_to_end(&mut body)?;
+    check(body.len(), Some(max))?;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_stops_reading_bodies_that_are_too_large() {
+        assert_eq!(read_at_most(&b"four"[..], 4).unwrap(), b"four");
+        let e = read_at_most(&b"fives"[..], 4).unwrap_err();
+        assert!(is_too_large(&e));
+        assert!(!is_too_large(&io::Error::other("other")));
+        assert!(check(10, None).is_ok());
+    }
+}
@@ -0,0 +1,129 @@
+#[cfg(unix)]
+use libc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// How often waiting threads look to see whether the run has been resumed.
+const POLL: Duration = Duration::from_millis(10);
+
+/// The pauses of a run, kept in atomics alone so that signal handlers can take and end them.
+struct Pauses {
+    paused: AtomicBool,
+    since: AtomicU64,
+    total: AtomicU64,
+}
+
+static PAUSES: Pauses = Pauses::new();
+
+impl Pauses {
+    const fn new() -> Pauses {
+        Pauses {
+            paused: AtomicBool::new(false),
+            since: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+        }
+    }
+
+    fn pause(&self, now: u64) {
+        if !self.paused.load(Ordering::SeqCst) {
+            self.since.store(now, Ordering::SeqCst);
+            self.paused.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn resume(&self, now: u64) {
+        if self.paused.swap(false, Ordering::SeqCst) {
+            let since = self.since.load(Ordering::SeqCst);
+            self.total.fetch_add(now.saturating_sub(since), Ordering::SeqCst);
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// How long the run has been paused in all, counting a pause still going on.
+    fn paused_for(&self, now: u64) -> Duration {
+        let mut total = self.total.load(Ordering::SeqCst);
+        if self.is_paused() {
+            total += now.saturating_sub(self.since.load(Ordering::SeqCst));
+        }
+        Duration::from_nanos(total)
+    }
+}
+
+/// Pauses the run on `SIGUSR1`, the threads making no new requests while the connections
+/// they hold stay open, and resumes it on `SIGUSR2` or a second `SIGUSR1`. Child processes
+/// have handlers of their own, so the signal goes to the whole process group to pause them.
+#[cfg(unix)]
+pub fn listen() {
+    extern "C" fn on_signal(signal: libc::c_int) {
+        let now = now();
+        if signal == libc::SIGUSR1 && !PAUSES.is_paused() {
+            PAUSES.pause(now);
+        } else {
+            PAUSES.resume(now);
+        }
+    }
+
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGUSR1, handler);
+        libc::signal(libc::SIGUSR2, handler);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn listen() {}
+
+/// Holds the thread up for as long as the run is paused.
+pub fn wait() {
+    while PAUSES.is_paused() {
+        thread::sleep(POLL);
+    }
+}
+
+/// How long the run has been paused, to be left out of its throughput.
+pub fn paused_for() -> Duration {
+    PAUSES.paused_for(now())
+}
+
+/// The nanoseconds on a monotonic clock, read in a way that is safe within signal handlers.
+#[cfg(unix)]
+fn now() -> u64 {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut time) };
+    time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+#[cfg(not(unix))]
+fn now() -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_adds_up_the_pauses() {
+        let pauses = Pauses::new();
+        pauses.resume(5);
+        assert_eq!(pauses.paused_for(10), Duration::new(0, 0));
+
+        pauses.pause(10);
+        pauses.pause(15);
+        assert!(pauses.is_paused());
+        assert_eq!(pauses.paused_for(30), Duration::from_nanos(20));
+        pauses.resume(40);
+        assert!(!pauses.is_paused());
+
+        pauses.pause(100);
+        pauses.resume(101);
+        assert_eq!(pauses.paused_for(500), Duration::from_nanos(31));
+    }
+}
@@ -0,0 +1,214 @@
+use hyper::header::Headers;
+use std::fs::File;
+use std::sync::Arc;
+use wasmi::{self, Linker, Memory, Module, Store, TypedFunc};
+
+/// A WebAssembly module with hooks that run around each request, such as to sign requests
+/// or to check responses. The module exports its `memory` and an `alloc(len) -> ptr` for
+/// rench to write into, along with either or both of the hooks:
+///
+/// * `before_request(ptr, len) -> i64` is given the request as `METHOD URL\n` followed by
+///   its body, and returns the headers to add as `Name: value` lines, packed as the
+///   pointer in the high 32 bits and the length in the low ones, or 0 for none.
+/// * `after_response(ptr, len) -> i32` is given the response as its status on a line,
+///   its headers as `Name: value` lines, a blank line and its body. Anything but 0 counts
+///   the response as failed.
+///
+/// The module is compiled once, then each thread runs its own instance of it.
+#[derive(Clone)]
+pub struct Plugin {
+    engine: wasmi::Engine,
+    module: Arc<Module>,
+}
+
+impl Plugin {
+    /// Compiles the module in the file.
+    pub fn load(path: &str) -> Result<Plugin, String> {
+        let file = File::open(path).map_err(|e| format!("Could not open {}: {}", path, e))?;
+        let engine = wasmi::Engine::default();
+        let module =
+            Module::new(&engine, file).map_err(|e| format!("Invalid plugin {}: {}", path, e))?;
+        Ok(Plugin {
+            engine,
+            module: Arc::new(module),
+        })
+    }
+
+    /// Sets up an instance of the module with memory of its own.
+    pub fn instantiate(&self) -> Result<Instance, String> {
+        let mut store = Store::new(&self.engine, ());
+        let instance = Linker::<()>::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| format!("Could not start the plugin: {}", e))?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("The plugin does not export its memory")?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|e| format!("The plugin needs alloc(len) -> ptr: {}", e))?;
+        let before_request = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "before_request")
+            .ok();
+        let after_response = instance
+            .get_typed_func::<(i32, i32), i32>(&store, "after_response")
+            .ok();
+        if before_request.is_none() && after_response.is_none() {
+            return Err("The plugin exports neither before_request nor after_response".to_string());
+        }
+        Ok(Instance {
+            store,
+            memory,
+            alloc,
+            before_request,
+            after_response,
+        })
+    }
+}
+
+/// An instance of a plugin, which can only be run by one thread at a time.
+pub struct Instance {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    before_request: Option<TypedFunc<(i32, i32), i64>>,
+    after_response: Option<TypedFunc<(i32, i32), i32>>,
+}
+
+impl Instance {
+    /// The headers the plugin adds to the request, none if it has no hook for requests.
+    pub fn before_request(
+        &mut self,
+        method: &str,
+        url: &str,
+        body: &[u8],
+    ) -> Result<Vec<(String, String)>, String> {
+        let hook = match self.before_request {
+            Some(hook) => hook,
+            None => return Ok(Vec::new()),
+        };
+        let mut input = format!("{} {}\n", method, url).into_bytes();
+        input.extend_from_slice(body);
+        let (ptr, len) = self.write(&input)?;
+        let packed = hook
+            .call(&mut self.store, (ptr, len))
+            .map_err(|e| format!("The plugin's before_request failed: {}", e))?;
+        if packed == 0 {
+            return Ok(Vec::new());
+        }
+        let output = self.read((packed >> 32) as u32, packed as u32)?;
+        Ok(String::from_utf8_lossy(&output)
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .filter(|(name, _)| !name.is_empty())
+            .collect())
+    }
+
+    /// Whether the plugin accepts the response, as it does when it has no hook for them.
+    pub fn after_response(
+        &mut self,
+        status: u16,
+        headers: &Headers,
+        body: &[u8],
+    ) -> Result<bool, String> {
+        let hook = match self.after_response {
+            Some(hook) => hook,
+            None => return Ok(true),
+        };
+        let mut input = format!("{}\n", status);
+        for header in headers.iter() {
+            input.push_str(&format!("{}: {}\n", header.name(), header.value_string()));
+        }
+        input.push('\n');
+        let mut input = input.into_bytes();
+        input.extend_from_slice(body);
+        let (ptr, len) = self.write(&input)?;
+        let verdict = hook
+            .call(&mut self.store, (ptr, len))
+            .map_err(|e| format!("The plugin's after_response failed: {}", e))?;
+        Ok(verdict == 0)
+    }
+
+    /// Copies the bytes into memory the plugin allocated for them.
+    fn write(&mut self, bytes: &[u8]) -> Result<(i32, i32), String> {
+        let len = bytes.len() as i32;
+        let ptr = self
+            .alloc
+            .call(&mut self.store, len)
+            .map_err(|e| format!("The plugin's alloc failed: {}", e))?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, bytes)
+            .map_err(|e| format!("The plugin allocated memory out of bounds: {}", e))?;
+        Ok((ptr, len))
+    }
+
+    fn read(&self, ptr: u32, len: u32) -> Result<Vec<u8>, String> {
+        let mut bytes = vec![0; len as usize];
+        self.memory
+            .read(&self.store, ptr as usize, &mut bytes)
+            .map_err(|e| format!("The plugin returned memory out of bounds: {}", e))?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use wat;
+
+    /// Adds `X-Signed: yes` to every request and rejects responses with a 5xx status.
+    const PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 0) "X-Signed: yes\n")
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "before_request") (param $ptr i32) (param $len i32) (result i64)
+            (i64.const 14))
+          (func (export "after_response") (param $ptr i32) (param $len i32) (result i32)
+            (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 53))))
+    "#;
+
+    fn load(name: &str, text: &str) -> Result<Plugin, String> {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, wat::parse_str(text).unwrap()).unwrap();
+        Plugin::load(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn it_runs_the_hooks() {
+        let mut plugin = load("rench_plugin.wasm", PLUGIN)
+            .unwrap()
+            .instantiate()
+            .unwrap();
+        assert_eq!(
+            plugin
+                .before_request("GET", "http://localhost/", b"")
+                .unwrap(),
+            vec![("X-Signed".to_string(), "yes".to_string())]
+        );
+        let headers = Headers::new();
+        assert_eq!(plugin.after_response(200, &headers, b"ok"), Ok(true));
+        assert_eq!(plugin.after_response(503, &headers, b""), Ok(false));
+    }
+
+    #[test]
+    fn it_needs_a_hook() {
+        let plugin = load(
+            "rench_plugin_without_hooks.wasm",
+            r#"(module
+                 (memory (export "memory") 1)
+                 (func (export "alloc") (param i32) (result i32) (i32.const 0)))"#,
+        )
+        .unwrap();
+        assert!(plugin.instantiate().is_err());
+        assert!(Plugin::load("/does/not/exist.wasm").is_err());
+    }
+}
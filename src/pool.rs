@@ -0,0 +1,46 @@
+/// Limits the idle connections a thread keeps for reuse, which hyper's pool has no limit
+/// of its own for. As a thread makes one request at a time, the pool holds at most one idle
+/// connection for each origin, so the connections of the first origins are kept and those
+/// of any others are closed once their request is answered.
+pub struct IdleLimit {
+    max: usize,
+    kept: Vec<String>,
+}
+
+impl IdleLimit {
+    pub fn new(max: usize) -> IdleLimit {
+        IdleLimit {
+            max,
+            kept: Vec::new(),
+        }
+    }
+
+    /// Whether the connection of a request of the origin, such as `http://host:8080`, can
+    /// be kept idle for reuse.
+    pub fn keeps(&mut self, origin: &str) -> bool {
+        if self.kept.iter().any(|kept| kept == origin) {
+            return true;
+        }
+        if self.kept.len() < self.max {
+            self.kept.push(origin.to_string());
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_the_connections_of_the_first_origins() {
+        let mut limit = IdleLimit::new(2);
+        let kept: Vec<bool> = ["http://a", "http://b", "http://c", "http://a", "http://c"]
+            .iter()
+            .map(|origin| limit.keeps(origin))
+            .collect();
+        assert_eq!(kept, vec![true, true, false, true, false]);
+        assert!(!IdleLimit::new(0).keeps("http://a"));
+    }
+}
@@ -0,0 +1,189 @@
+use message::Message;
+use plan::Plan;
+use serde_json::{self, Value};
+use stats::Fact;
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+/// The environment variable a child is given the pages of a sitemap or crawl in, one to a
+/// line, so that only the parent finds them.
+const PAGES: &str = "RENCH_PAGES";
+
+/// The part of the run a child process makes, given to it as `index/count`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Share {
+    index: usize,
+    count: usize,
+}
+
+impl Share {
+    pub fn new(index: usize, count: usize) -> Share {
+        Share { index, count }
+    }
+
+    pub fn parse(share: &str) -> Result<Share, String> {
+        let mut parts = share
+            .splitn(2, '/')
+            .map(|part| part.trim().parse::<usize>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(index)), Some(Ok(count))) if index < count => Ok(Share::new(index, count)),
+            _ => Err(format!("Invalid process share {}", share)),
+        }
+    }
+
+    /// This process's part of the total, spread the same way work is spread over threads.
+    pub fn of(&self, total: usize) -> usize {
+        Plan::new(self.count, total).distribute()[self.index]
+    }
+
+    /// How many of the total the processes before this one have.
+    pub fn offset(&self, total: usize) -> usize {
+        Plan::new(self.count, total).distribute()[..self.index]
+            .iter()
+            .sum()
+    }
+
+    /// This process's part of the items, which no other process has any of.
+    pub fn part<T>(&self, mut items: Vec<T>) -> Vec<T> {
+        let offset = self.offset(items.len());
+        let len = self.of(items.len());
+        items.drain(offset..offset + len).collect()
+    }
+}
+
+/// The arguments to start a child with, which are this process's without `--processes`.
+pub fn child_args(args: &[String], share: Share) -> Vec<String> {
+    let mut child = Vec::with_capacity(args.len() + 1);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--processes" {
+            args.next();
+        } else if !arg.starts_with("--processes=") {
+            child.push(arg.clone());
+        }
+    }
+    child.push(format!("--child={}/{}", share.index, share.count));
+    child
+}
+
+/// The pages the parent gave this child, when it gave any.
+pub fn pages() -> Option<Vec<String>> {
+    env::var(PAGES)
+        .ok()
+        .map(|pages| pages.lines().map(String::from).collect())
+}
+
+/// Runs a share of the work in a child process and sends the facts it reports on to the
+/// collector. The child's workers are numbered after those of the children before it, and
+/// it is given the pages, if any, in place of finding them again.
+pub fn spawn(
+    share: Share,
+    threads: usize,
+    pages: &[String],
+    collector: Sender<Message<Fact>>,
+) -> thread::JoinHandle<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut command = Command::new(env::current_exe().expect("Expected to find the rench binary"));
+    command.args(child_args(&args, share)).stdout(Stdio::piped());
+    if !pages.is_empty() {
+        command.env(PAGES, pages.join("\n"));
+    }
+    let mut child = command.spawn().expect("Expected to start a child process");
+    let offset = share.offset(threads);
+
+    thread::spawn(move || {
+        let stdout = child.stdout.take().expect("Child to have a stdout");
+        for line in BufReader::new(stdout).lines() {
+            let line = line.expect("Expected to read from the child process");
+            let json: Value = serde_json::from_str(&line).expect("Expected a fact from the child");
+            let fact = Fact::from_json(&json).expect("Expected a valid fact from the child");
+            let fact = match fact.worker() {
+                Some(worker) => fact.with_worker(worker + offset),
+                None => fact,
+            };
+            collector
+                .send(Message::Body(fact))
+                .expect("to send the fact correctly");
+        }
+        let status = child.wait().expect("Child process to finish");
+        if !status.success() {
+            panic!("Child process {} failed with {}", share.index, status);
+        }
+        collector
+            .send(Message::Eof)
+            .expect("to send None correctly");
+    })
+}
+
+/// Collects facts in a child process, writing each to stdout as a line of json for the
+/// parent to read. Ends once every one of the threads in the plan has finished.
+pub fn forward(plan: Plan) -> (Sender<Message<Fact>>, thread::JoinHandle<()>) {
+    let (sender, receiver) = channel::<Message<Fact>>();
+    let handle = thread::spawn(move || {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        let mut eof_count = 0;
+        while eof_count < plan.threads() {
+            match receiver.recv().expect("To receive correctly") {
+                Message::Body(fact) => writeln!(stdout, "{}", fact.to_json())
+                    .expect("Expected to write the fact to the parent"),
+                Message::Eof => eof_count += 1,
+            }
+        }
+        stdout
+            .flush()
+            .expect("Expected to write the facts to the parent");
+    });
+    (sender, handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_splits_the_work_between_processes() {
+        let shares: Vec<Share> = (0..3).map(|index| Share::new(index, 3)).collect();
+        assert_eq!(
+            shares.iter().map(|share| share.of(10)).collect::<Vec<_>>(),
+            vec![4, 3, 3]
+        );
+        assert_eq!(
+            shares
+                .iter()
+                .map(|share| share.offset(10))
+                .collect::<Vec<_>>(),
+            vec![0, 4, 7]
+        );
+        assert_eq!(shares[1].part((0..10).collect()), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn it_parses_shares() {
+        assert_eq!(Share::parse("1/4"), Ok(Share::new(1, 4)));
+        assert!(Share::parse("4/4").is_err());
+        assert!(Share::parse("1").is_err());
+    }
+
+    #[test]
+    fn it_removes_the_process_count_from_child_args() {
+        let args: Vec<String> = vec![
+            "http://localhost",
+            "--processes",
+            "2",
+            "-c",
+            "4",
+            "--processes=2",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        assert_eq!(
+            child_args(&args, Share::new(1, 2)),
+            vec!["http://localhost", "-c", "4", "--child=1/2"]
+        );
+    }
+}
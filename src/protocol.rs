@@ -0,0 +1,209 @@
+use stats::Fact;
+use std::time::Instant;
+
+/// The request to make next, to one of the targets the requests take turns between.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    /// Which of the targets the request goes to.
+    pub index: usize,
+    /// How many requests were made before this one.
+    pub n: usize,
+}
+
+/// Makes a single request of some protocol, holding on to whatever it needs between
+/// requests such as open connections. Closures that take the target are protocols too.
+pub trait Protocol {
+    fn run_one(&mut self, target: Target) -> Fact;
+
+    /// Tidies up once every request has been made, such as by closing connections.
+    fn finish(&mut self) {}
+}
+
+impl<F> Protocol for F
+where
+    F: FnMut(Target) -> Fact,
+{
+    fn run_one(&mut self, target: Target) -> Fact {
+        self(target)
+    }
+}
+
+/// Makes the requests one after another, sharing them between the targets by their weights
+/// and handing each fact to the collector as soon as it is made. Targets of the same weight
+/// take turns. The requests stop early once `more` says there are to be no more.
+pub fn run<P, M, F>(mut protocol: P, requests: usize, weights: &[u32], mut more: M, mut collect: F)
+where
+    P: Protocol,
+    M: FnMut() -> bool,
+    F: FnMut(Fact),
+{
+    let mut schedule = Schedule::new(weights);
+    for n in 0..requests {
+        if !more() {
+            break;
+        }
+        let target = Target {
+            index: schedule.next(),
+            n,
+        };
+        collect(protocol.run_one(target).with_target(target.index));
+    }
+    protocol.finish();
+}
+
+/// Times the iterations of a virtual user, each so many requests long, attaching the time
+/// an iteration took to the fact of its last request. An iteration is timed from its first
+/// request, so that setting up the protocol is not counted in the first.
+pub struct Iterations {
+    length: usize,
+    made: usize,
+    started: Option<Instant>,
+}
+
+impl Iterations {
+    pub fn new(length: usize) -> Iterations {
+        Iterations {
+            length,
+            made: 0,
+            started: None,
+        }
+    }
+
+    /// Starts the clock of the iteration as a request of it is about to be made, unless it
+    /// has already started.
+    pub fn begin(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    /// Whether the virtual user is between iterations, having made none of the next one's
+    /// requests.
+    pub fn is_between(&self) -> bool {
+        self.made == 0
+    }
+
+    /// Counts the request of the fact, ending the iteration when it is the last of one.
+    pub fn record(&mut self, fact: Fact) -> Fact {
+        self.made += 1;
+        if self.made < self.length {
+            return fact;
+        }
+        let took = self
+            .started
+            .take()
+            .map(|started| started.elapsed())
+            .unwrap_or_default();
+        self.made = 0;
+        fact.with_iteration(took)
+    }
+}
+
+/// Picks the targets in proportion to their weights, spread out as evenly as they can be,
+/// by the smooth weighted round robin nginx balances its upstreams with.
+struct Schedule {
+    weights: Vec<i64>,
+    current: Vec<i64>,
+    total: i64,
+}
+
+impl Schedule {
+    fn new(weights: &[u32]) -> Schedule {
+        Schedule {
+            weights: weights.iter().map(|&weight| i64::from(weight)).collect(),
+            current: vec![0; weights.len()],
+            total: weights.iter().map(|&weight| i64::from(weight)).sum(),
+        }
+    }
+
+    fn next(&mut self) -> usize {
+        let mut best = 0;
+        for index in 0..self.weights.len() {
+            self.current[index] += self.weights[index];
+            if self.current[index] > self.current[best] {
+                best = index;
+            }
+        }
+        self.current[best] -= self.total;
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn it_round_robins_the_targets() {
+        let mut targets = Vec::new();
+        let protocol = |target: Target| {
+            targets.push(target);
+            Fact::elapsed(Duration::new(0, 0))
+        };
+        let mut facts = 0;
+        run(protocol, 5, &[1, 1], || true, |_| facts += 1);
+
+        assert_eq!(facts, 5);
+        let indexes: Vec<usize> = targets.iter().map(|target| target.index).collect();
+        assert_eq!(indexes, vec![0, 1, 0, 1, 0]);
+        assert_eq!(targets[4], Target { index: 0, n: 4 });
+    }
+
+    #[test]
+    fn it_stops_when_there_are_to_be_no_more() {
+        let protocol = |_| Fact::elapsed(Duration::new(0, 0));
+        let mut left = 3;
+        let mut facts = 0;
+        run(
+            protocol,
+            5,
+            &[1],
+            || {
+                left -= 1;
+                left >= 0
+            },
+            |_| facts += 1,
+        );
+        assert_eq!(facts, 3);
+    }
+
+    #[test]
+    fn it_times_whole_iterations() {
+        let mut iterations = Iterations::new(2);
+        let fact = || Fact::elapsed(Duration::new(0, 0));
+        let ended: Vec<bool> = (0..4)
+            .map(|_| iterations.record(fact()).to_json().get("iteration").is_some())
+            .collect();
+        assert_eq!(ended, vec![false, true, false, true]);
+        assert!(iterations.is_between());
+        iterations.record(fact());
+        assert!(!iterations.is_between());
+    }
+
+    #[test]
+    fn it_times_iterations_from_their_first_request() {
+        let mut iterations = Iterations::new(1);
+        thread::sleep(Duration::from_millis(50));
+        iterations.begin();
+        let took = iterations.record(Fact::elapsed(Duration::new(0, 0))).to_json()["iteration"]
+            .as_u64()
+            .expect("Expected the iteration to be timed in nanoseconds");
+        assert!(took < 50_000_000);
+    }
+
+    #[test]
+    fn it_shares_requests_by_weight() {
+        let mut schedule = Schedule::new(&[5, 1, 1]);
+        let picks: Vec<usize> = (0..7).map(|_| schedule.next()).collect();
+        assert_eq!(picks, vec![0, 0, 1, 0, 2, 0, 0]);
+
+        let mut schedule = Schedule::new(&[80, 15, 5]);
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            counts[schedule.next()] += 1;
+        }
+        assert_eq!(counts, [800, 150, 50]);
+    }
+}
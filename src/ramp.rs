@@ -0,0 +1,64 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The end of a run of virtual users, which starts as soon as the first of them is through
+/// its iterations. The others finish the iteration they are on without starting another,
+/// unless the window for finishing runs out first. The threads of a process share it, while
+/// each child process ramps down on its own.
+#[derive(Clone, Debug)]
+pub struct RampDown {
+    window: Duration,
+    started: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RampDown {
+    pub fn new(window: Duration) -> RampDown {
+        RampDown {
+            window,
+            started: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Starts ramping down, unless another virtual user already has.
+    pub fn begin(&self) {
+        let mut started = self.started.lock().expect("Expected the ramp down lock");
+        if started.is_none() {
+            *started = Some(Instant::now());
+        }
+    }
+
+    /// Whether the run is ramping down.
+    pub fn is_ramping_down(&self) -> bool {
+        self.started().is_some()
+    }
+
+    /// Whether the window for finishing iterations has run out, when ramping down.
+    pub fn is_over(&self) -> bool {
+        self.started()
+            .is_some_and(|started| started.elapsed() >= self.window)
+    }
+
+    fn started(&self) -> Option<Instant> {
+        *self.started.lock().expect("Expected the ramp down lock")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_ramps_down_once_begun() {
+        let ramp = RampDown::new(Duration::from_millis(20));
+        let user = ramp.clone();
+        assert!(!user.is_ramping_down());
+        assert!(!user.is_over());
+
+        ramp.begin();
+        assert!(user.is_ramping_down());
+        assert!(!user.is_over());
+        ::std::thread::sleep(Duration::from_millis(30));
+        user.begin();
+        assert!(user.is_over());
+    }
+}
@@ -0,0 +1,346 @@
+use serde_json::{self, Value};
+use stats::Summary;
+use std::io::{self, Write};
+
+/// The formats a summary can be reported in, the first being the default.
+pub const FORMATS: [&str; 6] = ["text", "json", "csv", "prometheus", "markdown", "tap"];
+
+/// Writes out the summary of a run in some format. New formats only need to implement
+/// this, working from the summary's json when they need its numbers.
+pub trait Reporter {
+    fn report(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// The reporter for one of the formats.
+pub fn reporter(format: &str) -> Result<Box<dyn Reporter>, String> {
+    match format {
+        "text" => Ok(Box::new(Text)),
+        "json" => Ok(Box::new(Json)),
+        "csv" => Ok(Box::new(Csv)),
+        "prometheus" => Ok(Box::new(Prometheus)),
+        "markdown" => Ok(Box::new(Markdown)),
+        "tap" => Ok(Box::new(Tap::default())),
+        _ => Err(format!(
+            "Expected one of {} but got {}",
+            FORMATS.join(", "),
+            format
+        )),
+    }
+}
+
+/// The summary as it is printed at the end of a run, charts and all.
+pub struct Text;
+
+impl Reporter for Text {
+    fn report(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", summary)
+    }
+}
+
+/// The summary as pretty printed json, with durations in milliseconds.
+pub struct Json;
+
+impl Reporter for Json {
+    fn report(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, &summary.to_json())?;
+        writeln!(out)
+    }
+}
+
+/// A header and a single row with the headline numbers, so that the rows of many runs
+/// can be put together in a spreadsheet. Durations are in milliseconds.
+pub struct Csv;
+
+impl Reporter for Csv {
+    fn report(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        let json = summary.to_json();
+        let latency = &json["latency"];
+        let mut columns: Vec<(String, String)> = vec![
+            ("requests".to_string(), json["requests"].to_string()),
+            ("average".to_string(), latency["average"].to_string()),
+            ("stddev".to_string(), latency["stddev"].to_string()),
+            ("median".to_string(), latency["median"].to_string()),
+            ("min".to_string(), latency["min"].to_string()),
+            ("max".to_string(), latency["max"].to_string()),
+        ];
+        columns.extend(
+            entries(&latency["table"])
+                .into_iter()
+                .map(|(name, ms)| (name, ms.to_string())),
+        );
+        columns.push(("data".to_string(), json["data"].to_string()));
+        columns.push(("sent".to_string(), json["sent"].to_string()));
+        // Only runs time their throughput, summaries put back together from facts do not.
+        if !json["throughput"].is_null() {
+            columns.push((
+                "p99_rps_correlation".to_string(),
+                match json["throughput"]["correlation"] {
+                    Value::Null => String::new(),
+                    ref correlation => correlation.to_string(),
+                },
+            ));
+        }
+        columns.extend(
+            entries(&json["status_codes"])
+                .into_iter()
+                .map(|(status, count)| (format!("status_{}", status), count.to_string())),
+        );
+
+        let (names, values): (Vec<String>, Vec<String>) = columns.into_iter().unzip();
+        writeln!(out, "{}", names.join(","))?;
+        writeln!(out, "{}", values.join(","))
+    }
+}
+
+/// The summary in the prometheus text format, ready to be pushed to a gateway or served
+/// to a scraper. Durations are in seconds as prometheus expects.
+pub struct Prometheus;
+
+impl Reporter for Prometheus {
+    fn report(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        let json = summary.to_json();
+        let latency = &json["latency"];
+        // Rounded to the nanosecond so that float error does not creep into the output.
+        let seconds = |ms: f64| (ms * 1_000_000f64).round() / 1_000_000_000f64;
+        let requests = json["requests"].as_u64().unwrap_or(0);
+
+        metric(out, "rench_requests_total", "counter", "The requests made.")?;
+        writeln!(out, "rench_requests_total {}", requests)?;
+
+        metric(
+            out,
+            "rench_latency_seconds",
+            "summary",
+            "How long the requests took.",
+        )?;
+        for (name, ms) in entries(&latency["table"]) {
+            let quantile = (name[1..].parse::<f64>().unwrap_or(0f64) * 10_000f64).round() / 1e6;
+            writeln!(
+                out,
+                "rench_latency_seconds{{quantile=\"{}\"}} {}",
+                quantile,
+                seconds(ms.as_f64().unwrap_or(0f64))
+            )?;
+        }
+        writeln!(
+            out,
+            "rench_latency_seconds_sum {}",
+            seconds(latency["average"].as_f64().unwrap_or(0f64) * requests as f64)
+        )?;
+        writeln!(out, "rench_latency_seconds_count {}", requests)?;
+
+        metric(
+            out,
+            "rench_responses_total",
+            "counter",
+            "The responses by their status.",
+        )?;
+        for (status, count) in entries(&json["status_codes"]) {
+            writeln!(
+                out,
+                "rench_responses_total{{status=\"{}\"}} {}",
+                status, count
+            )?;
+        }
+        metric(
+            out,
+            "rench_failures_total",
+            "counter",
+            "The requests that failed by why.",
+        )?;
+        for (failure, count) in entries(&json["failures"]) {
+            writeln!(
+                out,
+                "rench_failures_total{{failure=\"{}\"}} {}",
+                failure, count
+            )?;
+        }
+
+        metric(
+            out,
+            "rench_received_bytes_total",
+            "counter",
+            "The bytes received.",
+        )?;
+        writeln!(out, "rench_received_bytes_total {}", json["data"])?;
+        metric(
+            out,
+            "rench_sent_bytes_total",
+            "counter",
+            "The bytes uploaded.",
+        )?;
+        writeln!(out, "rench_sent_bytes_total {}", json["sent"])
+    }
+}
+
+/// Tables of the headline numbers, the statuses and the failures in github flavored markdown,
+/// for pull requests and the summaries of ci jobs. Durations are in milliseconds.
+pub struct Markdown;
+
+impl Reporter for Markdown {
+    fn report(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        let json = summary.to_json();
+        let latency = &json["latency"];
+        let ms = |ms: &Value| format!("{:.3} ms", ms.as_f64().unwrap_or(0f64));
+        writeln!(out, "### rench results")?;
+        writeln!(out)?;
+        writeln!(out, "| Measure | Value |")?;
+        writeln!(out, "| --- | --: |")?;
+        writeln!(out, "| Requests | {} |", json["requests"])?;
+        for name in &["average", "median", "min", "max"] {
+            let mut label = name.to_string();
+            label[..1].make_ascii_uppercase();
+            writeln!(out, "| {} | {} |", label, ms(&latency[*name]))?;
+        }
+        for (name, duration) in entries(&latency["table"]) {
+            writeln!(out, "| {} | {} |", name, ms(&duration))?;
+        }
+        writeln!(out, "| Data | {} bytes |", json["data"])?;
+        for (title, key) in &[("Status", "status_codes"), ("Failure", "failures")] {
+            let counts = entries(&json[*key]);
+            if counts.is_empty() {
+                continue;
+            }
+            writeln!(out)?;
+            writeln!(out, "| {} | Requests |", title)?;
+            writeln!(out, "| --- | --: |")?;
+            for (name, count) in counts {
+                writeln!(out, "| {} | {} |", name, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The checks of a run as tests of the test anything protocol, for harnesses that already
+/// read it, followed by the headline numbers as comments.
+#[derive(Default)]
+pub struct Tap {
+    tests: Vec<(bool, String)>,
+}
+
+impl Tap {
+    /// Adds a test, which passed or not, with what it was of.
+    pub fn with_test(mut self, passed: bool, description: String) -> Self {
+        self.tests.push((passed, description));
+        self
+    }
+}
+
+impl Reporter for Tap {
+    fn report(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        let json = summary.to_json();
+        let latency = &json["latency"];
+        writeln!(out, "TAP version 13")?;
+        if self.tests.is_empty() {
+            writeln!(out, "1..0 # SKIP no thresholds to check")?;
+        } else {
+            writeln!(out, "1..{}", self.tests.len())?;
+        }
+        for (number, (passed, description)) in self.tests.iter().enumerate() {
+            let result = if *passed { "ok" } else { "not ok" };
+            writeln!(out, "{} {} - {}", result, number + 1, description)?;
+        }
+        writeln!(
+            out,
+            "# {} requests, median {} ms, p99 {} ms",
+            json["requests"], latency["median"], latency["table"]["p99"]
+        )
+    }
+}
+
+fn metric(out: &mut dyn Write, name: &str, kind: &str, help: &str) -> io::Result<()> {
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} {}", name, kind)
+}
+
+/// The keys and values of a json object, in the order of the keys.
+fn entries(object: &Value) -> Vec<(String, Value)> {
+    let mut entries: Vec<(String, Value)> = object
+        .as_object()
+        .map(|object| {
+            object
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content_length::ContentLength;
+    use stats::Fact;
+    use std::time::Duration;
+
+    fn report(format: &str) -> String {
+        let facts = vec![
+            Fact::record(ContentLength::new(10), 200, Duration::from_millis(10)),
+            Fact::record(ContentLength::new(10), 200, Duration::from_millis(30)),
+            Fact::record(ContentLength::new(0), 500, Duration::from_millis(20)),
+        ];
+        let mut out = Vec::new();
+        reporter(format)
+            .unwrap()
+            .report(&Summary::from_facts(&facts), &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn it_reports_csv() {
+        let csv = report("csv");
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "requests,average,stddev,median,min,max,p50,p75,p90,p95,p99,p99.9,data,sent,\
+             status_200,status_500"
+        );
+        assert!(lines[1].starts_with("3,20.0,10.0,20.0,10.0,30.0,20.0,"));
+        assert!(lines[1].ends_with(",20,0,2,1"));
+    }
+
+    #[test]
+    fn it_reports_prometheus() {
+        let prometheus = report("prometheus");
+        assert!(prometheus.contains("# TYPE rench_latency_seconds summary\n"));
+        assert!(prometheus.contains("rench_requests_total 3\n"));
+        assert!(prometheus.contains("rench_latency_seconds{quantile=\"0.99\"} 0.03\n"));
+        assert!(prometheus.contains("rench_latency_seconds{quantile=\"0.999\"} 0.03\n"));
+        assert!(prometheus.contains("rench_latency_seconds_sum 0.06\n"));
+        assert!(prometheus.contains("rench_responses_total{status=\"500\"} 1\n"));
+    }
+
+    #[test]
+    fn it_reports_markdown() {
+        let markdown = report("markdown");
+        assert!(markdown.starts_with("### rench results\n"));
+        assert!(markdown.contains("| Median | 20.000 ms |\n"));
+        assert!(markdown.contains("| p99 | 30.000 ms |\n"));
+        assert!(markdown.contains("| Status | Requests |\n| --- | --: |\n| 200 | 2 |\n"));
+        assert!(!markdown.contains("| Failure |"));
+    }
+
+    #[test]
+    fn it_reports_tap() {
+        assert!(report("tap").starts_with("TAP version 13\n1..0 # SKIP"));
+
+        let tap = Tap::default()
+            .with_test(true, "p95 < 1s".to_string())
+            .with_test(false, "rps > 1000".to_string());
+        let mut out = Vec::new();
+        tap.report(&Summary::from_facts(&[]), &mut out).unwrap();
+        let tap = String::from_utf8(out).unwrap();
+        assert!(tap.contains("\n1..2\nok 1 - p95 < 1s\nnot ok 2 - rps > 1000\n# 0 requests"));
+    }
+
+    #[test]
+    fn it_reports_json() {
+        let json: Value = serde_json::from_str(&report("json")).unwrap();
+        assert_eq!(json["requests"], 3);
+        assert!(reporter("xml").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Keeps a uniform random sample of everything pushed to it, so that memory stays bounded
+/// however long a run goes on for. Without a size, everything is kept.
+pub struct Reservoir<T> {
+    items: Vec<T>,
+    size: Option<usize>,
+    seen: usize,
+    rng: StdRng,
+}
+
+impl<T> Reservoir<T> {
+    /// Keeps at most `size` items.
+    pub fn new(size: usize) -> Reservoir<T> {
+        Reservoir {
+            items: Vec::with_capacity(size),
+            size: Some(size),
+            seen: 0,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Keeps every item, starting with room for `capacity` of them.
+    pub fn unbounded(capacity: usize) -> Reservoir<T> {
+        Reservoir {
+            items: Vec::with_capacity(capacity),
+            size: None,
+            seen: 0,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Keeps the item with the chance that leaves every item seen so far equally likely
+    /// to be in the sample.
+    pub fn push(&mut self, item: T) {
+        self.seen += 1;
+        match self.size {
+            Some(size) if self.items.len() >= size => {
+                let index = self.rng.gen_range(0..self.seen);
+                if index < size {
+                    self.items[index] = item;
+                }
+            }
+            _ => self.items.push(item),
+        }
+    }
+
+    /// How many items were pushed, including those that were not kept.
+    pub fn seen(&self) -> usize {
+        self.seen
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_everything_when_unbounded() {
+        let mut reservoir = Reservoir::unbounded(0);
+        (0..5).for_each(|n| reservoir.push(n));
+        assert_eq!(reservoir.seen(), 5);
+        assert_eq!(reservoir.into_vec(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn it_keeps_a_bounded_sample() {
+        let mut reservoir = Reservoir::new(10);
+        (0..1000).for_each(|n| reservoir.push(n));
+        assert_eq!(reservoir.seen(), 1000);
+        let sample = reservoir.into_vec();
+        assert_eq!(sample.len(), 10);
+        assert!(sample.iter().all(|&n| n < 1000));
+        // The chance of keeping none of the later items is vanishingly small.
+        assert!(sample.iter().any(|&n| n >= 10));
+    }
+}
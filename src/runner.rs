@@ -1,6 +1,8 @@
+use affinity;
 use engine::Engine;
 use plan::Plan;
 use message::Message;
+use processes::{self, Share};
 use stats::Fact;
 use std::{thread, sync::mpsc::Sender};
 
@@ -12,14 +14,49 @@ pub struct Runner {
 impl Runner {
     /// Launches the runner with a plan. It will tell the engine to run and broadcast the
     /// facts that the engine produces. The plan tells the runner how many threads to run
-    /// on and how to distribute the work.
-    pub fn start(plan: Plan, eng: &Engine, collector: &Sender<Message<Fact>>) -> Runner {
+    /// on and how to distribute the work. When cores are given, the threads are pinned to
+    /// them in turn.
+    pub fn start(
+        plan: Plan,
+        eng: &Engine,
+        collector: &Sender<Message<Fact>>,
+        cores: &[usize],
+    ) -> Runner {
         let handles = plan.distribute()
             .into_iter()
-            .map(|work| {
+            .enumerate()
+            .map(|(i, work)| {
                 let collector = collector.clone();
                 let eng = eng.clone();
-                thread::spawn(move || Self::run(work, eng, &collector))
+                let core = if cores.is_empty() {
+                    None
+                } else {
+                    Some(cores[i % cores.len()])
+                };
+                thread::spawn(move || {
+                    if let Some(core) = core {
+                        affinity::pin(core).expect("Expected to pin the thread to the core");
+                    }
+                    Self::run(i, work, eng, &collector)
+                })
+            })
+            .collect();
+        Runner { handles }
+    }
+
+    /// Launches child processes that split the plan between them, the facts they report
+    /// being broadcast as though the threads were this process's own. The pages of a sitemap
+    /// or crawl are handed to the children rather than found by each of them.
+    pub fn start_processes(
+        plan: Plan,
+        processes: usize,
+        pages: &[String],
+        collector: &Sender<Message<Fact>>,
+    ) -> Runner {
+        let handles = (0..processes)
+            .map(|index| {
+                let share = Share::new(index, processes);
+                processes::spawn(share, plan.threads(), pages, collector.clone())
             })
             .collect();
         Runner { handles }
@@ -33,14 +70,14 @@ impl Runner {
             .for_each(|h| h.join().expect("Sending thread to finish"));
     }
 
-    fn run(work: usize, eng: Engine, collector: &Sender<Message<Fact>>) {
-        eng.run(work, |fact| {
+    fn run(worker: usize, work: usize, eng: Engine, collector: &Sender<Message<Fact>>) {
+        eng.with_user(worker).run(work, |fact| {
             collector
-                .send(Message::Body(fact))
+                .send(Message::Body(fact.with_worker(worker)))
                 .expect("to send the fact correctly");
         });
         collector
-            .send(Message::EOF)
+            .send(Message::Eof)
             .expect("to send None correctly");
     }
 }
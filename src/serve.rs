@@ -0,0 +1,274 @@
+use httparse;
+use hyper::StatusCode;
+use rand::{self, Rng};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+/// The body of every response that may have one.
+const BODY: &[u8] = b"Hello from rench\n";
+
+/// A http/1.1 server to benchmark against, which answers every request after a delay with
+/// a status drawn from a weighted mix.
+#[derive(Clone, Debug)]
+pub struct Server {
+    latency: Duration,
+    jitter: Duration,
+    status_mix: Vec<(u16, u32)>,
+}
+
+/// A server that answers every request with a 200 straight away.
+impl Default for Server {
+    fn default() -> Server {
+        Server {
+            latency: Duration::new(0, 0),
+            jitter: Duration::new(0, 0),
+            status_mix: vec![(200, 1)],
+        }
+    }
+}
+
+impl Server {
+    /// Waits this long before answering each request.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Varies the wait by up to this much either way, evenly spread.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Answers with each status in proportion to its weight.
+    pub fn with_status_mix(mut self, status_mix: Vec<(u16, u32)>) -> Self {
+        self.status_mix = status_mix;
+        self
+    }
+
+    /// Serves every connection the listener accepts on a thread of its own.
+    pub fn run(self, listener: TcpListener) {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let server = self.clone();
+            thread::spawn(move || {
+                // The client going away mid request is no concern of the server's.
+                let _ = server.handle(stream);
+            });
+        }
+    }
+
+    /// Serves the listener's connections in the background, for rench's own tests to
+    /// make requests to.
+    #[cfg(test)]
+    pub fn spawn(self, listener: TcpListener) -> thread::JoinHandle<()> {
+        thread::spawn(move || self.run(listener))
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut buffer = Vec::new();
+        loop {
+            let (end, length, close, head) = loop {
+                let mut headers = [httparse::EMPTY_HEADER; 64];
+                let mut request = httparse::Request::new(&mut headers);
+                if let httparse::Status::Complete(end) = request
+                    .parse(&buffer)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                {
+                    let header = |name: &str| {
+                        request
+                            .headers
+                            .iter()
+                            .find(|header| header.name.eq_ignore_ascii_case(name))
+                            .map(|header| String::from_utf8_lossy(header.value).to_lowercase())
+                    };
+                    let length = header("content-length")
+                        .and_then(|length| length.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    let close = match header("connection") {
+                        Some(connection) => connection.contains("close"),
+                        None => request.version == Some(0),
+                    };
+                    break (end, length, close, request.method == Some("HEAD"));
+                }
+                if fill(&mut stream, &mut buffer)? == 0 {
+                    return Ok(());
+                }
+            };
+            while buffer.len() < end + length {
+                if fill(&mut stream, &mut buffer)? == 0 {
+                    return Ok(());
+                }
+            }
+            buffer.drain(..end + length);
+
+            thread::sleep(self.delay());
+            let status = self.status();
+            let reason = StatusCode::try_from(status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or("Unknown");
+            // Informational, no content and not modified responses never have a body, and
+            // the answer to a head request only says how long the body would have been.
+            let bodiless = status < 200 || status == 204 || status == 304;
+            // Written in one go, as a response split over writes waits on delayed acks.
+            let mut response = format!(
+                "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\n{}{}\r\n",
+                status,
+                reason,
+                if bodiless {
+                    String::new()
+                } else {
+                    format!("Content-Length: {}\r\n", BODY.len())
+                },
+                if close { "Connection: close\r\n" } else { "" }
+            )
+            .into_bytes();
+            if !bodiless && !head {
+                response.extend_from_slice(BODY);
+            }
+            stream.write_all(&response)?;
+            if close {
+                return Ok(());
+            }
+        }
+    }
+
+    fn delay(&self) -> Duration {
+        if self.jitter == Duration::new(0, 0) {
+            return self.latency;
+        }
+        let jitter = self.jitter.as_nanos() as u64;
+        let offset = rand::thread_rng().gen_range(0..=2 * jitter);
+        (self.latency + Duration::from_nanos(offset))
+            .checked_sub(self.jitter)
+            .unwrap_or_default()
+    }
+
+    fn status(&self) -> u16 {
+        let total: u32 = self.status_mix.iter().map(|&(_, weight)| weight).sum();
+        let mut pick = rand::thread_rng().gen_range(0..total);
+        for &(status, weight) in &self.status_mix {
+            if pick < weight {
+                return status;
+            }
+            pick -= weight;
+        }
+        unreachable!("The pick is less than the total of the weights")
+    }
+}
+
+fn fill(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> io::Result<usize> {
+    let mut chunk = [0; 16 * 1024];
+    let len = stream.read(&mut chunk)?;
+    buffer.extend_from_slice(&chunk[..len]);
+    Ok(len)
+}
+
+/// Parses a mix of statuses with their weights, such as `200:95,500:5`.
+pub fn parse_status_mix(mix: &str) -> Result<Vec<(u16, u32)>, String> {
+    let mix = mix
+        .split(',')
+        .map(|part| {
+            let mut parts = part.splitn(2, ':').map(str::trim);
+            match (parts.next().map(str::parse), parts.next().map(str::parse)) {
+                (Some(Ok(status)), Some(Ok(weight))) if (100..600).contains(&status) => {
+                    Ok((status, weight))
+                }
+                _ => Err(format!("Expected status:weight but got {}", part)),
+            }
+        })
+        .collect::<Result<Vec<(u16, u32)>, String>>()?;
+    if mix.iter().all(|&(_, weight)| weight == 0) {
+        return Err("At least one status needs a weight".to_string());
+    }
+    Ok(mix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn it_parses_status_mixes() {
+        assert_eq!(
+            parse_status_mix("200:95, 500:5"),
+            Ok(vec![(200, 95), (500, 5)])
+        );
+        assert!(parse_status_mix("200").is_err());
+        assert!(parse_status_mix("200:most").is_err());
+        assert!(parse_status_mix("999:1").is_err());
+        assert!(parse_status_mix("200:0").is_err());
+    }
+
+    #[test]
+    fn it_answers_requests_on_one_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        Server::default()
+            .with_latency(Duration::from_millis(5))
+            .with_jitter(Duration::from_millis(2))
+            .with_status_mix(vec![(503, 1)])
+            .spawn(listener);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        for _ in 0..2 {
+            stream
+                .write_all(b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\nhi")
+                .unwrap();
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "HTTP/1.1 503 Service Unavailable\r\n");
+            while line != "\r\n" {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+            }
+            let mut body = vec![0; BODY.len()];
+            reader.read_exact(&mut body).unwrap();
+            assert_eq!(body, BODY);
+        }
+    }
+
+    /// Makes the request twice on one connection and returns the head of the first
+    /// response, which can only be followed by the second if it had no body.
+    fn answer_twice(server: Server, request: &[u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        server.spawn(listener);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        stream.write_all(request).unwrap();
+        stream.write_all(request).unwrap();
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            reader.read_line(&mut head).unwrap();
+        }
+        let mut next = String::new();
+        reader.read_line(&mut next).unwrap();
+        assert!(next.starts_with("HTTP/1.1 "), "{:?} follows the response", next);
+        head
+    }
+
+    #[test]
+    fn it_answers_head_requests_without_a_body() {
+        let head = answer_twice(Server::default(), b"HEAD / HTTP/1.1\r\nHost: x\r\n\r\n");
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains(&format!("Content-Length: {}\r\n", BODY.len())));
+    }
+
+    #[test]
+    fn it_answers_no_content_without_a_body() {
+        let server = Server::default().with_status_mix(vec![(204, 1)]);
+        let head = answer_twice(server, b"GET / HTTP/1.1\r\nHost: x\r\n\r\n");
+        assert!(head.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert!(!head.contains("Content-Length"));
+    }
+}
@@ -0,0 +1,136 @@
+use fake;
+use hyper::header::Headers;
+use hyper::Uri;
+use std::collections::HashMap;
+
+/// Makes the threads virtual users with sessions of their own that stick with them for the
+/// whole run, so that load balancers keeping users to one backend and caches kept per user
+/// see the traffic real users would make.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sessions {
+    header: Option<String>,
+}
+
+impl Sessions {
+    /// Sends each user's session id in the header with every request it makes.
+    pub fn with_header(mut self, header: String) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    /// Starts the session of a virtual user, with an id of its own.
+    pub fn start(&self) -> Session {
+        Session {
+            id: fake::uuid(),
+            header: self.header.clone(),
+            cookies: HashMap::new(),
+        }
+    }
+}
+
+/// A virtual user's session: its id, and the cookies it has been given kept by host and sent
+/// back with its later requests to the host as a browser would. Attributes of cookies other
+/// than a `Max-Age` deleting them are not looked at, so every cookie a host set goes with
+/// every request to it.
+#[derive(Debug)]
+pub struct Session {
+    id: String,
+    header: Option<String>,
+    cookies: HashMap<String, Vec<(String, String)>>,
+}
+
+impl Session {
+    /// Adds the session id and the cookies of the url's host to a request, after any cookies
+    /// the request already has.
+    pub fn add_to(&self, url: &str, headers: &mut Headers) {
+        if let Some(ref header) = self.header {
+            headers.set_raw(header.clone(), self.id.clone());
+        }
+        let cookies = match self.cookies.get(&host(url)) {
+            Some(cookies) if !cookies.is_empty() => cookies,
+            _ => return,
+        };
+        let mut line = headers
+            .get_raw("Cookie")
+            .and_then(|raw| raw.one())
+            .map(|cookie| format!("{}; ", String::from_utf8_lossy(cookie)))
+            .unwrap_or_default();
+        let pairs: Vec<String> = cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        line.push_str(&pairs.join("; "));
+        headers.set_raw("Cookie", line);
+    }
+
+    /// Keeps the cookies set by a response from the url, given the lines of its `Set-Cookie`
+    /// headers.
+    pub fn store<'a, I>(&mut self, url: &str, set_cookies: I)
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut set_cookies = set_cookies.into_iter().peekable();
+        if set_cookies.peek().is_none() {
+            return;
+        }
+        let cookies = self.cookies.entry(host(url)).or_default();
+        for line in set_cookies {
+            let line = String::from_utf8_lossy(line);
+            let mut attributes = line.split(';');
+            let (name, value) = match attributes.next().and_then(|pair| pair.split_once('=')) {
+                Some((name, value)) => (name.trim().to_string(), value.trim().to_string()),
+                None => continue,
+            };
+            let deleted = attributes.any(|attribute| match attribute.split_once('=') {
+                Some((key, age)) if key.trim().eq_ignore_ascii_case("max-age") => {
+                    age.trim().parse::<i64>().is_ok_and(|age| age <= 0)
+                }
+                _ => false,
+            });
+            cookies.retain(|(kept, _)| *kept != name);
+            if !deleted {
+                cookies.push((name, value));
+            }
+        }
+    }
+}
+
+fn host(url: &str) -> String {
+    url.parse::<Uri>()
+        .ok()
+        .and_then(|uri| uri.authority().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_sends_back_the_cookies_of_the_host() {
+        let mut session = Sessions::default().with_header("X-Session".to_string()).start();
+        session.store(
+            "http://a/login",
+            vec![&b"sid=1; Path=/; HttpOnly"[..], &b"theme=dark"[..]],
+        );
+        session.store("http://b/", vec![&b"other=x"[..]]);
+        session.store("http://a/", vec![&b"sid=2"[..], &b"theme=; Max-Age=0"[..]]);
+
+        let mut headers = Headers::new();
+        headers.set_raw("Cookie", "given=y");
+        session.add_to("http://a/cart", &mut headers);
+        assert_eq!(
+            headers.get_raw("Cookie").and_then(|raw| raw.one()),
+            Some(&b"given=y; sid=2"[..])
+        );
+        assert_eq!(
+            headers.get_raw("X-Session").and_then(|raw| raw.one()),
+            Some(session.id.as_bytes())
+        );
+
+        let mut headers = Headers::new();
+        session.add_to("http://c/", &mut headers);
+        assert!(headers.get_raw("Cookie").is_none());
+        assert_ne!(Sessions::default().start().id, session.id);
+    }
+}
@@ -0,0 +1,218 @@
+use base64;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use reqwest::Url;
+use std::env;
+use std::fs;
+
+/// An hmac of each request sent in a header, as webhooks and apis with their own signature
+/// schemes expect. It is given as the algorithm followed by `name=value` options, all split
+/// by colons:
+///
+/// * `header`, the header the signature is sent in, `X-Signature` by default
+/// * `key`, the secret, given as `env:NAME`, `file:PATH` or as is
+/// * `payload`, what is signed: the `body`, the default, the `url` or its `path`
+/// * `encoding`, `hex`, the default, or `base64`
+/// * `prefix`, put before the signature, such as `sha256=`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    algorithm: Algorithm,
+    header: String,
+    key: Vec<u8>,
+    payload: Payload,
+    base64: bool,
+    prefix: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// The part of the request that is signed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Payload {
+    Body,
+    Url,
+    Path,
+}
+
+impl Signature {
+    /// Parses the scheme, such as `hmac-sha256:header=X-Signature:key=env:API_KEY`.
+    pub fn parse(spec: &str) -> Result<Signature, String> {
+        let mut parts = spec.split(':');
+        let algorithm = match parts.next().map(str::to_ascii_lowercase).as_deref() {
+            Some("hmac-sha1") => Algorithm::Sha1,
+            Some("hmac-sha256") => Algorithm::Sha256,
+            Some("hmac-sha512") => Algorithm::Sha512,
+            _ => {
+                return Err(format!(
+                    "Expected hmac-sha1, hmac-sha256 or hmac-sha512 to begin {}",
+                    spec
+                ))
+            }
+        };
+        // A part without a name carries on the value before it, as in `key=env:API_KEY`.
+        let mut options: Vec<(&str, String)> = Vec::new();
+        for part in parts {
+            match (part.split_once('='), options.last_mut()) {
+                (Some((name, value)), _) if !name.is_empty() => {
+                    options.push((name.trim(), value.to_string()))
+                }
+                (_, Some(&mut (_, ref mut value))) => {
+                    value.push(':');
+                    value.push_str(part);
+                }
+                (_, None) => return Err(format!("Expected name=value but got {}", part)),
+            }
+        }
+
+        let mut signature = Signature {
+            algorithm,
+            header: "X-Signature".to_string(),
+            key: Vec::new(),
+            payload: Payload::Body,
+            base64: false,
+            prefix: String::new(),
+        };
+        let mut keyed = false;
+        for (name, value) in options {
+            match name {
+                "header" => signature.header = value,
+                "key" => {
+                    signature.key = read_key(&value)?;
+                    keyed = true;
+                }
+                "payload" => {
+                    signature.payload = match value.as_str() {
+                        "body" => Payload::Body,
+                        "url" => Payload::Url,
+                        "path" => Payload::Path,
+                        _ => return Err(format!("Expected body, url or path but got {}", value)),
+                    }
+                }
+                "encoding" => {
+                    signature.base64 = match value.as_str() {
+                        "hex" => false,
+                        "base64" => true,
+                        _ => return Err(format!("Expected hex or base64 but got {}", value)),
+                    }
+                }
+                "prefix" => signature.prefix = value,
+                _ => return Err(format!("Unknown signing option {}", name)),
+            }
+        }
+        if !keyed {
+            return Err(format!("Expected a key to sign with in {}", spec));
+        }
+        Ok(signature)
+    }
+
+    /// Whether the body is signed, which has to be known before the request is sent.
+    pub fn signs_body(&self) -> bool {
+        self.payload == Payload::Body
+    }
+
+    /// The header that signs a request to the url with the body.
+    pub fn sign(&self, url: &str, body: &[u8]) -> (String, String) {
+        let payload = match self.payload {
+            Payload::Body => body.to_vec(),
+            Payload::Url => url.as_bytes().to_vec(),
+            Payload::Path => match Url::parse(url) {
+                Ok(url) => match url.query() {
+                    Some(query) => format!("{}?{}", url.path(), query),
+                    None => url.path().to_string(),
+                },
+                Err(_) => url.to_string(),
+            }
+            .into_bytes(),
+        };
+        let digest = match self.algorithm {
+            Algorithm::Sha1 => MessageDigest::sha1(),
+            Algorithm::Sha256 => MessageDigest::sha256(),
+            Algorithm::Sha512 => MessageDigest::sha512(),
+        };
+        let mac = hmac(digest, &self.key, &payload);
+        let encoded = if self.base64 {
+            base64::encode(&mac)
+        } else {
+            mac.iter().map(|byte| format!("{:02x}", byte)).collect()
+        };
+        (self.header.clone(), format!("{}{}", self.prefix, encoded))
+    }
+}
+
+/// The secret a key option names.
+fn read_key(key: &str) -> Result<Vec<u8>, String> {
+    if let Some(name) = key.strip_prefix("env:") {
+        env::var(name)
+            .map(String::into_bytes)
+            .map_err(|_| format!("The signing key {} is not set", name))
+    } else if let Some(path) = key.strip_prefix("file:") {
+        fs::read_to_string(path)
+            .map(|key| key.trim_end_matches(['\r', '\n']).as_bytes().to_vec())
+            .map_err(|e| format!("Could not read {}: {}", path, e))
+    } else {
+        Ok(key.as_bytes().to_vec())
+    }
+}
+
+/// The hmac of the message under the key.
+pub fn hmac(digest: MessageDigest, key: &[u8], message: &[u8]) -> Vec<u8> {
+    let key = PKey::hmac(key).expect("Expected an hmac key");
+    let mut signer = Signer::new(digest, &key).expect("Expected an hmac signer");
+    signer.update(message).expect("Expected the hmac to update");
+    signer.sign_to_vec().expect("Expected the hmac to sign")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_schemes() {
+        env::set_var("RENCH_SIGN_KEY", "secret");
+        let signature = Signature::parse(
+            "hmac-sha256:header=X-Hub-Signature-256:key=env:RENCH_SIGN_KEY:prefix=sha256=",
+        )
+        .unwrap();
+        assert_eq!(signature.header, "X-Hub-Signature-256");
+        assert_eq!(signature.key, b"secret");
+        assert_eq!(signature.prefix, "sha256=");
+        assert!(signature.signs_body());
+        assert_eq!(
+            Signature::parse("hmac-sha1:key=a:b:payload=path")
+                .unwrap()
+                .key,
+            b"a:b"
+        );
+        assert!(Signature::parse("hmac-md5:key=a").is_err());
+        assert!(Signature::parse("hmac-sha256:header=X").is_err());
+        assert!(Signature::parse("hmac-sha256:key=a:payload=headers").is_err());
+        assert!(Signature::parse("hmac-sha256:key=env:RENCH_SIGNATURE_UNSET").is_err());
+    }
+
+    #[test]
+    fn it_signs_the_rfc_4231_example() {
+        let signature = Signature::parse("hmac-sha256:key=Jefe").unwrap();
+        assert_eq!(
+            signature.sign("http://a/", b"what do ya want for nothing?"),
+            (
+                "X-Signature".to_string(),
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843".to_string()
+            )
+        );
+        let path = Signature::parse("hmac-sha256:key=Jefe:payload=path:encoding=base64").unwrap();
+        assert_eq!(
+            path.sign("http://a/b?c=d", b"ignored"),
+            path.sign("http://other/b?c=d", b"")
+        );
+        assert_ne!(
+            path.sign("http://a/b", b""),
+            path.sign("http://a/b?c=d", b"")
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+/// The tests of whether two runs' latencies differ by more than chance.
+pub const TESTS: [&str; 2] = ["welch", "mann-whitney"];
+
+/// A test of whether the latencies of a candidate run differ from those of a baseline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Test {
+    /// Welch's t-test of the means, which assumes the means are roughly normal but not
+    /// that the runs vary alike.
+    Welch,
+    /// The Mann-Whitney U test of the ranks, which assumes nothing of the shape of the
+    /// latencies and so holds up to their long tails.
+    MannWhitney,
+}
+
+impl Test {
+    pub fn parse(name: &str) -> Result<Test, String> {
+        match name {
+            "welch" => Ok(Test::Welch),
+            "mann-whitney" => Ok(Test::MannWhitney),
+            _ => Err(format!(
+                "Expected one of {} but got {}",
+                TESTS.join(", "),
+                name
+            )),
+        }
+    }
+
+    /// Tests the latencies, in milliseconds, of the baseline against those of the
+    /// candidate.
+    pub fn run(self, baseline: &[f64], candidate: &[f64]) -> Result<Outcome, String> {
+        if baseline.len() < 2 || candidate.len() < 2 {
+            return Err("Testing significance needs at least two requests in each run".into());
+        }
+        match self {
+            Test::Welch => welch(baseline, candidate),
+            Test::MannWhitney => Ok(mann_whitney(baseline, candidate)),
+        }
+    }
+}
+
+/// What a test made of two runs.
+#[derive(Debug)]
+pub struct Outcome {
+    test: Test,
+    statistic: f64,
+    /// The chance of a difference at least this large were the runs alike, both ways.
+    p_value: f64,
+    /// How large the difference is, positive when the candidate is slower: Cohen's d for
+    /// Welch's test and the rank-biserial correlation for Mann-Whitney's.
+    effect_size: f64,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, statistic, effect) = match self.test {
+            Test::Welch => ("Welch's t-test", "t", "Cohen's d"),
+            Test::MannWhitney => ("Mann-Whitney U test", "U", "rank-biserial r"),
+        };
+        writeln!(f, "{}:", name)?;
+        writeln!(f, "  {:<16} {:.3}", statistic, self.statistic)?;
+        writeln!(
+            f,
+            "  {:<16} {:.4}{}",
+            "p-value",
+            self.p_value,
+            if self.p_value < 0.05 {
+                ", significant at the 5% level"
+            } else {
+                ""
+            }
+        )?;
+        write!(f, "  {:<16} {:+.3}", effect, self.effect_size)
+    }
+}
+
+fn welch(baseline: &[f64], candidate: &[f64]) -> Result<Outcome, String> {
+    let moments = |values: &[f64]| {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1f64);
+        (n, mean, variance)
+    };
+    let (n1, mean1, variance1) = moments(baseline);
+    let (n2, mean2, variance2) = moments(candidate);
+    let (share1, share2) = (variance1 / n1, variance2 / n2);
+    if share1 + share2 == 0f64 {
+        return Err("Welch's t-test needs latencies that vary".into());
+    }
+    let t = (mean2 - mean1) / (share1 + share2).sqrt();
+    let df = (share1 + share2).powi(2)
+        / (share1.powi(2) / (n1 - 1f64) + share2.powi(2) / (n2 - 1f64));
+    let pooled =
+        (((n1 - 1f64) * variance1 + (n2 - 1f64) * variance2) / (n1 + n2 - 2f64)).sqrt();
+    Ok(Outcome {
+        test: Test::Welch,
+        statistic: t,
+        p_value: incomplete_beta(df / 2f64, 0.5, df / (df + t * t)),
+        effect_size: (mean2 - mean1) / pooled,
+    })
+}
+
+fn mann_whitney(baseline: &[f64], candidate: &[f64]) -> Outcome {
+    let mut all: Vec<(f64, bool)> = baseline
+        .iter()
+        .map(|&v| (v, false))
+        .chain(candidate.iter().map(|&v| (v, true)))
+        .collect();
+    all.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("Expected latencies to compare"));
+    // Tied latencies share the average of their ranks.
+    let (mut candidate_ranks, mut ties, mut i) = (0f64, 0f64, 0);
+    while i < all.len() {
+        let tied = all[i..].iter().take_while(|&&(v, _)| v == all[i].0).count();
+        let rank = (2 * i + tied + 1) as f64 / 2f64;
+        candidate_ranks += rank * all[i..i + tied].iter().filter(|&&(_, c)| c).count() as f64;
+        let tied_f = tied as f64;
+        ties += tied_f.powi(3) - tied_f;
+        i += tied;
+    }
+    let (n1, n2) = (baseline.len() as f64, candidate.len() as f64);
+    let n = n1 + n2;
+    let u = candidate_ranks - n2 * (n2 + 1f64) / 2f64;
+    let mean = n1 * n2 / 2f64;
+    let sigma = (n1 * n2 / 12f64 * ((n + 1f64) - ties / (n * (n - 1f64)))).sqrt();
+    let p_value = if sigma == 0f64 {
+        1f64
+    } else {
+        let z = ((u - mean).abs() - 0.5).max(0f64) / sigma;
+        (2f64 * (1f64 - normal_cdf(z))).min(1f64)
+    };
+    Outcome {
+        test: Test::MannWhitney,
+        statistic: u,
+        p_value,
+        effect_size: 2f64 * u / (n1 * n2) - 1f64,
+    }
+}
+
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * (1f64 + erf(z / 2f64.sqrt()))
+}
+
+/// The error function, to within about 1e-7 (Abramowitz and Stegun 7.1.26).
+fn erf(x: f64) -> f64 {
+    let t = 1f64 / (1f64 + 0.327_591_1 * x.abs());
+    let poly = [1.061_405_429, -1.453_152_027, 1.421_413_741, -0.284_496_736, 0.254_829_592]
+        .iter()
+        .fold(0f64, |poly, a| (poly + a) * t);
+    let y = 1f64 - poly * (-x * x).exp();
+    if x < 0f64 {
+        -y
+    } else {
+        y
+    }
+}
+
+/// The logarithm of the gamma function, by Lanczos' approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const G: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1f64 - x);
+    }
+    let x = x - 1f64;
+    let sum = G[1..]
+        .iter()
+        .enumerate()
+        .fold(G[0], |sum, (i, g)| sum + g / (x + i as f64 + 1f64));
+    let t = x + 7.5;
+    0.5 * (2f64 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// The regularized incomplete beta function, by its continued fraction.
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0f64 {
+        return 0f64;
+    }
+    if x >= 1f64 {
+        return 1f64;
+    }
+    // The fraction converges quickly only below this, above which the symmetry is used.
+    if x > (a + 1f64) / (a + b + 2f64) {
+        return 1f64 - incomplete_beta(b, a, 1f64 - x);
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1f64 - x).ln())
+        .exp();
+    const TINY: f64 = 1e-300;
+    let (mut c, mut d) = (1f64, 1f64 - (a + b) * x / (a + 1f64));
+    d = 1f64 / if d.abs() < TINY { TINY } else { d };
+    let mut fraction = d;
+    for m in 1..200 {
+        let m = f64::from(m);
+        for &numerator in &[
+            m * (b - m) * x / ((a + 2f64 * m - 1f64) * (a + 2f64 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2f64 * m) * (a + 2f64 * m + 1f64)),
+        ] {
+            d = 1f64 + numerator * d;
+            d = 1f64 / if d.abs() < TINY { TINY } else { d };
+            c = 1f64 + numerator / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            fraction *= c * d;
+        }
+        if (c * d - 1f64).abs() < 1e-12 {
+            break;
+        }
+    }
+    front * fraction / a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASELINE: [f64; 5] = [1f64, 2f64, 3f64, 4f64, 5f64];
+    const CANDIDATE: [f64; 5] = [2f64, 3f64, 4f64, 5f64, 6f64];
+
+    #[test]
+    fn it_runs_welchs_t_test() {
+        let outcome = Test::Welch.run(&BASELINE, &CANDIDATE).unwrap();
+        assert!((outcome.statistic - 1f64).abs() < 1e-9);
+        assert!((outcome.p_value - 0.346_594).abs() < 1e-4);
+        assert!((outcome.effect_size - 0.632_456).abs() < 1e-4);
+        assert!(Test::Welch.run(&[1f64, 1f64], &[1f64, 1f64]).is_err());
+    }
+
+    #[test]
+    fn it_runs_the_mann_whitney_u_test() {
+        let outcome = Test::MannWhitney.run(&BASELINE, &CANDIDATE).unwrap();
+        assert_eq!(outcome.statistic, 17f64);
+        assert!((outcome.p_value - 0.3976).abs() < 1e-3);
+        assert!((outcome.effect_size - 0.36).abs() < 1e-9);
+        assert!(Test::MannWhitney.run(&[1f64], &CANDIDATE).is_err());
+    }
+
+    #[test]
+    fn it_counts_ties_too_many_to_cube_as_integers() {
+        let mut baseline = vec![1f64; 1_400_000];
+        let mut candidate = baseline.clone();
+        baseline.push(2f64);
+        candidate.push(3f64);
+        let outcome = Test::MannWhitney.run(&baseline, &candidate).unwrap();
+        assert!(outcome.p_value.is_finite());
+    }
+
+    #[test]
+    fn it_parses_the_tests() {
+        assert_eq!(Test::parse("mann-whitney"), Ok(Test::MannWhitney));
+        assert!(Test::parse("anova").is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use deadline;
+use openssl::hash::{hash2, MessageDigest};
+use reqwest::Url;
+use signature::hmac;
+use std::env;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The payload hash sent for bodies that are streamed, and so cannot be hashed up front.
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Signs requests with aws signature version 4, so that api gateway, s3 and opensearch
+/// endpoints can be benchmarked directly. Every request is signed afresh, as the time and
+/// the body it is sent with are part of the signature.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SigV4 {
+    region: String,
+    service: String,
+    credentials: Credentials,
+}
+
+/// The keys requests are signed with, along with the token of temporary credentials.
+#[derive(Clone, Debug, PartialEq)]
+struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl SigV4 {
+    /// Parses the scope given as `region/service`, signing with the credentials aws tools
+    /// would find: those in the environment, or else the `AWS_PROFILE` (or default) profile
+    /// of the shared credentials file.
+    pub fn parse(scope: &str) -> Result<SigV4, String> {
+        match scope.split_once('/') {
+            Some((region, service)) if !region.is_empty() && !service.is_empty() => {
+                SigV4::new(region, service)
+            }
+            _ => Err(format!("Expected region/service but got {}", scope)),
+        }
+    }
+
+    /// Signs for the service in the region with the credentials aws tools would find.
+    pub fn new(region: &str, service: &str) -> Result<SigV4, String> {
+        Ok(SigV4 {
+            region: region.to_string(),
+            service: service.to_string(),
+            credentials: Credentials::ambient()?,
+        })
+    }
+
+    /// The headers that sign a request made at the time, with the payload, or without it
+    /// when the body is streamed.
+    pub fn sign(
+        &self,
+        method: &str,
+        url: &str,
+        payload: Option<&[u8]>,
+        time: SystemTime,
+    ) -> Result<Vec<(String, String)>, String> {
+        let url = Url::parse(url).map_err(|e| format!("Could not sign {}: {}", url, e))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(format!("Could not sign {}: it has no host", url)),
+        };
+        let payload_hash = match payload {
+            Some(payload) => hex(&sha256(payload)),
+            None => UNSIGNED_PAYLOAD.to_string(),
+        };
+        let mut headers = vec![
+            ("host".to_string(), host),
+            ("x-amz-content-sha256".to_string(), payload_hash.clone()),
+            ("x-amz-date".to_string(), amz_date(time)),
+        ];
+        if let Some(ref token) = self.credentials.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        let authorization = self.authorization(method, &url, &headers, &payload_hash);
+        // The http client sets the host itself.
+        headers.remove(0);
+        headers.push(("authorization".to_string(), authorization));
+        Ok(headers)
+    }
+
+    /// The `Authorization` header for a request with the headers, which are sorted by name
+    /// and include the `x-amz-date` the request is signed at.
+    fn authorization(
+        &self,
+        method: &str,
+        url: &Url,
+        headers: &[(String, String)],
+        payload_hash: &str,
+    ) -> String {
+        let date = &headers
+            .iter()
+            .find(|(name, _)| name == "x-amz-date")
+            .expect("Expected the request to be dated")
+            .1;
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method,
+            self.canonical_path(url),
+            canonical_query(url),
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!(
+            "{}/{}/{}/aws4_request",
+            &date[..8],
+            self.region,
+            self.service
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            date,
+            scope,
+            hex(&sha256(canonical_request.as_bytes()))
+        );
+
+        let secret = format!("AWS4{}", self.credentials.secret_access_key);
+        let key = [
+            &date[..8],
+            self.region.as_str(),
+            self.service.as_str(),
+            "aws4_request",
+        ]
+        .iter()
+        .fold(secret.into_bytes(), |key, part| {
+            hmac_sha256(&key, part.as_bytes())
+        });
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.credentials.access_key_id,
+            scope,
+            signed_headers,
+            hex(&hmac_sha256(&key, string_to_sign.as_bytes()))
+        )
+    }
+
+    /// The path as aws canonicalizes it, which is encoded twice over for every service but
+    /// s3.
+    fn canonical_path(&self, url: &Url) -> String {
+        let path = url.path();
+        if self.service == "s3" {
+            path.to_string()
+        } else {
+            path.split('/')
+                .map(|segment| encode(segment.as_bytes()))
+                .collect::<Vec<_>>()
+                .join("/")
+        }
+    }
+}
+
+impl Credentials {
+    fn ambient() -> Result<Credentials, String> {
+        if let (Ok(access_key_id), Ok(secret_access_key)) = (
+            env::var("AWS_ACCESS_KEY_ID"),
+            env::var("AWS_SECRET_ACCESS_KEY"),
+        ) {
+            return Ok(Credentials {
+                access_key_id,
+                secret_access_key,
+                session_token: env::var("AWS_SESSION_TOKEN").ok(),
+            });
+        }
+        let path = match env::var("AWS_SHARED_CREDENTIALS_FILE") {
+            Ok(path) => path,
+            Err(_) => format!("{}/.aws/credentials", env::var("HOME").unwrap_or_default()),
+        };
+        let profile = env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string());
+        let text = fs::read_to_string(&path).map_err(|e| {
+            format!(
+                "No aws credentials in the environment, and could not read {}: {}",
+                path, e
+            )
+        })?;
+        Credentials::from_profile(&text, &profile)
+            .ok_or_else(|| format!("No aws credentials for the {} profile in {}", profile, path))
+    }
+
+    /// Reads the keys of the profile from the text of a shared credentials file.
+    fn from_profile(text: &str, profile: &str) -> Option<Credentials> {
+        let mut section = None;
+        let mut values = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('[') && line.ends_with(']') {
+                section = Some(line[1..line.len() - 1].trim());
+            } else if section == Some(profile) {
+                if let Some((name, value)) = line.split_once('=') {
+                    values.push((name.trim(), value.trim()));
+                }
+            }
+        }
+        let value = |name: &str| {
+            values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        Some(Credentials {
+            access_key_id: value("aws_access_key_id")?,
+            secret_access_key: value("aws_secret_access_key")?,
+            session_token: value("aws_session_token"),
+        })
+    }
+}
+
+/// The query sorted by name and then value, with each encoded the way aws expects.
+fn canonical_query(url: &Url) -> String {
+    let mut params: Vec<(String, String)> = url
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            (encode(&decode(name)), encode(&decode(value)))
+        })
+        .collect();
+    params.sort();
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Percent encodes all but the unreserved characters.
+fn encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// The time in the basic iso 8601 format aws dates requests with, `20150830T123600Z`.
+fn amz_date(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = deadline::civil_from_days((seconds / 86_400) as i64);
+    let rest = seconds % 86_400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+fn sha256(bytes: &[u8]) -> Vec<u8> {
+    hash2(MessageDigest::sha256(), bytes)
+        .expect("Expected sha256 to hash")
+        .to_vec()
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    hmac(MessageDigest::sha256(), key, message)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn example() -> SigV4 {
+        SigV4 {
+            region: "us-east-1".to_string(),
+            service: "iam".to_string(),
+            credentials: Credentials {
+                access_key_id: "AKIDEXAMPLE".to_string(),
+                secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+                session_token: None,
+            },
+        }
+    }
+
+    #[test]
+    fn it_signs_the_aws_example() {
+        let url =
+            Url::parse("https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08").unwrap();
+        let headers = vec![
+            (
+                "content-type".to_string(),
+                "application/x-www-form-urlencoded; charset=utf-8".to_string(),
+            ),
+            ("host".to_string(), "iam.amazonaws.com".to_string()),
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+        ];
+        assert_eq!(
+            example().authorization("GET", &url, &headers, &hex(&sha256(b""))),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn it_signs_each_request_afresh() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_440_938_160);
+        let mut sigv4 = example();
+        sigv4.credentials.session_token = Some("token".to_string());
+        let headers = sigv4
+            .sign("POST", "http://localhost:8080/a b", Some(b"{}"), time)
+            .unwrap();
+        let names: Vec<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "x-amz-content-sha256",
+                "x-amz-date",
+                "x-amz-security-token",
+                "authorization"
+            ]
+        );
+        assert_eq!(headers[1].1, "20150830T123600Z");
+        assert!(headers[3]
+            .1
+            .contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token"));
+        let later = sigv4
+            .sign(
+                "POST",
+                "http://localhost:8080/a b",
+                Some(b"{}"),
+                time + Duration::from_secs(1),
+            )
+            .unwrap();
+        assert_ne!(headers[3], later[3]);
+        let streamed = sigv4.sign("POST", "http://localhost/", None, time).unwrap();
+        assert_eq!(streamed[0].1, UNSIGNED_PAYLOAD);
+    }
+
+    #[test]
+    fn it_canonicalizes_urls() {
+        let url = Url::parse("https://host/a%20b/c?b=2&a=x%2By&a=1&flag").unwrap();
+        assert_eq!(canonical_query(&url), "a=1&a=x%2By&b=2&flag=");
+        assert_eq!(example().canonical_path(&url), "/a%2520b/c");
+        assert_eq!(amz_date(UNIX_EPOCH), "19700101T000000Z");
+        assert_eq!(
+            amz_date(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "20000229T000000Z"
+        );
+    }
+
+    #[test]
+    fn it_reads_shared_credentials() {
+        let text = "[default]\naws_access_key_id = A\naws_secret_access_key = S\n\n\
+                    [ci]\naws_access_key_id=B\naws_secret_access_key=T\naws_session_token=X\n";
+        let ci = Credentials::from_profile(text, "ci").unwrap();
+        assert_eq!(ci.access_key_id, "B");
+        assert_eq!(ci.session_token, Some("X".to_string()));
+        assert_eq!(
+            Credentials::from_profile(text, "default")
+                .unwrap()
+                .secret_access_key,
+            "S"
+        );
+        assert_eq!(Credentials::from_profile(text, "missing"), None);
+    }
+}
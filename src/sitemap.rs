@@ -0,0 +1,121 @@
+use regex::Regex;
+use reqwest;
+use std::io::Read;
+
+/// How many nested sitemaps of a sitemap index are followed at most.
+const MAX_SITEMAPS: usize = 50;
+
+/// Fetches the pages listed in a sitemap, following the sitemaps of a sitemap index.
+/// Only pages matching the filter are kept, up to the limit.
+pub fn pages(url: &str, limit: usize, filter: Option<&Regex>) -> Result<Vec<String>, String> {
+    let xml = fetch(url)?;
+    let locations = if is_index(&xml) {
+        let mut pages = Vec::new();
+        for sitemap in locations(&xml).iter().take(MAX_SITEMAPS) {
+            pages.extend(locations(&fetch(sitemap)?));
+            if keep(&pages, filter).len() >= limit {
+                break;
+            }
+        }
+        pages
+    } else {
+        locations(&xml)
+    };
+
+    let mut pages = keep(&locations, filter);
+    pages.truncate(limit);
+    if pages.is_empty() {
+        return Err(format!("No pages to benchmark in {}", url));
+    }
+    Ok(pages)
+}
+
+/// Fetches the body of a page as text.
+pub fn fetch(url: &str) -> Result<String, String> {
+    let mut response = reqwest::get(url).map_err(|e| format!("Could not fetch {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Fetching {} returned {}", url, response.status()));
+    }
+    let mut xml = String::new();
+    response
+        .read_to_string(&mut xml)
+        .map_err(|e| format!("Could not read {}: {}", url, e))?;
+    Ok(xml)
+}
+
+fn keep(locations: &[String], filter: Option<&Regex>) -> Vec<String> {
+    locations
+        .iter()
+        .filter(|location| filter.is_none_or(|filter| filter.is_match(location)))
+        .cloned()
+        .collect()
+}
+
+/// Whether the sitemap lists other sitemaps rather than pages.
+fn is_index(xml: &str) -> bool {
+    xml.contains("<sitemapindex")
+}
+
+/// The urls in the `<loc>` elements of a sitemap.
+fn locations(xml: &str) -> Vec<String> {
+    let mut locations = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<loc>") {
+        rest = &rest[start + "<loc>".len()..];
+        let end = match rest.find("</loc>") {
+            Some(end) => end,
+            None => break,
+        };
+        let location = rest[..end]
+            .trim()
+            .trim_start_matches("<![CDATA[")
+            .trim_end_matches("]]>")
+            .replace("&amp;", "&")
+            .replace("&apos;", "'")
+            .replace("&quot;", "\"")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">");
+        locations.push(location);
+        rest = &rest[end..];
+    }
+    locations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SITEMAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://example.com/</loc></url>
+  <url>
+    <loc> https://example.com/blog/one?a=1&amp;b=2 </loc>
+    <lastmod>2024-01-01</lastmod>
+  </url>
+  <url><loc><![CDATA[https://example.com/blog/two]]></loc></url>
+</urlset>"#;
+
+    #[test]
+    fn it_reads_page_locations() {
+        assert_eq!(
+            locations(SITEMAP),
+            vec![
+                "https://example.com/",
+                "https://example.com/blog/one?a=1&b=2",
+                "https://example.com/blog/two",
+            ]
+        );
+        assert!(!is_index(SITEMAP));
+        assert!(is_index(
+            "<sitemapindex><sitemap><loc>a</loc></sitemap></sitemapindex>"
+        ));
+    }
+
+    #[test]
+    fn it_filters_pages() {
+        let filter = Regex::new("/blog/").unwrap();
+        let pages = keep(&locations(SITEMAP), Some(&filter));
+        assert_eq!(pages.len(), 2);
+        assert_eq!(keep(&locations(SITEMAP), None).len(), 3);
+    }
+}
@@ -0,0 +1,352 @@
+use futures::future::{self, Future};
+use hyper::server::Service;
+use hyper::Uri;
+use net2::{TcpBuilder, TcpStreamExt};
+use proxy::{Proxies, Proxy};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio_core::net;
+use tokio_core::reactor::Handle;
+
+/// Source addresses are shared round robin between every connection on every thread.
+static NEXT_BIND: AtomicUsize = AtomicUsize::new(0);
+
+/// Local ports are handed out in turn the same way.
+static NEXT_PORT: AtomicUsize = AtomicUsize::new(0);
+
+/// How the client's sockets are set up before they connect.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    bind: Vec<IpAddr>,
+    port_range: Option<(u16, u16)>,
+    nodelay: bool,
+    send_buffer: Option<usize>,
+    recv_buffer: Option<usize>,
+}
+
+impl Options {
+    /// Binds every connection to one of the addresses, taking turns between them.
+    pub fn with_bind(mut self, bind: Vec<IpAddr>) -> Self {
+        self.bind = bind;
+        self
+    }
+
+    /// Only connects from local ports within the inclusive range.
+    pub fn with_port_range(mut self, first: u16, last: u16) -> Self {
+        self.port_range = Some((first, last));
+        self
+    }
+
+    /// Disables Nagle's algorithm so small writes are sent straight away.
+    pub fn with_nodelay(mut self) -> Self {
+        self.nodelay = true;
+        self
+    }
+
+    /// Sets the size of the kernel's send buffer for each socket.
+    pub fn with_send_buffer(mut self, size: usize) -> Self {
+        self.send_buffer = Some(size);
+        self
+    }
+
+    /// Sets the size of the kernel's receive buffer for each socket.
+    pub fn with_recv_buffer(mut self, size: usize) -> Self {
+        self.recv_buffer = Some(size);
+        self
+    }
+
+    /// Creates a socket that is ready to connect to the address.
+    pub fn socket(&self, addr: &SocketAddr) -> io::Result<TcpBuilder> {
+        let builder = if addr.is_ipv4() {
+            TcpBuilder::new_v4()?
+        } else {
+            TcpBuilder::new_v6()?
+        };
+        let ip = self.next_bind(addr)?;
+        match (ip, self.port_range) {
+            (ip, Some(range)) => {
+                let ip = ip.unwrap_or(if addr.is_ipv4() {
+                    IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+                } else {
+                    IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+                });
+                bind_in_range(&builder, ip, range)?;
+            }
+            (Some(ip), None) => {
+                builder
+                    .bind(SocketAddr::new(ip, 0))
+                    .map_err(|e| not_local(ip, e))?;
+            }
+            (None, None) => (),
+        }
+        Ok(builder)
+    }
+
+    /// Opens a blocking connection to the address. The builder cannot tune a socket
+    /// before it connects, so the buffer sizes are only set once it has.
+    pub fn connect(&self, addr: &SocketAddr) -> io::Result<TcpStream> {
+        let stream = self.socket(addr)?.connect(addr)?;
+        self.tune(&stream)?;
+        Ok(stream)
+    }
+
+    /// Opens a connection to the address on the reactor.
+    pub fn connect_async(
+        &self,
+        addr: &SocketAddr,
+        handle: &Handle,
+    ) -> Box<dyn Future<Item = net::TcpStream, Error = io::Error>> {
+        let stream = self
+            .socket(addr)
+            .and_then(|socket| socket.to_tcp_stream())
+            .and_then(|stream| self.tune(&stream).map(|_| stream));
+        match stream {
+            Ok(stream) => net::TcpStream::connect_stream(stream, addr, handle),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+
+    /// Applies the socket options. Those opened on the reactor are tuned before they connect,
+    /// so the receive window can be scaled to the buffer, while blocking ones can only be
+    /// once they have connected.
+    fn tune(&self, socket: &TcpStream) -> io::Result<()> {
+        if self.nodelay {
+            socket.set_nodelay(true)?;
+        }
+        if let Some(size) = self.send_buffer {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer {
+            socket.set_recv_buffer_size(size)?;
+        }
+        Ok(())
+    }
+
+    /// Picks the next source address that can reach the address, if any were given.
+    fn next_bind(&self, addr: &SocketAddr) -> io::Result<Option<IpAddr>> {
+        if self.bind.is_empty() {
+            return Ok(None);
+        }
+        let candidates: Vec<&IpAddr> = self
+            .bind
+            .iter()
+            .filter(|ip| ip.is_ipv4() == addr.is_ipv4())
+            .collect();
+        if candidates.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("No address to bind that can reach {}", addr),
+            ));
+        }
+        let next = NEXT_BIND.fetch_add(1, Ordering::Relaxed);
+        Ok(Some(*candidates[next % candidates.len()]))
+    }
+}
+
+/// Connects to the proxy, tunnelling through it to https urls. The tunnel is set up before
+/// the connection is handed to the reactor, which is only waiting on this one request as
+/// each thread makes one at a time.
+fn connect_through(
+    options: &Options,
+    handle: &Handle,
+    proxy: &Proxy,
+    uri: &Uri,
+) -> io::Result<net::TcpStream> {
+    let mut stream = options.connect(&proxy.addr()?)?;
+    if uri.scheme() == Some("https") {
+        let host = uri
+            .host()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Url must have a host"))?;
+        proxy.tunnel(&mut stream, host, uri.port().unwrap_or(443))?;
+    }
+    net::TcpStream::from_stream(stream, handle)
+}
+
+/// Whether an error came from running out of local ports to connect from, which a connect
+/// says with `AddrNotAvailable` and a bind, or a range with every port tried, with
+/// `AddrInUse`. Binding an address that is not the host's is told apart by `not_local`.
+pub fn is_port_exhaustion(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::AddrInUse || e.kind() == io::ErrorKind::AddrNotAvailable
+}
+
+/// Whether the host has the address, so that sockets can be bound to it.
+pub fn is_local(ip: IpAddr) -> bool {
+    UdpSocket::bind(SocketAddr::new(ip, 0)).is_ok()
+}
+
+/// The error of binding an address, which is not one of running out of ports when it is
+/// for the address not being the host's.
+fn not_local(ip: IpAddr, e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::AddrNotAvailable {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Cannot bind {}, which is not an address of this host", ip),
+        )
+    } else {
+        e
+    }
+}
+
+/// Binds the next free port in the range, trying each port in it at most once.
+fn bind_in_range(builder: &TcpBuilder, ip: IpAddr, (first, last): (u16, u16)) -> io::Result<()> {
+    builder.reuse_address(true)?;
+    let size = usize::from(last - first) + 1;
+    for _ in 0..size {
+        let port = first as usize + NEXT_PORT.fetch_add(1, Ordering::Relaxed) % size;
+        match builder.bind(SocketAddr::new(ip, port as u16)) {
+            Ok(_) => return Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::AddrInUse => continue,
+            Err(e) => return Err(not_local(ip, e)),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AddrInUse,
+        format!("Every local port from {} to {} is in use", first, last),
+    ))
+}
+
+/// A hyper connector that opens its sockets with the options.
+pub struct Connector {
+    options: Options,
+    handle: Handle,
+    proxies: Proxies,
+}
+
+impl Connector {
+    pub fn new(options: Options, handle: &Handle) -> Connector {
+        Connector {
+            options,
+            handle: handle.clone(),
+            proxies: Proxies::default(),
+        }
+    }
+
+    /// Connects to the proxies instead of the hosts of the urls they are used for.
+    pub fn with_proxies(mut self, proxies: Proxies) -> Self {
+        self.proxies = proxies;
+        self
+    }
+}
+
+impl Service for Connector {
+    type Request = Uri;
+    type Response = net::TcpStream;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = net::TcpStream, Error = io::Error>>;
+
+    fn call(&self, uri: Uri) -> Self::Future {
+        if let Some(proxy) = self.proxies.for_url(uri.as_ref()) {
+            let (options, handle, proxy) =
+                (self.options.clone(), self.handle.clone(), proxy.clone());
+            // Hyper asks for a connection while it checks its pool for one, so the tunnel is
+            // only set up once the connection is waited on.
+            return Box::new(future::lazy(move || {
+                connect_through(&options, &handle, &proxy, &uri)
+            }));
+        }
+        let resolve = || -> io::Result<SocketAddr> {
+            let host = uri
+                .host()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Url must have a host"))?
+                .trim_matches(|c| c == '[' || c == ']');
+            let port = uri.port().unwrap_or(if uri.scheme() == Some("https") {
+                443
+            } else {
+                80
+            });
+            (host, port).to_socket_addrs()?.next().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No addresses for {}", host),
+                )
+            })
+        };
+        match resolve() {
+            Ok(addr) => self.options.connect_async(&addr, &self.handle),
+            Err(e) => Box::new(future::err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn it_binds_to_the_given_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let local: IpAddr = "127.0.0.2".parse().unwrap();
+
+        let stream = Options::default()
+            .with_bind(vec![local])
+            .connect(&addr)
+            .unwrap();
+        assert_eq!(stream.local_addr().unwrap().ip(), local);
+    }
+
+    #[test]
+    fn it_does_not_take_a_foreign_address_for_port_exhaustion() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // An address of the documentation range, which no host has.
+        let foreign: IpAddr = "192.0.2.1".parse().unwrap();
+        assert!(!is_local(foreign));
+        assert!(is_local("127.0.0.2".parse().unwrap()));
+
+        for options in &[
+            Options::default().with_bind(vec![foreign]),
+            Options::default().with_bind(vec![foreign]).with_port_range(20000, 20010),
+        ] {
+            let error = options.connect(&addr).unwrap_err();
+            assert!(!is_port_exhaustion(&error));
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn it_binds_ports_in_the_range() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        let last = port.saturating_add(100);
+        let options = Options::default().with_port_range(port, last);
+        let local = options.connect(&addr).unwrap().local_addr().unwrap().port();
+        assert!(local > port && local <= last);
+
+        let options = Options::default().with_port_range(port, port);
+        let error = options.connect(&addr).unwrap_err();
+        assert!(is_port_exhaustion(&error));
+    }
+
+    #[test]
+    fn it_tunes_the_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let stream = Options::default()
+            .with_nodelay()
+            .with_send_buffer(64 * 1024)
+            .with_recv_buffer(128 * 1024)
+            .connect(&addr)
+            .unwrap();
+        assert!(stream.nodelay().unwrap());
+        // Linux doubles the size asked for to leave room for its own bookkeeping.
+        assert!(stream.send_buffer_size().unwrap() >= 64 * 1024);
+        assert!(stream.recv_buffer_size().unwrap() >= 128 * 1024);
+    }
+
+    #[test]
+    fn it_only_binds_addresses_of_the_same_family() {
+        let addr: SocketAddr = "127.0.0.1:80".parse().unwrap();
+        let options = Options::default().with_bind(vec!["::1".parse().unwrap()]);
+        assert!(options.next_bind(&addr).is_err());
+
+        let options = Options::default();
+        assert_eq!(options.next_bind(&addr).unwrap(), None);
+    }
+}
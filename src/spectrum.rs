@@ -0,0 +1,172 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+/// The percentiles summarized above the spectrum, as wrk2 prints them.
+const SUMMARY: [f64; 8] = [50.0, 75.0, 90.0, 99.0, 99.9, 99.99, 99.999, 100.0];
+
+/// How many percentiles are reported each time the distance to 100% halves.
+const TICKS_PER_HALF_DISTANCE: f64 = 5.0;
+
+/// The precision hdr histograms keep with three significant digits.
+const SUB_BUCKETS: u64 = 2048;
+
+/// Prints the latencies the way wrk2 does, as a summary of the common percentiles followed
+/// by the detailed percentile spectrum, so tools that parse wrk2's output can read it.
+pub fn spectrum(mut durations: Vec<Duration>) -> String {
+    let mut out = String::new();
+    if durations.is_empty() {
+        return out;
+    }
+    durations.sort();
+    let ms: Vec<f64> = durations
+        .iter()
+        .map(|d| d.as_secs_f64() * 1_000f64)
+        .collect();
+    let count = ms.len();
+
+    writeln!(
+        out,
+        "  Latency Distribution (HdrHistogram - Recorded Latency)"
+    )
+    .unwrap();
+    for &percentile in SUMMARY.iter() {
+        let value = ms[index_at(percentile, count)];
+        writeln!(out, "{:7.3}%{:>10}", percentile, format_ms(value)).unwrap();
+    }
+    writeln!(out).unwrap();
+
+    writeln!(out, "  Detailed Percentile spectrum:").unwrap();
+    writeln!(
+        out,
+        "{:>12} {:>12} {:>12} {:>12}\n",
+        "Value", "Percentile", "TotalCount", "1/(1-Percentile)"
+    )
+    .unwrap();
+    let mut percentile = 0f64;
+    loop {
+        let index = index_at(percentile, count);
+        // Every value equal to the one reported counts toward the total, as in a histogram.
+        let total = ms.iter().rposition(|&v| v <= ms[index]).unwrap_or(index) + 1;
+        writeln!(
+            out,
+            "{:12.3} {:12.6} {:12} {:12.2}",
+            ms[index],
+            percentile / 100f64,
+            total,
+            1f64 / (1f64 - percentile / 100f64)
+        )
+        .unwrap();
+        if total == count {
+            if percentile < 100f64 {
+                writeln!(out, "{:12.3} {:12.6} {:12}", ms[count - 1], 1f64, count).unwrap();
+            }
+            break;
+        }
+        let half_distance = (100f64 / (100f64 - percentile)).log2().floor() + 1f64;
+        percentile += 100f64 / (TICKS_PER_HALF_DISTANCE * 2f64.powf(half_distance));
+    }
+
+    let mean = ms.iter().sum::<f64>() / count as f64;
+    let stddev = if count > 1 {
+        (ms.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+    } else {
+        0f64
+    };
+    writeln!(
+        out,
+        "#[Mean    = {:12.3}, StdDeviation   = {:12.3}]",
+        mean, stddev
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "#[Max     = {:12.3}, Total count    = {:12}]",
+        ms[count - 1],
+        count
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "#[Buckets = {:12}, SubBuckets     = {:12}]",
+        buckets(durations[count - 1]),
+        SUB_BUCKETS
+    )
+    .unwrap();
+    writeln!(out, "{}", "-".repeat(58)).unwrap();
+    out
+}
+
+/// The index of the value at the percentile of the sorted values.
+fn index_at(percentile: f64, count: usize) -> usize {
+    let rank = (percentile / 100f64 * count as f64).ceil() as usize;
+    rank.max(1).min(count) - 1
+}
+
+/// How many buckets a histogram of microseconds needs to hold the longest latency.
+fn buckets(max: Duration) -> u32 {
+    let micros = max.as_secs() * 1_000_000 + u64::from(max.subsec_micros());
+    let mut untrackable = SUB_BUCKETS;
+    let mut buckets = 1;
+    while untrackable <= micros {
+        untrackable <<= 1;
+        buckets += 1;
+    }
+    buckets
+}
+
+/// Formats a latency with the unit wrk uses for it.
+fn format_ms(ms: f64) -> String {
+    if ms < 1f64 {
+        format!("{:.2}us", ms * 1_000f64)
+    } else if ms < 1_000f64 {
+        format!("{:.2}ms", ms)
+    } else {
+        format!("{:.2}s", ms / 1_000f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&ms| Duration::from_millis(ms)).collect()
+    }
+
+    #[test]
+    fn it_summarizes_the_common_percentiles() {
+        let out = spectrum(millis(&(1..=100).collect::<Vec<u64>>()));
+        assert!(out.contains(" 50.000%   50.00ms\n"));
+        assert!(out.contains(" 90.000%   90.00ms\n"));
+        assert!(out.contains("100.000%  100.00ms\n"));
+    }
+
+    #[test]
+    fn it_prints_the_detailed_spectrum() {
+        let out = spectrum(millis(&[1, 2, 3, 4]));
+        let lines: Vec<&str> = out.lines().collect();
+        let start = lines
+            .iter()
+            .position(|line| line.contains("Detailed Percentile spectrum"))
+            .unwrap();
+        assert_eq!(
+            lines[start + 3],
+            "       1.000     0.000000            1         1.00"
+        );
+        assert_eq!(lines.last(), Some(&"-".repeat(58).as_str()));
+        assert!(out.contains("       4.000     1.000000            4\n"));
+        assert!(out.contains("#[Max     =        4.000, Total count    =            4]"));
+    }
+
+    #[test]
+    fn it_is_empty_without_latencies() {
+        assert_eq!(spectrum(Vec::new()), "");
+    }
+
+    #[test]
+    fn it_counts_the_buckets_needed() {
+        assert_eq!(buckets(Duration::from_micros(100)), 1);
+        assert_eq!(buckets(Duration::from_micros(2048)), 2);
+        assert_eq!(buckets(Duration::from_secs(1)), 10);
+    }
+}
@@ -0,0 +1,53 @@
+/// Removes every complete event from the buffer and returns the size of each. An event
+/// ends with a blank line; anything after the last blank line is left for the next read.
+/// Blocks made only of comments are keep alives rather than events and are skipped.
+pub fn drain_events(buffer: &mut Vec<u8>) -> Vec<u64> {
+    let mut events = Vec::new();
+    while let Some((end, separator)) = find_event_end(buffer) {
+        let rest = buffer.split_off(end + separator);
+        let block = ::std::mem::replace(buffer, rest);
+        let block = &block[..end];
+        let is_event = block
+            .split(|&b| b == b'\n' || b == b'\r')
+            .any(|line| !line.is_empty() && line[0] != b':');
+        if is_event {
+            events.push(block.len() as u64);
+        }
+    }
+    events
+}
+
+fn find_event_end(buffer: &[u8]) -> Option<(usize, usize)> {
+    const SEPARATORS: [&[u8]; 3] = [b"\r\n\r\n", b"\n\n", b"\r\r"];
+    SEPARATORS
+        .iter()
+        .filter_map(|separator| {
+            buffer
+                .windows(separator.len())
+                .position(|window| window == *separator)
+                .map(|end| (end, separator.len()))
+        })
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_drains_complete_events() {
+        let mut buffer = b"data: one\n\ndata: two\n\ndata: thr".to_vec();
+        assert_eq!(drain_events(&mut buffer), vec![9, 9]);
+        assert_eq!(buffer, b"data: thr".to_vec());
+
+        buffer.extend_from_slice(b"ee\r\n\r\n");
+        assert_eq!(drain_events(&mut buffer), vec![11]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn it_skips_comments() {
+        let mut buffer = b": keep alive\n\nevent: ping\ndata: 1\n\n".to_vec();
+        assert_eq!(drain_events(&mut buffer), vec![19]);
+    }
+}
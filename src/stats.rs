@@ -1,8 +1,18 @@
 use std::time::Duration;
 use std::{cmp, fmt};
-use chart::Chart;
+use bootstrap;
+use chart::{self, Chart};
 use content_length::ContentLength;
-use std::collections::HashMap;
+use failure::Failure;
+use metadata::Metadata;
+use monitor::Usage;
+use throughput::{Rates, Spread};
+use tls;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::{Map, Value};
+use significance::{Outcome, Test};
+use std::collections::{BTreeMap, HashMap};
 
 trait ToMilliseconds {
     fn to_ms(&self) -> f64;
@@ -57,32 +67,406 @@ mod millisecond_tests {
 /// A single datum or "fact" about the requests
 #[derive(Debug)]
 pub struct Fact {
-    status: u16,
+    status: Option<u16>,
+    grpc_status: Option<u16>,
     duration: Duration,
     content_length: ContentLength,
+    connect: Option<Duration>,
+    failure: Option<Failure>,
+    resumed: Option<bool>,
+    sent: ContentLength,
+    continued: Option<Duration>,
+    authentication: Option<Duration>,
+    iteration: Option<Duration>,
+    ramping_down: bool,
+    version: Option<&'static str>,
+    tls_version: Option<&'static str>,
+    cipher: Option<String>,
+    alpn: Option<String>,
+    expires_in: Option<i64>,
+    pushed: u32,
+    pushed_bytes: ContentLength,
+    target: Option<usize>,
+    worker: Option<usize>,
+    group: Option<String>,
+    cache_hit: Option<bool>,
 }
 
 impl Fact {
     pub fn record(content_length: ContentLength, status: u16, duration: Duration) -> Fact {
         Fact {
             duration,
-            status,
+            status: Some(status),
+            grpc_status: None,
             content_length,
+            connect: None,
+            failure: None,
+            resumed: None,
+            sent: ContentLength::zero(),
+            continued: None,
+            authentication: None,
+            iteration: None,
+            ramping_down: false,
+            version: None,
+            tls_version: None,
+            cipher: None,
+            alpn: None,
+            expires_in: None,
+            pushed: 0,
+            pushed_bytes: ContentLength::zero(),
+            target: None,
+            worker: None,
+            group: None,
+            cache_hit: None,
         }
     }
+
+    /// Records something that has no status or content, such as opening a connection.
+    pub fn elapsed(duration: Duration) -> Fact {
+        Fact {
+            status: None,
+            ..Fact::record(ContentLength::zero(), 0, duration)
+        }
+    }
+
+    /// Attaches the grpc status the call ended with, which is counted apart from the http
+    /// status of the response that carried it.
+    pub fn with_grpc_status(mut self, status: u16) -> Fact {
+        self.grpc_status = Some(status);
+        self
+    }
+
+    /// Attaches the time it took to set up the connection this fact was the first to use.
+    pub fn with_connect(mut self, connect: Duration) -> Fact {
+        self.connect = Some(connect);
+        self
+    }
+
+    /// Marks the request as failed despite having received a response.
+    pub fn with_failure(mut self, failure: Failure) -> Fact {
+        self.failure = Some(failure);
+        self
+    }
+
+    /// Attaches the size of the body that was uploaded with the request.
+    pub fn with_sent(mut self, sent: ContentLength) -> Fact {
+        self.sent = sent;
+        self
+    }
+
+    /// Attaches the time the server took to answer `Expect: 100-continue` with a go ahead.
+    pub fn with_continue(mut self, continued: Duration) -> Fact {
+        self.continued = Some(continued);
+        self
+    }
+
+    /// Attaches the time an authentication handshake took before the request could be made,
+    /// which is not counted in its duration.
+    pub fn with_authentication(mut self, authentication: Duration) -> Fact {
+        self.authentication = Some(authentication);
+        self
+    }
+
+    /// Attaches the time the iteration of the virtual user that this fact's request ended
+    /// took, from the start of its first request.
+    pub fn with_iteration(mut self, iteration: Duration) -> Fact {
+        self.iteration = Some(iteration);
+        self
+    }
+
+    /// Marks the request as made while the run was ramping down.
+    pub fn with_ramping_down(mut self) -> Fact {
+        self.ramping_down = true;
+        self
+    }
+
+    /// Whether the request was made while the run was ramping down.
+    pub fn is_ramping_down(&self) -> bool {
+        self.ramping_down
+    }
+
+    /// Attaches the http version the server answered with.
+    pub fn with_version(mut self, version: &'static str) -> Fact {
+        self.version = Some(version);
+        self
+    }
+
+    /// Attaches the tls version negotiated by the connection this fact was the first to use.
+    pub fn with_tls_version(mut self, version: &'static str) -> Fact {
+        self.tls_version = Some(version);
+        self
+    }
+
+    /// Attaches the cipher suite negotiated by the connection this fact was the first to use.
+    pub fn with_cipher(mut self, cipher: String) -> Fact {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Attaches the protocol the server chose by alpn for the connection this fact was the
+    /// first to use.
+    pub fn with_alpn(mut self, alpn: String) -> Fact {
+        self.alpn = Some(alpn);
+        self
+    }
+
+    /// Attaches how many seconds were left before the certificate of the server expired, as
+    /// it was presented to the connection this fact was the first to use.
+    pub fn with_expires_in(mut self, seconds: i64) -> Fact {
+        self.expires_in = Some(seconds);
+        self
+    }
+
+    /// Attaches the streams the server pushed alongside the response, and the size of their
+    /// bodies, none of which is counted as the response's own.
+    pub fn with_pushed(mut self, pushed: u32, bytes: ContentLength) -> Fact {
+        self.pushed = pushed;
+        self.pushed_bytes = bytes;
+        self
+    }
+
+    /// Attaches which of the urls the request went to.
+    pub fn with_target(mut self, target: usize) -> Fact {
+        self.target = Some(target);
+        self
+    }
+
+    /// Whether the request was given up on for taking longer than it was allowed.
+    fn is_timeout(&self) -> bool {
+        self.failure == Some(Failure::Timeout)
+    }
+
+    /// Whether the request failed or the server answered with an error, over http or grpc.
+    fn is_error(&self) -> bool {
+        self.failure.is_some()
+            || self.status.is_some_and(|status| status >= 400)
+            || self.grpc_status.is_some_and(|status| status != 0)
+    }
+
+    /// Attaches the worker thread that made the request.
+    pub fn with_worker(mut self, worker: usize) -> Fact {
+        self.worker = Some(worker);
+        self
+    }
+
+    /// Attaches the group the response falls into, such as the backend that served it.
+    pub fn with_group(mut self, group: String) -> Fact {
+        self.group = Some(group);
+        self
+    }
+
+    /// Marks the response as served from a cache in front of the server, or not.
+    pub fn with_cache_hit(mut self, hit: bool) -> Fact {
+        self.cache_hit = Some(hit);
+        self
+    }
+
+    /// How long the request took.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// How long the request took, unless it timed out, as timeouts only say how long the
+    /// requests were allowed and are counted apart from the latencies.
+    pub fn latency(&self) -> Option<Duration> {
+        if self.is_timeout() {
+            None
+        } else {
+            Some(self.duration)
+        }
+    }
+
+    /// The size of the body of the response.
+    pub fn content_length(&self) -> &ContentLength {
+        &self.content_length
+    }
+
+    /// The class of the status of the response, such as `5xx`, or `failed` when there was
+    /// no response.
+    pub fn class(&self) -> &'static str {
+        match self.status.map(|status| status / 100) {
+            Some(1) => "1xx",
+            Some(2) => "2xx",
+            Some(3) => "3xx",
+            Some(4) => "4xx",
+            Some(5) => "5xx",
+            _ => "failed",
+        }
+    }
+
+    /// The worker thread that made the request, if it is known.
+    pub fn worker(&self) -> Option<usize> {
+        self.worker
+    }
+
+    /// What the request came to: its status, or how it failed when it was not for its
+    /// status.
+    pub fn outcome(&self) -> String {
+        match (self.failure, self.status) {
+            (Some(Failure::Status), Some(status)) | (None, Some(status)) => status.to_string(),
+            (Some(failure), _) => failure.to_string(),
+            (None, None) => "no response".to_string(),
+        }
+    }
+
+    /// A copy of the fact with only what the headline numbers of a summary are of, for
+    /// summaries of a run so far.
+    pub fn headline(&self) -> Fact {
+        Fact {
+            status: self.status,
+            grpc_status: self.grpc_status,
+            failure: self.failure,
+            sent: ContentLength::new(self.sent.bytes()),
+            ..Fact::record(ContentLength::new(self.content_length.bytes()), 0, self.duration)
+        }
+    }
+
+    /// Marks the fact as a tls handshake, either resuming an earlier session or not.
+    pub fn with_resumed(mut self, resumed: bool) -> Fact {
+        self.resumed = Some(resumed);
+        self
+    }
+}
+
+/// Facts are passed between processes as json, with durations in nanoseconds.
+impl Fact {
+    pub fn to_json(&self) -> Value {
+        let mut json = Map::new();
+        let nanos = |d: Duration| Value::from(d.as_secs() * 1_000_000_000 + u64::from(d.subsec_nanos()));
+        json.insert("duration".to_string(), nanos(self.duration));
+        json.insert(
+            "content_length".to_string(),
+            Value::from(self.content_length.bytes()),
+        );
+        json.insert("sent".to_string(), Value::from(self.sent.bytes()));
+        if let Some(status) = self.status {
+            json.insert("status".to_string(), Value::from(status));
+        }
+        if let Some(status) = self.grpc_status {
+            json.insert("grpc_status".to_string(), Value::from(status));
+        }
+        if let Some(connect) = self.connect {
+            json.insert("connect".to_string(), nanos(connect));
+        }
+        if let Some(continued) = self.continued {
+            json.insert("continued".to_string(), nanos(continued));
+        }
+        if let Some(authentication) = self.authentication {
+            json.insert("authentication".to_string(), nanos(authentication));
+        }
+        if let Some(iteration) = self.iteration {
+            json.insert("iteration".to_string(), nanos(iteration));
+        }
+        if self.ramping_down {
+            json.insert("ramping_down".to_string(), Value::from(true));
+        }
+        if let Some(failure) = self.failure {
+            json.insert("failure".to_string(), Value::from(failure.key()));
+        }
+        if let Some(resumed) = self.resumed {
+            json.insert("resumed".to_string(), Value::from(resumed));
+        }
+        if let Some(version) = self.version {
+            json.insert("version".to_string(), Value::from(version));
+        }
+        if let Some(version) = self.tls_version {
+            json.insert("tls_version".to_string(), Value::from(version));
+        }
+        if let Some(ref cipher) = self.cipher {
+            json.insert("cipher".to_string(), Value::from(cipher.as_str()));
+        }
+        if let Some(ref alpn) = self.alpn {
+            json.insert("alpn".to_string(), Value::from(alpn.as_str()));
+        }
+        if let Some(seconds) = self.expires_in {
+            json.insert("certificate_expires_in".to_string(), Value::from(seconds));
+        }
+        if self.pushed > 0 {
+            json.insert("pushed".to_string(), Value::from(self.pushed));
+            json.insert("pushed_bytes".to_string(), Value::from(self.pushed_bytes.bytes()));
+        }
+        if let Some(target) = self.target {
+            json.insert("target".to_string(), Value::from(target as u64));
+        }
+        if let Some(worker) = self.worker {
+            json.insert("worker".to_string(), Value::from(worker as u64));
+        }
+        if let Some(ref group) = self.group {
+            json.insert("group".to_string(), Value::from(group.as_str()));
+        }
+        if let Some(hit) = self.cache_hit {
+            json.insert("cache_hit".to_string(), Value::from(hit));
+        }
+        Value::Object(json)
+    }
+
+    pub fn from_json(json: &Value) -> Result<Fact, String> {
+        let nanos = |key: &str| {
+            json.get(key)
+                .and_then(Value::as_u64)
+                .map(|n| Duration::new(n / 1_000_000_000, (n % 1_000_000_000) as u32))
+        };
+        let bytes = |key: &str| ContentLength::new(json.get(key).and_then(Value::as_u64).unwrap_or(0));
+        Ok(Fact {
+            status: json.get("status").and_then(Value::as_u64).map(|s| s as u16),
+            grpc_status: json.get("grpc_status").and_then(Value::as_u64).map(|s| s as u16),
+            duration: nanos("duration").ok_or_else(|| format!("No duration in {}", json))?,
+            content_length: bytes("content_length"),
+            connect: nanos("connect"),
+            failure: match json.get("failure").and_then(Value::as_str) {
+                Some(failure) => Some(failure.parse()?),
+                None => None,
+            },
+            resumed: json.get("resumed").and_then(Value::as_bool),
+            sent: bytes("sent"),
+            continued: nanos("continued"),
+            authentication: nanos("authentication"),
+            iteration: nanos("iteration"),
+            ramping_down: json.get("ramping_down").and_then(Value::as_bool).unwrap_or(false),
+            version: match json.get("version").and_then(Value::as_str) {
+                Some("HTTP/0.9") => Some("HTTP/0.9"),
+                Some("HTTP/1.0") => Some("HTTP/1.0"),
+                Some("HTTP/1.1") => Some("HTTP/1.1"),
+                Some("HTTP/2") => Some("HTTP/2"),
+                Some(version) => return Err(format!("Unknown http version {}", version)),
+                None => None,
+            },
+            tls_version: match json.get("tls_version").and_then(Value::as_str) {
+                Some(version) => Some(tls::Version::parse(version)?.name()),
+                None => None,
+            },
+            cipher: json.get("cipher").and_then(Value::as_str).map(String::from),
+            alpn: json.get("alpn").and_then(Value::as_str).map(String::from),
+            expires_in: json.get("certificate_expires_in").and_then(Value::as_i64),
+            pushed: json.get("pushed").and_then(Value::as_u64).unwrap_or(0) as u32,
+            pushed_bytes: bytes("pushed_bytes"),
+            target: json.get("target").and_then(Value::as_u64).map(|t| t as usize),
+            worker: json.get("worker").and_then(Value::as_u64).map(|w| w as usize),
+            group: json.get("group").and_then(Value::as_str).map(String::from),
+            cache_hit: json.get("cache_hit").and_then(Value::as_bool),
+        })
+    }
 }
 
+#[derive(Debug)]
 struct DurationStats {
     sorted: Vec<Duration>,
 }
 
 impl DurationStats {
     fn from_facts(facts: &[Fact]) -> DurationStats {
-        let mut sorted: Vec<Duration> = facts.iter().map(|f| f.duration).collect();
+        Self::new(facts.iter().map(|f| f.duration).collect())
+    }
+
+    fn new(mut sorted: Vec<Duration>) -> DurationStats {
         sorted.sort();
         Self { sorted }
     }
 
+    fn is_empty(&self) -> bool {
+        self.sorted.is_empty()
+    }
+
     fn max(&self) -> Option<Duration> {
         self.sorted.last().cloned()
     }
@@ -93,7 +477,7 @@ impl DurationStats {
 
     fn median(&self) -> Duration {
         let mid = self.sorted.len() / 2;
-        if self.sorted.len() % 2 == 0 {
+        if self.sorted.len().is_multiple_of(2) {
             // even
             (self.sorted[mid - 1] + self.sorted[mid]) / 2
         } else {
@@ -119,19 +503,37 @@ impl DurationStats {
     }
 
     fn latency_histogram(&self) -> Vec<u32> {
-        let mut latency_histogram = vec![0; 100];
+        match self.max() {
+            Some(max) => self.histogram_up_to(max),
+            None => vec![0; 100],
+        }
+    }
 
-        if let Some(max) = self.max() {
-            let bin_size = max.to_ms() / 100.;
+    /// The histogram of the durations up to the limit, leaving out those above it.
+    fn histogram_up_to(&self, limit: Duration) -> Vec<u32> {
+        let mut latency_histogram = vec![0; 100];
+        let bin_size = limit.to_ms() / 100.;
 
-            for duration in &self.sorted {
-                let index = (duration.to_ms() / bin_size) as usize;
-                latency_histogram[cmp::min(index, 49)] += 1;
-            }
+        for duration in self.sorted.iter().filter(|&&duration| duration <= limit) {
+            let index = (duration.to_ms() / bin_size) as usize;
+            latency_histogram[cmp::min(index, 49)] += 1;
         }
         latency_histogram
     }
 
+    /// Counts the durations into buckets of the width from zero, those past the last bucket
+    /// falling into it.
+    fn fixed_histogram(&self, width: Duration) -> Vec<u32> {
+        let max = self.max().unwrap_or_default();
+        let buckets = cmp::min((max.as_nanos() / width.as_nanos()) as usize + 1, MAX_BUCKETS);
+        let mut histogram = vec![0; buckets];
+        for duration in &self.sorted {
+            let index = (duration.as_nanos() / width.as_nanos()) as usize;
+            histogram[cmp::min(index, buckets - 1)] += 1;
+        }
+        histogram
+    }
+
     fn percentiles(&self) -> Vec<Duration> {
         (0..100)
             .map(|n| {
@@ -143,11 +545,87 @@ impl DurationStats {
             .collect()
     }
 
+    /// The nearest ranked duration at or below which the percentage of durations fall.
+    fn percentile(&self, percentage: f64) -> Duration {
+        // Rounded first so that 99.9% of 1000 is 999 rather than a hair over it.
+        let rank = (percentage / 100f64 * self.sorted.len() as f64 * 1e6).round() / 1e6;
+        let rank = rank.ceil() as usize;
+        self.sorted[cmp::min(cmp::max(rank, 1), self.sorted.len()) - 1]
+    }
+
     fn total(&self) -> Duration {
         self.sorted.iter().sum()
     }
 }
 
+/// The version of the json summaries are written as, raised whenever a field is removed or
+/// changes its meaning, while fields may be added within a version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The percentiles reported as exact numbers, to be copied out of the report.
+const PERCENTILE_TABLE: [f64; 6] = [50.0, 75.0, 90.0, 95.0, 99.0, 99.9];
+
+/// The share of the concurrency asked for below which the requests in flight are warned
+/// about, as the client could not keep its workers busy.
+const KEPT_BUSY: f64 = 0.8;
+
+/// The percentiles of the detailed distribution in tenths of a percent: every tenth up to
+/// p90, every percent up to p99 and every tenth of a percent beyond.
+fn distribution_tenths() -> Vec<u32> {
+    (1..=9)
+        .map(|tens| tens * 100)
+        .chain((91..=99).map(|percent| percent * 10))
+        .chain(991..=1000)
+        .collect()
+}
+
+/// The most latencies clamped out of the charts that are listed one by one.
+const CLAMPED_LISTED: usize = 10;
+
+/// The most buckets of a fixed width the latencies are counted into.
+const MAX_BUCKETS: usize = 100;
+
+/// The most bars the responses by status over time are charted with.
+const CLASS_BARS: usize = 100;
+
+/// How close to expiring a server's certificate is warned about when no window is given.
+pub const DEFAULT_EXPIRY_WINDOW: Duration = Duration::from_secs(30 * 86_400);
+
+/// A mean of the latencies other than the arithmetic, which a few outliers can dominate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mean {
+    /// The mean with this percentage of the latencies left out at each end.
+    Trimmed(f64),
+    /// The exponent of the mean of the logarithms, which weighs a latency by its ratio to
+    /// the others rather than its distance.
+    Geometric,
+}
+
+impl Mean {
+    /// Parses a mean given as `trimmed:5%` or `geometric`.
+    pub fn parse(mean: &str) -> Result<Mean, String> {
+        let invalid = || format!("Expected trimmed:PERCENT% or geometric but got {}", mean);
+        match mean.split_once(':') {
+            _ if mean == "geometric" => Ok(Mean::Geometric),
+            Some(("trimmed", percent)) => percent
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .ok()
+                .filter(|&percent| (0f64..50f64).contains(&percent))
+                .map(Mean::Trimmed)
+                .ok_or_else(invalid),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn name(&self) -> String {
+        match *self {
+            Mean::Trimmed(percent) => format!("trimmed_{}", percent),
+            Mean::Geometric => "geometric".to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum ChartSize {
     None,
@@ -166,108 +644,1183 @@ pub struct Summary {
     stddev: Duration,
     count: u32,
     content_length: ContentLength,
+    sent: ContentLength,
+    upload_rate: Option<f64>,
     percentiles: Vec<Duration>,
+    percentile_table: Vec<(f64, Duration)>,
+    /// The durations of the requests that did not time out, which the latencies are of.
+    latencies: DurationStats,
+    timeouts: u32,
+    /// The percentiles with the timeouts counted at the timeout, when asked for.
+    pessimistic: Option<(Duration, Vec<(f64, Duration)>)>,
     latency_histogram: Vec<u32>,
+    /// The width of the buckets of the histogram, when they are not a share of the longest.
+    histogram_bucket: Option<Duration>,
     status_counts: HashMap<u16, u32>,
+    grpc_status_counts: HashMap<u16, u32>,
+    failure_counts: HashMap<Failure, u32>,
+    error_counts: Vec<(String, u32)>,
+    version_counts: HashMap<&'static str, u32>,
+    tls_version_counts: HashMap<&'static str, u32>,
+    cipher_counts: HashMap<String, u32>,
+    alpn_counts: HashMap<String, u32>,
+    http2_asked: bool,
+    http2_connections: u32,
+    pushed: u32,
+    pushed_bytes: ContentLength,
+    expires_in: Option<i64>,
+    expiry_window: Duration,
+    connections: Option<PhaseSummary>,
+    continues: Option<PhaseSummary>,
+    authentications: Option<PhaseSummary>,
+    iterations: Option<PhaseSummary>,
+    handshakes: Option<HandshakeSummary>,
+    usage: Option<Usage>,
+    metadata: Option<Metadata>,
+    workers: Vec<WorkerSummary>,
+    per_worker: bool,
+    groups: Vec<GroupSummary>,
+    group_header: Option<String>,
+    slowest_groups: bool,
+    targets: Vec<TargetSummary>,
+    per_url: bool,
+    cache: Option<CacheSummary>,
+    sizes: Option<SizeSummary>,
+    throughput: Option<Rates>,
+    confidence: Option<Confidence>,
+    means: Vec<(Mean, Duration)>,
+    distribution: bool,
+    /// The concurrency asked for, and the requests per second that were achieved.
+    concurrency: Option<(usize, f64)>,
+    sampled_from: Option<u32>,
+    proxies: Vec<String>,
     chart_size: ChartSize,
+    chart_style: chart::Style,
+    chart_height: Option<u32>,
+    /// The percentile past which latencies are left out of the scale of the charts.
+    chart_clamp: Option<f64>,
 }
 
-impl Summary {
-    /// From a set of facts, calculate the statistics.
-    pub fn from_facts(facts: &[Fact]) -> Summary {
-        if facts.is_empty() {
-            return Summary::zero();
-        }
-        let content_length = Self::total_content_length(&facts);
-        let count = facts.len() as u32;
-        let status_counts = facts.iter().fold(
-            HashMap::with_capacity(699),
-            |mut acc: HashMap<u16, u32>, fact| {
-                let count = if let Some(current) = acc.get(&fact.status) {
-                    current + 1
-                } else {
-                    1
-                };
-                acc.insert(fact.status, count);
-                acc
-            },
-        );
+/// The statistics around one phase of some of the requests, such as setting up the
+/// connections that engines hold open across many requests.
+#[derive(Debug)]
+struct PhaseSummary {
+    count: u32,
+    average: Duration,
+    max: Duration,
+}
 
-        Summary {
-            count,
-            content_length,
-            status_counts,
-            ..Summary::from_durations(&DurationStats::from_facts(&facts))
+impl PhaseSummary {
+    fn new(durations: Vec<Duration>) -> Option<PhaseSummary> {
+        let stats = DurationStats::new(durations);
+        if stats.is_empty() {
+            return None;
         }
+        Some(PhaseSummary {
+            count: stats.sorted.len() as u32,
+            average: stats.average(),
+            max: stats.max().expect("Checked for emptiness"),
+        })
     }
+}
 
-    pub fn with_chart_size(mut self, size: ChartSize) -> Self {
-        self.chart_size = size;
-        self
-    }
+/// Splits tls handshakes between those that resumed a session and those that did not.
+#[derive(Debug)]
+struct HandshakeSummary {
+    full: u32,
+    full_average: Duration,
+    resumed: u32,
+    resumed_average: Duration,
+}
 
-    fn from_durations(stats: &DurationStats) -> Summary {
-        let average = stats.average();
-        let stddev = stats.stddev();
-        let median = stats.median();
-        let min = stats.min().expect("Returned early if empty");
-        let max = stats.max().expect("Returned early if empty");
-        let latency_histogram = stats.latency_histogram();
-        let percentiles = stats.percentiles();
+impl HandshakeSummary {
+    fn from_facts(facts: &[Fact]) -> Option<HandshakeSummary> {
+        let split = |resumed: bool| {
+            DurationStats::new(
+                facts
+                    .iter()
+                    .filter(|f| f.resumed == Some(resumed))
+                    .map(|f| f.duration)
+                    .collect(),
+            )
+        };
+        let (full, resumed) = (split(false), split(true));
+        let average = |stats: &DurationStats| {
+            if stats.is_empty() {
+                Duration::new(0, 0)
+            } else {
+                stats.average()
+            }
+        };
 
-        Summary {
-            average,
-            stddev,
-            median,
-            min,
-            max,
-            percentiles,
-            latency_histogram,
-            ..Summary::zero()
+        if full.is_empty() && resumed.is_empty() {
+            None
+        } else {
+            Some(HandshakeSummary {
+                full: full.sorted.len() as u32,
+                full_average: average(&full),
+                resumed: resumed.sorted.len() as u32,
+                resumed_average: average(&resumed),
+            })
         }
     }
+}
 
-    fn zero() -> Summary {
-        Summary {
-            average: Duration::new(0, 0),
-            stddev: Duration::new(0, 0),
-            median: Duration::new(0, 0),
-            max: Duration::new(0, 0),
-            min: Duration::new(0, 0),
-            count: 0,
-            content_length: ContentLength::zero(),
-            percentiles: vec![Duration::new(0, 0); 100],
-            latency_histogram: vec![0; 0],
-            status_counts: HashMap::new(),
-            chart_size: ChartSize::Medium,
-        }
+/// The requests a single worker thread made, to spot work that was not spread evenly.
+#[derive(Debug)]
+struct WorkerSummary {
+    worker: usize,
+    count: u32,
+    median: Duration,
+    p90: Duration,
+    p99: Duration,
+    max: Duration,
+}
+
+impl WorkerSummary {
+    fn from_facts(facts: &[Fact]) -> Vec<WorkerSummary> {
+        let by_worker = facts.iter().fold(
+            BTreeMap::new(),
+            |mut acc: BTreeMap<usize, Vec<Duration>>, fact| {
+                if let Some(worker) = fact.worker {
+                    acc.entry(worker).or_default().push(fact.duration);
+                }
+                acc
+            },
+        );
+        by_worker
+            .into_iter()
+            .map(|(worker, durations)| {
+                let stats = DurationStats::new(durations);
+                let percentiles = stats.percentiles();
+                WorkerSummary {
+                    worker,
+                    count: stats.sorted.len() as u32,
+                    median: stats.median(),
+                    p90: percentiles[90],
+                    p99: percentiles[99],
+                    max: stats.max().expect("Workers have at least one fact"),
+                }
+            })
+            .collect()
     }
+}
 
-    fn total_content_length(facts: &[Fact]) -> ContentLength {
-        facts.iter().fold(ContentLength::zero(), |len, fact| {
-            len + &fact.content_length
-        })
+/// The requests that went to one of the urls.
+#[derive(Debug)]
+struct TargetSummary {
+    target: usize,
+    url: String,
+    count: u32,
+    errors: u32,
+    median: Duration,
+    p99: Duration,
+}
+
+impl TargetSummary {
+    fn from_facts(facts: &[Fact]) -> Vec<TargetSummary> {
+        let by_target = facts.iter().fold(
+            BTreeMap::new(),
+            |mut acc: BTreeMap<usize, Vec<&Fact>>, fact| {
+                if let Some(target) = fact.target {
+                    acc.entry(target).or_default().push(fact);
+                }
+                acc
+            },
+        );
+        by_target
+            .into_iter()
+            .map(|(target, facts)| {
+                let stats = DurationStats::new(facts.iter().map(|f| f.duration).collect());
+                TargetSummary {
+                    target,
+                    url: format!("#{}", target),
+                    count: facts.len() as u32,
+                    errors: facts.iter().filter(|f| f.is_error()).count() as u32,
+                    median: stats.median(),
+                    p99: stats.percentile(99.0),
+                }
+            })
+            .collect()
     }
 
-    fn chart<T>(&self, vec: &[T]) -> String
-    where
-        T: Copy + Into<f64>,
-    {
-        let (height, scale) = match self.chart_size {
-            ChartSize::None => return String::new(),
-            ChartSize::Small => (7, 3),
-            ChartSize::Medium => (10, 2),
-            ChartSize::Large => (20, 1),
-        };
-        use stats::scale_array;
-        Chart::new().height(height).make(&scale_array(&vec, scale))
+    fn error_rate(&self) -> f64 {
+        f64::from(self.errors) / f64::from(self.count)
     }
 }
 
-fn scale_array<T>(vec: &[T], scale_array: usize) -> Vec<T>
-where
-    T: Copy,
-{
+/// The requests whose responses fell into one group, such as those from one backend.
+#[derive(Debug)]
+struct GroupSummary {
+    group: String,
+    count: u32,
+    median: Duration,
+    p99: Duration,
+    max: Duration,
+    status_counts: BTreeMap<u16, u32>,
+}
+
+impl GroupSummary {
+    fn from_facts(facts: &[Fact]) -> Vec<GroupSummary> {
+        let by_group = facts.iter().fold(
+            BTreeMap::new(),
+            |mut acc: BTreeMap<&str, Vec<&Fact>>, fact| {
+                if let Some(ref group) = fact.group {
+                    acc.entry(group.as_str()).or_default().push(fact);
+                }
+                acc
+            },
+        );
+        by_group
+            .into_iter()
+            .map(|(group, facts)| {
+                let stats = DurationStats::new(facts.iter().map(|f| f.duration).collect());
+                let status_counts = facts.iter().filter_map(|f| f.status).fold(
+                    BTreeMap::new(),
+                    |mut acc, status| {
+                        *acc.entry(status).or_insert(0) += 1;
+                        acc
+                    },
+                );
+                GroupSummary {
+                    group: group.to_string(),
+                    count: facts.len() as u32,
+                    median: stats.median(),
+                    p99: stats.percentile(99.0),
+                    max: stats.max().expect("Groups have at least one fact"),
+                    status_counts,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The bounds within which the mean and p99 latencies fall with some confidence, so that
+/// the numbers of a single run come with error bars.
+#[derive(Debug)]
+struct Confidence {
+    level: f64,
+    mean: (Duration, Duration),
+    p99: (Duration, Duration),
+}
+
+/// Splits the responses a cache answered from those it passed on to the server.
+#[derive(Debug)]
+struct CacheSummary {
+    hits: Option<Distribution>,
+    misses: Option<Distribution>,
+}
+
+/// The shape of the latencies of some of the requests.
+#[derive(Debug)]
+struct Distribution {
+    count: u32,
+    median: Duration,
+    p99: Duration,
+}
+
+impl Distribution {
+    fn new(durations: Vec<Duration>) -> Option<Distribution> {
+        let stats = DurationStats::new(durations);
+        if stats.is_empty() {
+            return None;
+        }
+        Some(Distribution {
+            count: stats.sorted.len() as u32,
+            median: stats.median(),
+            p99: stats.percentile(99.0),
+        })
+    }
+}
+
+impl CacheSummary {
+    fn from_facts(facts: &[Fact]) -> Option<CacheSummary> {
+        let split = |hit: bool| {
+            Distribution::new(
+                facts
+                    .iter()
+                    .filter(|f| f.cache_hit == Some(hit))
+                    .map(|f| f.duration)
+                    .collect(),
+            )
+        };
+        match (split(true), split(false)) {
+            (None, None) => None,
+            (hits, misses) => Some(CacheSummary { hits, misses }),
+        }
+    }
+
+    fn hit_ratio(&self) -> f64 {
+        let count = |d: &Option<Distribution>| d.as_ref().map(|d| d.count).unwrap_or(0);
+        let hits = count(&self.hits);
+        f64::from(hits) / f64::from(hits + count(&self.misses))
+    }
+}
+
+/// The sizes of the bodies of the responses, which often explain latencies that vary.
+#[derive(Debug)]
+struct SizeSummary {
+    min: u64,
+    median: u64,
+    p99: u64,
+    max: u64,
+    histogram: Vec<u32>,
+}
+
+impl SizeSummary {
+    fn from_facts(facts: &[Fact]) -> Option<SizeSummary> {
+        let mut sizes: Vec<u64> = facts
+            .iter()
+            .filter(|f| f.status.is_some())
+            .map(|f| f.content_length.bytes())
+            .collect();
+        if sizes.is_empty() {
+            return None;
+        }
+        sizes.sort_unstable();
+        let at = |percentage: f64| {
+            let rank = (percentage / 100f64 * sizes.len() as f64).ceil() as usize;
+            sizes[cmp::min(cmp::max(rank, 1), sizes.len()) - 1]
+        };
+        let (min, max) = (sizes[0], sizes[sizes.len() - 1]);
+        // Each bar is 2% of the range of the sizes, the largest falling into the last.
+        let mut histogram = vec![0; 50];
+        if max > min {
+            for size in &sizes {
+                let index = ((size - min) as f64 / (max - min) as f64 * 50f64) as usize;
+                histogram[cmp::min(index, 49)] += 1;
+            }
+        }
+        Some(SizeSummary {
+            min,
+            median: at(50f64),
+            p99: at(99f64),
+            max,
+            histogram,
+        })
+    }
+
+    /// Whether the responses were not all the same size, leaving something to chart.
+    fn varies(&self) -> bool {
+        self.max > self.min
+    }
+}
+
+impl Summary {
+    /// The requests that failed or were answered with an error by category, the most common
+    /// first, with responses counted by the class of their status.
+    fn error_counts(facts: &[Fact]) -> Vec<(String, u32)> {
+        let mut counts: BTreeMap<String, u32> = BTreeMap::new();
+        for fact in facts {
+            let category = match (fact.failure, fact.status) {
+                (Some(Failure::Status), Some(status)) => format!("{}xx responses", status / 100),
+                (Some(failure), _) => failure.to_string(),
+                (None, Some(status)) if status >= 400 => format!("{}xx responses", status / 100),
+                _ => continue,
+            };
+            *counts.entry(category).or_insert(0) += 1;
+        }
+        let mut counts: Vec<(String, u32)> = counts.into_iter().collect();
+        counts.sort_by_key(|&(_, count)| ::std::cmp::Reverse(count));
+        counts
+    }
+
+    /// From a set of facts, calculate the statistics.
+    pub fn from_facts(facts: &[Fact]) -> Summary {
+        if facts.is_empty() {
+            return Summary::zero();
+        }
+        let content_length = Self::total_content_length(facts);
+        let count = facts.len() as u32;
+        let latencies = DurationStats::new(facts.iter().filter_map(Fact::latency).collect());
+        let durations = if latencies.is_empty() {
+            Summary::zero()
+        } else {
+            Summary::from_durations(&latencies)
+        };
+        let status_counts = facts.iter().filter_map(|f| f.status).fold(
+            HashMap::with_capacity(699),
+            |mut acc: HashMap<u16, u32>, status| {
+                let count = if let Some(current) = acc.get(&status) {
+                    current + 1
+                } else {
+                    1
+                };
+                acc.insert(status, count);
+                acc
+            },
+        );
+
+        let failure_counts = facts.iter().filter_map(|f| f.failure).fold(
+            HashMap::new(),
+            |mut acc: HashMap<Failure, u32>, failure| {
+                *acc.entry(failure).or_insert(0) += 1;
+                acc
+            },
+        );
+
+        let grpc_status_counts = facts.iter().filter_map(|f| f.grpc_status).fold(
+            HashMap::new(),
+            |mut acc: HashMap<u16, u32>, status| {
+                *acc.entry(status).or_insert(0) += 1;
+                acc
+            },
+        );
+
+        let version_counts = facts.iter().filter_map(|f| f.version).fold(
+            HashMap::new(),
+            |mut acc: HashMap<&'static str, u32>, version| {
+                *acc.entry(version).or_insert(0) += 1;
+                acc
+            },
+        );
+
+        let tls_version_counts = facts.iter().filter_map(|f| f.tls_version).fold(
+            HashMap::new(),
+            |mut acc: HashMap<&'static str, u32>, version| {
+                *acc.entry(version).or_insert(0) += 1;
+                acc
+            },
+        );
+
+        let cipher_counts = facts.iter().filter_map(|f| f.cipher.as_ref()).fold(
+            HashMap::new(),
+            |mut acc: HashMap<String, u32>, cipher| {
+                *acc.entry(cipher.clone()).or_insert(0) += 1;
+                acc
+            },
+        );
+
+        let alpn_counts = facts.iter().filter_map(|f| f.alpn.as_ref()).fold(
+            HashMap::new(),
+            |mut acc: HashMap<String, u32>, alpn| {
+                *acc.entry(alpn.clone()).or_insert(0) += 1;
+                acc
+            },
+        );
+
+        let connections = PhaseSummary::new(facts.iter().filter_map(|f| f.connect).collect());
+        let continues = PhaseSummary::new(facts.iter().filter_map(|f| f.continued).collect());
+        let authentications =
+            PhaseSummary::new(facts.iter().filter_map(|f| f.authentication).collect());
+        let iterations = PhaseSummary::new(facts.iter().filter_map(|f| f.iteration).collect());
+
+        let sent = facts
+            .iter()
+            .map(|fact| &fact.sent)
+            .sum::<ContentLength>();
+
+        // Only the time spent on requests that uploaded something counts toward the rate.
+        let upload_time: Duration = facts
+            .iter()
+            .filter(|fact| fact.sent.bytes() > 0)
+            .map(|fact| fact.duration)
+            .sum();
+        let upload_rate = if upload_time > Duration::new(0, 0) {
+            Some(sent.bytes() as f64 / (upload_time.to_ms() / 1_000f64))
+        } else {
+            None
+        };
+
+        Summary {
+            count,
+            content_length,
+            sent,
+            upload_rate,
+            status_counts,
+            grpc_status_counts,
+            failure_counts,
+            error_counts: Self::error_counts(facts),
+            version_counts,
+            tls_version_counts,
+            cipher_counts,
+            alpn_counts,
+            http2_connections: facts
+                .iter()
+                .filter(|f| f.version == Some("HTTP/2") && f.connect.is_some())
+                .count() as u32,
+            pushed: facts.iter().map(|f| f.pushed).sum(),
+            pushed_bytes: facts
+                .iter()
+                .map(|fact| &fact.pushed_bytes)
+                .sum(),
+            expires_in: facts.iter().filter_map(|f| f.expires_in).min(),
+            connections,
+            continues,
+            authentications,
+            iterations,
+            handshakes: HandshakeSummary::from_facts(facts),
+            workers: WorkerSummary::from_facts(facts),
+            groups: GroupSummary::from_facts(facts),
+            targets: TargetSummary::from_facts(facts),
+            cache: CacheSummary::from_facts(facts),
+            sizes: SizeSummary::from_facts(facts),
+            timeouts: facts.iter().filter(|f| f.is_timeout()).count() as u32,
+            latencies,
+            ..durations
+        }
+    }
+
+    pub fn with_chart_size(mut self, size: ChartSize) -> Self {
+        self.chart_size = size;
+        self
+    }
+
+    /// Counts the latencies into buckets of the width, such as 0 to 10 ms and 10 to 20 ms,
+    /// so that the histograms of runs can be laid over each other.
+    pub fn with_histogram_bucket(mut self, width: Option<Duration>) -> Self {
+        let width = match width {
+            Some(width) => width,
+            None => return self,
+        };
+        if !self.latencies.is_empty() {
+            self.latency_histogram = self.latencies.fixed_histogram(width);
+            self.histogram_bucket = Some(width);
+        }
+        self
+    }
+
+    /// Leaves the latencies past the percentile, such as 99.5, out of the scale of the
+    /// charts, listing them instead, so that a few outliers do not flatten the charts.
+    pub fn with_chart_clamp(mut self, percentage: Option<f64>) -> Self {
+        self.chart_clamp = percentage;
+        self
+    }
+
+    /// The latency the charts are clamped at, and those past it, the longest first.
+    fn clamped(&self) -> Option<(Duration, Vec<Duration>)> {
+        let percentage = self.chart_clamp?;
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let clamp = self.latencies.percentile(percentage);
+        let past = self.latencies.sorted.iter().rev().take_while(|&&d| d > clamp).cloned();
+        Some((clamp, past.collect()))
+    }
+
+    /// Draws the charts in the style, such as braille for finer detail.
+    pub fn with_chart_style(mut self, style: chart::Style) -> Self {
+        self.chart_style = style;
+        self
+    }
+
+    /// Draws the charts with this many rows, rather than as many as their size has.
+    pub fn with_chart_height(mut self, height: Option<u32>) -> Self {
+        self.chart_height = height;
+        self
+    }
+
+    /// Names the proxies the requests went through.
+    pub fn with_proxies(mut self, proxies: Vec<String>) -> Self {
+        self.proxies = proxies;
+        self
+    }
+
+    /// Marks the requests as having been asked to use http/2, so that responses that came
+    /// over an older version are warned about.
+    pub fn with_http2_asked(mut self, asked: bool) -> Self {
+        self.http2_asked = asked;
+        self
+    }
+
+    /// Warns about servers' certificates that expire within the window.
+    pub fn with_expiry_window(mut self, window: Duration) -> Self {
+        self.expiry_window = window;
+        self
+    }
+
+    /// Names the urls the requests went to, by which they are broken down when there are
+    /// several of them.
+    pub fn with_urls(mut self, urls: &[String]) -> Self {
+        for target in &mut self.targets {
+            if let Some(url) = urls.get(target.target) {
+                target.url = url.clone();
+            }
+        }
+        self.per_url = true;
+        self
+    }
+
+    /// Adds what running the benchmark cost rench itself.
+    pub fn with_usage(mut self, usage: Option<Usage>) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Adds what the run was, where and when, to its json.
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Adds how the throughput of each second of the run spread.
+    pub fn with_throughput(mut self, throughput: Option<Rates>) -> Self {
+        self.throughput = throughput;
+        self
+    }
+
+    /// Adds the finer grained distribution of the latencies, down to every tenth of a
+    /// percent in the tail.
+    pub fn with_latency_distribution(mut self) -> Self {
+        self.distribution = true;
+        self
+    }
+
+    /// The latency at each percentile of the detailed distribution, when asked for.
+    fn distribution(&self) -> Vec<(f64, Duration)> {
+        if !self.distribution || self.latencies.is_empty() {
+            return Vec::new();
+        }
+        distribution_tenths()
+            .into_iter()
+            .map(|tenths| {
+                let percentage = f64::from(tenths) / 10f64;
+                (percentage, self.latencies.percentile(percentage))
+            })
+            .collect()
+    }
+
+    /// Adds other means of the latencies than the arithmetic.
+    pub fn with_means(mut self, means: &[Mean]) -> Self {
+        if self.latencies.is_empty() {
+            return self;
+        }
+        let sorted = &self.latencies.sorted;
+        self.means = means
+            .iter()
+            .map(|&mean| {
+                let ms = match mean {
+                    Mean::Trimmed(percent) => {
+                        let cut = (sorted.len() as f64 * percent / 100f64) as usize;
+                        let kept = &sorted[cut..sorted.len() - cut];
+                        kept.iter().map(|d| d.to_ms()).sum::<f64>() / kept.len() as f64
+                    }
+                    // Instant responses have no logarithm, and so are left out.
+                    Mean::Geometric => {
+                        let logs: Vec<f64> = sorted
+                            .iter()
+                            .map(|d| d.to_ms())
+                            .filter(|&ms| ms > 0f64)
+                            .map(f64::ln)
+                            .collect();
+                        if logs.is_empty() {
+                            0f64
+                        } else {
+                            (logs.iter().sum::<f64>() / logs.len() as f64).exp()
+                        }
+                    }
+                };
+                (mean, Duration::from(MS(ms)))
+            })
+            .collect();
+        self
+    }
+
+    /// Adds bootstrapped intervals of the mean and p99 latencies at a confidence, such as 95
+    /// for 95%.
+    pub fn with_confidence(mut self, level: f64) -> Self {
+        if self.latencies.is_empty() {
+            return self;
+        }
+        let sample: Vec<f64> = self.latencies.sorted.iter().map(|d| d.to_ms()).collect();
+        let mut rng = StdRng::from_entropy();
+        let mut interval = |statistic: fn(&mut [f64]) -> f64| {
+            let (low, high) = bootstrap::interval(&sample, level, statistic, &mut rng);
+            (Duration::from(MS(low)), Duration::from(MS(high)))
+        };
+        self.confidence = Some(Confidence {
+            level,
+            mean: interval(bootstrap::mean),
+            p99: interval(bootstrap::p99),
+        });
+        self
+    }
+
+    /// Adds the concurrency asked for and the requests per second achieved, by which the
+    /// requests that were actually in flight are told.
+    pub fn with_concurrency(mut self, concurrency: usize, rps: f64) -> Self {
+        self.concurrency = Some((concurrency, rps));
+        self
+    }
+
+    /// The requests in flight on average, by Little's law the requests per second times
+    /// how long they took on average.
+    fn effective_concurrency(&self) -> Option<f64> {
+        self.concurrency.map(|(_, rps)| rps * self.average.as_secs_f64())
+    }
+
+    /// Breaks the requests down by the worker thread that made them.
+    pub fn with_per_worker(mut self) -> Self {
+        self.per_worker = true;
+        self
+    }
+
+    /// Names the response header the requests were grouped by.
+    pub fn with_group_header(mut self, header: &str) -> Self {
+        self.group_header = Some(header.to_string());
+        self
+    }
+
+    /// Only keeps the groups with the longest median, slowest first.
+    pub fn with_slowest_groups(mut self, count: usize) -> Self {
+        self.groups.sort_by_key(|group| cmp::Reverse(group.median));
+        self.groups.truncate(count);
+        self.slowest_groups = true;
+        self
+    }
+
+    /// Adds percentiles that count the requests that timed out as having taken the timeout,
+    /// as a bound on what the latencies would have been had they been waited for.
+    pub fn with_pessimistic_percentiles(mut self, timeout: Duration) -> Self {
+        let mut durations = self.latencies.sorted.clone();
+        durations.extend(vec![timeout; self.timeouts as usize]);
+        let stats = DurationStats::new(durations);
+        if !stats.is_empty() {
+            let table = PERCENTILE_TABLE
+                .iter()
+                .map(|&percentage| (percentage, stats.percentile(percentage)))
+                .collect();
+            self.pessimistic = Some((timeout, table));
+        }
+        self
+    }
+
+    /// Notes how many requests were made when the facts are only a sample of them.
+    pub fn with_sampled_from(mut self, total: usize) -> Self {
+        if total as u32 > self.count {
+            self.sampled_from = Some(total as u32);
+        }
+        self
+    }
+
+    /// How many requests were summarized.
+    pub fn requests(&self) -> u32 {
+        self.count
+    }
+
+    /// How many of the requests got a response, of whatever status.
+    pub fn responses(&self) -> u32 {
+        self.status_counts.values().sum()
+    }
+
+    /// Whether any request has a latency, as those that timed out do not.
+    pub fn has_latencies(&self) -> bool {
+        !self.latencies.is_empty()
+    }
+
+    /// The latency at a percentile of the requests, when any request has one.
+    pub fn latency_at(&self, percentage: f64) -> Option<Duration> {
+        if self.has_latencies() {
+            Some(self.latencies.percentile(percentage))
+        } else {
+            None
+        }
+    }
+
+    pub fn average(&self) -> Duration {
+        self.average
+    }
+
+    pub fn median(&self) -> Duration {
+        self.median
+    }
+
+    pub fn min(&self) -> Duration {
+        self.min
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// How many of the requests failed or were answered with an error.
+    pub fn errors(&self) -> u32 {
+        self.error_counts.iter().map(|&(_, count)| count).sum()
+    }
+
+    /// The share of the requests that failed or were answered with an error.
+    pub fn error_rate(&self) -> f64 {
+        if self.count == 0 {
+            return 0f64;
+        }
+        self.errors() as f64 / self.count as f64
+    }
+
+    /// The total size of the bodies uploaded with the requests.
+    pub fn sent(&self) -> &ContentLength {
+        &self.sent
+    }
+
+    /// The summary as json, with durations in milliseconds, in the `SCHEMA_VERSION` it
+    /// carries.
+    pub fn to_json(&self) -> Value {
+        let phase = |phase: &Option<PhaseSummary>| match *phase {
+            Some(ref phase) => json!({
+                "count": phase.count,
+                "average": phase.average.to_ms(),
+                "max": phase.max.to_ms(),
+            }),
+            None => Value::Null,
+        };
+        let counts = |counts: Vec<(String, u32)>| {
+            Value::Object(
+                counts
+                    .into_iter()
+                    .map(|(key, count)| (key, Value::from(count)))
+                    .collect(),
+            )
+        };
+        let table = |table: &[(f64, Duration)]| {
+            Value::Object(
+                table
+                    .iter()
+                    .map(|&(percentage, duration)| {
+                        (format!("p{}", percentage), Value::from(duration.to_ms()))
+                    })
+                    .collect(),
+            )
+        };
+        json!({
+            "schema_version": SCHEMA_VERSION,
+            "metadata": self.metadata.as_ref().map(Metadata::to_json),
+            "requests": self.count,
+            "timeouts": self.timeouts,
+            "sampled_from": self.sampled_from,
+            "proxies": self.proxies,
+            "latency": {
+                "average": self.average.to_ms(),
+                "stddev": self.stddev.to_ms(),
+                "median": self.median.to_ms(),
+                "min": self.min.to_ms(),
+                "max": self.max.to_ms(),
+                "percentiles": self.percentiles.iter().map(|d| d.to_ms()).collect::<Vec<f64>>(),
+                "table": table(&self.percentile_table),
+                "distribution": if self.distribution {
+                    table(&self.distribution())
+                } else {
+                    Value::Null
+                },
+                "histogram": match self.histogram_bucket {
+                    Some(width) => json!({
+                        "bucket": width.to_ms(),
+                        "counts": self.latency_histogram,
+                    }),
+                    None => Value::Null,
+                },
+                "means": Value::Object(
+                    self.means
+                        .iter()
+                        .map(|(mean, duration)| (mean.name(), Value::from(duration.to_ms())))
+                        .collect(),
+                ),
+                "pessimistic": match self.pessimistic {
+                    Some((timeout, ref pessimistic)) => json!({
+                        "timeout": timeout.to_ms(),
+                        "table": table(pessimistic),
+                    }),
+                    None => Value::Null,
+                },
+            },
+            "data": self.content_length.bytes(),
+            "throughput": match self.throughput {
+                Some(ref rates) => {
+                    let spread = |spread: &Spread| {
+                        json!({"min": spread.min, "median": spread.median, "max": spread.max})
+                    };
+                    json!({
+                        "seconds": rates.windows,
+                        "requests_per_second": spread(&rates.requests),
+                        "bytes_per_second": spread(&rates.bytes),
+                        "points": rates
+                            .points
+                            .iter()
+                            .map(|&(rps, p99)| json!({"rps": rps, "p99": p99}))
+                            .collect::<Vec<Value>>(),
+                        "correlation": rates.correlation,
+                        "classes": rates
+                            .classes
+                            .iter()
+                            .map(|(class, counts)| (class.to_string(), json!(counts)))
+                            .collect::<Map<String, Value>>(),
+                    })
+                }
+                None => Value::Null,
+            },
+            "confidence": match self.confidence {
+                Some(ref confidence) => json!({
+                    "level": confidence.level,
+                    "mean": [confidence.mean.0.to_ms(), confidence.mean.1.to_ms()],
+                    "p99": [confidence.p99.0.to_ms(), confidence.p99.1.to_ms()],
+                }),
+                None => Value::Null,
+            },
+            "concurrency": match self.concurrency {
+                Some((configured, _)) => json!({
+                    "configured": configured,
+                    "effective": self.effective_concurrency(),
+                }),
+                None => Value::Null,
+            },
+            "response_sizes": match self.sizes {
+                Some(ref sizes) => json!({
+                    "min": sizes.min,
+                    "median": sizes.median,
+                    "p99": sizes.p99,
+                    "max": sizes.max,
+                    "histogram": sizes.histogram,
+                }),
+                None => Value::Null,
+            },
+            "sent": self.sent.bytes(),
+            "status_codes": counts(
+                self.status_counts
+                    .iter()
+                    .map(|(status, &count)| (status.to_string(), count))
+                    .collect(),
+            ),
+            "grpc_status_codes": counts(
+                self.grpc_status_counts
+                    .iter()
+                    .map(|(status, &count)| (status.to_string(), count))
+                    .collect(),
+            ),
+            "failures": counts(
+                self.failure_counts
+                    .iter()
+                    .map(|(failure, &count)| (failure.key().to_string(), count))
+                    .collect(),
+            ),
+            "versions": counts(
+                self.version_counts
+                    .iter()
+                    .map(|(version, &count)| (version.to_string(), count))
+                    .collect(),
+            ),
+            "tls_versions": counts(
+                self.tls_version_counts
+                    .iter()
+                    .map(|(version, &count)| (version.to_string(), count))
+                    .collect(),
+            ),
+            "ciphers": counts(
+                self.cipher_counts
+                    .iter()
+                    .map(|(cipher, &count)| (cipher.clone(), count))
+                    .collect(),
+            ),
+            "alpn": counts(
+                self.alpn_counts
+                    .iter()
+                    .map(|(alpn, &count)| (alpn.clone(), count))
+                    .collect(),
+            ),
+            "http2": match self.version_counts.get("HTTP/2") {
+                Some(&streams) => json!({
+                    "connections": self.http2_connections,
+                    "streams": streams,
+                }),
+                None => Value::Null,
+            },
+            "pushed": {
+                "streams": self.pushed,
+                "bytes": self.pushed_bytes.bytes(),
+            },
+            "certificate_expires_in": self.expires_in,
+            "urls": self
+                .targets
+                .iter()
+                .filter(|_| self.per_url)
+                .map(|target| {
+                    json!({
+                        "url": target.url,
+                        "requests": target.count,
+                        "error_rate": target.error_rate(),
+                        "median": target.median.to_ms(),
+                        "p99": target.p99.to_ms(),
+                    })
+                })
+                .collect::<Vec<Value>>(),
+            "connections": phase(&self.connections),
+            "continues": phase(&self.continues),
+            "authentications": phase(&self.authentications),
+            "iterations": phase(&self.iterations),
+        })
+    }
+
+    /// How many responses came over a version of http older than http/2.
+    fn downgraded(&self) -> u32 {
+        self.version_counts
+            .iter()
+            .filter(|&(&version, _)| version != "HTTP/2")
+            .map(|(_, &count)| count)
+            .sum()
+    }
+
+    fn from_durations(stats: &DurationStats) -> Summary {
+        let average = stats.average();
+        let stddev = stats.stddev();
+        let median = stats.median();
+        let min = stats.min().expect("Returned early if empty");
+        let max = stats.max().expect("Returned early if empty");
+        let latency_histogram = stats.latency_histogram();
+        let percentiles = stats.percentiles();
+        let percentile_table = PERCENTILE_TABLE
+            .iter()
+            .map(|&percentage| (percentage, stats.percentile(percentage)))
+            .collect();
+
+        Summary {
+            average,
+            stddev,
+            median,
+            min,
+            max,
+            percentiles,
+            percentile_table,
+            latency_histogram,
+            ..Summary::zero()
+        }
+    }
+
+    fn zero() -> Summary {
+        Summary {
+            average: Duration::new(0, 0),
+            stddev: Duration::new(0, 0),
+            median: Duration::new(0, 0),
+            max: Duration::new(0, 0),
+            min: Duration::new(0, 0),
+            count: 0,
+            content_length: ContentLength::zero(),
+            sent: ContentLength::zero(),
+            upload_rate: None,
+            percentiles: vec![Duration::new(0, 0); 100],
+            percentile_table: Vec::new(),
+            latencies: DurationStats::new(Vec::new()),
+            timeouts: 0,
+            pessimistic: None,
+            latency_histogram: vec![0; 0],
+            histogram_bucket: None,
+            status_counts: HashMap::new(),
+            grpc_status_counts: HashMap::new(),
+            failure_counts: HashMap::new(),
+            error_counts: Vec::new(),
+            version_counts: HashMap::new(),
+            tls_version_counts: HashMap::new(),
+            cipher_counts: HashMap::new(),
+            alpn_counts: HashMap::new(),
+            http2_asked: false,
+            http2_connections: 0,
+            pushed: 0,
+            pushed_bytes: ContentLength::zero(),
+            expires_in: None,
+            expiry_window: DEFAULT_EXPIRY_WINDOW,
+            connections: None,
+            continues: None,
+            authentications: None,
+            iterations: None,
+            handshakes: None,
+            usage: None,
+            metadata: None,
+            workers: Vec::new(),
+            per_worker: false,
+            groups: Vec::new(),
+            group_header: None,
+            slowest_groups: false,
+            targets: Vec::new(),
+            per_url: false,
+            cache: None,
+            sizes: None,
+            throughput: None,
+            confidence: None,
+            means: Vec::new(),
+            distribution: false,
+            concurrency: None,
+            sampled_from: None,
+            proxies: Vec::new(),
+            chart_size: ChartSize::Medium,
+            chart_style: chart::Style::Bars,
+            chart_height: None,
+            chart_clamp: None,
+        }
+    }
+
+    fn total_content_length(facts: &[Fact]) -> ContentLength {
+        facts.iter().map(|fact| &fact.content_length).sum()
+    }
+
+    fn chart<T>(&self, vec: &[T]) -> String
+    where
+        T: Copy + Into<f64>,
+    {
+        let scale = match self.chart_size {
+            ChartSize::None => return String::new(),
+            ChartSize::Small => 3,
+            ChartSize::Medium => 2,
+            ChartSize::Large => 1,
+        };
+        use stats::scale_array;
+        self.unscaled_chart(&scale_array(vec, scale))
+    }
+
+    /// A chart of every datum, where leaving some out to fit would lose them.
+    fn unscaled_chart<T>(&self, vec: &[T]) -> String
+    where
+        T: Copy + Into<f64>,
+    {
+        let height = match self.chart_size {
+            ChartSize::None => return String::new(),
+            ChartSize::Small => 7,
+            ChartSize::Medium => 10,
+            ChartSize::Large => 20,
+        };
+        Chart::new()
+            .height(self.chart_height.unwrap_or(height))
+            .style(self.chart_style)
+            .make(vec)
+    }
+}
+
+impl Summary {
+    /// Charts the responses of each class second by second, so that it shows when errors
+    /// began, several seconds to a bar when there are too many to fit.
+    fn write_classes(&self, f: &mut fmt::Formatter, classes: &[(&str, Vec<u32>)]) -> fmt::Result {
+        let height = match self.chart_size {
+            ChartSize::None => return Ok(()),
+            ChartSize::Small => 2,
+            ChartSize::Medium => 3,
+            ChartSize::Large => 5,
+        };
+        let height = self.chart_height.unwrap_or(height);
+        let seconds = classes.first().map(|(_, counts)| counts.len()).unwrap_or(0);
+        let per_bar = cmp::max(seconds.div_ceil(CLASS_BARS), 1);
+        writeln!(f)?;
+        match per_bar {
+            1 => writeln!(f, "Responses by status over time (each bar is a second)")?,
+            _ => writeln!(f, "Responses by status over time (each bar is {} seconds)", per_bar)?,
+        }
+        for (class, counts) in classes {
+            let bars: Vec<u32> = counts.chunks(per_bar).map(|chunk| chunk.iter().sum()).collect();
+            writeln!(f, "{}:", class)?;
+            write!(f, "{}", Chart::new().height(height).style(self.chart_style).make(&bars))?;
+        }
+        Ok(())
+    }
+}
+
+/// The seconds in whole days, or in hours when there is less than a day of them.
+fn days(seconds: u64) -> String {
+    match (seconds / 86_400, seconds / 3_600) {
+        (0, 1) => "1 hour".to_string(),
+        (0, hours) => format!("{} hours", hours),
+        (1, _) => "1 day".to_string(),
+        (days, _) => format!("{} days", days),
+    }
+}
+
+fn scale_array<T>(vec: &[T], scale_array: usize) -> Vec<T>
+where
+    T: Copy,
+{
     vec.iter()
         .enumerate()
         .filter(|&(i, _)| i % scale_array == 0)
@@ -275,47 +1828,577 @@ where
         .collect()
 }
 
-#[cfg(test)]
-mod scale_array_tests {
-    use super::*;
+#[cfg(test)]
+mod scale_array_tests {
+    use super::*;
+
+    #[test]
+    fn it_scale_arrays_an_array() {
+        let vec = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+        assert_eq!(scale_array(&vec, 2), vec![1, 3, 5, 7, 9]);
+        assert_eq!(scale_array(&vec, 3), vec![1, 4, 7]);
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Summary")?;
+        writeln!(
+            f,
+            "  Average:   {} ms (std: {} ms)",
+            self.average.to_ms(),
+            self.stddev.to_ms()
+        )?;
+        for &(mean, duration) in &self.means {
+            match mean {
+                Mean::Trimmed(percent) => writeln!(
+                    f,
+                    "  Trimmed:   {} ms ({}% left out at each end)",
+                    duration.to_ms(),
+                    percent
+                )?,
+                Mean::Geometric => writeln!(f, "  Geometric: {} ms", duration.to_ms())?,
+            }
+        }
+        writeln!(f, "  Median:    {} ms", self.median.to_ms())?;
+        writeln!(f, "  Longest:   {} ms", self.max.to_ms())?;
+        writeln!(f, "  Shortest:  {} ms", self.min.to_ms())?;
+        writeln!(f, "  Requests:  {}", self.count)?;
+        if self.timeouts > 0 {
+            writeln!(f, "  Timeouts:  {}, left out of the latencies", self.timeouts)?;
+        }
+        if let Some(total) = self.sampled_from {
+            writeln!(f, "  Sampled:   {} of {} requests", self.count, total)?;
+        }
+        if !self.proxies.is_empty() {
+            writeln!(f, "  Proxy:     {}", self.proxies.join(", "))?;
+        }
+        writeln!(f, "  Data:      {}", self.content_length)?;
+        if self.sent.bytes() > 0 {
+            writeln!(f, "  Sent:      {}", self.sent)?;
+        }
+        if let Some(rate) = self.upload_rate {
+            let rate = ContentLength::new(rate as u64);
+            writeln!(f, "  Upload:    {} / second per request", rate)?;
+        }
+        if !self.percentile_table.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Percentiles:")?;
+            for &(percentage, duration) in &self.percentile_table {
+                let label = format!("p{}:", percentage);
+                writeln!(f, "  {:<10} {} ms", label, duration.to_ms())?;
+            }
+        }
+        let distribution = self.distribution();
+        if !distribution.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Latency distribution:")?;
+            for (percentage, duration) in distribution {
+                writeln!(f, "  {:>7} {:>12.3} ms", format!("{}%", percentage), duration.to_ms())?;
+            }
+        }
+        if let Some(ref confidence) = self.confidence {
+            writeln!(f)?;
+            writeln!(f, "{}% confidence intervals:", confidence.level)?;
+            for &(label, (low, high)) in &[("Mean:", confidence.mean), ("p99:", confidence.p99)] {
+                writeln!(f, "  {:<10} {:.3} to {:.3} ms", label, low.to_ms(), high.to_ms())?;
+            }
+        }
+        if let Some((timeout, ref table)) = self.pessimistic {
+            writeln!(f)?;
+            writeln!(f, "Pessimistic percentiles, timeouts at {} ms:", timeout.to_ms())?;
+            for &(percentage, duration) in table {
+                let label = format!("p{}:", percentage);
+                writeln!(f, "  {:<10} {} ms", label, duration.to_ms())?;
+            }
+        }
+        if let Some(ref connections) = self.connections {
+            writeln!(f)?;
+            writeln!(f, "Connections:")?;
+            writeln!(f, "  Opened:    {}", connections.count)?;
+            writeln!(f, "  Average:   {} ms", connections.average.to_ms())?;
+            writeln!(f, "  Longest:   {} ms", connections.max.to_ms())?;
+        }
+        if let Some(ref continues) = self.continues {
+            writeln!(f)?;
+            writeln!(f, "100 Continue:")?;
+            writeln!(f, "  Received:  {}", continues.count)?;
+            writeln!(f, "  Average:   {} ms", continues.average.to_ms())?;
+            writeln!(f, "  Longest:   {} ms", continues.max.to_ms())?;
+        }
+        if let Some(ref authentications) = self.authentications {
+            writeln!(f)?;
+            writeln!(f, "Authentication:")?;
+            writeln!(f, "  Made:      {}", authentications.count)?;
+            writeln!(f, "  Average:   {} ms", authentications.average.to_ms())?;
+            writeln!(f, "  Longest:   {} ms", authentications.max.to_ms())?;
+        }
+        if let Some(ref iterations) = self.iterations {
+            writeln!(f)?;
+            writeln!(f, "Iterations:")?;
+            writeln!(f, "  Completed: {}", iterations.count)?;
+            writeln!(f, "  Average:   {} ms", iterations.average.to_ms())?;
+            writeln!(f, "  Longest:   {} ms", iterations.max.to_ms())?;
+        }
+        if let Some(ref handshakes) = self.handshakes {
+            writeln!(f)?;
+            writeln!(f, "Handshakes:")?;
+            writeln!(
+                f,
+                "  Full:      {} (average {} ms)",
+                handshakes.full,
+                handshakes.full_average.to_ms()
+            )?;
+            writeln!(
+                f,
+                "  Resumed:   {} (average {} ms)",
+                handshakes.resumed,
+                handshakes.resumed_average.to_ms()
+            )?;
+        }
+        if let Some(ref cache) = self.cache {
+            writeln!(f)?;
+            writeln!(f, "Cache:")?;
+            writeln!(f, "  Hit ratio: {:.1}%", cache.hit_ratio() * 100f64)?;
+            for &(label, split) in &[("Hits:", &cache.hits), ("Misses:", &cache.misses)] {
+                if let Some(ref split) = *split {
+                    writeln!(
+                        f,
+                        "  {:<10} {} (median {} ms, p99 {} ms)",
+                        label,
+                        split.count,
+                        split.median.to_ms(),
+                        split.p99.to_ms()
+                    )?;
+                }
+            }
+        }
+        if let Some(ref rates) = self.throughput {
+            writeln!(f)?;
+            writeln!(f, "Throughput per second, over {} seconds:", rates.windows)?;
+            writeln!(
+                f,
+                "  Requests:  min {}, median {}, max {}",
+                rates.requests.min, rates.requests.median, rates.requests.max
+            )?;
+            let bytes = |rate: f64| ContentLength::new(rate as u64);
+            writeln!(
+                f,
+                "  Data:      min {}, median {}, max {}",
+                bytes(rates.bytes.min),
+                bytes(rates.bytes.median),
+                bytes(rates.bytes.max)
+            )?;
+            if let Some(correlation) = rates.correlation {
+                writeln!(
+                    f,
+                    "  p99 vs requests: correlation {:.2}{}",
+                    correlation,
+                    if correlation > 0.5 {
+                        ", the latency rose with the throughput"
+                    } else {
+                        ""
+                    }
+                )?;
+            }
+        }
+        if let Some(ref sizes) = self.sizes {
+            writeln!(f)?;
+            writeln!(f, "Response sizes:")?;
+            writeln!(f, "  Smallest:  {}", ContentLength::new(sizes.min))?;
+            writeln!(f, "  Median:    {}", ContentLength::new(sizes.median))?;
+            writeln!(f, "  p99:       {}", ContentLength::new(sizes.p99))?;
+            writeln!(f, "  Largest:   {}", ContentLength::new(sizes.max))?;
+        }
+        if !self.status_counts.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Status codes:")?;
+            let mut status_counts: Vec<(&u16, &u32)> = self.status_counts.iter().collect();
+            status_counts.sort_by_key(|&(&code, _)| code);
+            for (k, v) in status_counts {
+                writeln!(f, "  {}: {}", k, v)?;
+            }
+        }
+        if !self.grpc_status_counts.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "gRPC status codes:")?;
+            let mut grpc_status_counts: Vec<(&u16, &u32)> =
+                self.grpc_status_counts.iter().collect();
+            grpc_status_counts.sort_by_key(|&(&code, _)| code);
+            for (k, v) in grpc_status_counts {
+                writeln!(f, "  {}: {}", k, v)?;
+            }
+        }
+        if !self.version_counts.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "HTTP versions:")?;
+            let mut version_counts: Vec<(&&str, &u32)> = self.version_counts.iter().collect();
+            version_counts.sort();
+            for (k, v) in version_counts {
+                writeln!(f, "  {}: {}", k, v)?;
+            }
+            if self.http2_asked && self.downgraded() > 0 {
+                writeln!(
+                    f,
+                    "  Warning:   {} responses came over an older version than the http/2 asked \
+                     for, so these are not all http/2 results",
+                    self.downgraded()
+                )?;
+            }
+        }
+        if let Some(&streams) = self.version_counts.get("HTTP/2") {
+            writeln!(f)?;
+            writeln!(f, "HTTP/2 multiplexing:")?;
+            writeln!(f, "  Connections: {}", self.http2_connections)?;
+            if self.http2_connections > 0 {
+                writeln!(
+                    f,
+                    "  Streams:     {} ({:.1} per connection)",
+                    streams,
+                    f64::from(streams) / f64::from(self.http2_connections)
+                )?;
+            } else {
+                writeln!(f, "  Streams:     {}", streams)?;
+            }
+            if self.pushed > 0 {
+                writeln!(f, "  Pushed:      {} streams, {}", self.pushed, self.pushed_bytes)?;
+            }
+        }
+        if !self.tls_version_counts.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "TLS versions:")?;
+            let mut tls_version_counts: Vec<(&&str, &u32)> =
+                self.tls_version_counts.iter().collect();
+            tls_version_counts.sort();
+            for (k, v) in tls_version_counts {
+                writeln!(f, "  {}: {}", k, v)?;
+            }
+            match self.expires_in {
+                Some(seconds) if seconds < 0 => writeln!(
+                    f,
+                    "  Warning:   a server's certificate expired {} ago",
+                    days(seconds.unsigned_abs())
+                )?,
+                Some(seconds) if (seconds as u64) < self.expiry_window.as_secs() => writeln!(
+                    f,
+                    "  Warning:   a server's certificate expires in {}",
+                    days(seconds as u64)
+                )?,
+                _ => {}
+            }
+        }
+        if !self.alpn_counts.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "ALPN:")?;
+            let mut alpn_counts: Vec<(&String, &u32)> = self.alpn_counts.iter().collect();
+            alpn_counts.sort();
+            for (k, v) in alpn_counts {
+                writeln!(f, "  {}: {}", k, v)?;
+            }
+        }
+        if !self.cipher_counts.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "TLS ciphers:")?;
+            let mut cipher_counts: Vec<(&String, &u32)> = self.cipher_counts.iter().collect();
+            cipher_counts.sort();
+            for (k, v) in cipher_counts {
+                writeln!(f, "  {}: {}", k, v)?;
+            }
+        }
+        if !self.error_counts.is_empty() {
+            writeln!(f)?;
+            let errors: u32 = self.error_counts.iter().map(|&(_, count)| count).sum();
+            writeln!(f, "Errors: {} of {} requests", errors, self.count)?;
+            let width = self.error_counts.iter().map(|(category, _)| category.len()).max();
+            let width = width.unwrap_or(0);
+            for (category, count) in &self.error_counts {
+                let share = 100f64 * *count as f64 / self.count as f64;
+                writeln!(f, "  {:<width$} {:>8} {:>7.2}%", category, count, share, width = width)?;
+            }
+        }
+        if self.per_worker && !self.workers.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Workers:")?;
+            for worker in &self.workers {
+                writeln!(
+                    f,
+                    "  {}: {} requests, median {} ms, p90 {} ms, p99 {} ms, longest {} ms",
+                    worker.worker,
+                    worker.count,
+                    worker.median.to_ms(),
+                    worker.p90.to_ms(),
+                    worker.p99.to_ms(),
+                    worker.max.to_ms()
+                )?;
+            }
+        }
+        if self.per_url && self.targets.len() > 1 {
+            writeln!(f)?;
+            writeln!(f, "Per url:")?;
+            let width = self.targets.iter().map(|t| t.url.len()).max().unwrap_or(0);
+            writeln!(
+                f,
+                "  {:<width$}  {:>8}  {:>7}  {:>12}  {:>12}",
+                "url",
+                "requests",
+                "errors",
+                "median ms",
+                "p99 ms",
+                width = width
+            )?;
+            for target in &self.targets {
+                writeln!(
+                    f,
+                    "  {:<width$}  {:>8}  {:>6.1}%  {:>12.3}  {:>12.3}",
+                    target.url,
+                    target.count,
+                    target.error_rate() * 100f64,
+                    target.median.to_ms(),
+                    target.p99.to_ms(),
+                    width = width
+                )?;
+            }
+        }
+        if !self.groups.is_empty() {
+            writeln!(f)?;
+            match (self.group_header.as_ref(), self.slowest_groups) {
+                (Some(header), true) => writeln!(f, "Slowest {}s:", header)?,
+                (Some(header), false) => writeln!(f, "By {}:", header)?,
+                (None, true) => writeln!(f, "Slowest groups:")?,
+                (None, false) => writeln!(f, "Groups:")?,
+            }
+            for group in &self.groups {
+                let statuses: Vec<String> = group
+                    .status_counts
+                    .iter()
+                    .map(|(status, count)| format!("{}: {}", status, count))
+                    .collect();
+                writeln!(
+                    f,
+                    "  {}: {} requests, median {} ms, p99 {} ms, longest {} ms ({})",
+                    group.group,
+                    group.count,
+                    group.median.to_ms(),
+                    group.p99.to_ms(),
+                    group.max.to_ms(),
+                    statuses.join(", ")
+                )?;
+            }
+        }
+        if let (Some((configured, rps)), Some(effective)) =
+            (self.concurrency, self.effective_concurrency())
+        {
+            writeln!(f)?;
+            writeln!(f, "Concurrency:")?;
+            writeln!(f, "  Asked for: {}", configured)?;
+            writeln!(
+                f,
+                "  In flight: {:.1} ({:.1} requests / second at {:.3} ms on average)",
+                effective,
+                rps,
+                self.average.to_ms()
+            )?;
+            if effective < configured as f64 * KEPT_BUSY {
+                writeln!(
+                    f,
+                    "  Warning:   only {:.0}% of the concurrency asked for was kept in flight, \
+                     so the client rather than the server may have held the throughput back",
+                    effective / configured as f64 * 100f64
+                )?;
+            }
+        }
+        if let Some(ref usage) = self.usage {
+            writeln!(f)?;
+            writeln!(f, "Client:")?;
+            writeln!(
+                f,
+                "  CPU:       {:.0}% (peak {:.0}%)",
+                usage.average_cpu * 100f64,
+                usage.peak_cpu * 100f64
+            )?;
+            writeln!(f, "  Memory:    {} peak", usage.peak_memory)?;
+            if usage.is_saturated() {
+                writeln!(
+                    f,
+                    "  Warning:   rench was close to maxing out its cpu, so the results may \
+                     show the limits of the client rather than the server"
+                )?;
+            }
+        }
+        if self.chart_size != ChartSize::None {
+            writeln!(f)?;
+            let clamped = self.clamped();
+            let limit = clamped.as_ref().map(|&(clamp, _)| clamp);
+            writeln!(f, "Latency Percentiles (2% of requests per bar):")?;
+            let percentiles: Vec<f64> = self
+                .percentiles
+                .iter()
+                .map(|&d| limit.map_or(d, |limit| cmp::min(d, limit)).to_ms())
+                .collect();
+            writeln!(f, "{}", self.chart(&percentiles))?;
+            writeln!(f)?;
+            match self.histogram_bucket {
+                Some(width) => {
+                    let buckets = self.latency_histogram.len();
+                    let last = width * (buckets as u32 - 1);
+                    if buckets == MAX_BUCKETS && self.max >= width * buckets as u32 {
+                        writeln!(
+                            f,
+                            "Latency Histogram (each bar is {} ms, the last {} ms and over)",
+                            width.to_ms(),
+                            last.to_ms()
+                        )?;
+                    } else {
+                        writeln!(f, "Latency Histogram (each bar is {} ms)", width.to_ms())?;
+                    }
+                    let mut histogram = self.latency_histogram.clone();
+                    if let Some(limit) = limit {
+                        histogram.truncate(limit.as_nanos().div_ceil(width.as_nanos()) as usize);
+                    }
+                    writeln!(f, "{}", self.unscaled_chart(&histogram))?;
+                }
+                None => match limit {
+                    Some(limit) => {
+                        writeln!(f, "Latency Histogram (each bar is 2% of the clamped latency)")?;
+                        writeln!(f, "{}", self.chart(&self.latencies.histogram_up_to(limit)))?;
+                    }
+                    None => {
+                        writeln!(f, "Latency Histogram (each bar is 2% of max latency)")?;
+                        writeln!(f, "{}", self.chart(&self.latency_histogram))?;
+                    }
+                },
+            }
+            if let Some((clamp, ref past)) = clamped {
+                if !past.is_empty() {
+                    writeln!(
+                        f,
+                        "Clamped at p{}, {} ms, leaving out of the charts:",
+                        self.chart_clamp.expect("Clamped at a percentile"),
+                        clamp.to_ms()
+                    )?;
+                    for duration in past.iter().take(CLAMPED_LISTED) {
+                        writeln!(f, "  {} ms", duration.to_ms())?;
+                    }
+                    if past.len() > CLAMPED_LISTED {
+                        writeln!(f, "  and {} more", past.len() - CLAMPED_LISTED)?;
+                    }
+                }
+            }
+            if let Some(rates) = self.throughput.as_ref().filter(|rates| rates.windows > 1) {
+                self.write_classes(f, &rates.classes)?;
+            }
+            if let Some(sizes) = self.sizes.as_ref().filter(|sizes| sizes.varies()) {
+                writeln!(f)?;
+                writeln!(f, "Response Size Histogram (each bar is 2% of the range of sizes)")?;
+                writeln!(f, "{}", self.chart(&sizes.histogram))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a run compares with a baseline run, measure by measure.
+#[derive(Debug)]
+pub struct Comparison {
+    baseline: Measures,
+    candidate: Measures,
+    significance: Option<Outcome>,
+}
+
+/// The measures of a run that are compared.
+#[derive(Debug)]
+struct Measures {
+    count: u32,
+    average: Duration,
+    median: Duration,
+    p90: Duration,
+    p99: Duration,
+    max: Duration,
+    error_rate: f64,
+    /// The latencies in milliseconds, for testing the significance of a difference.
+    sample: Vec<f64>,
+}
+
+impl Measures {
+    fn from_facts(facts: &[Fact]) -> Option<Measures> {
+        let stats = DurationStats::from_facts(facts);
+        if stats.is_empty() {
+            return None;
+        }
+        let errors = facts.iter().filter(|f| f.is_error()).count();
+        Some(Measures {
+            count: facts.len() as u32,
+            average: stats.average(),
+            median: stats.median(),
+            p90: stats.percentile(90.0),
+            p99: stats.percentile(99.0),
+            max: stats.max().expect("Returned early if empty"),
+            error_rate: errors as f64 / facts.len() as f64,
+            sample: stats.sorted.iter().map(|d| d.to_ms()).collect(),
+        })
+    }
+}
 
-    #[test]
-    fn it_scale_arrays_an_array() {
-        let vec = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
-        assert_eq!(scale_array(&vec, 2), vec![1, 3, 5, 7, 9]);
-        assert_eq!(scale_array(&vec, 3), vec![1, 4, 7]);
+impl Comparison {
+    /// Compares the facts of two runs, which both need at least one fact.
+    pub fn new(baseline: &[Fact], candidate: &[Fact]) -> Result<Comparison, String> {
+        let measures = |facts, name| {
+            Measures::from_facts(facts).ok_or_else(|| format!("The {} run has no requests", name))
+        };
+        Ok(Comparison {
+            baseline: measures(baseline, "baseline")?,
+            candidate: measures(candidate, "candidate")?,
+            significance: None,
+        })
+    }
+
+    /// Tests whether the latencies differ by more than chance.
+    pub fn with_test(mut self, test: Test) -> Result<Comparison, String> {
+        self.significance = Some(test.run(&self.baseline.sample, &self.candidate.sample)?);
+        Ok(self)
+    }
+
+    fn latencies(&self) -> Vec<(&'static str, Duration, Duration)> {
+        let (baseline, candidate) = (&self.baseline, &self.candidate);
+        vec![
+            ("Average", baseline.average, candidate.average),
+            ("Median", baseline.median, candidate.median),
+            ("p90", baseline.p90, candidate.p90),
+            ("p99", baseline.p99, candidate.p99),
+            ("Longest", baseline.max, candidate.max),
+        ]
     }
 }
 
-impl fmt::Display for Summary {
+impl fmt::Display for Comparison {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "Summary")?;
+        writeln!(f, "{:<10} {:>14} {:>14} {:>9}", "", "Baseline", "Candidate", "Change")?;
         writeln!(
             f,
-            "  Average:   {} ms (std: {} ms)",
-            self.average.to_ms(),
-            self.stddev.to_ms()
+            "{:<10} {:>14} {:>14}",
+            "Requests", self.baseline.count, self.candidate.count
         )?;
-        writeln!(f, "  Median:    {} ms", self.median.to_ms())?;
-        writeln!(f, "  Longest:   {} ms", self.max.to_ms())?;
-        writeln!(f, "  Shortest:  {} ms", self.min.to_ms())?;
-        writeln!(f, "  Requests:  {}", self.count)?;
-        writeln!(f, "  Data:      {}", self.content_length)?;
-        writeln!(f)?;
-        writeln!(f, "Status codes:")?;
-        let mut status_counts: Vec<(&u16, &u32)> = self.status_counts.iter().collect();
-        status_counts.sort_by(|&(&code_a, _), &(&code_b, _)| code_a.cmp(&code_b));
-        for (k, v) in status_counts {
-            writeln!(f, "  {}: {}", k, v)?;
+        for (name, baseline, candidate) in self.latencies() {
+            let change = (candidate.to_ms() - baseline.to_ms()) / baseline.to_ms() * 100f64;
+            writeln!(
+                f,
+                "{:<10} {:>11.3} ms {:>11.3} ms {:>+8.1}%",
+                name,
+                baseline.to_ms(),
+                candidate.to_ms(),
+                change
+            )?;
         }
-        if self.chart_size != ChartSize::None {
-            writeln!(f)?;
-            writeln!(f, "Latency Percentiles (2% of requests per bar):")?;
-            let percentiles: Vec<f64> = self.percentiles.iter().map(|d| d.to_ms()).collect();
-            writeln!(f, "{}", self.chart(&percentiles))?;
+        writeln!(
+            f,
+            "{:<10} {:>13.2}% {:>13.2}%",
+            "Errors",
+            self.baseline.error_rate * 100f64,
+            self.candidate.error_rate * 100f64
+        )?;
+        if let Some(ref significance) = self.significance {
             writeln!(f)?;
-            writeln!(f, "Latency Histogram (each bar is 2% of max latency)")?;
-            writeln!(f, "{}", self.chart(&self.latency_histogram))?;
+            writeln!(f, "{}", significance)?;
         }
         Ok(())
     }
@@ -326,27 +2409,15 @@ mod summary_tests {
     use super::*;
 
     fn ok_zero_length_fact(duration: Duration) -> Fact {
-        Fact {
-            status: 200,
-            duration: duration,
-            content_length: ContentLength::zero(),
-        }
+        Fact::record(ContentLength::zero(), 200, duration)
     }
 
     fn ok_instant_fact(content_length: ContentLength) -> Fact {
-        Fact {
-            status: 200,
-            duration: Duration::new(0, 0),
-            content_length,
-        }
+        Fact::record(content_length, 200, Duration::new(0, 0))
     }
 
     fn zero_length_instant_fact(status: u16) -> Fact {
-        Fact {
-            status,
-            duration: Duration::new(0, 0),
-            content_length: ContentLength::zero(),
-        }
+        Fact::record(ContentLength::zero(), status, Duration::new(0, 0))
     }
 
     #[test]
@@ -468,6 +2539,25 @@ mod summary_tests {
         assert_eq!(summary.content_length.bytes(), 500);
     }
 
+    #[test]
+    fn summarizes_the_sizes_of_responses() {
+        let mut facts: Vec<Fact> = (1..=100)
+            .map(|bytes| ok_instant_fact(ContentLength::new(bytes * 100)))
+            .collect();
+        facts.push(Fact::elapsed(Duration::new(0, 0)).with_failure(Failure::Timeout));
+        let summary = Summary::from_facts(&facts);
+        let sizes = summary.sizes.as_ref().unwrap();
+        assert_eq!((sizes.min, sizes.median, sizes.p99, sizes.max), (100, 5_000, 9_900, 10_000));
+        assert_eq!(sizes.histogram.iter().sum::<u32>(), 100);
+        assert_eq!(sizes.histogram[49], 2);
+        assert_eq!(summary.to_json()["response_sizes"]["median"], 5_000);
+        assert!(summary.to_string().contains("Response Size Histogram"));
+
+        let same = Summary::from_facts(&[ok_instant_fact(ContentLength::new(10))]);
+        assert!(!same.to_string().contains("Response Size Histogram"));
+        assert!(Summary::from_facts(&[]).sizes.is_none());
+    }
+
     #[test]
     fn counts_status_codes() {
         let facts: Vec<Fact> = vec![
@@ -483,4 +2573,612 @@ mod summary_tests {
         let summary = Summary::from_facts(&facts);
         assert_eq!(summary.status_counts.get(&200), Some(&4));
     }
+
+    #[test]
+    fn does_not_count_facts_without_a_status() {
+        let facts = [
+            Fact::elapsed(Duration::new(1, 0)),
+            zero_length_instant_fact(200),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.status_counts.len(), 1);
+        assert_eq!(summary.status_counts.get(&200), Some(&1));
+    }
+
+    #[test]
+    fn counts_grpc_status_codes_apart_from_http_ones() {
+        let facts = [
+            zero_length_instant_fact(200).with_grpc_status(0),
+            zero_length_instant_fact(200).with_grpc_status(14),
+            zero_length_instant_fact(200),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.status_counts.len(), 1);
+        assert_eq!(summary.status_counts.get(&200), Some(&3));
+        assert_eq!(summary.grpc_status_counts.len(), 2);
+        assert_eq!(summary.grpc_status_counts.get(&14), Some(&1));
+        assert!(facts[1].is_error());
+        assert!(!facts[0].is_error());
+        let fact = Fact::from_json(&facts[1].to_json()).unwrap();
+        assert_eq!(fact.grpc_status, Some(14));
+    }
+
+    #[test]
+    fn splits_full_and_resumed_handshakes() {
+        let facts = [
+            Fact::elapsed(Duration::new(3, 0)).with_resumed(false),
+            Fact::elapsed(Duration::new(1, 0)).with_resumed(true),
+            Fact::elapsed(Duration::new(2, 0)).with_resumed(true),
+        ];
+        let handshakes = Summary::from_facts(&facts).handshakes.unwrap();
+        assert_eq!(handshakes.full, 1);
+        assert_eq!(handshakes.full_average, Duration::new(3, 0));
+        assert_eq!(handshakes.resumed, 2);
+        assert_eq!(handshakes.resumed_average, Duration::new(1, 500_000_000));
+
+        let facts = [ok_zero_length_fact(Duration::new(1, 0))];
+        assert!(Summary::from_facts(&facts).handshakes.is_none());
+    }
+
+    #[test]
+    fn computes_the_upload_rate_of_requests_that_sent_a_body() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0)).with_sent(ContentLength::new(1024)),
+            ok_zero_length_fact(Duration::new(1, 0)).with_sent(ContentLength::new(3072)),
+            ok_zero_length_fact(Duration::new(5, 0)),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.sent, ContentLength::new(4096));
+        assert_eq!(summary.upload_rate, Some(2048.0));
+
+        let facts = [ok_zero_length_fact(Duration::new(1, 0))];
+        assert_eq!(Summary::from_facts(&facts).upload_rate, None);
+    }
+
+    #[test]
+    fn summarizes_the_wait_for_100_continue() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(3, 0)).with_continue(Duration::new(1, 0)),
+            ok_zero_length_fact(Duration::new(3, 0)).with_continue(Duration::new(2, 0)),
+            ok_zero_length_fact(Duration::new(3, 0)),
+        ];
+        let continues = Summary::from_facts(&facts).continues.unwrap();
+        assert_eq!(continues.count, 2);
+        assert_eq!(continues.average, Duration::new(1, 500_000_000));
+        assert_eq!(continues.max, Duration::new(2, 0));
+    }
+
+    #[test]
+    fn summarizes_authentication_handshakes_apart() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(3, 0)).with_authentication(Duration::new(2, 0)),
+            ok_zero_length_fact(Duration::new(3, 0)),
+        ];
+        let summary = Summary::from_facts(&facts);
+        let authentications = summary.authentications.as_ref().unwrap();
+        assert_eq!(authentications.count, 1);
+        assert_eq!(authentications.max, Duration::new(2, 0));
+        assert_eq!(summary.to_json()["authentications"]["count"], 1);
+        assert_eq!(summary.median, Duration::new(3, 0));
+    }
+
+    #[test]
+    fn counts_http_versions() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0)).with_version("HTTP/1.0"),
+            ok_zero_length_fact(Duration::new(1, 0)).with_version("HTTP/1.0"),
+            ok_zero_length_fact(Duration::new(1, 0)),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.version_counts.len(), 1);
+        assert_eq!(summary.version_counts.get("HTTP/1.0"), Some(&2));
+    }
+
+    #[test]
+    fn counts_tls_versions_and_ciphers_of_connections() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0))
+                .with_connect(Duration::new(0, 5))
+                .with_tls_version("TLSv1.3"),
+            ok_zero_length_fact(Duration::new(1, 0)),
+            Fact::elapsed(Duration::new(1, 0))
+                .with_tls_version("TLSv1.2")
+                .with_cipher("ECDHE-RSA-AES128-GCM-SHA256".to_string()),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.tls_version_counts.get("TLSv1.3"), Some(&1));
+        assert_eq!(summary.tls_version_counts.get("TLSv1.2"), Some(&1));
+        assert_eq!(summary.to_json()["tls_versions"]["TLSv1.3"], 1);
+        assert_eq!(summary.to_json()["ciphers"]["ECDHE-RSA-AES128-GCM-SHA256"], 1);
+    }
+
+    #[test]
+    fn warns_when_http2_was_negotiated_down() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0))
+                .with_version("HTTP/2")
+                .with_alpn("h2".to_string()),
+            ok_zero_length_fact(Duration::new(1, 0))
+                .with_version("HTTP/1.1")
+                .with_alpn("http/1.1".to_string()),
+            ok_zero_length_fact(Duration::new(1, 0)).with_version("HTTP/1.1"),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.alpn_counts.get("h2"), Some(&1));
+        assert_eq!(summary.to_json()["alpn"]["http/1.1"], 1);
+        assert_eq!(summary.downgraded(), 2);
+        assert!(!format!("{}", summary).contains("Warning"));
+        let summary = summary.with_http2_asked(true);
+        assert!(format!("{}", summary).contains("2 responses came over an older version"));
+    }
+
+    #[test]
+    fn counts_http2_streams_per_connection() {
+        let stream = || ok_zero_length_fact(Duration::new(1, 0)).with_version("HTTP/2");
+        let facts = [
+            stream().with_connect(Duration::from_millis(5)),
+            stream(),
+            stream(),
+            stream().with_connect(Duration::from_millis(5)),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.to_json()["http2"]["connections"], 2);
+        assert_eq!(summary.to_json()["http2"]["streams"], 4);
+        assert!(format!("{}", summary).contains("4 (2.0 per connection)"));
+        assert!(Summary::from_facts(&[stream()]).to_json()["http2"]["streams"] == 1);
+    }
+
+    #[test]
+    fn breaks_several_urls_down() {
+        let facts = [
+            zero_length_instant_fact(200).with_target(0),
+            zero_length_instant_fact(500).with_target(1),
+            zero_length_instant_fact(200).with_target(1),
+            ok_zero_length_fact(Duration::new(2, 0)).with_target(1),
+        ];
+        let urls = ["http://a/".to_string(), "http://b/".to_string()];
+        let summary = Summary::from_facts(&facts).with_urls(&urls);
+        let json = summary.to_json();
+        assert_eq!(json["urls"][0]["url"], "http://a/");
+        assert_eq!(json["urls"][1]["requests"], 3);
+        assert!((json["urls"][1]["error_rate"].as_f64().unwrap() - 1.0 / 3.0).abs() < 1e-9);
+        let text = format!("{}", summary);
+        assert!(text.contains("Per url:"));
+        assert!(text.contains("http://b/         3    33.3%"));
+        let one = Summary::from_facts(&facts[..1]).with_urls(&urls);
+        assert!(!format!("{}", one).contains("Per url:"));
+        assert!(!format!("{}", Summary::from_facts(&facts)).contains("Per url:"));
+    }
+
+    #[test]
+    fn counts_pushed_streams_apart_from_responses() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0))
+                .with_version("HTTP/2")
+                .with_pushed(2, ContentLength::new(300)),
+            Fact::record(ContentLength::new(100), 200, Duration::new(1, 0))
+                .with_version("HTTP/2")
+                .with_pushed(1, ContentLength::new(50)),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.content_length, ContentLength::new(100));
+        assert_eq!(summary.to_json()["pushed"]["streams"], 3);
+        assert_eq!(summary.to_json()["pushed"]["bytes"], 350);
+        assert!(format!("{}", summary).contains("Pushed:      3 streams, 350 B"));
+        let fact = Fact::from_json(&facts[0].to_json()).unwrap();
+        assert_eq!((fact.pushed, fact.pushed_bytes.bytes()), (2, 300));
+    }
+
+    #[test]
+    fn warns_of_certificates_about_to_expire() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0))
+                .with_tls_version("TLSv1.3")
+                .with_expires_in(90 * 86_400),
+            ok_zero_length_fact(Duration::new(1, 0))
+                .with_tls_version("TLSv1.3")
+                .with_expires_in(10 * 86_400),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.to_json()["certificate_expires_in"], 10 * 86_400);
+        assert!(format!("{}", summary).contains("certificate expires in 10 days"));
+        let summary = summary.with_expiry_window(Duration::from_secs(7 * 86_400));
+        assert!(!format!("{}", summary).contains("Warning"));
+        let expired = [ok_zero_length_fact(Duration::new(1, 0))
+            .with_tls_version("TLSv1.2")
+            .with_expires_in(-3_600)];
+        assert!(format!("{}", Summary::from_facts(&expired)).contains("expired 1 hour ago"));
+    }
+
+    #[test]
+    fn counts_failures() {
+        let facts: Vec<Fact> = vec![
+            zero_length_instant_fact(200).with_failure(Failure::GraphQl),
+            zero_length_instant_fact(200),
+            zero_length_instant_fact(200).with_failure(Failure::GraphQl),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.failure_counts.get(&Failure::GraphQl), Some(&2));
+        assert_eq!(summary.status_counts.get(&200), Some(&3));
+    }
+
+    #[test]
+    fn counts_timeouts_apart_from_the_latencies() {
+        let facts: Vec<Fact> = vec![
+            ok_zero_length_fact(Duration::from_millis(10)),
+            ok_zero_length_fact(Duration::from_millis(20)),
+            Fact::elapsed(Duration::from_millis(1000)).with_failure(Failure::Timeout),
+            Fact::elapsed(Duration::from_millis(1000)).with_failure(Failure::Timeout),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(summary.timeouts, 2);
+        assert_eq!(summary.max, Duration::from_millis(20));
+        assert!(summary.pessimistic.is_none());
+
+        let summary = summary.with_pessimistic_percentiles(Duration::from_millis(500));
+        let (_, ref table) = summary.pessimistic.clone().unwrap();
+        assert_eq!(table[0], (50.0, Duration::from_millis(20)));
+        assert_eq!(table[1], (75.0, Duration::from_millis(500)));
+        let text = format!("{}", summary);
+        assert!(text.contains("Timeouts:  2, left out of the latencies"));
+        assert!(text.contains("Pessimistic percentiles, timeouts at 500 ms:"));
+        assert_eq!(summary.to_json()["latency"]["pessimistic"]["table"]["p99"], 500.0);
+
+        let all = [Fact::elapsed(Duration::from_millis(1000)).with_failure(Failure::Timeout)];
+        assert_eq!(Summary::from_facts(&all).timeouts, 1);
+    }
+
+    #[test]
+    fn tabulates_errors_by_category() {
+        let facts: Vec<Fact> = vec![
+            Fact::elapsed(Duration::new(0, 0)).with_failure(Failure::ConnectRefused),
+            zero_length_instant_fact(503),
+            zero_length_instant_fact(404),
+            zero_length_instant_fact(500),
+            zero_length_instant_fact(200),
+            zero_length_instant_fact(302).with_failure(Failure::Status),
+        ];
+        let summary = Summary::from_facts(&facts);
+        assert_eq!(
+            summary.error_counts,
+            vec![
+                ("5xx responses".to_string(), 2),
+                ("3xx responses".to_string(), 1),
+                ("4xx responses".to_string(), 1),
+                ("connections refused".to_string(), 1),
+            ]
+        );
+        let text = format!("{}", summary);
+        assert!(text.contains("Errors: 5 of 6 requests"));
+        assert!(text.contains("  connections refused        1   16.67%"));
+    }
+
+    #[test]
+    fn summarizes_connection_setup_times() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0)).with_connect(Duration::new(2, 0)),
+            ok_zero_length_fact(Duration::new(1, 0)),
+            ok_zero_length_fact(Duration::new(1, 0)).with_connect(Duration::new(4, 0)),
+        ];
+        let connections = Summary::from_facts(&facts)
+            .connections
+            .expect("Connections to be summarized");
+        assert_eq!(connections.count, 2);
+        assert_eq!(connections.average, Duration::new(3, 0));
+        assert_eq!(connections.max, Duration::new(4, 0));
+    }
+
+    #[test]
+    fn omits_connections_when_none_were_timed() {
+        let facts = [ok_zero_length_fact(Duration::new(1, 0))];
+        assert!(Summary::from_facts(&facts).connections.is_none());
+    }
+
+    #[test]
+    fn breaks_requests_down_by_worker() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0)).with_worker(1),
+            ok_zero_length_fact(Duration::new(3, 0)).with_worker(0),
+            ok_zero_length_fact(Duration::new(2, 0)).with_worker(1),
+        ];
+        let workers = Summary::from_facts(&facts).workers;
+        assert_eq!(workers.len(), 2);
+        assert_eq!((workers[0].worker, workers[0].count), (0, 1));
+        assert_eq!((workers[1].worker, workers[1].count), (1, 2));
+        assert_eq!(workers[1].max, Duration::new(2, 0));
+    }
+
+    #[test]
+    fn details_the_distribution_of_the_tail() {
+        let facts: Vec<Fact> = (1..=1_000)
+            .map(|ms| ok_zero_length_fact(Duration::from_millis(ms)))
+            .collect();
+        let summary = Summary::from_facts(&facts);
+        assert!(summary.distribution().is_empty());
+        let summary = summary.with_latency_distribution();
+        let distribution = summary.distribution();
+        assert_eq!(distribution.len(), 28);
+        assert_eq!(distribution[0], (10f64, Duration::from_millis(100)));
+        assert_eq!(distribution[18], (99.1, Duration::from_millis(991)));
+        assert_eq!(distribution[27], (100f64, Duration::from_millis(1_000)));
+        assert_eq!(summary.to_json()["latency"]["distribution"]["p99.5"], 995f64);
+        assert!(summary.to_string().contains("    99.9%      999.000 ms"));
+    }
+
+    #[test]
+    fn takes_trimmed_and_geometric_means() {
+        let mut facts: Vec<Fact> = (0..9)
+            .map(|_| ok_zero_length_fact(Duration::from_millis(10)))
+            .collect();
+        facts.push(ok_zero_length_fact(Duration::from_millis(1_000)));
+        let summary = Summary::from_facts(&facts)
+            .with_means(&[Mean::parse("trimmed:10%").unwrap(), Mean::Geometric]);
+        assert_eq!(summary.average, Duration::from_millis(109));
+        assert_eq!(summary.means[0].1, Duration::from_millis(10));
+        assert!((summary.means[1].1.to_ms() - 15.848_931).abs() < 1e-3);
+        assert_eq!(summary.to_json()["latency"]["means"]["trimmed_10"], 10f64);
+        assert!(summary.to_string().contains("  Trimmed:   10 ms (10% left out at each end)"));
+
+        assert_eq!(Mean::parse("trimmed:5"), Ok(Mean::Trimmed(5f64)));
+        assert!(Mean::parse("trimmed:50%").is_err());
+        assert!(Mean::parse("harmonic").is_err());
+    }
+
+    #[test]
+    fn bounds_the_mean_and_p99_with_confidence() {
+        let facts: Vec<Fact> = (1..=100)
+            .map(|ms| ok_zero_length_fact(Duration::from_millis(ms)))
+            .collect();
+        let summary = Summary::from_facts(&facts).with_confidence(95f64);
+        let confidence = summary.confidence.as_ref().unwrap();
+        assert!(confidence.mean.0 < summary.average && summary.average < confidence.mean.1);
+        assert!(confidence.p99.1 <= Duration::from_millis(100));
+        assert_eq!(summary.to_json()["confidence"]["level"], 95f64);
+        assert!(summary.to_string().contains("95% confidence intervals:"));
+        assert!(Summary::from_facts(&[]).with_confidence(95f64).confidence.is_none());
+    }
+
+    #[test]
+    fn charts_the_classes_of_responses_over_time() {
+        let spread = || Spread {
+            min: 0f64,
+            median: 0f64,
+            max: 0f64,
+        };
+        let rates = Rates {
+            windows: 250,
+            requests: spread(),
+            bytes: spread(),
+            points: Vec::new(),
+            correlation: None,
+            classes: vec![("2xx", vec![10; 250]), ("5xx", vec![0; 250])],
+        };
+        let summary = Summary::from_facts(&[ok_zero_length_fact(Duration::new(1, 0))])
+            .with_throughput(Some(rates));
+        let report = summary.to_string();
+        assert!(report.contains("Responses by status over time (each bar is 3 seconds)"));
+        assert!(report.contains("\n5xx:\n"));
+        assert_eq!(summary.to_json()["throughput"]["classes"]["2xx"][0], 10);
+    }
+
+    #[test]
+    fn counts_latencies_into_buckets_of_a_width() {
+        let facts = [
+            ok_zero_length_fact(Duration::from_millis(3)),
+            ok_zero_length_fact(Duration::from_millis(9)),
+            ok_zero_length_fact(Duration::from_millis(10)),
+            ok_zero_length_fact(Duration::from_millis(35)),
+        ];
+        let summary =
+            Summary::from_facts(&facts).with_histogram_bucket(Some(Duration::from_millis(10)));
+        assert_eq!(summary.latency_histogram, vec![2, 1, 0, 1]);
+        assert_eq!(summary.to_json()["latency"]["histogram"]["bucket"], 10f64);
+        assert!(summary.to_string().contains("Latency Histogram (each bar is 10 ms)"));
+
+        let long =
+            Summary::from_facts(&facts).with_histogram_bucket(Some(Duration::from_micros(100)));
+        assert_eq!(long.latency_histogram.len(), MAX_BUCKETS);
+        assert_eq!(long.latency_histogram[MAX_BUCKETS - 1], 2);
+        assert!(long.to_string().contains("(each bar is 0.1 ms, the last 9.9 ms and over)"));
+    }
+
+    #[test]
+    fn clamps_outliers_out_of_the_charts() {
+        let mut facts: Vec<Fact> = (1..=198)
+            .map(|ms| ok_zero_length_fact(Duration::from_millis(ms)))
+            .collect();
+        facts.push(ok_zero_length_fact(Duration::new(30, 0)));
+        facts.push(ok_zero_length_fact(Duration::new(20, 0)));
+        let summary = Summary::from_facts(&facts).with_chart_clamp(Some(99f64));
+        let (clamp, past) = summary.clamped().unwrap();
+        assert_eq!(clamp, Duration::from_millis(198));
+        assert_eq!(past, vec![Duration::new(30, 0), Duration::new(20, 0)]);
+        let report = summary.to_string();
+        assert!(report.contains("Clamped at p99, 198 ms, leaving out of the charts:\n  30000 ms"));
+        assert_eq!(summary.latency_histogram[0], 198);
+        assert_eq!(summary.latencies.histogram_up_to(clamp)[0], 1);
+        assert!(Summary::from_facts(&facts).clamped().is_none());
+    }
+
+    #[test]
+    fn draws_charts_of_the_height_asked_for() {
+        let facts = [ok_zero_length_fact(Duration::new(1, 0))];
+        let rows = |summary: &Summary| summary.chart(&summary.latency_histogram).lines().count();
+        assert_eq!(rows(&Summary::from_facts(&facts)), 10);
+        assert_eq!(rows(&Summary::from_facts(&facts).with_chart_height(Some(4))), 4);
+        let small = Summary::from_facts(&facts).with_chart_size(ChartSize::Small);
+        assert_eq!(rows(&small.with_chart_height(Some(30))), 30);
+    }
+
+    #[test]
+    fn estimates_the_requests_in_flight() {
+        let facts = [
+            ok_zero_length_fact(Duration::from_millis(100)),
+            ok_zero_length_fact(Duration::from_millis(300)),
+        ];
+        let summary = Summary::from_facts(&facts).with_concurrency(8, 20f64);
+        assert_eq!(summary.effective_concurrency(), Some(4f64));
+        assert_eq!(summary.to_json()["concurrency"]["configured"], 8);
+        assert!(summary.to_string().contains("only 50% of the concurrency"));
+
+        let busy = Summary::from_facts(&facts).with_concurrency(4, 20f64);
+        assert!(!busy.to_string().contains("Warning"));
+        assert_eq!(Summary::from_facts(&facts).effective_concurrency(), None);
+    }
+
+    #[test]
+    fn reads_back_facts_from_json() {
+        let fact = Fact::record(ContentLength::new(10), 201, Duration::new(1, 5))
+            .with_connect(Duration::new(0, 300))
+            .with_failure(Failure::Dns)
+            .with_version("HTTP/1.1")
+            .with_tls_version("TLSv1.2")
+            .with_cipher("TLS_AES_128_GCM_SHA256".to_string())
+            .with_iteration(Duration::new(2, 0))
+            .with_ramping_down()
+            .with_worker(3);
+        let read = Fact::from_json(&fact.to_json()).unwrap();
+        assert_eq!(read.status, Some(201));
+        assert_eq!(read.duration, Duration::new(1, 5));
+        assert_eq!(read.content_length, ContentLength::new(10));
+        assert_eq!(read.connect, Some(Duration::new(0, 300)));
+        assert_eq!(read.failure, Some(Failure::Dns));
+        assert_eq!(read.version, Some("HTTP/1.1"));
+        assert_eq!(read.tls_version, Some("TLSv1.2"));
+        assert_eq!(read.cipher, Some("TLS_AES_128_GCM_SHA256".to_string()));
+        assert_eq!(read.worker, Some(3));
+        assert_eq!(read.iteration, Some(Duration::new(2, 0)));
+        assert!(read.is_ramping_down());
+        assert_eq!(read.resumed, None);
+    }
+
+    #[test]
+    fn keeps_the_headline_of_facts() {
+        let fact = Fact::record(ContentLength::new(10), 503, Duration::new(1, 5))
+            .with_failure(Failure::Status)
+            .with_sent(ContentLength::new(4))
+            .with_worker(3);
+        let headline = fact.headline();
+        assert_eq!(headline.status, Some(503));
+        assert_eq!(headline.failure, Some(Failure::Status));
+        assert_eq!(headline.duration, Duration::new(1, 5));
+        assert_eq!(headline.content_length, ContentLength::new(10));
+        assert_eq!(headline.sent, ContentLength::new(4));
+        assert_eq!(headline.worker, None);
+    }
+
+    #[test]
+    fn converts_to_json() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0)).with_failure(Failure::Dns),
+            zero_length_instant_fact(404),
+        ];
+        let json = Summary::from_facts(&facts).to_json();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert!(json["metadata"].is_null());
+        assert_eq!(json["requests"], 2);
+        assert_eq!(json["latency"]["max"], 1_000f64);
+        assert_eq!(json["status_codes"]["404"], 1);
+        assert_eq!(json["failures"]["dns"], 1);
+        assert_eq!(json["connections"], Value::Null);
+    }
+
+    #[test]
+    fn tabulates_exact_percentiles() {
+        let facts: Vec<Fact> = (1..=1000)
+            .map(|ms| ok_zero_length_fact(Duration::from_millis(ms)))
+            .collect();
+        let table = Summary::from_facts(&facts).percentile_table;
+        assert_eq!(table[0], (50.0, Duration::from_millis(500)));
+        assert_eq!(table[3], (95.0, Duration::from_millis(950)));
+        assert_eq!(table[5], (99.9, Duration::from_millis(999)));
+    }
+
+    #[test]
+    fn groups_requests_by_their_group() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0)).with_group("b".to_string()),
+            zero_length_instant_fact(500).with_group("a".to_string()),
+            ok_zero_length_fact(Duration::new(3, 0)).with_group("b".to_string()),
+            ok_zero_length_fact(Duration::new(5, 0)),
+        ];
+        let groups = Summary::from_facts(&facts).groups;
+        assert_eq!(groups.len(), 2);
+        assert_eq!((groups[0].group.as_str(), groups[0].count), ("a", 1));
+        assert_eq!(groups[0].status_counts.get(&500), Some(&1));
+        assert_eq!((groups[1].group.as_str(), groups[1].count), ("b", 2));
+        assert_eq!(groups[1].max, Duration::new(3, 0));
+    }
+
+    #[test]
+    fn splits_cache_hits_from_misses() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0)).with_cache_hit(true),
+            ok_zero_length_fact(Duration::new(1, 0)).with_cache_hit(true),
+            ok_zero_length_fact(Duration::new(1, 0)).with_cache_hit(true),
+            ok_zero_length_fact(Duration::new(4, 0)).with_cache_hit(false),
+            ok_zero_length_fact(Duration::new(9, 0)),
+        ];
+        let cache = Summary::from_facts(&facts).cache.expect("Cache to be summarized");
+        assert_eq!(cache.hit_ratio(), 0.75);
+        assert_eq!(cache.hits.map(|hits| hits.count), Some(3));
+        assert_eq!(cache.misses.map(|misses| misses.p99), Some(Duration::new(4, 0)));
+    }
+
+    #[test]
+    fn omits_the_cache_without_cache_headers() {
+        let facts = [ok_zero_length_fact(Duration::new(1, 0))];
+        assert!(Summary::from_facts(&facts).cache.is_none());
+    }
+
+    #[test]
+    fn keeps_the_slowest_groups() {
+        let facts = [
+            ok_zero_length_fact(Duration::new(1, 0)).with_group("a".to_string()),
+            ok_zero_length_fact(Duration::new(3, 0)).with_group("b".to_string()),
+            ok_zero_length_fact(Duration::new(2, 0)).with_group("c".to_string()),
+        ];
+        let summary = Summary::from_facts(&facts)
+            .with_group_header("page")
+            .with_slowest_groups(2);
+        let groups: Vec<&str> = summary.groups.iter().map(|g| g.group.as_str()).collect();
+        assert_eq!(groups, vec!["b", "c"]);
+        assert!(summary.to_string().contains("Slowest pages:"));
+    }
+
+    #[test]
+    fn compares_two_runs() {
+        let baseline = [
+            ok_zero_length_fact(Duration::new(1, 0)),
+            ok_zero_length_fact(Duration::new(1, 0)),
+        ];
+        let candidate = [
+            ok_zero_length_fact(Duration::new(2, 0)),
+            zero_length_instant_fact(500),
+        ];
+        let comparison = Comparison::new(&baseline, &candidate).unwrap();
+        assert_eq!(comparison.candidate.error_rate, 0.5);
+        let report = comparison.to_string();
+        assert!(report.contains("Longest       1000.000 ms    2000.000 ms   +100.0%"));
+        assert!(report.contains("Errors              0.00%         50.00%"));
+        assert!(Comparison::new(&baseline, &[]).is_err());
+    }
+
+    #[test]
+    fn tests_the_significance_of_a_comparison() {
+        let run = |ms: &[u64]| -> Vec<Fact> {
+            ms.iter()
+                .map(|&ms| ok_zero_length_fact(Duration::from_millis(ms)))
+                .collect()
+        };
+        let comparison = Comparison::new(&run(&[10, 11, 12, 13]), &run(&[20, 21, 22, 23]))
+            .unwrap()
+            .with_test(Test::MannWhitney)
+            .unwrap();
+        let report = comparison.to_string();
+        assert!(report.contains("Mann-Whitney U test:"));
+        assert!(report.contains("significant at the 5% level"));
+        assert!(Comparison::new(&run(&[10]), &run(&[20])).unwrap().with_test(Test::Welch).is_err());
+    }
 }
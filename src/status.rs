@@ -0,0 +1,77 @@
+/// The statuses that are, or that are not, failures, so that responses a benchmark does not
+/// expect count as errors even when the server answered them without complaint.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StatusCheck {
+    /// Only the statuses listed are failures.
+    FailOn(Vec<Statuses>),
+    /// Every status but those listed is a failure.
+    Expect(Vec<Statuses>),
+}
+
+/// A status, or a whole class of them such as `5xx`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Statuses {
+    One(u16),
+    Class(u16),
+}
+
+impl Statuses {
+    fn contains(self, status: u16) -> bool {
+        match self {
+            Statuses::One(one) => status == one,
+            Statuses::Class(class) => status / 100 == class,
+        }
+    }
+}
+
+impl StatusCheck {
+    /// Whether a response with the status counts as a failure.
+    pub fn fails(&self, status: u16) -> bool {
+        match *self {
+            StatusCheck::FailOn(ref listed) => listed.iter().any(|s| s.contains(status)),
+            StatusCheck::Expect(ref listed) => !listed.iter().any(|s| s.contains(status)),
+        }
+    }
+}
+
+/// Reads a list of statuses and classes of them, such as `200,204` or `4xx,5xx`.
+pub fn parse(list: &str) -> Result<Vec<Statuses>, String> {
+    list.split(',')
+        .map(str::trim)
+        .map(|status| {
+            let invalid = || format!("Expected a status such as 404 or 5xx but got {}", status);
+            let lower = status.to_ascii_lowercase();
+            match lower.strip_suffix("xx") {
+                Some(class) => match class.parse::<u16>() {
+                    Ok(class) if (1..=5).contains(&class) => Ok(Statuses::Class(class)),
+                    _ => Err(invalid()),
+                },
+                None => match lower.parse::<u16>() {
+                    Ok(one) if (100..=599).contains(&one) => Ok(Statuses::One(one)),
+                    _ => Err(invalid()),
+                },
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_checks_statuses() {
+        let fail_on = StatusCheck::FailOn(parse("4xx, 5XX").unwrap());
+        assert!(fail_on.fails(404));
+        assert!(fail_on.fails(503));
+        assert!(!fail_on.fails(302));
+
+        let expect = StatusCheck::Expect(parse("200,204").unwrap());
+        assert!(!expect.fails(204));
+        assert!(expect.fails(201));
+
+        assert!(parse("6xx").is_err());
+        assert!(parse("99").is_err());
+        assert!(parse("ok").is_err());
+    }
+}
@@ -0,0 +1,273 @@
+use fake;
+use serde_json::{Map, Value};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tera::{Context, Tera};
+
+/// The name the template is registered under.
+const NAME: &str = "template";
+
+/// A tera template that renders a fresh body, url or header value for every request, with
+/// the functions of the `fake` module to make up data. Templates see:
+///
+/// * `n`, how many requests the thread made before this one
+/// * `request`, the number of the request counting every thread's, unique within a run
+/// * `url`, the url the request is made to
+/// * `timestamp`, the milliseconds since the unix epoch
+/// * `env`, the environment variables rench was started with
+/// * `vu`, the number of the virtual user, or thread, making the request
+/// * `row`, the row of the data file the request takes its turn on, by column name
+#[derive(Clone)]
+pub struct Template {
+    tera: Tera,
+    content_type: String,
+    env: Value,
+    rows: Arc<Vec<Value>>,
+    unique: bool,
+    sticky: bool,
+    user: usize,
+    taken: Arc<AtomicUsize>,
+    requests: Arc<AtomicUsize>,
+}
+
+impl Template {
+    /// Reads the template in the file. Its content type goes by the extension before the
+    /// `.tera`, so that `body.json.tera` is sent as json.
+    pub fn load(path: &str) -> Result<Template, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let mut template = Template::parse(&text)?;
+        let name = path.trim_end_matches(".tera");
+        template.content_type = match Path::new(name).extension().and_then(|ext| ext.to_str()) {
+            Some("json") => "application/json",
+            Some("xml") => "application/xml",
+            Some("html") => "text/html",
+            _ => "text/plain",
+        }
+        .to_string();
+        Ok(template)
+    }
+
+    /// A template of plain text.
+    pub fn parse(text: &str) -> Result<Template, String> {
+        let mut tera = Tera::default();
+        fake::register(&mut tera);
+        tera.add_raw_template(NAME, &fake::name_arguments(text))
+            .map_err(|e| format!("Invalid template: {}", describe(&e)))?;
+        let env = env::vars()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect::<Map<String, Value>>();
+        Ok(Template {
+            tera,
+            content_type: "text/plain".to_string(),
+            env: Value::Object(env),
+            rows: Arc::new(Vec::new()),
+            unique: false,
+            sticky: false,
+            user: 0,
+            taken: Arc::new(AtomicUsize::new(0)),
+            requests: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Hands the rows out to the requests in turn.
+    pub fn with_rows(mut self, rows: Vec<Value>) -> Self {
+        self.rows = Arc::new(rows);
+        self
+    }
+
+    /// Hands each row out to one request at most, across every thread, rather than starting
+    /// over once they have all had their turn. Requests are only made while there are rows
+    /// left to take.
+    pub fn with_unique_rows(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
+    /// Takes a row for a request about to be made, false once a template with unique rows has
+    /// none left. Templates that start over on the rows always have one.
+    pub fn take_row(&self) -> bool {
+        !self.unique || self.taken.fetch_add(1, Ordering::Relaxed) < self.rows.len()
+    }
+
+    /// Gives each virtual user one of the rows for all of its requests, rather than handing
+    /// them out to the requests in turn.
+    pub fn with_sticky_rows(mut self) -> Self {
+        self.sticky = true;
+        self
+    }
+
+    /// Renders the template for the requests of the nth virtual user.
+    pub fn with_user(mut self, user: usize) -> Self {
+        self.user = user;
+        self
+    }
+
+    /// The content type the rendered bodies are sent with.
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    /// Renders the template for the nth request the thread makes to the url.
+    pub fn render(&self, n: usize, url: &str) -> Result<String, String> {
+        let request = self.requests.fetch_add(1, Ordering::Relaxed);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or(0);
+
+        let mut context = Context::new();
+        context.insert("n", &n);
+        context.insert("request", &request);
+        context.insert("url", url);
+        context.insert("timestamp", &timestamp);
+        context.insert("env", &self.env);
+        context.insert("vu", &self.user);
+        if !self.rows.is_empty() {
+            let row = if self.sticky { self.user } else { request };
+            context.insert("row", &self.rows[row % self.rows.len()]);
+        }
+        self.tera
+            .render(NAME, &context)
+            .map_err(|e| describe(&e))
+    }
+}
+
+/// The error along with what caused it, as tera keeps the useful part in its source.
+fn describe(error: &::tera::Error) -> String {
+    use std::error::Error;
+
+    let mut description = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        description.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    description
+}
+
+/// Reads the rows of a csv file whose first line names the columns. Fields may be quoted,
+/// with a doubled quote standing for a quote.
+pub fn read_rows(path: &str) -> Result<Vec<Value>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let columns = match lines.next() {
+        Some(header) => fields(header),
+        None => return Err(format!("{} has no header row", path)),
+    };
+    lines
+        .enumerate()
+        .map(|(i, line)| {
+            let values = fields(line);
+            if values.len() != columns.len() {
+                return Err(format!(
+                    "Expected {} fields on row {} of {} but got {}",
+                    columns.len(),
+                    i + 1,
+                    path,
+                    values.len()
+                ));
+            }
+            Ok(Value::Object(
+                columns
+                    .iter()
+                    .cloned()
+                    .zip(values.into_iter().map(Value::String))
+                    .collect(),
+            ))
+        })
+        .collect()
+}
+
+fn fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_renders_each_request() {
+        let template =
+            Template::parse(r#"{"order": "{{ request }}-{{ n }}", "url": "{{ url }}"}"#).unwrap();
+        let worker = template.clone();
+        assert_eq!(
+            template.render(0, "http://a/").unwrap(),
+            r#"{"order": "0-0", "url": "http://a/"}"#
+        );
+        assert_eq!(
+            worker.render(0, "http://a/").unwrap(),
+            r#"{"order": "1-0", "url": "http://a/"}"#
+        );
+        assert!(Template::parse("{{ unclosed").is_err());
+        assert!(Template::parse("{{ missing }}")
+            .unwrap()
+            .render(0, "")
+            .is_err());
+    }
+
+    #[test]
+    fn it_takes_turns_on_the_rows() {
+        let rows = vec![json!({"sku": "a"}), json!({"sku": "b"})];
+        let template = Template::parse("{{ row.sku }}").unwrap().with_rows(rows);
+        let bodies: Vec<String> = (0..3).map(|n| template.render(n, "").unwrap()).collect();
+        assert_eq!(bodies, vec!["a", "b", "a"]);
+    }
+
+    #[test]
+    fn it_takes_unique_rows_until_they_run_out() {
+        let rows = vec![json!({"sku": "a"}), json!({"sku": "b"})];
+        let template = Template::parse("{{ row.sku }}")
+            .unwrap()
+            .with_rows(rows)
+            .with_unique_rows();
+        let worker = template.clone();
+        assert!(template.take_row());
+        assert!(worker.take_row());
+        assert!(!template.take_row());
+        assert!(!worker.take_row());
+        let bodies = vec![template.render(0, "").unwrap(), worker.render(0, "").unwrap()];
+        assert_eq!(bodies, vec!["a", "b"]);
+
+        let cycled = Template::parse("").unwrap().with_rows(vec![json!({})]);
+        assert!((0..3).all(|_| cycled.take_row()));
+    }
+
+    #[test]
+    fn it_sticks_to_the_row_of_the_user() {
+        let rows = vec![json!({"sku": "a"}), json!({"sku": "b"})];
+        let template = Template::parse("{{ vu }}{{ row.sku }}")
+            .unwrap()
+            .with_rows(rows)
+            .with_sticky_rows();
+        let user = template.clone().with_user(3);
+        let bodies: Vec<String> = (0..2).map(|n| user.render(n, "").unwrap()).collect();
+        assert_eq!(bodies, vec!["3b", "3b"]);
+        assert_eq!(template.render(0, "").unwrap(), "0a");
+    }
+
+    #[test]
+    fn it_splits_csv_fields() {
+        assert_eq!(fields("a,b"), vec!["a", "b"]);
+        assert_eq!(fields(r#""a, ""b""",,c"#), vec![r#"a, "b""#, "", "c"]);
+    }
+}
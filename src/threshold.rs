@@ -0,0 +1,281 @@
+use duration;
+use stats::Summary;
+use std::fmt;
+use std::time::Duration;
+
+/// A measure of a run that a threshold holds it to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Metric {
+    Percentile(f64),
+    Average,
+    Median,
+    Min,
+    Max,
+    ErrorRate,
+    Rps,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bound {
+    Below,
+    AtMost,
+    Above,
+    AtLeast,
+}
+
+/// A service level a run is to meet, such as `p95 < 150ms`, `error_rate < 0.5%` or
+/// `rps > 1000`, checked once it is over.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Threshold {
+    name: String,
+    metric: Metric,
+    bound: Bound,
+    /// Milliseconds for latencies, a fraction for the error rate.
+    limit: f64,
+}
+
+/// How a run measured up to a threshold.
+#[derive(Debug)]
+pub struct Outcome<'a> {
+    pub threshold: &'a Threshold,
+    /// What was measured, which is nothing for latencies when no request has one.
+    pub measured: Option<f64>,
+    pub passed: bool,
+}
+
+impl Threshold {
+    /// Reads a threshold such as `p95 < 150ms`, named after itself unless it is given as
+    /// `name = p95 < 150ms`.
+    pub fn parse(line: &str) -> Result<Threshold, String> {
+        let (name, expression) = match line.split_once('=') {
+            Some((name, expression)) if !name.contains(['<', '>']) => {
+                (name.trim(), expression.trim())
+            }
+            _ => (line.trim(), line.trim()),
+        };
+        let invalid = || format!("Expected a threshold such as p95 < 150ms but got {}", line);
+        let operators = [
+            ("<=", Bound::AtMost),
+            (">=", Bound::AtLeast),
+            ("<", Bound::Below),
+            (">", Bound::Above),
+        ];
+        let (split, bound, len) = operators
+            .iter()
+            .filter_map(|&(operator, bound)| {
+                expression.find(operator).map(|at| (at, bound, operator.len()))
+            })
+            .min_by_key(|&(at, _, _)| at)
+            .ok_or_else(invalid)?;
+        let (metric, limit) = (expression[..split].trim(), expression[split + len..].trim());
+        let metric = match metric.to_ascii_lowercase().as_str() {
+            "average" | "mean" => Metric::Average,
+            "median" => Metric::Median,
+            "min" => Metric::Min,
+            "max" => Metric::Max,
+            "error_rate" => Metric::ErrorRate,
+            "rps" => Metric::Rps,
+            metric => match metric.strip_prefix('p').map(str::parse::<f64>) {
+                Some(Ok(percentage)) if percentage > 0f64 && percentage <= 100f64 => {
+                    Metric::Percentile(percentage)
+                }
+                _ => return Err(invalid()),
+            },
+        };
+        let limit = match metric {
+            Metric::ErrorRate => match limit.strip_suffix('%') {
+                Some(percent) => percent.trim().parse::<f64>().map(|percent| percent / 100f64),
+                None => limit.parse::<f64>(),
+            }
+            .map_err(|_| invalid())?,
+            Metric::Rps => limit.parse::<f64>().map_err(|_| invalid())?,
+            _ => duration::parse(limit)?.as_secs_f64() * 1_000f64,
+        };
+        Ok(Threshold {
+            name: name.to_string(),
+            metric,
+            bound,
+            limit,
+        })
+    }
+
+    /// The name the threshold is reported by.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Checks the threshold against the summary of a run that made so many requests a
+    /// second.
+    pub fn check(&self, summary: &Summary, rps: f64) -> Outcome<'_> {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1_000f64;
+        let latency = |duration: Duration| Some(ms(duration)).filter(|_| summary.has_latencies());
+        let measured = match self.metric {
+            Metric::Percentile(percentage) => summary.latency_at(percentage).map(ms),
+            Metric::Average => latency(summary.average()),
+            Metric::Median => latency(summary.median()),
+            Metric::Min => latency(summary.min()),
+            Metric::Max => latency(summary.max()),
+            Metric::ErrorRate => Some(summary.error_rate()),
+            Metric::Rps => Some(rps),
+        };
+        let passed = measured.is_some_and(|measured| match self.bound {
+            Bound::Below => measured < self.limit,
+            Bound::AtMost => measured <= self.limit,
+            Bound::Above => measured > self.limit,
+            Bound::AtLeast => measured >= self.limit,
+        });
+        Outcome {
+            threshold: self,
+            measured,
+            passed,
+        }
+    }
+}
+
+impl Outcome<'_> {
+    /// What was measured, in the units of the threshold.
+    fn measured_text(&self) -> String {
+        match (self.measured, self.threshold.metric) {
+            (None, _) => "no latencies measured".to_string(),
+            (Some(rate), Metric::ErrorRate) => format!("{:.2}%", rate * 100f64),
+            (Some(rps), Metric::Rps) => format!("{:.1}", rps),
+            (Some(ms), _) => format!("{:.3} ms", ms),
+        }
+    }
+}
+
+impl Outcome<'_> {
+    /// The threshold along with what was measured.
+    pub fn describe(&self) -> String {
+        match self.measured {
+            Some(_) => format!("{} (measured {})", self.threshold.name, self.measured_text()),
+            None => format!("{} ({})", self.threshold.name, self.measured_text()),
+        }
+    }
+}
+
+impl fmt::Display for Outcome<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let verdict = if self.passed { "pass" } else { "FAIL" };
+        write!(f, "{}  {}", verdict, self.describe())
+    }
+}
+
+/// Reads the thresholds of the `[thresholds]` section of a config file, one to a line, with
+/// `#` starting comments. Other sections are left alone.
+pub fn parse_config(config: &str) -> Result<Vec<Threshold>, String> {
+    let mut section = None;
+    let mut thresholds = Vec::new();
+    for line in config.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = Some(line[1..line.len() - 1].trim().to_string());
+        } else if section.as_deref() == Some("thresholds") {
+            thresholds.push(Threshold::parse(line)?);
+        }
+    }
+    Ok(thresholds)
+}
+
+/// Writes how the run measured up to its thresholds as a JUnit test suite, one test case
+/// to a threshold, for CI systems to show alongside their tests.
+pub fn junit(outcomes: &[Outcome]) -> String {
+    let failures = outcomes.iter().filter(|outcome| !outcome.passed).count();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuite name=\"rench\" tests=\"{}\" failures=\"{}\">\n",
+        outcomes.len(),
+        failures
+    ));
+    for outcome in outcomes {
+        let name = escape(outcome.threshold.name());
+        if outcome.passed {
+            xml.push_str(&format!("  <testcase classname=\"thresholds\" name=\"{}\"/>\n", name));
+        } else {
+            xml.push_str(&format!(
+                "  <testcase classname=\"thresholds\" name=\"{}\">\n    \
+                 <failure message=\"{}\"/>\n  </testcase>\n",
+                name,
+                escape(&outcome.to_string())
+            ));
+        }
+    }
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+/// A github flavored markdown table of how the run measured up to its thresholds.
+pub fn markdown(outcomes: &[Outcome]) -> String {
+    let mut markdown = String::from("### Thresholds\n\n");
+    markdown.push_str("| | Threshold | Measured |\n| --- | --- | --: |\n");
+    for outcome in outcomes {
+        markdown.push_str(&format!(
+            "| {} | {} | {} |\n",
+            if outcome.passed { "✅" } else { "❌" },
+            outcome.threshold.name().replace('|', "\\|"),
+            outcome.measured_text()
+        ));
+    }
+    markdown
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stats::Fact;
+    use content_length::ContentLength;
+
+    #[test]
+    fn it_reads_the_thresholds_section() {
+        let thresholds = parse_config(
+            "[run]\nrequests = 10\n\n[thresholds]\n# Latency\np95 < 150ms\n\
+             errors = error_rate <= 0.5%\nrps >= 1000\n",
+        )
+        .unwrap();
+        assert_eq!(thresholds.len(), 3);
+        assert_eq!(thresholds[0].name(), "p95 < 150ms");
+        assert_eq!(thresholds[0].metric, Metric::Percentile(95f64));
+        assert_eq!(thresholds[0].limit, 150f64);
+        assert_eq!(thresholds[1].name(), "errors");
+        assert_eq!(thresholds[1].bound, Bound::AtMost);
+        assert!((thresholds[1].limit - 0.005).abs() < 1e-12);
+        assert_eq!(thresholds[2].metric, Metric::Rps);
+
+        assert!(Threshold::parse("p95 150ms").is_err());
+        assert!(Threshold::parse("speed < 1s").is_err());
+        assert!(parse_config("[thresholds]\np101 < 1s").is_err());
+    }
+
+    #[test]
+    fn it_checks_thresholds_against_a_run() {
+        let facts: Vec<Fact> = (1..=10)
+            .map(|ms| Fact::record(ContentLength::zero(), 200, Duration::from_millis(ms)))
+            .collect();
+        let summary = Summary::from_facts(&facts);
+        let thresholds = parse_config(
+            "[thresholds]\np90 <= 9ms\nmax < 10ms\nerror_rate < 1%\nrps > 100",
+        )
+        .unwrap();
+        let outcomes: Vec<Outcome> =
+            thresholds.iter().map(|threshold| threshold.check(&summary, 50f64)).collect();
+        let passed: Vec<bool> = outcomes.iter().map(|outcome| outcome.passed).collect();
+        assert_eq!(passed, vec![true, false, true, false]);
+        assert_eq!(outcomes[1].to_string(), "FAIL  max < 10ms (measured 10.000 ms)");
+
+        assert!(markdown(&outcomes).contains("| ❌ | rps > 100 | 50.0 |\n"));
+
+        let xml = junit(&outcomes);
+        assert!(xml.contains("tests=\"4\" failures=\"2\""));
+        assert!(xml.contains("<testcase classname=\"thresholds\" name=\"p90 &lt;= 9ms\"/>"));
+    }
+}
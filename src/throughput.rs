@@ -0,0 +1,237 @@
+use stats::Fact;
+use std::cmp;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long each window of the run is.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// The classes of responses counted in each window, as `Fact::class` gives them.
+const CLASSES: [&str; 6] = ["1xx", "2xx", "3xx", "4xx", "5xx", "failed"];
+
+/// How the requests and bytes of each second of a run spread, to tell a steady throughput
+/// from one that only averages out.
+#[derive(Debug)]
+pub struct Rates {
+    pub windows: usize,
+    pub requests: Spread,
+    pub bytes: Spread,
+    /// The requests and p99 latency in milliseconds of each window that had any.
+    pub points: Vec<(f64, f64)>,
+    /// How closely the p99 of the windows followed their requests, from -1 to 1, when there
+    /// are enough windows that vary to tell.
+    pub correlation: Option<f64>,
+    /// The responses of each class that any window had, counted window by window including
+    /// the last and partial one.
+    pub classes: Vec<(&'static str, Vec<u32>)>,
+}
+
+/// The least, median and most of something per second.
+#[derive(Debug, PartialEq)]
+pub struct Spread {
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+impl Spread {
+    fn of(mut values: Vec<f64>) -> Spread {
+        values.sort_by(|a, b| a.partial_cmp(b).expect("Expected rates to compare"));
+        let mid = values.len() / 2;
+        let median = if values.len().is_multiple_of(2) {
+            (values[mid - 1] + values[mid]) / 2f64
+        } else {
+            values[mid]
+        };
+        Spread {
+            min: values[0],
+            median,
+            max: values[values.len() - 1],
+        }
+    }
+}
+
+/// Counts the responses of a run into windows of a second as they arrive, seconds in which
+/// nothing arrived counting as windows of none.
+#[derive(Clone)]
+pub struct Throughput {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    since: Instant,
+    bytes: u64,
+    durations: Vec<Duration>,
+    classes: [u32; 6],
+    windows: Vec<Window>,
+}
+
+/// The requests that arrived in a second.
+#[derive(Clone)]
+struct Window {
+    requests: u32,
+    bytes: u64,
+    p99: Option<Duration>,
+    classes: [u32; 6],
+}
+
+impl State {
+    /// The window so far.
+    fn window(&self) -> Window {
+        let mut durations = self.durations.clone();
+        durations.sort();
+        let rank = (durations.len() as f64 * 0.99).ceil() as usize;
+        Window {
+            requests: durations.len() as u32,
+            bytes: self.bytes,
+            p99: durations.get(cmp::max(rank, 1) - 1).cloned(),
+            classes: self.classes,
+        }
+    }
+
+    fn close_window(&mut self) {
+        let window = self.window();
+        self.windows.push(window);
+        self.since += WINDOW;
+        self.bytes = 0;
+        self.durations.clear();
+        self.classes = [0; 6];
+    }
+}
+
+impl Throughput {
+    pub fn start() -> Throughput {
+        Throughput::starting_at(Instant::now())
+    }
+
+    fn starting_at(since: Instant) -> Throughput {
+        Throughput {
+            state: Arc::new(Mutex::new(State {
+                since,
+                bytes: 0,
+                durations: Vec::new(),
+                classes: [0; 6],
+                windows: Vec::new(),
+            })),
+        }
+    }
+
+    pub fn record(&self, fact: &Fact) {
+        self.record_at(fact, Instant::now());
+    }
+
+    fn record_at(&self, fact: &Fact, now: Instant) {
+        let mut state = self.state.lock().expect("Expected the throughput lock");
+        while now.duration_since(state.since) >= WINDOW {
+            state.close_window();
+        }
+        state.durations.push(fact.duration());
+        if let Some(class) = CLASSES.iter().position(|&class| class == fact.class()) {
+            state.classes[class] += 1;
+        }
+        state.bytes += fact.content_length().bytes();
+    }
+
+    /// The spread of the whole windows, the last and partial one left out unless it is all
+    /// there is, or `None` when nothing was recorded.
+    pub fn rates(&self) -> Option<Rates> {
+        let state = self.state.lock().expect("Expected the throughput lock");
+        let mut windows = state.windows.clone();
+        let partial = if state.durations.is_empty() {
+            None
+        } else {
+            Some(state.window())
+        };
+        let mut every = windows.clone();
+        every.extend(partial.clone());
+        if windows.is_empty() {
+            windows.extend(partial);
+        }
+        if windows.is_empty() {
+            return None;
+        }
+        let classes = CLASSES
+            .iter()
+            .enumerate()
+            .map(|(i, &class)| (class, every.iter().map(|w| w.classes[i]).collect::<Vec<_>>()))
+            .filter(|(_, counts)| counts.iter().any(|&count| count > 0))
+            .collect();
+        let points: Vec<(f64, f64)> = windows
+            .iter()
+            .filter_map(|window| {
+                let p99 = window.p99?.as_secs_f64() * 1_000f64;
+                Some((f64::from(window.requests), p99))
+            })
+            .collect();
+        Some(Rates {
+            windows: windows.len(),
+            requests: Spread::of(windows.iter().map(|w| f64::from(w.requests)).collect()),
+            bytes: Spread::of(windows.iter().map(|w| w.bytes as f64).collect()),
+            correlation: correlation(&points),
+            points,
+            classes,
+        })
+    }
+}
+
+/// Pearson's correlation of the pairs, when there are at least three and both sides vary.
+fn correlation(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 3 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0f64, 0f64, 0f64);
+    for &(x, y) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_x == 0f64 || variance_y == 0f64 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use content_length::ContentLength;
+
+    #[test]
+    fn it_spreads_the_seconds_of_a_run() {
+        let start = Instant::now();
+        let throughput = Throughput::starting_at(start);
+        let fact = || Fact::record(ContentLength::new(100), 200, Duration::from_millis(1));
+        for millis in &[0, 100, 200, 1_100, 3_500, 3_600, 3_700, 3_800] {
+            throughput.record_at(&fact(), start + Duration::from_millis(*millis));
+        }
+        let rates = throughput.rates().unwrap();
+        assert_eq!(rates.windows, 3);
+        assert_eq!(
+            rates.requests,
+            Spread {
+                min: 0f64,
+                median: 1f64,
+                max: 3f64
+            }
+        );
+        assert_eq!(rates.bytes.max, 300f64);
+        assert_eq!(rates.points, vec![(3f64, 1f64), (1f64, 1f64)]);
+        assert_eq!(rates.correlation, None);
+        assert_eq!(rates.classes, vec![("2xx", vec![3, 1, 0, 4])]);
+
+        assert!(Throughput::starting_at(start).rates().is_none());
+    }
+
+    #[test]
+    fn it_correlates_latency_with_throughput() {
+        let rising = [(10f64, 5f64), (20f64, 7f64), (30f64, 9f64)];
+        assert!((correlation(&rising).unwrap() - 1f64).abs() < 1e-9);
+        let falling = [(10f64, 9f64), (20f64, 7f64), (30f64, 5f64)];
+        assert!((correlation(&falling).unwrap() + 1f64).abs() < 1e-9);
+        assert_eq!(correlation(&[(10f64, 5f64), (10f64, 7f64), (10f64, 9f64)]), None);
+        assert_eq!(correlation(&rising[..2]), None);
+    }
+}
@@ -0,0 +1,60 @@
+use futures::future::Either;
+use futures::Future;
+use std::io;
+use std::time::Duration;
+use tokio_core::reactor::{Handle, Timeout};
+
+/// The error a request fails with once it has taken longer than it was allowed.
+pub fn timed_out(timeout: Duration) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("No response within {} ms", timeout.as_millis()),
+    )
+}
+
+/// Fails the future with `timed_out` when it has not finished within the timeout, if there
+/// is one, leaving it be otherwise.
+pub fn within<F>(
+    future: F,
+    timeout: Option<Duration>,
+    handle: &Handle,
+) -> impl Future<Item = F::Item, Error = F::Error>
+where
+    F: Future,
+    F::Error: From<io::Error>,
+{
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Either::B(future),
+    };
+    let timer = Timeout::new(timeout, handle).expect("Expected a timer for the timeout");
+    Either::A(future.select2(timer).then(move |raced| match raced {
+        Ok(Either::A((item, _))) => Ok(item),
+        Err(Either::A((e, _))) => Err(e),
+        Ok(Either::B(_)) => Err(F::Error::from(timed_out(timeout))),
+        Err(Either::B((e, _))) => Err(F::Error::from(e)),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use tokio_core::reactor::Core;
+
+    #[test]
+    fn it_fails_futures_that_take_too_long() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+        let never = future::empty::<(), io::Error>();
+        let e = core
+            .run(within(never, Some(Duration::from_millis(10)), &handle))
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+
+        let done = future::ok::<_, io::Error>(1);
+        assert_eq!(core.run(within(done, Some(Duration::from_secs(10)), &handle)).unwrap(), 1);
+        let done = future::ok::<_, io::Error>(2);
+        assert_eq!(core.run(within(done, None, &handle)).unwrap(), 2);
+    }
+}
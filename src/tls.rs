@@ -0,0 +1,370 @@
+use base64;
+use foreign_types::ForeignTypeRef;
+use libc::{c_char, c_int, c_long};
+use openssl::asn1::Asn1TimeRef;
+use openssl::hash::{hash2, MessageDigest};
+use openssl::ssl::{SslContextBuilder, SslMethod, SslRef};
+use openssl_sys::{self, ASN1_TIME, SSL_CTX};
+use std::error::Error;
+use std::ffi::CString;
+use std::{fmt, io, ptr};
+
+// Only defined by the bindings of newer openssl versions than the one this is built against.
+const SSL_CTRL_SET_MIN_PROTO_VERSION: c_int = 123;
+const SSL_CTRL_SET_MAX_PROTO_VERSION: c_int = 124;
+
+extern "C" {
+    fn SSL_CTX_set_ciphersuites(ctx: *mut SSL_CTX, suites: *const c_char) -> c_int;
+    fn ASN1_TIME_diff(
+        days: *mut c_int,
+        seconds: *mut c_int,
+        from: *const ASN1_TIME,
+        to: *const ASN1_TIME,
+    ) -> c_int;
+}
+
+/// How connections are secured, openssl's defaults for anything not set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    versions: Versions,
+    ciphers: Option<String>,
+    server_name: Option<String>,
+    pins: Vec<Pin>,
+}
+
+impl Options {
+    /// Limits the tls versions connections may negotiate.
+    pub fn with_versions(mut self, versions: Versions) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// Limits the cipher suites connections may negotiate to those of the colon separated
+    /// list. The suites of tls 1.3, named `TLS_*`, are set apart from those of the versions
+    /// before it, so giving only one kind leaves the defaults of the other.
+    pub fn with_ciphers(mut self, ciphers: &str) -> Self {
+        self.ciphers = Some(ciphers.to_string());
+        self
+    }
+
+    /// Sends the name to servers in place of the host of the url, for the certificate they
+    /// present and the tenant they route to.
+    pub fn with_server_name(mut self, server_name: &str) -> Self {
+        self.server_name = Some(server_name.to_string());
+        self
+    }
+
+    /// The name sent to servers in place of the host of the url, if there is one.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Only trusts servers whose certificate has one of the public keys.
+    pub fn with_pins(mut self, pins: Vec<Pin>) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    /// The public keys servers are trusted with, any of them when there are none.
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
+
+    /// The options in a few words, for the configuration printed at the start of a run.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        match (self.versions.min, self.versions.max) {
+            (Some(min), Some(max)) if min == max => parts.push(min.name().to_string()),
+            (Some(min), Some(max)) => parts.push(format!("{} to {}", min.name(), max.name())),
+            (Some(min), None) => parts.push(format!("{} and up", min.name())),
+            (None, Some(max)) => parts.push(format!("up to {}", max.name())),
+            (None, None) => {}
+        }
+        if let Some(ref ciphers) = self.ciphers {
+            parts.push(format!("ciphers {}", ciphers));
+        }
+        if let Some(ref name) = self.server_name {
+            parts.push(format!("sni {}", name));
+        }
+        if !self.pins.is_empty() {
+            parts.push(format!("{} pinned key(s)", self.pins.len()));
+        }
+        if parts.is_empty() {
+            "openssl defaults".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    /// Whether openssl can secure connections this way.
+    pub fn check(&self) -> Result<(), String> {
+        let mut context =
+            SslContextBuilder::new(SslMethod::tls()).map_err(|e| e.to_string())?;
+        self.apply(&mut context)
+    }
+
+    /// Sets up the context's connections to be secured this way.
+    pub fn apply(&self, context: &mut SslContextBuilder) -> Result<(), String> {
+        self.versions.apply(context)?;
+        if let Some(ref ciphers) = self.ciphers {
+            let (suites, ciphers): (Vec<&str>, Vec<&str>) = ciphers
+                .split(':')
+                .filter(|cipher| !cipher.is_empty())
+                .partition(|cipher| cipher.starts_with("TLS_"));
+            if !ciphers.is_empty() {
+                context
+                    .set_cipher_list(&ciphers.join(":"))
+                    .map_err(|_| format!("None of the ciphers {} are known", ciphers.join(":")))?;
+            }
+            if !suites.is_empty() {
+                let list = CString::new(suites.join(":"))
+                    .map_err(|_| "Expected ciphers without nul bytes".to_string())?;
+                let set = unsafe { SSL_CTX_set_ciphersuites(context.as_ptr(), list.as_ptr()) };
+                if set == 0 {
+                    return Err(format!("None of the ciphers {} are known", suites.join(":")));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The sha256 digest of a public key that servers' certificates are expected to have, given
+/// as curl takes it, `sha256//` before the digest in base64.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pin(Vec<u8>);
+
+impl Pin {
+    /// Parses the pins split by semicolons.
+    pub fn parse(pins: &str) -> Result<Vec<Pin>, String> {
+        pins.split(';')
+            .map(|pin| {
+                let digest = pin
+                    .trim()
+                    .strip_prefix("sha256//")
+                    .and_then(|digest| base64::decode(digest).ok())
+                    .filter(|digest| digest.len() == 32);
+                match digest {
+                    Some(digest) => Ok(Pin(digest)),
+                    None => Err(format!("Expected a pin of sha256//BASE64 but got {}", pin)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Checks the certificate the server presented has one of the pinned public keys.
+pub fn check_pins(pins: &[Pin], ssl: &SslRef) -> io::Result<()> {
+    if pins.is_empty() {
+        return Ok(());
+    }
+    // The chain a client is given starts with the server's own certificate.
+    let key = ssl
+        .peer_cert_chain()
+        .and_then(|chain| chain.iter().next())
+        .and_then(|certificate| certificate.public_key().ok())
+        .and_then(|key| key.public_key_to_der().ok());
+    let digest = key.and_then(|key| hash2(MessageDigest::sha256(), &key).ok());
+    match digest {
+        Some(ref digest) if pins.iter().any(|pin| pin.0[..] == digest[..]) => Ok(()),
+        _ => Err(io::Error::other(PinMismatch)),
+    }
+}
+
+/// How many seconds are left before the certificate the server presented expires, fewer
+/// than none once it has.
+pub fn expires_in(ssl: &SslRef) -> Option<i64> {
+    let chain = ssl.peer_cert_chain()?;
+    let certificate = chain.iter().next()?;
+    seconds_until(certificate.not_after())
+}
+
+fn seconds_until(time: &Asn1TimeRef) -> Option<i64> {
+    let (mut days, mut seconds) = (0, 0);
+    // Diffing from no time at all diffs from now.
+    let diffed = unsafe { ASN1_TIME_diff(&mut days, &mut seconds, ptr::null(), time.as_ptr()) };
+    if diffed == 0 {
+        return None;
+    }
+    Some(i64::from(days) * 86_400 + i64::from(seconds))
+}
+
+#[derive(Debug)]
+struct PinMismatch;
+
+impl fmt::Display for PinMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The server's public key is not one of those pinned")
+    }
+}
+
+impl Error for PinMismatch {}
+
+/// Whether an error came from a server whose public key is not one of those pinned.
+pub fn is_pin_mismatch(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|inner| inner.is::<PinMismatch>())
+}
+
+/// A version of tls that connections can be pinned to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Version {
+    Tls10,
+    Tls11,
+    Tls12,
+    Tls13,
+}
+
+impl Version {
+    /// Parses a version given as `1.2` or as openssl names it, `TLSv1.2`.
+    pub fn parse(version: &str) -> Result<Version, String> {
+        let number = version.trim_start_matches("TLSv").trim_start_matches("tlsv");
+        match number {
+            "1" | "1.0" => Ok(Version::Tls10),
+            "1.1" => Ok(Version::Tls11),
+            "1.2" => Ok(Version::Tls12),
+            "1.3" => Ok(Version::Tls13),
+            _ => Err(format!(
+                "Expected a tls version of 1.0, 1.1, 1.2 or 1.3 but got {}",
+                version
+            )),
+        }
+    }
+
+    /// The name openssl gives the version, as it reports the one a connection negotiated.
+    pub fn name(self) -> &'static str {
+        match self {
+            Version::Tls10 => "TLSv1",
+            Version::Tls11 => "TLSv1.1",
+            Version::Tls12 => "TLSv1.2",
+            Version::Tls13 => "TLSv1.3",
+        }
+    }
+
+    fn protocol(self) -> c_long {
+        match self {
+            Version::Tls10 => 0x301,
+            Version::Tls11 => 0x302,
+            Version::Tls12 => 0x303,
+            Version::Tls13 => 0x304,
+        }
+    }
+}
+
+/// The range of tls versions connections may negotiate, whatever openssl allows by default
+/// at either end that is left open.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Versions {
+    min: Option<Version>,
+    max: Option<Version>,
+}
+
+impl Versions {
+    /// The versions from the lowest to the highest given.
+    pub fn new(min: Option<Version>, max: Option<Version>) -> Result<Versions, String> {
+        match (min, max) {
+            (Some(min), Some(max)) if min > max => Err(format!(
+                "The lowest tls version {} is above the highest {}",
+                min.name(),
+                max.name()
+            )),
+            _ => Ok(Versions { min, max }),
+        }
+    }
+
+    /// Pins connections to the one version.
+    pub fn only(version: Version) -> Versions {
+        Versions {
+            min: Some(version),
+            max: Some(version),
+        }
+    }
+
+    /// Limits the versions the context's connections negotiate.
+    fn apply(&self, context: &mut SslContextBuilder) -> Result<(), String> {
+        let bounds = [
+            (SSL_CTRL_SET_MIN_PROTO_VERSION, self.min),
+            (SSL_CTRL_SET_MAX_PROTO_VERSION, self.max),
+        ];
+        for &(command, version) in &bounds {
+            if let Some(version) = version {
+                let set = unsafe {
+                    openssl_sys::SSL_CTX_ctrl(
+                        context.as_ptr(),
+                        command,
+                        version.protocol(),
+                        ptr::null_mut(),
+                    )
+                };
+                if set == 0 {
+                    return Err(format!("OpenSSL cannot pin tls to {}", version.name()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+
+    #[test]
+    fn it_parses_versions() {
+        assert_eq!(Version::parse("1.2"), Ok(Version::Tls12));
+        assert_eq!(Version::parse("TLSv1.3"), Ok(Version::Tls13));
+        assert_eq!(Version::parse("1.0"), Ok(Version::Tls10));
+        assert_eq!(Version::parse(Version::Tls10.name()), Ok(Version::Tls10));
+        assert!(Version::parse("1.4").is_err());
+        assert!(Versions::new(Some(Version::Tls13), Some(Version::Tls12)).is_err());
+        assert_eq!(
+            Versions::new(Some(Version::Tls12), Some(Version::Tls12)),
+            Ok(Versions::only(Version::Tls12))
+        );
+    }
+
+    #[test]
+    fn it_describes_itself() {
+        assert_eq!(Options::default().describe(), "openssl defaults");
+        let options = Options::default()
+            .with_versions(Versions::new(Some(Version::Tls12), None).unwrap())
+            .with_server_name("example.com");
+        assert_eq!(options.describe(), "TLSv1.2 and up, sni example.com");
+        let only = Options::default().with_versions(Versions::only(Version::Tls13));
+        assert_eq!(only.describe(), "TLSv1.3");
+    }
+
+    #[test]
+    fn it_pins_contexts() {
+        let mut context = SslContextBuilder::new(SslMethod::tls()).unwrap();
+        assert!(Versions::only(Version::Tls13).apply(&mut context).is_ok());
+    }
+
+    #[test]
+    fn it_parses_pins() {
+        let digest = base64::encode(&[7; 32]);
+        let pins = Pin::parse(&format!("sha256//{}; sha256//{}", digest, digest)).unwrap();
+        assert_eq!(pins, vec![Pin(vec![7; 32]), Pin(vec![7; 32])]);
+        assert!(Pin::parse(&format!("sha1//{}", digest)).is_err());
+        assert!(Pin::parse("sha256//c2hvcnQ=").is_err());
+        assert!(is_pin_mismatch(&io::Error::other(PinMismatch)));
+        assert!(!is_pin_mismatch(&io::Error::other("refused")));
+    }
+
+    #[test]
+    fn it_counts_the_time_left_before_expiry() {
+        let time = Asn1Time::days_from_now(10).unwrap();
+        let left = seconds_until(&time).unwrap();
+        assert!(left > 10 * 86_400 - 60 && left <= 10 * 86_400);
+    }
+
+    #[test]
+    fn it_restricts_ciphers() {
+        let options = Options::default()
+            .with_ciphers("ECDHE-RSA-AES128-GCM-SHA256:TLS_AES_256_GCM_SHA384");
+        assert!(options.check().is_ok());
+        assert!(Options::default().with_ciphers("TLS_AES_256_GCM_SHA384").check().is_ok());
+        assert!(Options::default().with_ciphers("NOT-A-CIPHER").check().is_err());
+        assert!(Options::default().with_ciphers("TLS_NOT_A_SUITE").check().is_err());
+    }
+}